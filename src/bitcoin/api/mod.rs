@@ -1,15 +1,22 @@
+pub mod rpc;
+
 use serde::Serialize;
-use crate::bitcoin::{
-    blockchain::Blockchain,
-    miner::Handle as MinerHandle,
-    network::{
-        server::Handle as NetworkServerHandle,
-        message::Message,
-    },
-    transaction::{
-        generator::Handle as GeneratorHandle,
-        Mempool,
+use crate::{
+    bitcoin::{
+        blockchain::Blockchain,
+        configuration::Configuration,
+        miner::Handle as MinerHandle,
+        network::{
+            server::Handle as NetworkServerHandle,
+            message::Message,
+            worker::Validator,
+        },
+        transaction::{
+            generator::Handle as GeneratorHandle,
+            Mempool,
+        },
     },
+    types::jsonrpc,
 };
 
 use log::info;
@@ -31,7 +38,8 @@ pub struct Server {
     network: NetworkServerHandle,
     blockchain: Arc<Mutex<Blockchain>>,
     generator: GeneratorHandle,
-    mempool: Arc<Mutex<Mempool>>
+    mempool: Arc<Mutex<Mempool>>,
+    config: Configuration,
 }
 
 #[derive(Serialize)]
@@ -69,6 +77,7 @@ impl Server {
         blockchain: &Arc<Mutex<Blockchain>>,
         generator: &GeneratorHandle,
         mempool: &Arc<Mutex<Mempool>>,
+        config: &Configuration,
     ) {
         let handle = HTTPServer::http(&addr).unwrap();
         let server = Self {
@@ -78,6 +87,7 @@ impl Server {
             blockchain: Arc::clone(blockchain),
             generator: generator.clone(),
             mempool: Arc::clone(mempool),
+            config: config.clone(),
         };
         thread::spawn(move || {
             for req in server.handle.incoming_requests() {
@@ -86,6 +96,7 @@ impl Server {
                 let blockchain = Arc::clone(&server.blockchain);
                 let generator = server.generator.clone();
                 let mempool = Arc::clone(&server.mempool);
+                let config = server.config.clone();
                 thread::spawn(move || {
                     // a valid url requires a base
                     let base_url = Url::parse(&format!("http://{}/", &addr)).unwrap();
@@ -97,6 +108,18 @@ impl Server {
                         }
                     };
                     match url.path() {
+                        //JSON-RPC 2.0, a request or a batch in the body of a POST
+                        "/rpc" => {
+                            let ctx = rpc::RpcContext {
+                                validator: Validator::new(&blockchain, &mempool, &config),
+                                miner,
+                                network,
+                                blockchain,
+                                generator,
+                                mempool,
+                            };
+                            jsonrpc::serve(req, |method, params| rpc::dispatch(&ctx, method, params));
+                        }
                         "/miner/start" => {
                             let params = url.query_pairs();
                             let params: HashMap<_, _> = params.into_owned().collect();
@@ -0,0 +1,112 @@
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use crate::{
+    bitcoin::{
+        blockchain::Blockchain,
+        miner::Handle as MinerHandle,
+        network::{
+            message::Message,
+            server::Handle as NetworkServerHandle,
+            worker::{Validator, ValidationSource},
+        },
+        transaction::{
+            generator::Handle as GeneratorHandle,
+            Mempool,
+            Transaction,
+        },
+    },
+    types::{
+        hash::{H256, Hashable},
+        jsonrpc::{Params, RpcError, to_result, METHOD_NOT_FOUND},
+    },
+};
+
+//the state the methods act on, shared with the REST endpoints
+#[derive(Clone)]
+pub struct RpcContext {
+    pub miner: MinerHandle,
+    pub network: NetworkServerHandle,
+    pub blockchain: Arc<Mutex<Blockchain>>,
+    pub generator: GeneratorHandle,
+    pub mempool: Arc<Mutex<Mempool>>,
+    pub validator: Validator,
+}
+
+fn hash_param(params: &Params, name: &str, position: usize) -> Result<H256, RpcError> {
+    let hash: String = params.require(name, position)?;
+    let bytes = hex::decode(&hash)
+        .map_err(|e| RpcError::invalid_params(format!("invalid {}: {}", name, e)))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| RpcError::invalid_params(format!("{} must be 32 bytes", name)))?;
+    Ok(bytes.into())
+}
+
+pub fn dispatch(ctx: &RpcContext, method: &str, params: &Params) -> Result<Value, RpcError> {
+    match method {
+        "miner_start" => {
+            let lambda: u64 = params.get("lambda", 0)?.unwrap_or(0);
+            ctx.miner.start(lambda);
+            Ok(json!(true))
+        }
+        "miner_stop" => {
+            ctx.miner.exit();
+            Ok(json!(true))
+        }
+        "txGenerator_start" => {
+            let theta: u64 = params.require("theta", 0)?;
+            ctx.generator.start(theta);
+            Ok(json!(true))
+        }
+        "txGenerator_stop" => {
+            ctx.generator.exit();
+            Ok(json!(true))
+        }
+        "chain_getLongestChain" => {
+            let hashes = ctx.blockchain.lock().unwrap().all_blocks_in_longest_chain();
+            to_result(hashes.iter().map(|h| h.to_string()).collect::<Vec<String>>())
+        }
+        "chain_getTip" => Ok(json!(ctx.blockchain.lock().unwrap().tip().to_string())),
+        "chain_getBlock" => {
+            let hash = hash_param(params, "hash", 0)?;
+            let block = ctx.blockchain.lock().unwrap().get_block(&hash);
+            to_result(block.ok_or(RpcError::not_found("block not found"))?)
+        }
+        "mempool_status" => {
+            let tx_hashes: Vec<String> = ctx.mempool
+                .lock()
+                .unwrap()
+                .get_all_txs()
+                .iter()
+                .map(|h| h.to_string())
+                .collect();
+            Ok(json!({ "size": tx_hashes.len(), "tx_hashes": tx_hashes }))
+        }
+        "net_ping" => {
+            ctx.network.broadcast(Message::Ping(String::from("Test ping")));
+            Ok(json!(true))
+        }
+        //a transaction encoded with bincode as hex, validated as one from a peer,
+        //accepted into the mempool and announced
+        "tx_submit" => {
+            let raw: String = params.require("raw", 0)?;
+            let bytes = hex::decode(&raw)
+                .map_err(|e| RpcError::invalid_params(format!("invalid raw: {}", e)))?;
+            let tx: Transaction = bincode::deserialize(&bytes)
+                .map_err(|e| RpcError::invalid_params(format!("invalid transaction: {}", e)))?;
+            let hash = tx.hash();
+            if ctx.mempool.lock().unwrap().check(&hash)
+                || ctx.blockchain.lock().unwrap().get_tx_in_longest_chain(&hash).is_some()
+            {
+                return Err(RpcError::rejected("transaction already known"));
+            }
+            if !ctx.validator.validate_tx(&tx, ValidationSource::FromTransaction) {
+                return Err(RpcError::rejected("invalid transaction"));
+            }
+            ctx.mempool.lock().unwrap().insert(tx);
+            ctx.network.broadcast(Message::NewTransactionHashes(vec![hash]));
+            Ok(json!(hash.to_string()))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("method {} not found", method))),
+    }
+}
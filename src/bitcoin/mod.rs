@@ -139,6 +139,7 @@ pub mod configuration;
 //        &blockchain,
 //        &generator,
 //        &mempool,
+//        &config,
 //    );
 //
 //    loop {
@@ -36,7 +36,7 @@ pub struct Worker {
     blockchain: Arc<Mutex<Blockchain>>,
     blk_buff: HashMap<String, Block>,
     mempool: Arc<Mutex<Mempool>>,
-    config: Configuration,
+    validator: Validator,
 }
 
 #[derive(Clone)]
@@ -45,34 +45,25 @@ pub enum ValidationSource {
     FromTransaction,
 }
 
-impl Worker {
+//checks the transactions against the longest chain and the mempool, for the
+//network workers and the api
+#[derive(Clone)]
+pub struct Validator {
+    blockchain: Arc<Mutex<Blockchain>>,
+    mempool: Arc<Mutex<Mempool>>,
+    config: Configuration,
+}
+
+impl Validator {
     pub fn new(
-        num_worker: usize,
-        msg_src: smol::channel::Receiver<(Vec<u8>, peer::Handle)>,
-        server: &ServerHandle,
         blockchain: &Arc<Mutex<Blockchain>>,
         mempool: &Arc<Mutex<Mempool>>,
         config: &Configuration
     ) -> Self {
         Self {
-            msg_chan: msg_src,
-            num_worker,
-            server: server.clone(),
             blockchain: Arc::clone(blockchain),
-            blk_buff: HashMap::new(),
             mempool: Arc::clone(mempool),
-            config: config.clone()
-        }
-    }
-
-    pub fn start(self) {
-        let num_worker = self.num_worker;
-        for i in 0..num_worker {
-            let mut cloned = self.clone();
-            thread::spawn(move || {
-                cloned.worker_loop();
-                warn!("Worker thread {} exited", i);
-            });
+            config: config.clone(),
         }
     }
 
@@ -198,7 +189,38 @@ impl Worker {
         }
         true
     }
- 
+}
+
+impl Worker {
+    pub fn new(
+        num_worker: usize,
+        msg_src: smol::channel::Receiver<(Vec<u8>, peer::Handle)>,
+        server: &ServerHandle,
+        blockchain: &Arc<Mutex<Blockchain>>,
+        mempool: &Arc<Mutex<Mempool>>,
+        config: &Configuration
+    ) -> Self {
+        Self {
+            msg_chan: msg_src,
+            num_worker,
+            server: server.clone(),
+            blockchain: Arc::clone(blockchain),
+            blk_buff: HashMap::new(),
+            mempool: Arc::clone(mempool),
+            validator: Validator::new(blockchain, mempool, config),
+        }
+    }
+
+    pub fn start(self) {
+        let num_worker = self.num_worker;
+        for i in 0..num_worker {
+            let mut cloned = self.clone();
+            thread::spawn(move || {
+                cloned.worker_loop();
+                warn!("Worker thread {} exited", i);
+            });
+        }
+    }
 
     fn worker_loop(&mut self) {
         loop {
@@ -268,7 +290,7 @@ impl Worker {
                         //check whether the transactions inside are valid
                         let mut are_txs_valid = true;
                         for tx_ref in blk_ref.content.txs.data.iter() {
-                            if !self.validate_tx(tx_ref, ValidationSource::FromBlock) {
+                            if !self.validator.validate_tx(tx_ref, ValidationSource::FromBlock) {
                                 are_txs_valid = false;
                                 break;
                             } 
@@ -389,7 +411,7 @@ impl Worker {
                             continue;
                         }
                         //2.validate the transaction
-                        if !self.validate_tx(tx, ValidationSource::FromTransaction) {
+                        if !self.validator.validate_tx(tx, ValidationSource::FromTransaction) {
                             continue;
                         }
                         new_tx_hashs.push(tx_hash);
@@ -6,6 +6,7 @@ pub mod types;
 pub mod bitcoin;
 pub mod manifoldchain;
 pub mod optchain;
+pub mod wallet;
 pub mod tests;
 use clap::clap_app;
 use env_logger::Env;
@@ -25,6 +26,7 @@ use crate::{
         start as optchain_start,
         // configuration::Configuration as OptchainConfiguration,
     },
+    wallet::start as wallet_start,
 };
 
 
@@ -43,6 +45,15 @@ fn main() {
             (@arg verbose:
                 -v ... 
                 "Increases the verbosity of logging")
+            (@arg config:
+                --config [PATH]
+                "Loads the options from a JSON file keyed by option name, e.g. {\"shard_id\": 1}; the command line overrides it")
+            (@arg dump_config:
+                --dumpConfig
+                "Prints the effective options as a JSON file for --config after validating them, and exits")
+            (@arg genesis:
+                --genesis [PATH]
+                "Builds the genesis blocks from a JSON genesis file with the chain_id, shard_num, timestamp, targets and allocations of the network")
             (@arg peer_addr: 
                 --p2p [ADDR] 
                 default_value("127.0.0.1:6000") 
@@ -58,6 +69,10 @@ fn main() {
                 --("p2p-workers") [INT] 
                 default_value("1") 
                 "Sets the number of worker threads for P2P server")
+            (@arg sig_workers: 
+                --("sig-workers") [INT] 
+                default_value("4") 
+                "Sets the number of worker threads verifying signatures")
             (@arg shard_id:
                 --shardId [INT]
                 "Sets the shard id of the node")
@@ -97,6 +112,15 @@ fn main() {
             (@arg verbose:
                 -v ... 
                 "Increases the verbosity of logging")
+            (@arg config:
+                --config [PATH]
+                "Loads the options from a JSON file keyed by option name, e.g. {\"shard_id\": 1}; the command line overrides it")
+            (@arg dump_config:
+                --dumpConfig
+                "Prints the effective options as a JSON file for --config after validating them, and exits")
+            (@arg genesis:
+                --genesis [PATH]
+                "Builds the genesis blocks from a JSON genesis file with the chain_id, shard_num, timestamp, targets and allocations of the network")
             (@arg peer_addr: 
                 --p2p [ADDR] 
                 default_value("127.0.0.1:6000") 
@@ -166,7 +190,93 @@ fn main() {
             (@arg order_diff:
                 --oDiff [STR]
                 "Sets the difficulty of mining an ordering block")
+            (@arg mempool_size:
+                --mempoolSize [INT]
+                default_value("0")
+                "Sets the maximum number of transaction blocks in the mempool (0 for unbounded)")
+            (@arg mempool_policy:
+                --mempoolPolicy [STR]
+                default_value("oldest")
+                "Sets the mempool eviction policy (oldest or lowest-priority)")
+            (@arg mempool_persist:
+                --mempoolPersist
+                "Keeps the mempool across restarts")
+            (@arg data_dir:
+                --dataDir [PATH]
+                default_value("./DB")
+                "Sets the directory of the address book of the node")
+            (@arg max_outbound:
+                --maxOutbound [INT]
+                default_value("8")
+                "Sets the target number of outgoing peer connections")
+            (@arg outbound_per_shard:
+                --outboundPerShard [INT]
+                default_value("2")
+                "Sets the target number of outgoing peer connections to each shard")
+            (@arg relay_policy:
+                --relayPolicy [POLICY]
+                default_value("flood")
+                "Sets where blocks and symbols are relayed: flood, or shard optionally followed by overrides like shard,symbol=all")
+            (@arg link_config:
+                --linkConfig [PATH]
+                "Emulates the propagation_delay and bandwidths of a JSON file like scripts/config.json on the peer links")
+            (@arg compact_blocks:
+                --compactBlocks
+                "Relays proposer and availability blocks as tx block hashes instead of in full")
+            (@arg simulated_pow:
+                --simPow [SPEC]
+                "Mines at exponential arrival times instead of by proof of work, e.g. power=0.25,tx=20,prop=1,order=1,avai=2,inAvai=0.5 with the rates in blocks per second of the network; the difficulties and the lambda of the miner are then ignored")
+            (@arg miner_threads:
+                --minerThreads [INT]
+                default_value("1")
+                "Sets the number of threads hashing the block in mining")
+            (@arg hash_rate:
+                --hashRate [INT]
+                default_value("0")
+                "Limits the miner to this many hashes per second over all its threads, 0 means unlimited")
         )       
+        (@subcommand wallet =>
+            (about: "Manage keys and send coins through the API of a Manifoldchain or an OptChain node")
+            (@arg dir:
+                --dir [PATH]
+                default_value("wallet")
+                "Sets the directory of the PKCS#8 key files")
+            (@arg rpc:
+                --rpc [URL]
+                default_value("http://127.0.0.1:7000/rpc")
+                "Sets the JSON-RPC endpoint of the node of the shard of the key")
+            (@arg chain:
+                --chain [ID]
+                default_value("manifoldchain")
+                "Sets the chain id, which prefixes the addresses and must be the one of the node")
+            (@subcommand create =>
+                (about: "Creates a new key")
+                (@arg name: +required "Sets the name of the key"))
+            (@subcommand import =>
+                (about: "Imports a key from an Ed25519 PKCS#8 file")
+                (@arg name: +required "Sets the name of the key")
+                (@arg file: +required "Sets the PKCS#8 file to import"))
+            (@subcommand export =>
+                (about: "Exports a key to a PKCS#8 file")
+                (@arg name: +required "Sets the name of the key")
+                (@arg file: +required "Sets the PKCS#8 file to write"))
+            (@subcommand list =>
+                (about: "Lists the keys and their addresses"))
+            (@subcommand address =>
+                (about: "Prints the address and the public key of a key")
+                (@arg name: +required "Sets the name of the key"))
+            (@subcommand utxos =>
+                (about: "Lists the unspent outputs of a key")
+                (@arg name: +required "Sets the name of the key"))
+            (@subcommand balance =>
+                (about: "Prints the coins of a key")
+                (@arg name: +required "Sets the name of the key"))
+            (@subcommand send =>
+                (about: "Signs and submits a transaction paying the payees, with the change back to the key")
+                (@arg name: +required "Sets the name of the paying key")
+                (@arg to: --to +required ... [PAYEE] "Adds a payee as PUBKEY_HEX:VALUE")
+                (@arg utxo: --utxo ... [UTXO] "Spends an output as RAW_TX_HEX:INDEX, for OptChain nodes which keep no UTXO set"))
+        )
     )
     .get_matches();
 
@@ -177,6 +287,9 @@ fn main() {
         ("optchain", Some(sub_m)) => {
             optchain_start(sub_m);
         }
+        ("wallet", Some(sub_m)) => {
+            wallet_start(sub_m);
+        }
         _ => unreachable!(), // clap ensures one of the subcommands is used
     }
 
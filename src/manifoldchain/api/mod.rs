@@ -1,4 +1,6 @@
-use serde::Serialize;
+pub mod rpc;
+
+use serde::{Serialize, Deserialize};
 use crate::{
     manifoldchain::{
         multichain::Multichain,
@@ -20,10 +22,13 @@ use crate::{
         configuration::Configuration,
     },
     types::{
+        address::Address,
         hash::{
             H256,
             Hashable,
-        }
+        },
+        jsonrpc,
+        sig_verifier::SigVerifier,
     },
 };
 
@@ -48,6 +53,7 @@ pub struct Server {
     generator: GeneratorHandle,
     mempool: Arc<Mutex<Mempool>>,
     config: Configuration,
+    sig_verifier: SigVerifier,
 }
 
 #[derive(Serialize)]
@@ -56,6 +62,42 @@ struct ApiResponse {
     message: String,
 }
 
+//a utxo as the API shows it: the address owning it in the bech32 form of the
+//chain, and the tx holding it, encoded with bincode as hex, which spending it needs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UtxoView {
+    pub address: String,
+    pub tx_hash: String,
+    pub index: u32,
+    pub value: u32,
+    pub raw: String,
+}
+
+impl UtxoView {
+    pub fn new(tx: &Transaction, index: u32, address: &Address, chain_id: &str) -> Self {
+        let output = &tx.outputs[index as usize];
+        //a rejected tx gives back the coins of its inputs
+        let value = match tx.flag {
+            TxFlag::Reject => tx.inputs[index as usize].value,
+            _ => output.value,
+        };
+        UtxoView {
+            address: address.encode(chain_id),
+            tx_hash: tx.hash().to_string(),
+            index,
+            value,
+            raw: hex::encode(bincode::serialize(tx).unwrap()),
+        }
+    }
+
+    pub fn get_tx(&self) -> Result<Transaction, String> {
+        let bytes = hex::decode(&self.raw)
+            .map_err(|e| format!("invalid raw tx of utxo {}:{}: {}", self.tx_hash, self.index, e))?;
+        bincode::deserialize(&bytes)
+            .map_err(|e| format!("invalid tx of utxo {}:{}: {}", self.tx_hash, self.index, e))
+    }
+}
+
 macro_rules! respond_result {
     ( $req:expr, $success:expr, $message:expr ) => {{
         let content_type = "Content-Type: application/json".parse::<Header>().unwrap();
@@ -86,6 +128,7 @@ impl Server {
         generator: &GeneratorHandle,
         mempool: &Arc<Mutex<Mempool>>,
         config: &Configuration,
+        sig_verifier: &SigVerifier,
     ) {
         let handle = HTTPServer::http(&addr).unwrap();
         let server = Self {
@@ -96,6 +139,7 @@ impl Server {
             generator: generator.clone(),
             mempool: Arc::clone(mempool),
             config: config.clone(),
+            sig_verifier: sig_verifier.clone(),
         };
         thread::Builder::new()
             .name("api-server".to_string())
@@ -107,10 +151,12 @@ impl Server {
                     let generator = server.generator.clone();
                     let mempool = Arc::clone(&server.mempool);
                     let config = server.config.clone();
+                    let sig_verifier = server.sig_verifier.clone();
                     let validator = Validator::new(
                         &multichain,
                         &mempool,
                         &config,
+                        &sig_verifier,
                     );
                     thread::spawn(move || {
                         // a valid url requires a base
@@ -123,6 +169,19 @@ impl Server {
                             }
                         };
                         match url.path() {
+                            //JSON-RPC 2.0, a request or a batch in the body of a POST
+                            "/rpc" => {
+                                let ctx = rpc::RpcContext {
+                                    miner,
+                                    network,
+                                    multichain,
+                                    generator,
+                                    mempool,
+                                    config,
+                                    sig_verifier,
+                                };
+                                jsonrpc::serve(req, |method, params| rpc::dispatch(&ctx, method, params));
+                            }
                             "/miner/start" => {
                                 let params = url.query_pairs();
                                 let params: HashMap<_, _> = params.into_owned().collect();
@@ -280,32 +339,31 @@ impl Server {
                             "/blockchain/available-utxo" => {
                                 let params = url.query_pairs();
                                 let params: HashMap<_, _> = params.into_owned().collect();
-                                let user = match params.get("user") {
+                                let address = match params.get("address") {
                                     Some(v) => v,
                                     None => {
-                                        info!("missing user");
-                                        respond_result!(req, false, "missing user");
+                                        info!("missing address");
+                                        respond_result!(req, false, "missing address");
                                         return;
                                     }
                                 };
-                                let user = match user.parse::<String>() {
+                                let address = match Address::decode(address, config.chain_id()) {
                                     Ok(v) => v,
                                     Err(e) => {
-                                        info!("error passing user");
+                                        info!("error parsing address");
                                         respond_result!(
                                             req,
                                             false,
-                                            format!("error parsing user: {}", e)
+                                            format!("error parsing address: {}", e)
                                         );
                                         return;
                                     }
                                 };
-                                let user: H256 = user.into();
-                                let utxos = Self::get_available_utxo(
+                                let utxos = Self::get_utxo_views(
                                     &multichain,
                                     &validator,
                                     &config,
-                                    &user,
+                                    &address,
                                 );
                                 respond_json!(req, utxos);
                             }
@@ -331,6 +389,19 @@ impl Server {
         info!("API server listening at {}", &addr);
     }
 
+    //the available utxos of an address, in the form of the API
+    pub fn get_utxo_views(
+        multichain: &Multichain,
+        validator: &Validator,
+        config: &Configuration,
+        address: &Address,
+    ) -> Vec<UtxoView> {
+        Self::get_available_utxo(multichain, validator, config, &address.into())
+            .iter()
+            .map(|(tx, index)| UtxoView::new(tx, *index, address, config.chain_id()))
+            .collect()
+    }
+
     fn get_available_utxo(
         multichain: &Multichain, 
         validator: &Validator, 
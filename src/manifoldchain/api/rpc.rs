@@ -0,0 +1,125 @@
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use crate::{
+    manifoldchain::{
+        api::Server,
+        configuration::Configuration,
+        mempool::Mempool,
+        miner::Handle as MinerHandle,
+        multichain::Multichain,
+        network::{
+            message::Message,
+            server::Handle as NetworkServerHandle,
+        },
+        transaction::{
+            generator::Handle as GeneratorHandle,
+            Transaction,
+        },
+        validator::{Validator, ValidationSource},
+    },
+    types::{
+        address::Address,
+        hash::Hashable,
+        jsonrpc::{Params, RpcError, to_result, METHOD_NOT_FOUND},
+        sig_verifier::SigVerifier,
+    },
+};
+
+//the state the methods act on, shared with the REST endpoints
+#[derive(Clone)]
+pub struct RpcContext {
+    pub miner: MinerHandle,
+    pub network: NetworkServerHandle,
+    pub multichain: Multichain,
+    pub generator: GeneratorHandle,
+    pub mempool: Arc<Mutex<Mempool>>,
+    pub config: Configuration,
+    pub sig_verifier: SigVerifier,
+}
+
+//an address in the bech32 form of the chain of the node
+fn address_param(params: &Params, name: &str, position: usize, chain_id: &str) -> Result<Address, RpcError> {
+    let address: String = params.require(name, position)?;
+    Address::decode(&address, chain_id).map_err(RpcError::invalid_params)
+}
+
+pub fn dispatch(ctx: &RpcContext, method: &str, params: &Params) -> Result<Value, RpcError> {
+    match method {
+        "miner_start" => {
+            let lambda: u64 = params.get("lambda", 0)?.unwrap_or(0);
+            ctx.miner.start(lambda);
+            Ok(json!(true))
+        }
+        "miner_stop" => {
+            ctx.miner.exit();
+            Ok(json!(true))
+        }
+        "txGenerator_start" => {
+            let theta: u64 = params.require("theta", 0)?;
+            ctx.generator.start(theta);
+            Ok(json!(true))
+        }
+        "txGenerator_stop" => {
+            ctx.generator.exit();
+            Ok(json!(true))
+        }
+        "chain_getLongestChain" => {
+            let hashes = match params.get::<usize>("shard_id", 0)? {
+                Some(shard_id) => ctx.multichain.all_blocks_in_longest_chain_with_shard(shard_id),
+                None => ctx.multichain.all_blocks_in_longest_chain(),
+            };
+            to_result(hashes.iter().map(|h| h.to_string()).collect::<Vec<String>>())
+        }
+        "chain_getAvailableUtxos" => {
+            let address = address_param(params, "address", 0, ctx.config.chain_id())?;
+            let validator = Validator::new(&ctx.multichain, &ctx.mempool, &ctx.config, &ctx.sig_verifier);
+            to_result(Server::get_utxo_views(&ctx.multichain, &validator, &ctx.config, &address))
+        }
+        "mempool_status" => {
+            let tx_hashes: Vec<String> = ctx.mempool
+                .lock()
+                .unwrap()
+                .get_all_tx_hash()
+                .iter()
+                .map(|h| h.to_string())
+                .collect();
+            Ok(json!({ "size": tx_hashes.len(), "tx_hashes": tx_hashes }))
+        }
+        //the chain and the shard of a node, which wallets need to sign and classify their txs
+        "node_getInfo" => Ok(json!({
+            "protocol": "manifoldchain",
+            "chain_id": ctx.config.chain_id(),
+            "shard_id": ctx.config.shard_id,
+            "shard_num": ctx.config.shard_num,
+            "node_id": ctx.config.node_id,
+        })),
+        "net_ping" => {
+            ctx.network.broadcast(Message::Ping(String::from("Test ping")));
+            Ok(json!(true))
+        }
+        //a transaction encoded with bincode as hex, validated, accepted into the
+        //mempool and announced to the shard
+        "tx_submit" => {
+            let raw: String = params.require("raw", 0)?;
+            let bytes = hex::decode(&raw)
+                .map_err(|e| RpcError::invalid_params(format!("invalid raw: {}", e)))?;
+            let tx: Transaction = bincode::deserialize(&bytes)
+                .map_err(|e| RpcError::invalid_params(format!("invalid transaction: {}", e)))?;
+            let hash = tx.hash();
+            if ctx.mempool.lock().unwrap().get_tx(&hash).is_some() {
+                return Err(RpcError::rejected("transaction already known"));
+            }
+            let validator = Validator::new(&ctx.multichain, &ctx.mempool, &ctx.config, &ctx.sig_verifier);
+            if validator.validate_tx(&tx, None, None, ValidationSource::FromTransaction).is_err() {
+                return Err(RpcError::rejected("invalid transaction"));
+            }
+            ctx.mempool.lock().unwrap().insert_tx(tx);
+            ctx.network.broadcast_with_shard(
+                Message::NewTransactionHash((vec![hash], ctx.config.shard_id as u32)),
+                ctx.config.shard_id,
+            );
+            Ok(json!(hash.to_string()))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("method {} not found", method))),
+    }
+}
@@ -7,6 +7,7 @@ use crate::{
         block::{
             Info,
             Content,
+            BlockHeader,
             exclusive_block::ExclusiveBlock,
             consensus_block::ConsensusBlock,
             versa_block::{
@@ -27,8 +28,9 @@ use crate::{
     },
 };
 use std::{
+    cell::RefCell,
     cmp,
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     fs::File,
     io::{Write, Error},
     time::SystemTime,
@@ -82,6 +84,9 @@ pub struct Blockchain {
     //multiple blocks
     //states: HashMap<H256, State>, //block_hash -> static state
     states: Database<State>,
+    //the state last read from states, which the txs validated until the next
+    //block look up again
+    state_cache: RefCell<Option<(H256, State)>>,
     leaves: Vec<H256>,
     unverified_blocks: HashMap<(H256, usize), bool>,
     //to facilitate the insertion of two same blocks with the same parent
@@ -392,17 +397,8 @@ impl Blockchain {
         //     vec![], //global_parents
         // );
 
-        let mut cons_blk = ConsensusBlock::default();
-        cons_blk.set_shard_id(shard_id);
-        let cons_hash = cons_blk.hash();
-        let ex_blk = ExclusiveBlock::create(
-            cons_blk,
-            cons_hash,
-            vec![],
-        );
-
+        let ex_blk = Self::genesis_block(config, shard_id);
 
-        
         let genesis_hash = ex_blk.hash();
         let genesis_block = VersaBlock::ExBlock(ex_blk);
 
@@ -428,8 +424,8 @@ impl Blockchain {
         let mut hash2ver_status: HashMap<H256, VerStatus> = HashMap::new();
         hash2ver_status.insert(genesis_hash.clone(), VerStatus::Verified);
 
-        //intitialize a empty state to the genesis block
-        let initial_state: State = HashMap::new();
+        //intitialize the state of the genesis block
+        let initial_state: State = Self::genesis_state(config, shard_id);
         //let mut states: HashMap<H256, State> = HashMap::new();
         let mut states: Database<State> = 
             Database::<State>::new(format!("{:?}/blockchain/states", now));
@@ -444,6 +440,7 @@ impl Blockchain {
             root,
             tx_map: HashMap::new(),
             states,
+            state_cache: RefCell::new(None),
             unverified_blocks: HashMap::new(),
             dp_map: HashMap::new(),
             longest_chain_hash,
@@ -456,6 +453,48 @@ impl Blockchain {
         }
     }
 
+    //the genesis block of a shard, committing to the genesis file if any
+    pub fn genesis_block(config: &Configuration, shard_id: usize) -> ExclusiveBlock {
+        let mut cons_blk = match config.genesis.as_ref() {
+            Some(genesis) => ConsensusBlock::create(
+                BlockHeader::create(
+                    H256::default(), //parent
+                    0, //nonce
+                    H256::default(), //difficulty
+                    shard_id,
+                    genesis.get_timestamp(),
+                    genesis.commitment(), //merkle_root
+                ),
+                H256::default(),
+                H256::default(),
+                H256::default(),
+            ),
+            None => ConsensusBlock::default(),
+        };
+        cons_blk.set_shard_id(shard_id);
+        let cons_hash = cons_blk.hash();
+        ExclusiveBlock::create(
+            cons_blk,
+            cons_hash,
+            vec![],
+        )
+    }
+
+    //the utxos of the allocations of the genesis file owned by the shard
+    pub fn genesis_state(config: &Configuration, shard_id: usize) -> State {
+        let mut state: State = HashMap::new();
+        if let Some(genesis) = config.genesis.as_ref() {
+            for (i, allocation) in genesis.allocations.iter().enumerate() {
+                let tx = Transaction::create_allocation_tx(i, allocation);
+                if Validator::get_shard_id(&tx.outputs[0].receiver_addr, config.shard_num) != shard_id {
+                    continue;
+                }
+                state.insert((tx.hash(), 0), (tx, None));
+            }
+        }
+        state
+    }
+
     pub fn get_longest_verified_fork(&self) -> H256 {
         self.longest_verified_chain_hash.clone()
     }
@@ -465,6 +504,10 @@ impl Blockchain {
         self.hash2node.remove(hash);
         self.hash2ver_status.remove(hash);
         self.states.remove(hash);
+        let mut cache = self.state_cache.borrow_mut();
+        if cache.as_ref().is_some_and(|x| x.0 == *hash) {
+            *cache = None;
+        }
         //self.tx_map.retain(|_, val| *hash != val.0);
     }
 
@@ -1064,6 +1107,29 @@ impl Blockchain {
         self.states.into_map()
     }
 
+    pub fn get_state(&self, hash: &H256) -> Option<State> {
+        self.states.get(hash)
+    }
+
+    //the part of the state after a block holding the utxos spent by the txs,
+    //none if the block is unknown
+    pub fn get_spent_state(&self, hash: &H256, txs: &[Transaction]) -> Option<State> {
+        let mut cache = self.state_cache.borrow_mut();
+        if cache.as_ref().is_none_or(|x| x.0 != *hash) {
+            *cache = Some((*hash, self.states.get(hash)?));
+        }
+        let state = &cache.as_ref().unwrap().1;
+        let spent: HashSet<(H256, u32)> = txs
+            .iter()
+            .flat_map(|tx| tx.inputs.iter())
+            .map(|input| (input.tx_hash, input.index))
+            .collect();
+        Some(spent
+            .into_iter()
+            .filter_map(|utxo| state.get(&utxo).map(|entry| (utxo, entry.clone())))
+            .collect())
+    }
+
 
     //get the unverified leaves
     pub fn get_leaves(&self) -> Vec<H256> {
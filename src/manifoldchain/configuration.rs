@@ -1,4 +1,10 @@
-use crate::types::hash::H256;
+use crate::{
+    types::{
+        hash::H256,
+        options::Options,
+        genesis::GenesisSpec,
+    },
+};
 
 
 #[derive(Debug, Default, Clone)]
@@ -21,8 +27,15 @@ pub struct Configuration {
     pub exper_number: usize,
     pub exper_iter: usize,
     pub domestic_tx_ratio: f64,
+    pub genesis: Option<GenesisSpec>, //None means an empty genesis state, funded by initial txs
 }
 
+//the targets of the genesis file
+pub const DIFFICULTIES: [&str; 2] = ["exclusive_diff", "inclusive_diff"];
+
+//the chain id of a network without a genesis file
+pub const DEFAULT_CHAIN_ID: &str = "manifoldchain";
+
 impl Configuration {
     pub fn new() -> Self {
         //let mut difficulty_vec = [255u8; 32];
@@ -58,6 +71,92 @@ impl Configuration {
             exper_number: 0,
             exper_iter: 0,
             domestic_tx_ratio: 0.7,
+            genesis: None,
+        }
+    }
+
+    //the configuration of the node from its options, validated with validate()
+    pub fn from_options(options: &Options) -> Result<Self, String> {
+        let mut config = Configuration::new();
+        if let Some(path) = options.value_of("genesis")? {
+            config.genesis = Some(GenesisSpec::from_file(&path)?);
+        }
+        let genesis = config.genesis.clone();
+        config.difficulty = options.parse_difficulty("exclusive_diff", genesis.as_ref())?;
+        config.thredshold = options.parse_difficulty("inclusive_diff", genesis.as_ref())?;
+        config.shard_id = options.parse("shard_id")?;
+        config.node_id = options.parse("node_id")?;
+        config.exper_number = options.parse("exper_number")?;
+        config.exper_iter = options.parse("exper_iter")?;
+        //the shards of the genesis file when not given
+        config.shard_num = match (options.parse_opt("shard_num")?, genesis.as_ref()) {
+            (Some(shard_num), _) => shard_num,
+            (None, Some(genesis)) => genesis.shard_num,
+            (None, None) => options.parse("shard_num")?,
+        };
+        config.shard_size = options.parse("shard_size")?;
+        config.block_size = options.parse("block_size")?;
+        config.k = options.parse("confirmation_depth")?;
+        config.domestic_tx_ratio = options.parse("domestic_ratio")?;
+        Ok(config)
+    }
+
+    //the chain id which the signatures of the txs commit to
+    pub fn chain_id(&self) -> &str {
+        self.genesis
+            .as_ref()
+            .map_or(DEFAULT_CHAIN_ID, |x| x.chain_id.as_str())
+    }
+
+    //the broken invariants all at once, each naming the option to fix
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.shard_num == 0 {
+            errors.push(String::from("shard_num is 0: there must be at least one shard"));
+        } else if self.shard_num > self.max_shard_num {
+            errors.push(format!(
+                "shard_num {} is above the maximum of {} shards",
+                self.shard_num, self.max_shard_num
+            ));
+        } else if self.shard_id >= self.shard_num {
+            errors.push(format!(
+                "shard_id {} is out of range: it must be less than shard_num {}",
+                self.shard_id, self.shard_num
+            ));
+        }
+        //a mined hash above the inclusive target makes an exclusive block
+        if self.thredshold > self.difficulty {
+            errors.push(format!(
+                "inclusive_diff {} is above exclusive_diff {}: every block would be inclusive, lower inclusive_diff",
+                self.thredshold, self.difficulty
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.domestic_tx_ratio) {
+            errors.push(format!(
+                "domestic_ratio {} is not a ratio: it must be between 0 and 1",
+                self.domestic_tx_ratio
+            ));
+        }
+        if let Some(genesis) = self.genesis.as_ref() {
+            if genesis.shard_num != self.shard_num {
+                errors.push(format!(
+                    "shard_num {} differs from the {} shards of the genesis file: drop it or make them equal",
+                    self.shard_num, genesis.shard_num
+                ));
+            }
+            for name in genesis.targets.keys() {
+                if !DIFFICULTIES.contains(&name.as_str()) {
+                    errors.push(format!(
+                        "unknown target {} in the genesis file, expected one of {:?}",
+                        name, DIFFICULTIES
+                    ));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
 }
@@ -17,6 +17,7 @@ use crate::{
     types::{
         hash::{H256, Hashable},
         random::Random,
+        sig_verifier::SigVerifier,
     }, 
     manifoldchain::{
         block::{
@@ -78,12 +79,13 @@ pub struct Handle {
 
 pub fn new(multichain: &Multichain, 
     mempool: &Arc<Mutex<Mempool>>, 
-    config: &Configuration) -> (Context, Handle, Receiver<MinerMessage>) 
+    config: &Configuration,
+    sig_verifier: &SigVerifier) -> (Context, Handle, Receiver<MinerMessage>) 
 {
     let (signal_chan_sender, signal_chan_receiver) = unbounded();
     let (finished_block_sender, finished_block_receiver) = unbounded();
 
-    let validator = Validator::new(multichain, mempool, config);
+    let validator = Validator::new(multichain, mempool, config, sig_verifier);
 
     let ctx = Context {
         control_chan: signal_chan_receiver,
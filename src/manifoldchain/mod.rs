@@ -39,7 +39,8 @@ use crate::{
         confirmation::Confirmation,
     },
     types::{
-        hash::H256,
+        options::Options,
+        sig_verifier::{SigVerifier, DEFAULT_CACHE_SIZE},
         // random::Random,
     },
 };
@@ -59,7 +60,6 @@ use std::{
     time, 
     sync::{Arc, Mutex},
     num::ParseIntError,
-    convert::TryInto,
 };
 
 pub fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
@@ -70,143 +70,65 @@ pub fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
 }
 
 pub fn start(sub_com: &clap::ArgMatches) {
+    // the options of the command line over the ones of the config file
+    let options = Options::load(sub_com).unwrap_or_else(|e| {
+        error!("Error loading the configuration: {}", e);
+        process::exit(1);
+    });
+
     // parse p2p server address
-    let p2p_addr = sub_com
-        .value_of("peer_addr")
-        .unwrap()
-        .parse::<net::SocketAddr>()
+    let p2p_addr = options
+        .parse::<net::SocketAddr>("peer_addr")
         .unwrap_or_else(|e| {
             error!("Error parsing P2P server address: {}", e);
             process::exit(1);
         });
 
     // parse api server address
-    let api_addr = sub_com
-        .value_of("api_addr")
-        .unwrap()
-        .parse::<net::SocketAddr>()
+    let api_addr = options
+        .parse::<net::SocketAddr>("api_addr")
         .unwrap_or_else(|e| {
             error!("Error parsing API server address: {}", e);
             process::exit(1);
         });
-    //parse the shard id
-    let shard_id = sub_com
-        .value_of("shard_id")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the shard id: {}", e);
-            process::exit(1);
-        });
-    //parse the shard id
-    let node_id = sub_com
-        .value_of("node_id")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the node id: {}", e);
-            process::exit(1);
-        });
-    let exper_number = sub_com
-        .value_of("exper_number")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the experiment number: {}", e);
-            process::exit(1);
-        });
-    let exper_iter = sub_com
-        .value_of("exper_iter")
-        .unwrap()
-        .parse::<usize>()
+    let p2p_workers = options
+        .parse::<usize>("p2p_workers")
         .unwrap_or_else(|e| {
-            error!("Error parsing the experiment iteration: {}", e);
-            process::exit(1);
-        });
-    let shard_num = sub_com
-        .value_of("shard_num")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the shard_num number: {}", e);
-            process::exit(1);
-        });
-    let shard_size = sub_com
-        .value_of("shard_size")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the shard size: {}", e);
-            process::exit(1);
-        });
-    let block_size = sub_com
-        .value_of("block_size")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the block size: {}", e);
-            process::exit(1);
-        });
-    let confirmation_depth = sub_com
-        .value_of("confirmation_depth")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the confirmation depth: {}", e);
-            process::exit(1);
-        });
-    let exclusive_diff = sub_com
-        .value_of("exclusive_diff")
-        .unwrap()
-        .parse::<String>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the shard size: {}", e);
-            process::exit(1);
-        });
-    let inclusive_diff = sub_com
-        .value_of("inclusive_diff")
-        .unwrap()
-        .parse::<String>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the shard size: {}", e);
+            error!("Error parsing P2P workers: {}", e);
             process::exit(1);
         });
-    let domestic_ratio = sub_com
-        .value_of("domestic_ratio")
-        .unwrap()
-        .parse::<f64>()
+    let sig_workers = options
+        .parse::<usize>("sig_workers")
         .unwrap_or_else(|e| {
-            error!("Error parsing the domestic ratio: {}", e);
+            error!("Error parsing signature workers: {}", e);
             process::exit(1);
         });
-    let p2p_workers = sub_com
-        .value_of("p2p_workers")
-        .unwrap()
-        .parse::<usize>()
+    let known_peers = options
+        .values_of("known_peer")
         .unwrap_or_else(|e| {
-            error!("Error parsing P2P workers: {}", e);
+            error!("Error parsing the known peers: {}", e);
             process::exit(1);
         });
-    let mut config = Configuration::new();
-    let exclusive_diff_bytes: [u8; 32] = decode_hex(exclusive_diff.as_str())
-        .unwrap()
-        .try_into().unwrap();
-    let inclusive_diff_bytes: [u8; 32] = decode_hex(inclusive_diff.as_str())
-        .unwrap()
-        .try_into().unwrap();
-    let exclusive_diff_hash: H256 = exclusive_diff_bytes.into();
-    let inclusive_diff_hash: H256 = inclusive_diff_bytes.into();
-    config.difficulty = exclusive_diff_hash;
-    config.thredshold = inclusive_diff_hash;
-    config.shard_id = shard_id as usize;
-    config.node_id = node_id as usize;
-    config.exper_number = exper_number as usize;
-    config.exper_iter = exper_iter as usize;
-    config.shard_num = shard_num as usize;
-    config.shard_size = shard_size as usize;
-    config.block_size = block_size as usize;
-    config.k = confirmation_depth as usize;
-    config.domestic_tx_ratio = domestic_ratio as f64;
+
+    let config = Configuration::from_options(&options).unwrap_or_else(|e| {
+        error!("Error parsing the configuration: {}", e);
+        process::exit(1);
+    });
+    let dump_config = options.is_present("dump_config").unwrap_or(false);
+    if let Err(e) = options.check_unknown() {
+        error!("Error loading the configuration: {}", e);
+        process::exit(1);
+    }
+    if let Err(errors) = config.validate() {
+        for e in errors {
+            error!("Invalid configuration: {}", e);
+        }
+        process::exit(1);
+    }
+    if dump_config {
+        println!("{}", options.dump());
+        return;
+    }
     // let shard_id = format!("{:x}", shard_id);
     info!("configuration: {:?}", config);
 
@@ -248,6 +170,9 @@ pub fn start(sub_com: &clap::ArgMatches) {
     let (server_ctx, server) = NetworkServer::new(p2p_addr, msg_tx, &tx_generator_handle, config.shard_id).unwrap();
     server_ctx.start().unwrap();
     
+    // verify the signatures of the txs on a pool shared by the validators
+    let sig_verifier = SigVerifier::new(sig_workers, DEFAULT_CACHE_SIZE);
+
     // start the worker
     let worker_ctx = NetworkWorker::new(
         p2p_workers,
@@ -257,11 +182,12 @@ pub fn start(sub_com: &clap::ArgMatches) {
         &mempool,
         &config,
         &confirmation,
+        &sig_verifier,
     );
     worker_ctx.start();
 
     // start the miner
-    let (miner_ctx, miner, finished_block_chan) = Miner::new(&multichain, &mempool, &config);
+    let (miner_ctx, miner, finished_block_chan) = Miner::new(&multichain, &mempool, &config, &sig_verifier);
     let miner_worker_ctx = MinerWorker::new(
         &server, 
         finished_block_chan, 
@@ -279,8 +205,7 @@ pub fn start(sub_com: &clap::ArgMatches) {
 
     
     // connect to known peers
-    if let Some(known_peers) = sub_com.values_of("known_peer") {
-        let known_peers: Vec<String> = known_peers.map(|x| x.to_owned()).collect();
+    if !known_peers.is_empty() {
         let server = server.clone();
         thread::spawn(move || {
             for peer in known_peers {
@@ -331,6 +256,7 @@ pub fn start(sub_com: &clap::ArgMatches) {
         &tx_generator_handle,
         &mempool,
         &config,
+        &sig_verifier,
     );
 
     loop {
@@ -162,6 +162,26 @@ impl Multichain {
             .unwrap()
             .get_verify_status(hash)
     }
+    //the state after a block of the chain
+    pub fn get_state(&self, hash: &H256) -> Option<State> {
+        self.chains
+            .get(self.config.shard_id)
+            .unwrap()
+            .lock()
+            .unwrap()
+            .get_state(hash)
+    }
+
+    //the utxos spent by the txs in the state after a block of the chain
+    pub fn get_spent_state(&self, hash: &H256, txs: &[Transaction]) -> Option<State> {
+        self.chains
+            .get(self.config.shard_id)
+            .unwrap()
+            .lock()
+            .unwrap()
+            .get_spent_state(hash, txs)
+    }
+
     pub fn get_states(&self) -> HashMap<H256, State> {
         self.chains
             .get(self.config.shard_id)
@@ -1,6 +1,7 @@
 use crate::{
     types::{
         hash::{H256, Hashable},
+        sig_verifier::SigVerifier,
     },
     manifoldchain::{
         network::{
@@ -73,8 +74,9 @@ impl Worker {
         mempool: &Arc<Mutex<Mempool>>,
         config: &Configuration,
         confirmation: &Arc<Mutex<Confirmation>>,
+        sig_verifier: &SigVerifier,
     ) -> Self {
-        let validator = Validator::new(multichain, mempool, config);
+        let validator = Validator::new(multichain, mempool, config, sig_verifier);
         Self {
             msg_chan: msg_src,
             num_worker,
@@ -1,9 +1,11 @@
 use crate::{
     types::{
         key_pair,
+        address::Address,
         hash::{Hashable, H256}
     },
     manifoldchain::{
+        api::UtxoView,
         network::{
             message::Message,
             server::Handle as ServerHandle,
@@ -34,7 +36,7 @@ use std::{
 };
 use rand::{self, Rng};
 use log::{info};
-use ring::signature::{Ed25519KeyPair};
+use ring::signature::{Ed25519KeyPair, KeyPair};
 use reqwest;
 
 pub enum ControlSignal {
@@ -84,27 +86,18 @@ pub fn new_ctx(
     api_port: u16
 ) -> Context {
     
-    let mut rng = rand::thread_rng();    
     let mut users: HashMap<usize, H256> = HashMap::new();
     let mut keys: HashMap<H256, Ed25519KeyPair> = HashMap::new();
     
-    #[allow(unused_comparisons)]
-    //comparison is useless due to type limits: j, line 100
-    for i in 0..config.shard_num {
-        let mut random_bytes: [u8; 32] = rng.gen();
-        let mut j = 31;
-        let mut x: usize = i;
-        loop {
-            random_bytes[j] = (x % 256) as u8;
-            x = x / 256;
-            j -= 1;
-            if x <= 0 || j < 0 {
-                break;
-            }
-        }
-        let user_hash: H256 = (&random_bytes).into();
+    //one user per shard, whose address is the one of its key
+    while users.len() < config.shard_num {
         let key = key_pair::random();
-        users.insert(i, user_hash.clone());
+        let user_hash: H256 = Address::from_public_key(key.public_key().as_ref()).unwrap().into();
+        let shard_id = Validator::get_shard_id(&user_hash, config.shard_num);
+        if users.contains_key(&shard_id) {
+            continue;
+        }
+        users.insert(shard_id, user_hash);
         keys.insert(user_hash, key);
     }
 
@@ -233,7 +226,10 @@ impl Context {
                     thread::sleep(interval);
                 }
 
-                if is_initial {
+                //with a genesis file the users are funded by its allocations only
+                if is_initial && self.config.genesis.is_some() {
+                    is_initial = false;
+                } else if is_initial {
                     //generate the initial balance for each user
                     for _ in 0..self.config.initial_utxo_num {
                         for item in self.keys.iter() {
@@ -271,6 +267,12 @@ impl Context {
                 let sample_value = sample_value as f64;
                 
                 let mut receivers: Vec<H256> = vec![];
+                //the flag is signed, so it is set before the tx is created
+                let flag = if sample_value <= threshold {
+                    TxFlag::Domestic
+                } else {
+                    TxFlag::Input
+                };
                 if sample_value <= threshold {
                     //create domestic tx
                     receivers.push(self.users.get(&self.config.shard_id).unwrap().clone());
@@ -288,7 +290,7 @@ impl Context {
                 }
                 
                 let coins: Vec<usize> = (0..receivers.len()).map(|_| 1).collect();
-                if let Some(tx) = self.create_tx(payer.clone(), receivers.clone(), coins) {
+                if let Some(tx) = self.create_tx(payer, receivers.clone(), coins, flag) {
                     //classify the users by shards
                     info!("create non-initial tx");
                     let mut input_shards: HashMap::<usize, bool> = HashMap::new();
@@ -347,7 +349,7 @@ impl Context {
                     }
                     // no_utxo_count = 0;
                     //self.server.broadcast(Message::Transactions(txs));
-                } else {
+                } else if self.config.genesis.is_none() {
                     info!("create another new initial tx");
                     let initial_tx = Transaction::create_initial_tx(
                         (&payer, self.keys.get(&payer).unwrap()),
@@ -370,7 +372,8 @@ impl Context {
     fn create_tx(&mut self, 
         payer: H256, 
         receivers: Vec<H256>, 
-        coins: Vec<usize>) -> Option<Transaction> {
+        coins: Vec<usize>,
+        flag: TxFlag) -> Option<Transaction> {
         
         //I change only here
         return None;
@@ -478,7 +481,8 @@ impl Context {
                 utxos,
                 senders,
                 receivers_coins,
-                TxFlag::Domestic,
+                flag,
+                self.config.chain_id(),
             ).unwrap();
 
             Some(tx)
@@ -493,12 +497,15 @@ impl Context {
         -> Result<Vec<(Transaction, u32)>, Box<dyn std::error::Error>> 
     {
          
+        let address = Address::from_h256(user)?.encode(self.config.chain_id());
         let mut utxos: Vec<(Transaction, u32)> = Vec::new();
         for (val, _) in self.nodes.iter() {
-            let req_url = format!("http://{}:{}/blockchain/available-utxo?user={}", val, self.api_port, user);
+            let req_url = format!("http://{}:{}/blockchain/available-utxo?address={}", val, self.api_port, address);
             let resp = reqwest::blocking::get(req_url)?
-                .json::<Vec<(Transaction, u32)>>()?;
-            utxos.extend(resp);
+                .json::<Vec<UtxoView>>()?;
+            for utxo in resp {
+                utxos.push((utxo.get_tx()?, utxo.index));
+            }
         }
         
         Ok(utxos)
@@ -3,9 +3,11 @@ pub mod generator;
 use serde::{Serialize, Deserialize};
 use ring::signature::{self, Ed25519KeyPair, Signature, KeyPair};
 use crate::types::{
+    address::Address,
     hash::{H256, Hashable},
     random::Random,
     key_pair,
+    genesis::Allocation,
 };
 use rand::{self, Rng};
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, Hash, PartialEq)]
@@ -209,20 +211,30 @@ impl Transaction {
         }
         input_mem_size + output_mem_size + std::mem::size_of::<TxFlag>()
     }
+    /// The digest signed by the inputs of a transaction: the transaction without
+    /// the signatures, under the chain id, so a signature is bound to its outputs
+    /// and flag and cannot be replayed into another transaction or network
+    pub fn sighash(&self, chain_id: &str) -> H256 {
+        let mut unsigned = self.clone();
+        for input in unsigned.inputs.iter_mut() {
+            input.sig_ref = vec![];
+        }
+        let serialized_tx: Vec<u8> = bincode::serialize(&(chain_id, &unsigned)).unwrap();
+        ring::digest::digest(&ring::digest::SHA256, serialized_tx.as_slice()).into()
+    }
+
     /// Create digital signature of a transaction
-    pub fn sign(t: &Transaction, key: &Ed25519KeyPair) -> Signature {
-        let serialized_tx: Vec<u8> = bincode::serialize(t).unwrap();
-        key.sign(serialized_tx.as_slice())
+    pub fn sign(t: &Transaction, key: &Ed25519KeyPair, chain_id: &str) -> Signature {
+        key.sign(t.sighash(chain_id).as_ref())
     }
 
     /// Verify digital signature of a transaction, using public key instead of secret key
-    pub fn verify(t: &Transaction, public_key_ref: &[u8], sig_ref: &[u8]) -> bool {
+    pub fn verify(t: &Transaction, public_key_ref: &[u8], sig_ref: &[u8], chain_id: &str) -> bool {
         let peer_public_key = signature::UnparsedPublicKey::new(
             &signature::ED25519, 
             public_key_ref
         );
-        let serialized_tx: Vec<u8> = bincode::serialize(t).unwrap();
-        let res = peer_public_key.verify(serialized_tx.as_slice(), sig_ref);
+        let res = peer_public_key.verify(t.sighash(chain_id).as_ref(), sig_ref);
         match res {
             Ok(()) => {
                 true
@@ -233,9 +245,11 @@ impl Transaction {
         }
     }
 
+    //whether each input of tx is signed by the owner of the output it spends
     pub fn verify_owner(
         tx: &Transaction,  
         input_txs: Vec<&Transaction>, 
+        chain_id: &str,
     ) -> bool {
         if tx.inputs.len() != input_txs.len() {
             return false;
@@ -243,10 +257,18 @@ impl Transaction {
         for i in 0..tx.inputs.len() {
             let input = &tx.inputs[i];
             let input_tx = input_txs[i];
+            if input.tx_hash != input_tx.hash() {
+                return false;
+            }
+            let output = match input_tx.outputs.get(input.index as usize) {
+                Some(output) => output,
+                None => return false,
+            };
             if !Self::verify(
-                input_tx,
-                &input_tx.outputs[input.index as usize].public_key_ref,
+                tx,
+                &output.public_key_ref,
                 &input.sig_ref,
+                chain_id,
             ) {
                 return false;
             }
@@ -254,6 +276,16 @@ impl Transaction {
         true
     }
 
+    //whether each output pays the address of its public key
+    pub fn check_addresses(&self) -> bool {
+        self.outputs.iter().all(|output| {
+            match Address::from_public_key(&output.public_key_ref) {
+                Ok(address) => output.receiver_addr == address.into(),
+                Err(_) => false,
+            }
+        })
+    }
+
     pub fn get_related_hash(&self, flag: TxFlag) -> H256 {
         let mut tx = self.clone();
         tx.flag = flag;
@@ -274,6 +306,25 @@ impl Transaction {
         }
     }
 
+    //the initial tx of the index-th allocation of the genesis file, which is
+    //in the genesis state instead of a block; the index keeps equal allocations apart
+    pub fn create_allocation_tx(index: usize, allocation: &Allocation) -> Transaction {
+        let input = UtxoInput {
+            index: index as u32,
+            ..UtxoInput::default()
+        };
+        let output = UtxoOutput {
+            receiver_addr: allocation.get_address(),
+            value: allocation.value,
+            public_key_ref: allocation.get_public_key(),
+        };
+        Transaction {
+            inputs: vec![input],
+            outputs: vec![output],
+            flag: TxFlag::Initial,
+        }
+    }
+
     pub fn create_empty_tx(num_input: usize, num_output: usize) -> Transaction {
         let inputs: Vec<UtxoInput> = (0..num_input)
             .into_iter()
@@ -296,6 +347,22 @@ impl Transaction {
         senders: Vec<(&H256, &Ed25519KeyPair)>, //user_addr, user_key, sent_coin
         receivers: Vec<(&H256, &Ed25519KeyPair, usize)>, //user_addr, user_key, received_coin
         flag: TxFlag,
+        chain_id: &str,
+    ) -> Option<Transaction> {
+        let receivers: Vec<(&H256, &[u8], usize)> = receivers
+            .into_iter()
+            .map(|(addr, key, coins)| (addr, key.public_key().as_ref(), coins))
+            .collect();
+        Self::consume_to(utxos, senders, receivers, flag, chain_id)
+    }
+
+    //consume() for receivers known by their public key only, like the payees of a wallet
+    pub fn consume_to(
+        utxos: Vec<(&Transaction, usize)>, //tx, index 
+        senders: Vec<(&H256, &Ed25519KeyPair)>, //user_addr, user_key
+        receivers: Vec<(&H256, &[u8], usize)>, //user_addr, user_public_key, received_coin
+        flag: TxFlag,
+        chain_id: &str,
     ) -> Option<Transaction> {
 
         let mut inputs: Vec<UtxoInput> = vec![];
@@ -312,7 +379,7 @@ impl Transaction {
                         tx_hash: tx.hash(),
                         value: tx.outputs[index].value,
                         index: index as u32,
-                        sig_ref: Transaction::sign(tx, sender.1).as_ref().to_vec(),
+                        sig_ref: vec![],
                     }
                 }
                 &TxFlag::Domestic => {
@@ -321,7 +388,7 @@ impl Transaction {
                         tx_hash: tx.hash(),
                         value: tx.outputs[index].value,
                         index: index as u32,
-                        sig_ref: Transaction::sign(tx, sender.1).as_ref().to_vec(),
+                        sig_ref: vec![],
                     }
                 }
                 &TxFlag::Output => {
@@ -330,7 +397,7 @@ impl Transaction {
                         tx_hash: tx.hash(),
                         value: tx.outputs[index].value,
                         index: index as u32,
-                        sig_ref: Transaction::sign(tx, sender.1).as_ref().to_vec(),
+                        sig_ref: vec![],
                     }
                 }
                 &TxFlag::Reject => {
@@ -356,7 +423,7 @@ impl Transaction {
             let output  = UtxoOutput {
                 receiver_addr: x.0.clone(),
                 value: x.2 as u32,
-                public_key_ref: x.1.public_key().as_ref().to_vec(),
+                public_key_ref: x.1.to_vec(),
             };
             outputs.push(output);
             received_coins += x.2;
@@ -367,11 +434,18 @@ impl Transaction {
         }
         
 
-        Some(Transaction {
+        //the inputs sign the whole new tx, so it is only signed once complete
+        let mut tx = Transaction {
             inputs,
             outputs,
             flag,
-        })
+        };
+        for i in 0..utxos.len() {
+            if utxos[i].0.flag != TxFlag::Reject {
+                tx.inputs[i].sig_ref = Transaction::sign(&tx, senders[i].1, chain_id).as_ref().to_vec();
+            }
+        }
+        Some(tx)
                 
     }
 }
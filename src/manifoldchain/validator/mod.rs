@@ -34,6 +34,7 @@ use crate::{
     types::{
         hash::{Hashable, H256},
         merkle::MerkleTree,
+        sig_verifier::{SigJob, SigVerifier},
     },
 };
 use std::{
@@ -56,6 +57,7 @@ pub struct Validator {
     multichain: Multichain,
     mempool: Arc<Mutex<Mempool>>,
     config: Configuration,
+    sig_verifier: SigVerifier,
 }
 
 impl Clone for Validator {
@@ -64,6 +66,7 @@ impl Clone for Validator {
             multichain: self.multichain.clone(),
             mempool: Arc::clone(&self.mempool),
             config: self.config.clone(),
+            sig_verifier: self.sig_verifier.clone(),
         }
     }
 }
@@ -84,47 +87,67 @@ impl Validator {
     pub fn new(
         multichain: &Multichain,
         mempool: &Arc<Mutex<Mempool>>,
-        config: &Configuration
+        config: &Configuration,
+        sig_verifier: &SigVerifier,
     ) -> Self {
         Validator {
             multichain: multichain.clone(),
             mempool: Arc::clone(mempool),
             config: config.clone(),
+            sig_verifier: sig_verifier.clone(),
         }
     }
 
+    //the signature check of an input of tx by the owner of the utxo it spends
+    fn sig_job(tx_hash: &H256, index: usize, input: &UtxoInput, input_tx: &Transaction, message: &H256) -> SigJob {
+        SigJob {
+            tx_hash: *tx_hash,
+            input_index: index as u32,
+            public_key: input_tx.outputs[input.index as usize].public_key_ref.clone(),
+            message: message.as_ref().to_vec(),
+            signature: input.sig_ref.clone(),
+        }
+    }
+
+    //the utxo spent by the input at index of tx, whose owner must have signed
+    //tx; a signature already in the cache of the verifier is not checked again
     pub fn check_input_from_state(
-        input: & UtxoInput, 
-        state: & State
+        tx: & Transaction,
+        index: usize,
+        state: & State,
+        chain_id: &str,
+        verifier: &SigVerifier,
     ) -> Result<(Transaction, Option<Testimony>), FraudProof> 
     {
+        let input = &tx.inputs[index];
         match state.get(&(input.tx_hash.clone(), input.index)) {
             Some(item) => {
-                let tx = item.0.clone();
+                let input_tx = item.0.clone();
                 let tmy = item.1.clone();
-                let sig_vec = input.sig_ref.clone();
-                let index: usize = input.index as usize;
                 //handle reject-tx
-                if let TxFlag::Reject = tx.flag {
+                if let TxFlag::Reject = input_tx.flag {
                     //complete later
-                    return Ok((tx, tmy));
+                    return Ok((input_tx, tmy));
                 }
-                let output = tx.outputs.get(index).unwrap();
-                let pub_key = output.public_key_ref.clone();
-                match Transaction::verify(&tx, pub_key.as_slice(), sig_vec.as_slice()) {
-                    true => Ok((tx, tmy)),
+                let tx_hash = tx.hash();
+                let valid = verifier.is_verified(&tx_hash, index as u32) || {
+                    let job = Self::sig_job(&tx_hash, index, input, &input_tx, &tx.sighash(chain_id));
+                    verifier.verify(job)
+                };
+                match valid {
+                    true => Ok((input_tx, tmy)),
                     false => {
                         Err(FraudProof::WrongSignature(
                             WrongSignatureProof {
                                 shard_id: 0,
 
                                 invalid_block_hash: H256::default(),
-                                invalid_tx: Transaction::default(),
+                                invalid_tx: tx.clone(),
                                 invalid_tx_merkle_proof: vec![],
                                 invalid_index: 0,
 
                                 conflict_block_hash: H256::default(),
-                                conflict_tx: tx.clone(),
+                                conflict_tx: input_tx.clone(),
                                 conflict_tx_merkle_proof: vec![],
                                 conflict_index: 0,
                             }
@@ -140,6 +163,42 @@ impl Validator {
 
     
     
+    //verifies at once the signatures of the in-shard inputs of the domestic
+    //and input txs against the state, outside the chain lock, and caches the
+    //valid ones; false if one is invalid
+    pub fn verify_signatures(&self, txs: &[Transaction], state: &State) -> bool {
+        let mut jobs = vec![];
+        for tx in txs.iter() {
+            match tx.flag {
+                TxFlag::Domestic | TxFlag::Input => {}
+                _ => continue,
+            }
+            let tx_hash = tx.hash();
+            let mut sighash = None;
+            for (index, input) in tx.inputs.iter().enumerate() {
+                let shard_id = Self::get_shard_id(&input.sender_addr, self.config.shard_num);
+                if shard_id != self.config.shard_id
+                    || self.sig_verifier.is_verified(&tx_hash, index as u32) {
+                    continue;
+                }
+                //a missing utxo is reported when the tx is checked
+                let input_tx = match state.get(&(input.tx_hash, input.index)) {
+                    Some(item) => &item.0,
+                    None => continue,
+                };
+                if let TxFlag::Reject = input_tx.flag {
+                    continue;
+                }
+                if input_tx.outputs.get(input.index as usize).is_none() {
+                    continue;
+                }
+                let message = sighash.get_or_insert_with(|| tx.sighash(self.config.chain_id()));
+                jobs.push(Self::sig_job(&tx_hash, index, input, input_tx, message));
+            }
+        }
+        self.sig_verifier.verify_batch(jobs).into_iter().all(|x| x)
+    }
+
     pub fn get_shard_id(hash: &H256, shard_num: usize) -> usize {
         //one u8 can represent 256 shards
         let byte_size = shard_num / 256 + 1;
@@ -203,6 +262,15 @@ impl Validator {
 //        Ok(true)
 //    }
 
+    //the initial txs fund the users of an experiment, but with a genesis file
+    //the coins only come from its allocations, already in the genesis state
+    pub fn check_initial_tx(config: &Configuration) -> Result<bool, FraudProof> {
+        match config.genesis {
+            Some(_) => Err(FraudProof::UnsolvedFault),
+            None => Ok(true),
+        }
+    }
+
     pub fn validate_tx(
         &self, 
         tx: &Transaction,
@@ -217,13 +285,18 @@ impl Validator {
           //          Err(_) => return Err(FraudProof::UnsolvedFault),
           //      }
                 //we dont verify the initial transactions because it is for experiment setting
-            return Ok(true);
+            return Self::check_initial_tx(&self.config);
         }
 
         if let TxFlag::Empty = tx.flag {
             return Ok(true);
         }
 
+        //0. an output pays the address of its key, which alone can spend it
+        if !tx.check_addresses() {
+            return Err(FraudProof::UnsolvedFault);
+        }
+
         //1. check whether the tx belongs to the node accroding to the shard id
         let tx_hash = tx.hash();
         if !Self::check_tx_ownership(tx, self.config.shard_id, self.config.shard_num) {
@@ -271,16 +344,22 @@ impl Validator {
                         .get_tx_in_longest_chain(&tx_hash) {
                 return Err(FraudProof::UnsolvedFault);
             }
+            //verify the signatures when the tx is relayed, so they are cached
+            //when a block includes it
+            let longest = self.multichain.get_longest_chain_hash();
+            if let Some(state) = self.multichain.get_spent_state(&longest, std::slice::from_ref(tx)) {
+                if !self.verify_signatures(std::slice::from_ref(tx), &state) {
+                    return Err(FraudProof::UnsolvedFault);
+                }
+            }
             Ok(true)
         } else {
             let parent_hash = parent.unwrap();
             //4. check whether the tx is creating the initial balance
             //3. If it is a tx from block, check the double spending and signatrue
-            let states = self.multichain.get_states();
-
-            let state = states
-                .get(parent_hash)
-                .unwrap();
+            let state = self.multichain
+                .get_spent_state(parent_hash, std::slice::from_ref(tx))
+                .ok_or(FraudProof::UnsolvedFault)?;
             match self.check_tx_from_state(
                 tx,
                 tmy,
@@ -343,16 +422,18 @@ impl Validator {
                 return Err(FraudProof::UnsolvedFault);              
             }
         }
-        // let states = self.multichain.get_states();
-
-        // let state = states
-        //     .get(parent)
-        //     .unwrap();
+        //verify the signatures of all the txs in one batch before checking
+        //each tx, which then finds them and the parent state in the caches;
+        //the check of the tx with an invalid signature gives the proof
+        let txs = block.get_txs_ref().unwrap();
+        let signatures_valid = match self.multichain.get_spent_state(parent, txs) {
+            Some(state) => self.verify_signatures(txs, &state),
+            None => true,
+        };
 
 
         //check whether the transactions inside are invalid
         let mut set: HashMap<H256, (Transaction, usize)> = HashMap::new();
-        let txs = block.get_txs_ref().unwrap();
         let tmys = block.get_tmys().unwrap();
         for i in 0..txs.len() {
             let tx_ref = &txs[i];
@@ -470,6 +551,10 @@ impl Validator {
                 }
             }
         }
+        if !signatures_valid {
+            info!("validation: invalid signature in block {:?}", blk_hash);
+            return Err(FraudProof::UnsolvedFault);
+        }
         Ok(true)
     }
 
@@ -569,7 +654,7 @@ impl Validator {
     ) -> Result<bool, FraudProof> {
         let flag = tx.flag.clone();
         match flag {
-            TxFlag::Initial => Ok(true),
+            TxFlag::Initial => Self::check_initial_tx(&self.config),
             TxFlag::Empty => Ok(true),
             TxFlag::Domestic => self.check_domestic_input_tx_from_state(
                 tx,
@@ -612,7 +697,7 @@ impl Validator {
         state: &State
     ) -> Result<bool, FraudProof> {
         let mut set: HashMap<H256, bool> = HashMap::new();
-        for input in tx.inputs.iter() {
+        for (index, input) in tx.inputs.iter().enumerate() {
             let shard_id = Self::get_shard_id(
                 &input.sender_addr,
                 self.config.shard_num
@@ -645,7 +730,13 @@ impl Validator {
                 }
             }
             //check whether the coins exit in the state
-            match Self::check_input_from_state(input, state) {
+            match Self::check_input_from_state(
+                tx,
+                index,
+                state,
+                self.config.chain_id(),
+                &self.sig_verifier,
+            ) {
                 Ok((input_tx, possible_tmy)) => {
                     match possible_tmy {
                         Some(tmy) => {
@@ -875,10 +966,13 @@ impl Validator {
             FraudProof::WrongSignature(mut fp) => {
                 fp.shard_id = self.config.shard_id as u32;
                 fp.invalid_tx = tx.clone();                            
-                //complete the information of conflict tx
-                let conflict_tx_hash = tx.hash();
-                let (conflict_block, conflict_index) = self.multichain
-                    .get_block_with_tx(&conflict_tx_hash).unwrap();
+                //complete the information of conflict tx, the one holding the spent utxo
+                let conflict_tx_hash = fp.conflict_tx.hash();
+                let (conflict_block, conflict_index) = match self.multichain
+                    .get_block_with_tx(&conflict_tx_hash) {
+                    Some(res) => res,
+                    None => return FraudProof::UnsolvedFault,
+                };
                 fp.conflict_block_hash = conflict_block.hash();
                 let conflict_tx_merkle_proof = conflict_block
                     .get_tx_merkle_proof(conflict_index).unwrap();
@@ -1105,7 +1199,7 @@ impl Validator {
         input_coins != output_coins
     }
 
-    fn verify_wrongsig_fp(&self, fp: &WrongSignatureProof) -> bool {
+    pub fn verify_wrongsig_fp(&self, fp: &WrongSignatureProof) -> bool {
         let shard_id = fp.shard_id as usize;
         // let invalid_block = match self.multichain
         //     .get_block_by_shard(&fp.invalid_block_hash, shard_id) {
@@ -1152,9 +1246,10 @@ impl Validator {
                     Some(output) => {
                         let pub_key = output.public_key_ref.clone();
                         return !Transaction::verify(
-                            &fp.conflict_tx, 
+                            &fp.invalid_tx, 
                             pub_key.as_slice(), 
-                            sig_vec.as_slice()
+                            sig_vec.as_slice(),
+                            self.config.chain_id(),
                         ); 
                     }
                     None => return true,
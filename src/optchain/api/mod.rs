@@ -1,31 +1,32 @@
+pub mod query;
+pub mod rpc;
+pub mod stream;
+
 use serde::Serialize;
 use crate::{
     optchain::{
-        multichain::Multichain,
-        miner::Handle as MinerHandle,
-        network::{
-            server::Handle as NetworkServerHandle,
-            message::Message,
-        },
-        mempool::Mempool,
+        miner,
+        network::message::Message,
+        events::EventBus,
+        metrics::{Gauges, Metrics},
         // validator::{
         //     Validator,
         // },
-        configuration::Configuration,
         block::Info,
     },
     types::{
         hash::{
             H256,
             Hashable,
-        }
+        },
+        jsonrpc,
     },
 };
 
 use log::{info};
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
+    sync::Arc,
     thread,
     fs::File,
     io::{Write},
@@ -41,11 +42,9 @@ use chrono::{DateTime, Local};
 #[allow(dead_code)]
 pub struct Server {
     handle: HTTPServer,
-    miner: MinerHandle,
-    network: NetworkServerHandle,
-    multichain: Arc<Mutex<Multichain>>,
-    mempool: Arc<Mutex<Mempool>>,
-    config: Configuration,
+    ctx: rpc::RpcContext,
+    metrics: Arc<Metrics>,
+    events: Arc<EventBus>,
 }
 
 #[derive(Serialize)]
@@ -78,29 +77,31 @@ macro_rules! respond_json {
 impl Server {
     pub fn start(
         addr: std::net::SocketAddr,
-        miner: &MinerHandle,
-        network: &NetworkServerHandle,
-        multichain: &Arc<Mutex<Multichain>>,
-        mempool: &Arc<Mutex<Mempool>>,
-        config: &Configuration,
+        ctx: rpc::RpcContext,
+        metrics: &Arc<Metrics>,
+        events: &Arc<EventBus>,
     ) {
         let handle = HTTPServer::http(&addr).unwrap();
         let server = Self {
             handle,
-            miner: miner.clone(),
-            network: network.clone(),
-            multichain: Arc::clone(multichain),
-            mempool: Arc::clone(mempool),
-            config: config.clone(),
+            ctx,
+            metrics: Arc::clone(metrics),
+            events: Arc::clone(events),
         };
         thread::spawn(move || {
             for req in server.handle.incoming_requests() {
-                let miner = server.miner.clone();
-                let network = server.network.clone();
-                let multichain = Arc::clone(&server.multichain);
-                // let multichain = server.multichain.clone();
-                // let mempool = Arc::clone(&server.mempool);
-                let config = server.config.clone();
+                let rpc::RpcContext {
+                    miner,
+                    network,
+                    multichain,
+                    mempool,
+                    symbolpool,
+                    sync_manager,
+                    templates,
+                    config,
+                } = server.ctx.clone();
+                let metrics = Arc::clone(&server.metrics);
+                let events = Arc::clone(&server.events);
                 // let validator = Validator::new(
                 //     &multichain,
                 //     &mempool,
@@ -117,9 +118,73 @@ impl Server {
                         }
                     };
                     match url.path() {
+                        //JSON-RPC 2.0, a request or a batch in the body of a POST
+                        "/rpc" => {
+                            let ctx = rpc::RpcContext {
+                                miner,
+                                network,
+                                multichain,
+                                mempool,
+                                symbolpool,
+                                sync_manager,
+                                templates,
+                                config,
+                            };
+                            jsonrpc::serve(req, |method, params| rpc::dispatch(&ctx, method, params));
+                        }
+                        //the counters and gauges of the node in the Prometheus text format
+                        "/metrics" => {
+                            let mempool_stats = mempool.lock().unwrap().get_stats();
+                            let gauges = Gauges::collect(
+                                &metrics.lock_multichain(&multichain),
+                                mempool_stats,
+                                &symbolpool.lock().unwrap(),
+                            );
+                            let content_type = "Content-Type: text/plain; version=0.0.4"
+                                .parse::<Header>()
+                                .unwrap();
+                            let resp = Response::from_string(metrics.render(&gauges))
+                                .with_header(content_type);
+                            req.respond(resp).unwrap();
+                        }
+                        //the chain events as they happen, ?chain=proposer,availability&shard-id=1&format=sse|jsonl
+                        "/events" => {
+                            let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+                            let filter = stream::parse_filter(
+                                params.get("chain").map(|x| x.as_str()),
+                                params.get("shard-id").map(|x| x.as_str()),
+                            );
+                            let format = stream::parse_format(params.get("format").map(|x| x.as_str()));
+                            let (filter, format) = match (filter, format) {
+                                (Ok(filter), Ok(format)) => (filter, format),
+                                (Err(e), _) | (_, Err(e)) => {
+                                    respond_result!(req, false, e);
+                                    return;
+                                }
+                            };
+                            let receiver = events.subscribe(filter);
+                            let mut writer = req.into_writer();
+                            if let Err(e) = stream::serve(&mut writer, &receiver, format, stream::KEEPALIVE_INTERVAL) {
+                                info!("Event stream closed: {}", e);
+                            }
+                        }
                         "/miner/start" => {
                             let params = url.query_pairs();
                             let params: HashMap<_, _> = params.into_owned().collect();
+                            //the hash rate budget is optional, it is kept when absent
+                            if let Some(hash_rate) = params.get("hash-rate") {
+                                match hash_rate.parse::<u64>() {
+                                    Ok(v) => miner.set_hash_rate(v),
+                                    Err(e) => {
+                                        respond_result!(
+                                            req,
+                                            false,
+                                            format!("error parsing hash rate: {}", e)
+                                        );
+                                        return;
+                                    }
+                                }
+                            }
                             let lambda = match params.get("lambda") {
                                 Some(v) => v,
                                 None => {
@@ -141,14 +206,167 @@ impl Server {
                             miner.start(lambda);
                             respond_result!(req, true, "ok");
                         }
+                        "/miner/hash-rate" => {
+                            respond_json!(req, miner.get_hash_rate_stats());
+                        }
                         "/miner/end" => {
                             miner.exit();
                             respond_result!(req, true, "ok");
                         }
+                        "/miner/template" => {
+                            let block = miner::assemble_block(&multichain, &mempool, &config);
+                            let template = templates
+                                .lock()
+                                .unwrap()
+                                .insert(block, &config);
+                            respond_json!(req, template);
+                        }
+                        "/miner/submit" => {
+                            let params = url.query_pairs();
+                            let params: HashMap<_, _> = params.into_owned().collect();
+                            let id = match params.get("template").map(|v| v.parse::<u64>()) {
+                                Some(Ok(v)) => v,
+                                Some(Err(e)) => {
+                                    respond_result!(req, false, format!("error parsing template: {}", e));
+                                    return;
+                                }
+                                None => {
+                                    respond_result!(req, false, "missing template");
+                                    return;
+                                }
+                            };
+                            let nonce = match params.get("nonce").map(|v| v.parse::<u32>()) {
+                                Some(Ok(v)) => v,
+                                Some(Err(e)) => {
+                                    respond_result!(req, false, format!("error parsing nonce: {}", e));
+                                    return;
+                                }
+                                None => {
+                                    respond_result!(req, false, "missing nonce");
+                                    return;
+                                }
+                            };
+                            let result = templates
+                                .lock()
+                                .unwrap()
+                                .submit(id, nonce, &config);
+                            match result.and_then(|message| miner.submit(message)) {
+                                Ok(()) => respond_result!(req, true, "ok"),
+                                Err(e) => respond_result!(req, false, e),
+                            }
+                        }
+                        "/mempool/status" => {
+                            let stats = mempool
+                                .lock()
+                                .unwrap()
+                                .get_stats();
+                            respond_json!(req, stats);
+                        }
+                        "/network/sync" => {
+                            let progress = sync_manager
+                                .lock()
+                                .unwrap()
+                                .get_progress(&multichain.lock().unwrap());
+                            respond_json!(req, progress);
+                        }
+                        "/network/peers" => {
+                            respond_json!(req, network.get_peers());
+                        }
                         "/network/ping" => {
                             network.broadcast(Message::Ping(String::from("Test ping")));
                             respond_result!(req, true, "ok");
                         }
+                        "/blockchain/block" => {
+                            let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+                            let hash = match params.get("hash").map(|v| query::parse_hash(v)) {
+                                Some(Ok(v)) => v,
+                                Some(Err(e)) => {
+                                    respond_result!(req, false, e);
+                                    return;
+                                }
+                                None => {
+                                    respond_result!(req, false, "missing hash");
+                                    return;
+                                }
+                            };
+                            let view = query::get_block(&multichain.lock().unwrap(), &hash);
+                            match view {
+                                Some(view) => respond_json!(req, view),
+                                None => respond_result!(req, false, "block not found"),
+                            }
+                        }
+                        "/blockchain/tx-block" => {
+                            let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+                            let hash = match params.get("hash").map(|v| query::parse_hash(v)) {
+                                Some(Ok(v)) => v,
+                                Some(Err(e)) => {
+                                    respond_result!(req, false, e);
+                                    return;
+                                }
+                                None => {
+                                    respond_result!(req, false, "missing hash");
+                                    return;
+                                }
+                            };
+                            match query::get_tx_block(&multichain, &mempool, &hash) {
+                                Some(view) => respond_json!(req, view),
+                                None => respond_result!(req, false, "tx block not found"),
+                            }
+                        }
+                        "/blockchain/tips" => {
+                            let tips = query::get_tips(&multichain.lock().unwrap());
+                            respond_json!(req, tips);
+                        }
+                        "/blockchain/chain" => {
+                            let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+                            let chain = match params.get("chain") {
+                                Some(v) => query::parse_chain(v, params.get("shard-id").map(|x| x.as_str())),
+                                None => Err(String::from("missing chain")),
+                            };
+                            let chain = match chain {
+                                Ok(v) => v,
+                                Err(e) => {
+                                    respond_result!(req, false, e);
+                                    return;
+                                }
+                            };
+                            let offset = params.get("offset").map(|v| v.parse::<usize>()).unwrap_or(Ok(0));
+                            let limit = params
+                                .get("limit")
+                                .map(|v| v.parse::<usize>())
+                                .unwrap_or(Ok(query::DEFAULT_PAGE_SIZE));
+                            let (offset, limit) = match (offset, limit) {
+                                (Ok(offset), Ok(limit)) => (offset, limit),
+                                (Err(e), _) | (_, Err(e)) => {
+                                    respond_result!(req, false, format!("error parsing offset or limit: {}", e));
+                                    return;
+                                }
+                            };
+                            let page = query::get_chain_page(&multichain.lock().unwrap(), &chain, offset, limit);
+                            match page {
+                                Ok(page) => respond_json!(req, page),
+                                Err(e) => respond_result!(req, false, e),
+                            }
+                        }
+                        "/symbolpool/status" => {
+                            let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+                            let cmt_root = match params.get("cmt").map(|v| query::parse_hash(v)) {
+                                Some(Ok(v)) => v,
+                                Some(Err(e)) => {
+                                    respond_result!(req, false, e);
+                                    return;
+                                }
+                                None => {
+                                    respond_result!(req, false, "missing cmt");
+                                    return;
+                                }
+                            };
+                            let status = query::get_symbol_status(&symbolpool.lock().unwrap(), &cmt_root);
+                            match status {
+                                Some(status) => respond_json!(req, status),
+                                None => respond_result!(req, false, "symbols of the cmt were not requested"),
+                            }
+                        }
                         "/blockchain/log" => {
                             let path = format!("./log/optchain/exper_{}/iter_{}/{}.txt", config.exper_number, config.exper_iter, config.shard_id*config.shard_size+config.node_id);
                             let mut output = File::create(path).unwrap();
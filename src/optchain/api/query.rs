@@ -0,0 +1,298 @@
+use serde::{Serialize, Deserialize};
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use crate::{
+    optchain::{
+        block::{
+            BlockHeader,
+            Info,
+            transaction_block::TransactionBlock,
+            versa_block::VersaBlock,
+        },
+        mempool::Mempool,
+        multichain::{Multichain, ChainType},
+        symbolpool::SymbolPool,
+    },
+    types::hash::{H256, Hashable},
+};
+
+//the blocks of a chain listing when no limit is given, and at most
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+//a full 64 digit hex hash, as the views print them
+pub fn parse_hash(s: &str) -> Result<H256, String> {
+    let bytes = hex::decode(s).map_err(|e| format!("invalid hash {}: {}", s, e))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| format!("invalid hash {}: expected 32 bytes", s))?;
+    Ok(bytes.into())
+}
+
+//proposer, ordering or availability, the latter with a shard id
+pub fn parse_chain(name: &str, shard_id: Option<&str>) -> Result<ChainType, String> {
+    match name {
+        "proposer" => Ok(ChainType::Proposer),
+        "ordering" => Ok(ChainType::Ordering),
+        "availability" => {
+            let shard_id = shard_id.ok_or(String::from("missing shard id"))?;
+            let shard_id = shard_id
+                .parse::<u32>()
+                .map_err(|e| format!("error parsing shard id: {}", e))?;
+            Ok(ChainType::Availability(shard_id))
+        }
+        _ => Err(format!("unknown chain {}, expected proposer, ordering or availability", name)),
+    }
+}
+
+fn to_millis(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ShardHash {
+    pub hash: String,
+    pub shard_id: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HeaderView {
+    pub shard_id: usize,
+    pub prop_parent: String,
+    pub inter_parent: String,
+    pub global_parents: Vec<ShardHash>,
+    pub order_parent: String,
+    pub prop_root: String,
+    pub avai_root: String,
+    pub order_root: String,
+    pub cmt_root: String,
+    pub timestamp: u128, //ms since the epoch
+    pub extra_nonce: u32,
+}
+
+impl From<&BlockHeader> for HeaderView {
+    fn from(header: &BlockHeader) -> Self {
+        HeaderView {
+            shard_id: header.get_shard_id(),
+            prop_parent: header.get_prop_parent().to_string(),
+            inter_parent: header.get_inter_parent().to_string(),
+            global_parents: header
+                .get_global_parents()
+                .iter()
+                .map(|(hash, shard_id)| ShardHash { hash: hash.to_string(), shard_id: *shard_id })
+                .collect(),
+            order_parent: header.get_order_parent().to_string(),
+            prop_root: header.get_prop_root().to_string(),
+            avai_root: header.get_avai_root().to_string(),
+            order_root: header.get_order_root().to_string(),
+            cmt_root: header.get_cmt_root().to_string(),
+            timestamp: to_millis(header.get_timestamp()),
+            extra_nonce: header.get_extra_nonce(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockType {
+    Proposer,
+    ExclusiveAvailability,
+    InclusiveAvailability,
+    Ordering,
+}
+
+//where a block sits in one of the chains storing it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChainPosition {
+    pub chain: ChainType,
+    pub height: usize,
+    pub in_longest_chain: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockView {
+    pub hash: String,
+    pub block_type: BlockType,
+    pub nonce: u32,
+    pub header: HeaderView,
+    pub positions: Vec<ChainPosition>,
+    pub tx_blocks: Vec<String>, //proposed or made available, empty for ordering blocks
+    pub confirmed_avai_set: Vec<ShardHash>, //only for ordering blocks
+}
+
+pub fn get_block(multichain: &Multichain, hash: &H256) -> Option<BlockView> {
+    let chains = multichain.find_block(hash);
+    let block = multichain.get_block(chains.first()?, hash)?;
+    let positions = chains
+        .into_iter()
+        .filter_map(|chain| {
+            let height = multichain.get_block_height(&chain, hash)?;
+            let path = multichain.all_blocks_in_longest_chain(&chain);
+            Some(ChainPosition {
+                chain,
+                height,
+                in_longest_chain: path.get(height) == Some(hash),
+            })
+        })
+        .collect();
+    let block_type = match block {
+        VersaBlock::PropBlock(_) => BlockType::Proposer,
+        VersaBlock::ExAvaiBlock(_) => BlockType::ExclusiveAvailability,
+        VersaBlock::InAvaiBlock(_) => BlockType::InclusiveAvailability,
+        VersaBlock::OrderBlock(_) => BlockType::Ordering,
+    };
+    let tx_blocks = match block {
+        VersaBlock::OrderBlock(_) => vec![],
+        _ => block.get_tx_blocks().iter().map(|x| x.hash().to_string()).collect(),
+    };
+    let confirmed_avai_set = block
+        .get_confirmed_avai_set()
+        .unwrap_or_default()
+        .iter()
+        .map(|(hash, shard_id)| ShardHash { hash: hash.to_string(), shard_id: *shard_id as usize })
+        .collect();
+    Some(BlockView {
+        hash: hash.to_string(),
+        block_type,
+        nonce: block.get_nonce(),
+        header: HeaderView::from(&block.get_header()),
+        positions,
+        tx_blocks,
+        confirmed_avai_set,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TxBlockView {
+    pub hash: String,
+    pub nonce: u32,
+    pub header: HeaderView,
+    pub in_mempool: bool,
+    pub in_longest_proposer_chain: bool,
+}
+
+//a tx block waiting in the mempool or stored in the chains
+pub fn get_tx_block(
+    multichain: &Arc<Mutex<Multichain>>,
+    mempool: &Arc<Mutex<Mempool>>,
+    hash: &H256,
+) -> Option<TxBlockView> {
+    let pending = mempool.lock().unwrap().get_tx_blk(hash);
+    let (stored, in_longest_proposer_chain) = {
+        let multichain = multichain.lock().unwrap();
+        (
+            multichain.get_tx_blk(hash),
+            multichain.get_tx_blk_in_longest_proposer_chain(hash).is_some(),
+        )
+    };
+    let in_mempool = pending.is_some();
+    let tx_blk: TransactionBlock = pending.or(stored)?;
+    Some(TxBlockView {
+        hash: hash.to_string(),
+        nonce: tx_blk.get_nonce(),
+        header: HeaderView::from(&tx_blk.get_header()),
+        in_mempool,
+        in_longest_proposer_chain,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TipView {
+    pub chain: ChainType,
+    pub hash: String,
+    pub height: usize,
+}
+
+pub fn get_tips(multichain: &Multichain) -> Vec<TipView> {
+    multichain
+        .get_chain_types()
+        .into_iter()
+        .filter_map(|chain| {
+            let tip = multichain.get_tip(&chain)?;
+            Some(TipView {
+                chain,
+                hash: tip.to_string(),
+                height: multichain.get_chain_height(&chain)?,
+            })
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChainEntry {
+    pub height: usize,
+    pub hash: String,
+    pub timestamp: u128, //ms since the epoch
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChainPage {
+    pub chain: ChainType,
+    pub height: usize, //of the tip
+    pub offset: usize,
+    pub blocks: Vec<ChainEntry>,
+}
+
+//the blocks of the longest chain from the given height on, genesis being 0
+pub fn get_chain_page(
+    multichain: &Multichain,
+    chain: &ChainType,
+    offset: usize,
+    limit: usize,
+) -> Result<ChainPage, String> {
+    let height = multichain
+        .get_chain_height(chain)
+        .ok_or(format!("unknown chain {:?}", chain))?;
+    let blocks = multichain
+        .all_blocks_in_longest_chain(chain)
+        .into_iter()
+        .enumerate()
+        .skip(offset)
+        .take(limit.min(MAX_PAGE_SIZE))
+        .filter_map(|(height, hash)| {
+            let block = multichain.get_block(chain, &hash)?;
+            Some(ChainEntry {
+                height,
+                hash: hash.to_string(),
+                timestamp: to_millis(block.get_timestamp()),
+            })
+        })
+        .collect();
+    Ok(ChainPage {
+        chain: *chain,
+        height,
+        offset,
+        blocks,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SymbolStatus {
+    pub cmt_root: String,
+    pub shard_id: Option<usize>,
+    pub requested: Vec<usize>,
+    pub missing: Vec<usize>,
+    pub available: bool, //every requested symbol was received
+}
+
+//None if the symbols of the cmt were never requested
+pub fn get_symbol_status(symbolpool: &SymbolPool, cmt_root: &H256) -> Option<SymbolStatus> {
+    let requested = symbolpool.get_requested_indexs(cmt_root)?;
+    let missing: Vec<usize> = symbolpool
+        .get_unreceived_symbols(cmt_root)
+        .ok()?
+        .iter()
+        .map(|x| x.get_index())
+        .collect();
+    Some(SymbolStatus {
+        cmt_root: cmt_root.to_string(),
+        shard_id: symbolpool.get_cmt_shard(cmt_root),
+        requested,
+        available: missing.is_empty(),
+        missing,
+    })
+}
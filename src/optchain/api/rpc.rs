@@ -0,0 +1,174 @@
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use crate::{
+    optchain::{
+        api::query,
+        block::{Info, transaction_block::TransactionBlock},
+        configuration::Configuration,
+        mempool::Mempool,
+        miner::{
+            self,
+            Handle as MinerHandle,
+            template::TemplatePool,
+        },
+        multichain::Multichain,
+        transaction::Transaction,
+        network::{
+            message::Message,
+            relay::RelayClass,
+            server::Handle as NetworkServerHandle,
+            sync::SyncManager,
+            worker,
+        },
+        symbolpool::SymbolPool,
+    },
+    types::{
+        hash::{H256, Hashable},
+        jsonrpc::{Params, RpcError, to_result, METHOD_NOT_FOUND},
+    },
+};
+
+//the state the methods act on, shared with the REST endpoints
+#[derive(Clone)]
+pub struct RpcContext {
+    pub miner: MinerHandle,
+    pub network: NetworkServerHandle,
+    pub multichain: Arc<Mutex<Multichain>>,
+    pub mempool: Arc<Mutex<Mempool>>,
+    pub symbolpool: Arc<Mutex<SymbolPool>>,
+    pub sync_manager: Arc<Mutex<SyncManager>>,
+    pub templates: Arc<Mutex<TemplatePool>>,
+    pub config: Configuration,
+}
+
+fn hash_param(params: &Params, name: &str, position: usize) -> Result<H256, RpcError> {
+    let hash: String = params.require(name, position)?;
+    query::parse_hash(&hash).map_err(RpcError::invalid_params)
+}
+
+pub fn dispatch(ctx: &RpcContext, method: &str, params: &Params) -> Result<Value, RpcError> {
+    match method {
+        "miner_start" => {
+            let lambda: u64 = params.get("lambda", 0)?.unwrap_or(0);
+            if let Some(hash_rate) = params.get::<u64>("hash_rate", 1)? {
+                ctx.miner.set_hash_rate(hash_rate);
+            }
+            ctx.miner.start(lambda);
+            Ok(json!(true))
+        }
+        "miner_stop" => {
+            ctx.miner.exit();
+            Ok(json!(true))
+        }
+        "miner_hashRate" => to_result(ctx.miner.get_hash_rate_stats()),
+        "miner_getTemplate" => {
+            let block = miner::assemble_block(&ctx.multichain, &ctx.mempool, &ctx.config);
+            let template = ctx.templates
+                .lock()
+                .unwrap()
+                .insert(block, &ctx.config);
+            to_result(template)
+        }
+        "miner_submit" => {
+            let id: u64 = params.require("template", 0)?;
+            let nonce: u32 = params.require("nonce", 1)?;
+            let message = ctx.templates
+                .lock()
+                .unwrap()
+                .submit(id, nonce, &ctx.config)
+                .map_err(RpcError::rejected)?;
+            ctx.miner.submit(message).map_err(RpcError::internal)?;
+            Ok(json!(true))
+        }
+        "chain_getBlock" => {
+            let hash = hash_param(params, "hash", 0)?;
+            let view = query::get_block(&ctx.multichain.lock().unwrap(), &hash);
+            to_result(view.ok_or(RpcError::not_found("block not found"))?)
+        }
+        "chain_getTxBlock" => {
+            let hash = hash_param(params, "hash", 0)?;
+            let view = query::get_tx_block(&ctx.multichain, &ctx.mempool, &hash);
+            to_result(view.ok_or(RpcError::not_found("tx block not found"))?)
+        }
+        "chain_getTips" => to_result(query::get_tips(&ctx.multichain.lock().unwrap())),
+        "chain_getChain" => {
+            let chain: String = params.require("chain", 0)?;
+            let shard_id: Option<u32> = params.get("shard_id", 1)?;
+            let offset: usize = params.get("offset", 2)?.unwrap_or(0);
+            let limit: usize = params.get("limit", 3)?.unwrap_or(query::DEFAULT_PAGE_SIZE);
+            let shard_id = shard_id.map(|x| x.to_string());
+            let chain = query::parse_chain(&chain, shard_id.as_deref())
+                .map_err(RpcError::invalid_params)?;
+            let page = query::get_chain_page(&ctx.multichain.lock().unwrap(), &chain, offset, limit);
+            to_result(page.map_err(RpcError::not_found)?)
+        }
+        "mempool_status" => to_result(ctx.mempool.lock().unwrap().get_stats()),
+        "symbol_status" => {
+            let cmt_root = hash_param(params, "cmt", 0)?;
+            let status = query::get_symbol_status(&ctx.symbolpool.lock().unwrap(), &cmt_root);
+            to_result(status.ok_or(RpcError::not_found("symbols of the cmt were not requested"))?)
+        }
+        "net_peers" => to_result(ctx.network.get_peers()),
+        "net_ping" => {
+            ctx.network.broadcast(Message::Ping(String::from("Test ping")));
+            Ok(json!(true))
+        }
+        "net_sync" => {
+            let progress = ctx.sync_manager
+                .lock()
+                .unwrap()
+                .get_progress(&ctx.multichain.lock().unwrap());
+            to_result(progress)
+        }
+        //a tx block encoded with bincode as hex, checked as one from a peer, accepted
+        //into the mempool and relayed
+        "tx_submit" => {
+            let raw: String = params.require("raw", 0)?;
+            let bytes = hex::decode(&raw)
+                .map_err(|e| RpcError::invalid_params(format!("invalid raw: {}", e)))?;
+            let tx_blk: TransactionBlock = bincode::deserialize(&bytes)
+                .map_err(|e| RpcError::invalid_params(format!("invalid tx block: {}", e)))?;
+            let hash = tx_blk.hash();
+            let accepted = worker::accept_tx_blk(&ctx.multichain, &ctx.mempool, &tx_blk, &ctx.config)
+                .map_err(RpcError::rejected)?;
+            if !accepted {
+                return Err(RpcError::rejected("tx block already known or refused by the mempool"));
+            }
+            let shard_id = tx_blk.get_shard_id();
+            ctx.network.relay(Message::TxBlocks(vec![tx_blk]), RelayClass::TxBlock(shard_id));
+            ctx.miner.update();
+            Ok(json!(hash.to_string()))
+        }
+        //a signed transaction encoded with bincode as hex, packed into the next tx
+        //blocks of this node; nodes keep no utxo set, so its inputs are not looked up
+        "tx_submitTransaction" => {
+            let raw: String = params.require("raw", 0)?;
+            let bytes = hex::decode(&raw)
+                .map_err(|e| RpcError::invalid_params(format!("invalid raw: {}", e)))?;
+            let tx: Transaction = bincode::deserialize(&bytes)
+                .map_err(|e| RpcError::invalid_params(format!("invalid transaction: {}", e)))?;
+            if tx.inputs.is_empty() || tx.outputs.is_empty() {
+                return Err(RpcError::rejected("a transaction needs inputs and outputs"));
+            }
+            let sent: u64 = tx.inputs.iter().map(|x| x.value as u64).sum();
+            let received: u64 = tx.outputs.iter().map(|x| x.value as u64).sum();
+            if sent != received {
+                return Err(RpcError::rejected(format!("{} coins in but {} out", sent, received)));
+            }
+            let hash = tx.hash();
+            if !ctx.mempool.lock().unwrap().insert_tx(tx) {
+                return Err(RpcError::rejected("transaction already pending or too many are"));
+            }
+            Ok(json!(hash.to_string()))
+        }
+        //the chain and the shard of a node, which wallets need to sign their txs
+        "node_getInfo" => Ok(json!({
+            "protocol": "optchain",
+            "chain_id": ctx.config.chain_id(),
+            "shard_id": ctx.config.shard_id,
+            "shard_num": ctx.config.shard_num,
+            "node_id": ctx.config.node_id,
+        })),
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("method {} not found", method))),
+    }
+}
@@ -0,0 +1,94 @@
+use crossbeam::channel::{Receiver, RecvTimeoutError};
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+use crate::optchain::events::{ChainEvent, EventFilter};
+
+//the chain events streamed to a client, as server-sent events or JSON lines
+
+//a comment or an empty line is sent when there is no event for this long, so
+//that the proxies keep the connection open and a gone client is noticed
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    Sse,
+    JsonLines,
+}
+
+impl StreamFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            StreamFormat::Sse => "text/event-stream",
+            StreamFormat::JsonLines => "application/x-ndjson",
+        }
+    }
+
+    fn encode(&self, event: &ChainEvent) -> String {
+        let json = serde_json::to_string(event).unwrap();
+        match self {
+            StreamFormat::Sse => format!("data: {}\n\n", json),
+            StreamFormat::JsonLines => format!("{}\n", json),
+        }
+    }
+
+    fn keepalive(&self) -> &'static str {
+        match self {
+            StreamFormat::Sse => ": keepalive\n\n",
+            StreamFormat::JsonLines => "\n",
+        }
+    }
+}
+
+//sse when not given
+pub fn parse_format(format: Option<&str>) -> Result<StreamFormat, String> {
+    match format {
+        None | Some("sse") => Ok(StreamFormat::Sse),
+        Some("jsonl") => Ok(StreamFormat::JsonLines),
+        Some(x) => Err(format!("unknown format {}, expected sse or jsonl", x)),
+    }
+}
+
+//a comma separated list of chains, and a shard id
+pub fn parse_filter(chains: Option<&str>, shard_id: Option<&str>) -> Result<EventFilter, String> {
+    let chains: Vec<String> = match chains {
+        Some(chains) => chains.split(',').map(|x| x.trim().to_string()).collect(),
+        None => vec![],
+    };
+    for chain in chains.iter() {
+        if !["proposer", "availability", "ordering"].contains(&chain.as_str()) {
+            return Err(format!("unknown chain {}, expected proposer, ordering or availability", chain));
+        }
+    }
+    let shard_id = match shard_id {
+        Some(x) => Some(x.parse::<u32>().map_err(|e| format!("error parsing shard id: {}", e))?),
+        None => None,
+    };
+    Ok(EventFilter { chains, shard_id })
+}
+
+//write the response to the raw connection and the events as they come, until
+//the client is gone or the bus is dropped
+pub fn serve(
+    writer: &mut dyn Write,
+    events: &Receiver<ChainEvent>,
+    format: StreamFormat,
+    keepalive: Duration,
+) -> io::Result<()> {
+    //the length is unknown, the body ends with the connection
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        format.content_type()
+    )?;
+    writer.flush()?;
+    loop {
+        match events.recv_timeout(keepalive) {
+            Ok(event) => writer.write_all(format.encode(&event).as_bytes())?,
+            Err(RecvTimeoutError::Timeout) => writer.write_all(format.keepalive().as_bytes())?,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        writer.flush()?;
+    }
+}
@@ -119,6 +119,10 @@ impl AvailabilityBlock {
         self.nonce
     }
 
+    pub fn get_header(&self) -> BlockHeader {
+        self.header.clone()
+    }
+
     pub fn get_avai_tx_set(&self) -> Vec<TransactionBlock> {
         self.avai_tx_set.data.clone()
     }
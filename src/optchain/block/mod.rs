@@ -44,6 +44,7 @@ pub struct BlockHeader {
     // difficulty: H256,
     timestamp: SystemTime,
     // merkle_root: H256,
+    extra_nonce: u32, //changed by the miner once the nonces of a template are exhausted
 }
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, Hash, PartialEq)]
 pub struct BlockContent {
@@ -128,6 +129,7 @@ impl Random for BlockHeader {
             order_root,
             cmt_root,
             timestamp: SystemTime::now(),
+            extra_nonce: rng.gen(),
         }
     }
 }
@@ -180,6 +182,7 @@ impl Default for BlockHeader {
             order_root: H256::default(),
             cmt_root: H256::default(),
             timestamp: SystemTime::from(UNIX_EPOCH + Duration::new(0,0)),
+            extra_nonce: 0,
         }
     }
 }
@@ -222,16 +225,26 @@ impl BlockHeader {
             cmt_root,
             timestamp,
             // merkle_root
+            extra_nonce: 0,
         }
     }
     pub fn get_mem_size(&self) -> usize {
         H256::get_mem_size() * (5+self.global_parents.len())
-            + std::mem::size_of::<u32>()
+            + std::mem::size_of::<u32>() * 2
             + std::mem::size_of::<SystemTime>()
     }
     pub fn set_shard_id(&mut self, shard_id: usize) {
         self.shard_id = shard_id as u32;
     }
+    pub fn get_extra_nonce(&self) -> u32 {
+        self.extra_nonce
+    }
+    pub fn set_extra_nonce(&mut self, extra_nonce: u32) {
+        self.extra_nonce = extra_nonce;
+    }
+    pub fn set_timestamp(&mut self, timestamp: SystemTime) {
+        self.timestamp = timestamp;
+    }
 }
 
 impl Info for BlockHeader {
@@ -279,11 +292,16 @@ impl Info for BlockHeader {
             &ring::digest::SHA256,
             &self.shard_id.to_be_bytes()
         ).into();
+        let extra_nonce_hash: H256 = ring::digest::digest(
+            &ring::digest::SHA256,
+            &self.extra_nonce.to_be_bytes()
+        ).into();
         vec![
             // self.difficulty.clone(),
             time_hash,
             shard_id_hash,
             // self.merkle_root.clone(),
+            extra_nonce_hash,
         ]
     }
 }
@@ -544,6 +562,28 @@ impl Block {
         blk.hash() == blk.header.hash()
     }
 
+    //the same block under another extra nonce, giving a fresh space of nonces
+    pub fn with_extra_nonce(&self, extra_nonce: u32) -> Block {
+        let mut header = self.header.clone();
+        header.set_extra_nonce(extra_nonce);
+        Block {
+            hash: header.hash(),
+            header,
+            content: self.content.clone(),
+        }
+    }
+
+    //the same block stamped with another time, e.g. the virtual one of a simulation
+    pub fn with_timestamp(&self, timestamp: SystemTime) -> Block {
+        let mut header = self.header.clone();
+        header.set_timestamp(timestamp);
+        Block {
+            hash: header.hash(),
+            header,
+            content: self.content.clone(),
+        }
+    }
+
     pub fn get_header(&self) -> BlockHeader {
         self.header.clone()
     }
@@ -602,6 +642,7 @@ impl Block {
             order_root: confirmed_avai_root,
             cmt_root: symbol_merkle_tree.root.clone(),
             timestamp: SystemTime::now(),
+            extra_nonce: 0,
         };
 
         let content: BlockContent = BlockContent {
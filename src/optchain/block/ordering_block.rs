@@ -117,6 +117,10 @@ impl OrderingBlock {
         self.nonce
     }
 
+    pub fn get_header(&self) -> BlockHeader {
+        self.header.clone()
+    }
+
     pub fn get_confirmed_avai_set(&self) -> Vec<(H256, u32)> {
         self.confirmed_avai_set.clone()
     }
@@ -119,6 +119,10 @@ impl ProposerBlock {
         self.nonce
     }
 
+    pub fn get_header(&self) -> BlockHeader {
+        self.header.clone()
+    }
+
     pub fn get_prop_tx_set(&self) -> Vec<TransactionBlock> {
         self.prop_tx_set.data.clone()
     }
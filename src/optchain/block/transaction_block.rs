@@ -102,6 +102,9 @@ impl TransactionBlock {
     pub fn get_nonce(&self) -> u32 {
         self.nonce
     }
+    pub fn get_header(&self) -> BlockHeader {
+        self.header.clone()
+    }
     pub fn get_cmt_root(&self) -> H256 {
         self.header.get_cmt_root()
     }
@@ -6,6 +6,7 @@ use crate::{
             availability_block::AvailabilityBlock,
             transaction_block::TransactionBlock,
             ordering_block::OrderingBlock,
+            BlockHeader,
         },
     },
     types::hash::{H256, Hashable},
@@ -32,6 +33,17 @@ pub enum VersaHash {
     OrderHash(H256),
 }
 
+impl VersaHash {
+    pub fn get_hash(&self) -> H256 {
+        match self {
+            VersaHash::PropHash(h) => *h,
+            VersaHash::ExHash(h) => *h,
+            VersaHash::InHash(h) => *h,
+            VersaHash::OrderHash(h) => *h,
+        }
+    }
+}
+
 impl Default for VersaBlock {
     fn default() -> Self {
         VersaBlock::PropBlock(ProposerBlock::default())
@@ -71,6 +83,33 @@ impl VersaBlock {
         }
     }
 
+    pub fn get_versa_hash(&self) -> VersaHash {
+        match self {
+            VersaBlock::PropBlock(prop_block) => VersaHash::PropHash(prop_block.hash()),
+            VersaBlock::ExAvaiBlock(avai_block) => VersaHash::ExHash(avai_block.hash()),
+            VersaBlock::InAvaiBlock(avai_block) => VersaHash::InHash(avai_block.hash()),
+            VersaBlock::OrderBlock(order_block) => VersaHash::OrderHash(order_block.hash()),
+        }
+    }
+
+    pub fn get_header(&self) -> BlockHeader {
+        match self {
+            VersaBlock::PropBlock(prop_block) => prop_block.get_header(),
+            VersaBlock::ExAvaiBlock(avai_block) => avai_block.get_header(),
+            VersaBlock::InAvaiBlock(avai_block) => avai_block.get_header(),
+            VersaBlock::OrderBlock(order_block) => order_block.get_header(),
+        }
+    }
+
+    pub fn get_nonce(&self) -> u32 {
+        match self {
+            VersaBlock::PropBlock(prop_block) => prop_block.get_nonce(),
+            VersaBlock::ExAvaiBlock(avai_block) => avai_block.get_nonce(),
+            VersaBlock::InAvaiBlock(avai_block) => avai_block.get_nonce(),
+            VersaBlock::OrderBlock(order_block) => order_block.get_nonce(),
+        }
+    }
+
     pub fn get_shard_id(&self) -> Option<usize> {
         match self {
             VersaBlock::PropBlock(_) => None,
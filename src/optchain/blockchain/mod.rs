@@ -414,11 +414,59 @@ impl Blockchain {
 
     
 
+    /// Get a block locator of the longest chain: the tip, then hashes going back with
+    /// exponentially growing steps, ending with the genesis block
+    pub fn get_block_locator(&self) -> Vec<H256> {
+        let path = self.all_blocks_in_longest_chain();
+        let mut locator: Vec<H256> = vec![];
+        let mut index = path.len() - 1;
+        let mut step = 1;
+        loop {
+            locator.push(path[index]);
+            if index == 0 {
+                break;
+            }
+            if locator.len() >= 10 {
+                step *= 2;
+            }
+            index = index.saturating_sub(step);
+        }
+        locator
+    }
+
+    /// Get at most `max` blocks of the longest chain following the first locator hash
+    /// found in it. If none of them is found, start right after the genesis block.
+    pub fn get_blocks_after(&self, locator: &[H256], max: usize) -> Vec<VersaBlock> {
+        let path = self.all_blocks_in_longest_chain();
+        let start = locator
+            .iter()
+            .find_map(|hash| path.iter().position(|x| x == hash))
+            .unwrap_or(0) + 1;
+        path.iter()
+            .skip(start)
+            .take(max)
+            .filter_map(|hash| self.hash2blk.get(hash))
+            .collect()
+    }
+
     //Get all blocks' hashs of the path end with specific hash
     pub fn all_blocks_end_with_block(&self, hash: &H256) -> Option<Vec<H256>> {
         Node::get_path(&self.root, hash)
     }
 
+    //the blocks of the path to `old_tip` that are not on the path to `new_tip`,
+    //and the ones of the path to `new_tip` that are not on the path to `old_tip`
+    pub fn get_fork(&self, old_tip: &H256, new_tip: &H256) -> Option<(Vec<H256>, Vec<H256>)> {
+        let old_path = self.all_blocks_end_with_block(old_tip)?;
+        let new_path = self.all_blocks_end_with_block(new_tip)?;
+        let common = old_path
+            .iter()
+            .zip(new_path.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        Some((old_path[common..].to_vec(), new_path[common..].to_vec()))
+    }
+
     // get the block from H256
     pub fn get_block(&self, hash: &H256) -> Option<VersaBlock> {
         match self.hash2blk.get(hash) {
@@ -482,6 +530,11 @@ impl Blockchain {
         }
     }
 
+    //a tx block of any stored block, whether in the longest chain or not
+    pub fn get_tx_blk(&self, tx_blk_hash: &H256) -> Option<TransactionBlock> {
+        self.hash2cmt.get(tx_blk_hash)
+    }
+
     pub fn get_tx_blk_in_longest_chain(&self, tx_blk_hash: &H256) -> Option<TransactionBlock> {
         match self.cmt2blk.get(tx_blk_hash) {
             Some(consensus_blk) => {
@@ -0,0 +1,55 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+//The time seen by a node: the wall clock, or the virtual clock of a simulation,
+//which only moves when the simulation runs so that a run does not depend on how
+//fast the machine carries it out.
+#[derive(Debug, Clone, Default)]
+pub enum Clock {
+    #[default]
+    System,
+    Virtual {
+        start: Instant,
+        elapsed_ms: Arc<AtomicU64>,
+    },
+}
+
+impl Clock {
+    pub fn new_virtual() -> Self {
+        Clock::Virtual {
+            start: Instant::now(),
+            elapsed_ms: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        match self {
+            Clock::System => Instant::now(),
+            Clock::Virtual { start, elapsed_ms } => {
+                *start + Duration::from_millis(elapsed_ms.load(Ordering::Acquire))
+            }
+        }
+    }
+
+    //the timestamp of a block header, the virtual time counting from the unix epoch
+    pub fn timestamp(&self) -> SystemTime {
+        match self {
+            Clock::System => SystemTime::now(),
+            Clock::Virtual { elapsed_ms, .. } => {
+                UNIX_EPOCH + Duration::from_millis(elapsed_ms.load(Ordering::Acquire))
+            }
+        }
+    }
+
+    //move a virtual clock to `ms` after its start, the wall clock moves by itself
+    pub fn set_elapsed(&self, ms: u64) {
+        if let Clock::Virtual { elapsed_ms, .. } = self {
+            elapsed_ms.store(ms, Ordering::Release);
+        }
+    }
+}
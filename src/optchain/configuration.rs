@@ -1,4 +1,18 @@
-use crate::types::hash::H256;
+use crate::{
+    types::{
+        hash::H256,
+        options::Options,
+        genesis::GenesisSpec,
+    },
+    optchain::{
+        mempool::EvictionPolicy,
+        miner::simulated::SimulatedPow,
+        network::{
+            relay::{RelayPolicy, RelayScope},
+            link::LinkEmulation,
+        },
+    },
+};
 
 
 #[derive(Debug, Default, Clone)]
@@ -22,8 +36,28 @@ pub struct Configuration {
     pub shard_size: usize,
     pub exper_number: usize,
     pub exper_iter: usize,
+    pub mempool_capacity: usize, //0 means the mempool is unbounded
+    pub mempool_policy: EvictionPolicy,
+    pub mempool_persist: bool,
+    pub data_dir: String, //where the node keeps its address book
+    pub max_outbound: usize, //target number of outgoing connections
+    pub outbound_per_shard: usize, //target number of outgoing connections to each shard
+    pub relay_policy: RelayPolicy,
+    pub compact_blocks: bool, //relay proposer and availability blocks as tx block hashes
+    pub link_emulation: Option<LinkEmulation>, //None means the links are not shaped
+    pub simulated_pow: Option<SimulatedPow>, //None means the blocks are mined by real proof of work
+    pub miner_threads: usize, //threads hashing the block in mining
+    pub hash_rate: u64, //hashes per second of the miner, 0 means unlimited
+    pub genesis: Option<GenesisSpec>, //None means the default genesis blocks
 }
 
+//the targets of the block kinds, from the hardest
+pub const DIFFICULTIES: [&str; 5] = ["in_avai_diff", "avai_diff", "order_diff", "prop_diff", "tx_diff"];
+
+//the chain id of a network without a genesis file
+pub const DEFAULT_CHAIN_ID: &str = "optchain";
+pub const DEFAULT_DATA_DIR: &str = "./DB";
+
 impl Configuration {
     pub fn new() -> Self {
         Configuration {
@@ -46,6 +80,171 @@ impl Configuration {
             shard_size: 0,
             exper_number: 0,
             exper_iter: 0,
+            mempool_capacity: 0,
+            mempool_policy: EvictionPolicy::Oldest,
+            mempool_persist: false,
+            data_dir: String::from(DEFAULT_DATA_DIR),
+            max_outbound: 8,
+            outbound_per_shard: 2,
+            relay_policy: RelayPolicy::flood(),
+            compact_blocks: false,
+            link_emulation: None,
+            simulated_pow: None,
+            miner_threads: 1,
+            hash_rate: 0,
+            genesis: None,
+        }
+    }
+
+    //the configuration of the node from its options, validated with validate()
+    pub fn from_options(options: &Options) -> Result<Self, String> {
+        let mut config = Configuration::new();
+        if let Some(path) = options.value_of("genesis")? {
+            config.genesis = Some(GenesisSpec::from_file(&path)?);
+        }
+        let genesis = config.genesis.clone();
+        config.simulated_pow = options.parse_opt::<SimulatedPow>("simulated_pow")?;
+        match config.simulated_pow {
+            Some(sim_pow) => {
+                //the difficulties are ignored, but still known options of the file
+                for name in DIFFICULTIES.iter() {
+                    options.value_of(name)?;
+                }
+                sim_pow.apply_targets(&mut config);
+            }
+            None => {
+                config.tx_diff = options.parse_difficulty("tx_diff", genesis.as_ref())?;
+                config.prop_diff = options.parse_difficulty("prop_diff", genesis.as_ref())?;
+                config.avai_diff = options.parse_difficulty("avai_diff", genesis.as_ref())?;
+                config.in_avai_diff = options.parse_difficulty("in_avai_diff", genesis.as_ref())?;
+                config.order_diff = options.parse_difficulty("order_diff", genesis.as_ref())?;
+            }
+        }
+        config.block_size = options.parse("block_size")?;
+        config.symbol_size = options.parse("symbol_size")?;
+        config.num_symbol_per_block = config.block_size.checked_div(config.symbol_size).unwrap_or(0);
+        config.prop_size = options.parse("prop_size")?;
+        config.avai_size = options.parse("avai_size")?;
+        config.ex_req_num = options.parse("ex_req_num")?;
+        config.in_req_num = options.parse("in_req_num")?;
+        config.k = options.parse("confirmation_depth")?;
+        config.shard_id = options.parse("shard_id")?;
+        config.node_id = options.parse("node_id")?;
+        config.exper_number = options.parse("exper_number")?;
+        config.exper_iter = options.parse("exper_iter")?;
+        //the shards of the genesis file when not given
+        config.shard_num = match (options.parse_opt("shard_num")?, genesis.as_ref()) {
+            (Some(shard_num), _) => shard_num,
+            (None, Some(genesis)) => genesis.shard_num,
+            (None, None) => options.parse("shard_num")?,
+        };
+        config.shard_size = options.parse("shard_size")?;
+        config.mempool_capacity = options.parse("mempool_size")?;
+        config.mempool_policy = options.parse("mempool_policy")?;
+        config.mempool_persist = options.is_present("mempool_persist")?;
+        config.data_dir = options.parse("data_dir")?;
+        config.max_outbound = options.parse("max_outbound")?;
+        config.outbound_per_shard = options.parse("outbound_per_shard")?;
+        config.relay_policy = options.parse("relay_policy")?;
+        config.compact_blocks = options.is_present("compact_blocks")?;
+        if let Some(path) = options.value_of("link_config")? {
+            config.link_emulation = Some(LinkEmulation::from_file(&path)
+                .map_err(|e| format!("error loading the link emulation: {}", e))?);
+        }
+        config.miner_threads = options.parse("miner_threads")?;
+        config.hash_rate = options.parse("hash_rate")?;
+        Ok(config)
+    }
+
+    //the chain id which the signatures of the txs commit to
+    pub fn chain_id(&self) -> &str {
+        self.genesis
+            .as_ref()
+            .map_or(DEFAULT_CHAIN_ID, |x| x.chain_id.as_str())
+    }
+
+    //every broken invariant, as a message saying what to change
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.shard_num == 0 {
+            errors.push(String::from("shard_num is 0: there must be at least one shard"));
+        } else if self.shard_id >= self.shard_num {
+            errors.push(format!(
+                "shard_id {} is out of range: it must be less than shard_num {}",
+                self.shard_id, self.shard_num
+            ));
+        }
+        if self.symbol_size == 0 {
+            errors.push(String::from("symbol_size is 0: a block must be split into symbols of at least one byte"));
+        } else if !self.block_size.is_multiple_of(self.symbol_size) {
+            errors.push(format!(
+                "block_size {} is not a multiple of symbol_size {}: change one of them so that the block splits into whole symbols",
+                self.block_size, self.symbol_size
+            ));
+        } else {
+            if self.ex_req_num > self.num_symbol_per_block {
+                errors.push(format!(
+                    "ex_req_num {} is more than the {} symbols of a block (block_size / symbol_size): lower it or the symbol_size",
+                    self.ex_req_num, self.num_symbol_per_block
+                ));
+            }
+            if self.in_req_num > self.num_symbol_per_block {
+                errors.push(format!(
+                    "in_req_num {} is more than the {} symbols of a block (block_size / symbol_size): lower it or the symbol_size",
+                    self.in_req_num, self.num_symbol_per_block
+                ));
+            }
+        }
+        //a hash is checked against the hardest target first, so an easier block kind
+        //with a harder target would never be mined
+        let difficulties = [
+            ("in_avai_diff", &self.in_avai_diff),
+            ("avai_diff", &self.avai_diff),
+            ("order_diff", &self.order_diff),
+            ("prop_diff", &self.prop_diff),
+            ("tx_diff", &self.tx_diff),
+        ];
+        for pair in difficulties.windows(2) {
+            let ((lower_name, lower), (higher_name, higher)) = (pair[0], pair[1]);
+            if lower > higher {
+                errors.push(format!(
+                    "{} {} is above {} {}: the targets must satisfy in_avai_diff <= avai_diff <= order_diff <= prop_diff <= tx_diff",
+                    lower_name, lower, higher_name, higher
+                ));
+            }
+        }
+        if self.miner_threads == 0 {
+            errors.push(String::from("miner_threads is 0: the miner needs at least one thread"));
+        }
+        let policy = &self.relay_policy;
+        if self.compact_blocks
+            && policy.tx_block == RelayScope::Origin
+            && (policy.proposer == RelayScope::All || policy.in_availability == RelayScope::All)
+        {
+            errors.push(String::from(
+                "tx blocks are relayed within their shard but compact proposer or inclusive availability blocks to every peer, which could not rebuild them: relay tx=all or turn compact blocks off"
+            ));
+        }
+        if let Some(genesis) = self.genesis.as_ref() {
+            if genesis.shard_num != self.shard_num {
+                errors.push(format!(
+                    "shard_num {} differs from the {} shards of the genesis file: drop it or make them equal",
+                    self.shard_num, genesis.shard_num
+                ));
+            }
+            for name in genesis.targets.keys() {
+                if !DIFFICULTIES.contains(&name.as_str()) {
+                    errors.push(format!(
+                        "unknown target {} in the genesis file, expected one of {:?}",
+                        name, DIFFICULTIES
+                    ));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
 }
@@ -0,0 +1,246 @@
+use crossbeam::channel::{self, Receiver, Sender, TrySendError};
+use log::trace;
+use serde::{Serialize, Deserialize};
+use std::sync::Mutex;
+use crate::{
+    optchain::{
+        block::versa_block::VersaBlock,
+        metrics,
+        multichain::{ChainType, Multichain},
+    },
+    types::hash::{H256, Hashable},
+};
+
+//the events a subscriber has not read yet, further ones are dropped for it
+pub const SUBSCRIBER_BUFFER: usize = 4096;
+
+//what happened to the chains, published by the miner and network workers
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ChainEvent {
+    //sealed by the local miner, before it is inserted
+    BlockMined {
+        block_type: String,
+        hash: String,
+        chain: Option<ChainType>, //None for tx blocks
+    },
+    //inserted in a chain, locally mined or received
+    BlockAccepted {
+        block_type: String,
+        hash: String,
+        chain: ChainType,
+        height: usize,
+    },
+    //an availability block kept aside until the symbols of its cmts are received
+    BlockUnavailable {
+        block_type: String,
+        hash: String,
+        shard_id: usize,
+        cmts: Vec<String>,
+    },
+    CmtAvailable {
+        cmt: String,
+        shard_id: Option<usize>,
+    },
+    TipChanged {
+        chain: ChainType,
+        hash: String,
+        height: usize,
+    },
+    //the new tip does not extend the old one
+    Reorg {
+        chain: ChainType,
+        old_tip: String,
+        new_tip: String,
+        depth: usize, //blocks of the old longest chain left out
+    },
+    //an availability block confirmed by an ordering block
+    AvaiConfirmed {
+        hash: String,
+        shard_id: u32,
+        order_hash: String,
+    },
+}
+
+impl ChainEvent {
+    fn get_chain(&self) -> Option<ChainType> {
+        match self {
+            ChainEvent::BlockMined { chain, .. } => *chain,
+            ChainEvent::BlockAccepted { chain, .. }
+            | ChainEvent::TipChanged { chain, .. }
+            | ChainEvent::Reorg { chain, .. } => Some(*chain),
+            ChainEvent::BlockUnavailable { shard_id, .. } => Some(ChainType::Availability(*shard_id as u32)),
+            ChainEvent::CmtAvailable { shard_id, .. } => shard_id.map(|x| ChainType::Availability(x as u32)),
+            ChainEvent::AvaiConfirmed { shard_id, .. } => Some(ChainType::Availability(*shard_id)),
+        }
+    }
+}
+
+//the events a subscriber wants; an empty list of chains means all of them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventFilter {
+    pub chains: Vec<String>, //proposer, availability or ordering
+    pub shard_id: Option<u32>, //events of the proposer and ordering chains have no shard and always match
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &ChainEvent) -> bool {
+        //a confirmation is an event of the ordering chain too
+        let mut chains = event.get_chain().into_iter().collect::<Vec<ChainType>>();
+        if let ChainEvent::AvaiConfirmed { .. } = event {
+            chains.push(ChainType::Ordering);
+        }
+        let chain_ok = self.chains.is_empty() || chains.iter().any(|chain| {
+            self.chains.iter().any(|name| name == chain_name(chain))
+        });
+        let shard_ok = match (self.shard_id, event.get_chain()) {
+            (Some(shard_id), Some(ChainType::Availability(x))) => x == shard_id,
+            _ => true,
+        };
+        chain_ok && shard_ok
+    }
+}
+
+pub fn chain_name(chain: &ChainType) -> &'static str {
+    match chain {
+        ChainType::Proposer => "proposer",
+        ChainType::Availability(_) => "availability",
+        ChainType::Ordering => "ordering",
+    }
+}
+
+pub fn chain_of_block(block: &VersaBlock) -> ChainType {
+    match block {
+        VersaBlock::PropBlock(_) => ChainType::Proposer,
+        VersaBlock::ExAvaiBlock(_) | VersaBlock::InAvaiBlock(_) => {
+            ChainType::Availability(block.get_shard_id().unwrap() as u32)
+        }
+        VersaBlock::OrderBlock(_) => ChainType::Ordering,
+    }
+}
+
+//the events of `block` inserted under `parent` in a chain whose tip was `old_tip`,
+//none without subscribers so the chains are not walked for nothing
+pub fn insertion_events(
+    bus: &EventBus,
+    multichain: &Multichain,
+    chain: ChainType,
+    block: &VersaBlock,
+    parent: &H256,
+    old_tip: &H256,
+) -> Vec<ChainEvent> {
+    if !bus.has_subscribers() {
+        return vec![];
+    }
+    let hash = block.hash();
+    let mut events = vec![];
+    if let Some(height) = multichain.get_block_height(&chain, &hash) {
+        events.push(ChainEvent::BlockAccepted {
+            block_type: metrics::block_type(block).to_string(),
+            hash: hash.to_string(),
+            chain,
+            height,
+        });
+    }
+    if let (Some(tip), Some(height)) = (multichain.get_tip(&chain), multichain.get_chain_height(&chain)) {
+        if tip != *old_tip {
+            //the tip only moves to the inserted block, which extends the old tip but for a fork
+            let adopted = if parent != old_tip {
+                let (abandoned, adopted) = multichain
+                    .get_fork(&chain, old_tip, &tip)
+                    .unwrap_or_default();
+                events.push(ChainEvent::Reorg {
+                    chain,
+                    old_tip: old_tip.to_string(),
+                    new_tip: tip.to_string(),
+                    depth: abandoned.len(),
+                });
+                adopted
+            } else {
+                vec![hash]
+            };
+            events.push(ChainEvent::TipChanged {
+                chain,
+                hash: tip.to_string(),
+                height,
+            });
+            //the availability blocks are confirmed by the ordering blocks joining the
+            //longest chain, a side block confirms nothing until a reorg adopts it
+            if chain == ChainType::Ordering {
+                for order_hash in adopted {
+                    let confirmed = match order_hash == hash {
+                        true => block.get_confirmed_avai_set(),
+                        false => multichain
+                            .get_block(&chain, &order_hash)
+                            .and_then(|x| x.get_confirmed_avai_set()),
+                    };
+                    for (avai_hash, shard_id) in confirmed.unwrap_or_default() {
+                        events.push(ChainEvent::AvaiConfirmed {
+                            hash: avai_hash.to_string(),
+                            shard_id,
+                            order_hash: order_hash.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    events
+}
+
+pub struct EventBus {
+    subscribers: Mutex<Vec<(EventFilter, Sender<ChainEvent>)>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus {
+            subscribers: Mutex::new(vec![]),
+        }
+    }
+
+    //the subscription ends when the receiver is dropped
+    pub fn subscribe(&self, filter: EventFilter) -> Receiver<ChainEvent> {
+        let (sender, receiver) = channel::bounded(SUBSCRIBER_BUFFER);
+        self.subscribers.lock().unwrap().push((filter, sender));
+        receiver
+    }
+
+    pub fn num_subscribers(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        self.num_subscribers() > 0
+    }
+
+    pub fn publish(&self, event: ChainEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|(filter, sender)| {
+            if !filter.matches(&event) {
+                return true;
+            }
+            match sender.try_send(event.clone()) {
+                Ok(()) => true,
+                //a slow subscriber misses events rather than blocking the workers
+                Err(TrySendError::Full(_)) => {
+                    trace!("Dropping an event for a slow subscriber");
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+
+    pub fn publish_all(&self, events: Vec<ChainEvent>) {
+        for event in events {
+            self.publish(event);
+        }
+    }
+}
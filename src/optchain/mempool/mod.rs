@@ -8,29 +8,151 @@ use crate::{
     optchain::{
         configuration::Configuration,
         block::transaction_block::TransactionBlock,
+        transaction::Transaction,
     },
 };
-use std::collections::{VecDeque};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 // use log::{info, debug};
 use std::time::{SystemTime};
 
+//priority of tx blocks relayed by other nodes
+pub const DEFAULT_PRIORITY: u32 = 0;
+//priority of tx blocks mined by this node
+pub const LOCAL_PRIORITY: u32 = 1;
+//the most referenced tx blocks remembered, the oldest ones are forgotten first
+pub const MAX_REFERENCED: usize = 100000;
+//the most submitted txs waiting to be packed into a block of this node
+pub const MAX_PENDING_TXS: usize = 100000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    #[default]
+    Oldest, //evict the earliest inserted tx block
+    LowestPriority, //evict the tx block with the lowest priority, the oldest one among ties
+}
+
+impl std::str::FromStr for EvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oldest" => Ok(EvictionPolicy::Oldest),
+            "lowest-priority" => Ok(EvictionPolicy::LowestPriority),
+            _ => Err(format!("unknown eviction policy {}, expected oldest or lowest-priority", s)),
+        }
+    }
+}
+
+//the value stored in the database, it keeps the insertion order so that the queue
+//can be rebuilt after a restart
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MempoolEntry {
+    seq: u64,
+    priority: u32,
+    tx_blk: TransactionBlock,
+}
+
+impl Hashable for MempoolEntry {
+    fn hash(&self) -> H256 {
+        self.tx_blk.hash()
+    }
+}
+
+//a referenced tx block as stored, with its position in the referenced queue
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ReferencedEntry {
+    seq: u64,
+    hash: H256,
+}
+
+impl Hashable for ReferencedEntry {
+    fn hash(&self) -> H256 {
+        self.hash
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MempoolStats {
+    pub size: usize,
+    pub capacity: usize,
+    pub eviction_policy: EvictionPolicy,
+    pub persistent: bool,
+    pub inserted: u64,
+    pub duplicated: u64,
+    pub rejected_referenced: u64,
+    pub rejected_full: u64,
+    pub evicted: u64,
+    pub removed_referenced: u64,
+    pub restored_referenced: u64,
+    pub popped: u64,
+    pub pending_txs: usize,
+}
 
 pub struct Mempool {
-    tx_blk_map: Database<TransactionBlock>, //the key is the hash of the tx block, while value is the
-    //full block
-    tx_blk_queue: VecDeque<H256>,
+    tx_blk_map: Database<MempoolEntry>, //the key is the hash of the tx block, while value is the
+    //full block together with its position in the queue
+    tx_blk_queue: BTreeMap<u64, H256>, //seq -> tx block hash, ordered by insertion
+    priority_index: BTreeSet<(u32, u64)>, //(priority, seq), ordered by eviction preference
+    hash2seq: HashMap<H256, (u64, u32)>, //tx block hash -> (seq, priority)
+    referenced: HashSet<H256>, //tx blocks already referenced by the longest proposer chain
+    referenced_queue: VecDeque<H256>, //the referenced tx blocks, oldest first
+    referenced_map: Database<ReferencedEntry>, //the referenced tx blocks as stored
+    next_ref_seq: u64,
+    next_seq: u64,
+    capacity: usize, //0 means unbounded
+    eviction_policy: EvictionPolicy,
+    persistent: bool,
+    pending_txs: VecDeque<Transaction>, //txs submitted to this node, packed first by its miner
+    pending_tx_hashes: HashSet<H256>,
+    stats: MempoolStats,
 }
 
 
 impl Mempool {
     pub fn new(config: &Configuration) -> Self {
-        let now = SystemTime::now();
-        let tx_blk_map: Database<TransactionBlock> = 
-            Database::<TransactionBlock>::new(format!("node(shard-{},index-{})/mempool/tx_blk_map/{:?}", config.shard_id, config.node_id, now));
-        Mempool {
+        //a persistent mempool always reopens the same database, otherwise a fresh
+        //one is created for every run
+        let path = |name: &str| match config.mempool_persist {
+            true => format!("node(shard-{},index-{})/mempool/{}", config.shard_id, config.node_id, name),
+            false => format!("node(shard-{},index-{})/mempool/{}/{:?}", config.shard_id, config.node_id, name, SystemTime::now()),
+        };
+        let tx_blk_map: Database<MempoolEntry> = Database::<MempoolEntry>::new(path("tx_blk_map"));
+        let referenced_map: Database<ReferencedEntry> = Database::<ReferencedEntry>::new(path("referenced"));
+
+        let mut mempool = Mempool {
             tx_blk_map,
-            tx_blk_queue: VecDeque::new(),
+            tx_blk_queue: BTreeMap::new(),
+            priority_index: BTreeSet::new(),
+            hash2seq: HashMap::new(),
+            referenced: HashSet::new(),
+            referenced_queue: VecDeque::new(),
+            referenced_map,
+            next_ref_seq: 0,
+            next_seq: 0,
+            capacity: config.mempool_capacity,
+            eviction_policy: config.mempool_policy,
+            persistent: config.mempool_persist,
+            pending_txs: VecDeque::new(),
+            pending_tx_hashes: HashSet::new(),
+            stats: MempoolStats::default(),
+        };
+        //rebuild the in-memory indexes from the stored entries
+        for (hash, entry) in mempool.tx_blk_map.iter() {
+            mempool.tx_blk_queue.insert(entry.seq, hash);
+            mempool.priority_index.insert((entry.priority, entry.seq));
+            mempool.hash2seq.insert(hash, (entry.seq, entry.priority));
+            mempool.next_seq = mempool.next_seq.max(entry.seq + 1);
         }
+        //so that the tx blocks referenced before a restart are not packed again
+        let mut referenced: Vec<ReferencedEntry> = mempool.referenced_map.iter().map(|x| x.1).collect();
+        referenced.sort_by_key(|x| x.seq);
+        for entry in referenced {
+            mempool.referenced.insert(entry.hash);
+            mempool.referenced_queue.push_back(entry.hash);
+            mempool.next_ref_seq = entry.seq + 1;
+        }
+        mempool
     }
 
     pub fn get_size(&self) -> usize {
@@ -40,89 +162,247 @@ impl Mempool {
     pub fn get_queue_size(&self) -> usize {
         self.tx_blk_queue.len()
     }
-    
+
+    pub fn get_stats(&self) -> MempoolStats {
+        let mut stats = self.stats.clone();
+        stats.size = self.get_size();
+        stats.capacity = self.capacity;
+        stats.eviction_policy = self.eviction_policy;
+        stats.persistent = self.persistent;
+        stats.pending_txs = self.pending_txs.len();
+        stats
+    }
+
     pub fn get_tx_blocks(&mut self, num: usize) -> Result<Vec<TransactionBlock>, Vec<TransactionBlock>> {
         let mut tx_blocks: Vec<TransactionBlock> = vec![];
-        if num >= self.tx_blk_queue.len() {
-            while !self.tx_blk_queue.is_empty() {
-                let hash = self.tx_blk_queue.pop_front().unwrap();
-                let tx_blk = self.tx_blk_map.get(&hash).unwrap().clone();
-                self.tx_blk_map.remove(&hash);
-                tx_blocks.push(tx_blk);
-            }
-            Ok(tx_blocks)
-        } else {
-            for _i in 0..num {
-                let hash = self.tx_blk_queue.pop_front().unwrap();
-                let tx_blk = self.tx_blk_map.get(&hash).unwrap().clone();
-                self.tx_blk_map.remove(&hash);
-                tx_blocks.push(tx_blk);
+        while tx_blocks.len() < num {
+            match self.pop_one_tx_blk() {
+                Some(tx_blk) => tx_blocks.push(tx_blk),
+                None => return Err(tx_blocks),
             }
-            Err(tx_blocks)
         }
-        
+        Ok(tx_blocks)
     }
 
     pub fn insert_tx_blk(&mut self, tx_blk: TransactionBlock) -> bool {
+        self.insert_tx_blk_with_priority(tx_blk, DEFAULT_PRIORITY)
+    }
+
+    pub fn insert_tx_blk_with_priority(&mut self, tx_blk: TransactionBlock, priority: u32) -> bool {
         let hash = tx_blk.hash();
-        if self.tx_blk_map.contains_key(&hash) {
+        if self.hash2seq.contains_key(&hash) {
             //block already exists.
-            false
-        } else {
-            self.tx_blk_map.insert(hash.clone(), tx_blk.clone()).unwrap();
-            self.tx_blk_queue.push_back(hash);
-            true
+            self.stats.duplicated += 1;
+            return false;
+        }
+        if self.referenced.contains(&hash) {
+            //block is already referenced by the proposer chain
+            self.stats.rejected_referenced += 1;
+            return false;
         }
+        if self.capacity > 0 && self.get_size() >= self.capacity {
+            match self.choose_victim(priority) {
+                Some(victim) => {
+                    self.remove(&victim);
+                    self.stats.evicted += 1;
+                }
+                None => {
+                    self.stats.rejected_full += 1;
+                    return false;
+                }
+            }
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.tx_blk_map.insert(hash, MempoolEntry { seq, priority, tx_blk }).unwrap();
+        self.tx_blk_queue.insert(seq, hash);
+        self.priority_index.insert((priority, seq));
+        self.hash2seq.insert(hash, (seq, priority));
+        self.stats.inserted += 1;
+        true
+    }
+
+    //pick the tx block to evict for an incoming one with the given priority,
+    //None if the incoming block should be dropped instead
+    fn choose_victim(&self, priority: u32) -> Option<H256> {
+        match self.eviction_policy {
+            EvictionPolicy::Oldest => {
+                self.tx_blk_queue.values().next().cloned()
+            }
+            EvictionPolicy::LowestPriority => {
+                let (lowest_priority, seq) = self.priority_index.iter().next()?;
+                if *lowest_priority > priority {
+                    None
+                } else {
+                    self.tx_blk_queue.get(seq).cloned()
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, hash: &H256) -> Option<TransactionBlock> {
+        let (seq, priority) = self.hash2seq.remove(hash)?;
+        self.tx_blk_queue.remove(&seq);
+        self.priority_index.remove(&(priority, seq));
+        let entry = self.tx_blk_map.get(hash);
+        self.tx_blk_map.remove(hash);
+        entry.map(|e| e.tx_blk)
     }
 
     pub fn check(&self, hash: &H256) -> bool {
-        self.tx_blk_map.contains_key(hash)
+        self.hash2seq.contains_key(hash)
     }
-        
+
+    pub fn is_referenced(&self, hash: &H256) -> bool {
+        self.referenced.contains(hash)
+    }
+
     pub fn get_tx_blk(&self, hash: &H256) -> Option<TransactionBlock> {
         if self.check(hash) {
-            Some(self.tx_blk_map.get(hash).unwrap().clone())
+            self.tx_blk_map.get(hash).map(|e| e.tx_blk)
         } else {
             None
         }
     }
 
+    //all tx blocks, in insertion order
     pub fn get_all_tx_blks(&self) -> Vec<TransactionBlock> {
-
-        self.tx_blk_map
-            .iter()
-            .map(|(_, val)| val.clone())
+        self.tx_blk_queue
+            .values()
+            .filter_map(|hash| self.tx_blk_map.get(hash))
+            .map(|e| e.tx_blk)
             .collect()
-
     }
 
     pub fn delete_txs(&mut self, tx_blk_hashs: Vec<H256>) -> bool {
         for hash in tx_blk_hashs.iter() {
-            self.tx_blk_map.remove(&hash);
-            self.tx_blk_queue.retain(|x| x != hash);
+            self.remove(hash);
         }
         true
     }
 
-    
+    //called once the tx blocks are referenced by a proposer block on the longest chain,
+    //they are dropped and later copies of them are rejected
+    pub fn remove_referenced(&mut self, tx_blk_hashs: &[H256]) -> usize {
+        let mut removed = 0;
+        for hash in tx_blk_hashs.iter() {
+            self.add_referenced(*hash);
+            if self.remove(hash).is_some() {
+                removed += 1;
+            }
+        }
+        self.stats.removed_referenced += removed as u64;
+        removed
+    }
+
+    fn add_referenced(&mut self, hash: H256) {
+        if !self.referenced.insert(hash) {
+            return;
+        }
+        self.referenced_queue.push_back(hash);
+        let _ = self.referenced_map.insert(hash, ReferencedEntry { seq: self.next_ref_seq, hash });
+        self.next_ref_seq += 1;
+        if self.referenced_queue.len() > MAX_REFERENCED {
+            let oldest = self.referenced_queue.pop_front().unwrap();
+            self.referenced.remove(&oldest);
+            self.referenced_map.remove(&oldest);
+        }
+    }
+
+    pub fn get_referenced_size(&self) -> usize {
+        self.referenced.len()
+    }
+
+    //called once a reorg leaves the proposer blocks referencing the tx blocks out
+    //of the longest chain, they are accepted again and queued to be proposed
+    pub fn restore_referenced(&mut self, tx_blks: Vec<TransactionBlock>) -> usize {
+        let hashes: HashSet<H256> = tx_blks.iter().map(|x| x.hash()).collect();
+        for hash in hashes.iter() {
+            if self.referenced.remove(hash) {
+                self.referenced_map.remove(hash);
+            }
+        }
+        self.referenced_queue.retain(|x| !hashes.contains(x));
+        let mut restored = 0;
+        for tx_blk in tx_blks {
+            if self.insert_tx_blk(tx_blk) {
+                restored += 1;
+            }
+        }
+        self.stats.restored_referenced += restored as u64;
+        restored
+    }
+
+    //follow a move of the proposer tip: the tx blocks it now references leave the
+    //mempool, the ones of the blocks it left behind come back
+    pub fn follow_prop_fork(&mut self, adopted: &[TransactionBlock], abandoned: Vec<TransactionBlock>) {
+        let hashes: Vec<H256> = adopted.iter().map(|x| x.hash()).collect();
+        self.remove_referenced(&hashes);
+        if !abandoned.is_empty() {
+            self.restore_referenced(abandoned);
+        }
+    }
+
+    //the oldest tx blocks not in `excluded`, which stay in the mempool until they
+    //are referenced, so that a discarded block template loses none of them
+    pub fn peek_tx_blks(&self, num: usize, excluded: &HashSet<H256>) -> Vec<TransactionBlock> {
+        self.tx_blk_queue
+            .values()
+            .filter(|hash| !excluded.contains(*hash))
+            .filter_map(|hash| self.tx_blk_map.get(hash))
+            .map(|e| e.tx_blk)
+            .take(num)
+            .collect()
+    }
 
     pub fn pop_one_tx_blk(&mut self) -> Option<TransactionBlock> {
-        if self.tx_blk_queue.is_empty() {
-            None
-        } else {
-            let hash = self.tx_blk_queue.pop_front().unwrap();
-            let tx_blk = self.tx_blk_map.get(&hash).unwrap().clone();
-            self.tx_blk_map.remove(&hash);
-            Some(tx_blk)
+        let hash = *self.tx_blk_queue.values().next()?;
+        let tx_blk = self.remove(&hash);
+        if tx_blk.is_some() {
+            self.stats.popped += 1;
         }
+        tx_blk
     }
 
-    pub fn get_all_tx_blk_hash(&self) -> Vec<H256> {
-        self.tx_blk_map
+    //a tx submitted to this node, false when it is already pending or too many are;
+    //pending txs are not persisted
+    pub fn insert_tx(&mut self, tx: Transaction) -> bool {
+        let hash = tx.hash();
+        if self.pending_tx_hashes.contains(&hash) || self.pending_txs.len() >= MAX_PENDING_TXS {
+            return false;
+        }
+        self.pending_tx_hashes.insert(hash);
+        self.pending_txs.push_back(tx);
+        true
+    }
+
+    //the oldest pending txs, which stay pending until a block of this node packs them
+    pub fn peek_txs(&self, num: usize) -> Vec<Transaction> {
+        self.pending_txs
             .iter()
-            .map(|(key, _)| key.clone())
+            .take(num)
+            .cloned()
             .collect()
     }
-    
-}
 
+    pub fn remove_txs(&mut self, txs: &[Transaction]) {
+        let hashes: HashSet<H256> = txs
+            .iter()
+            .map(|x| x.hash())
+            .filter(|x| self.pending_tx_hashes.contains(x))
+            .collect();
+        if hashes.is_empty() {
+            return;
+        }
+        self.pending_txs.retain(|x| !hashes.contains(&x.hash()));
+        self.pending_tx_hashes.retain(|x| !hashes.contains(x));
+    }
+
+    pub fn get_all_tx_blk_hash(&self) -> Vec<H256> {
+        self.tx_blk_queue
+            .values()
+            .cloned()
+            .collect()
+    }
+
+}
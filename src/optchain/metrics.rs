@@ -0,0 +1,297 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Mutex,
+        MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+use crate::optchain::{
+    block::versa_block::VersaBlock,
+    mempool::MempoolStats,
+    multichain::{ChainType, Multichain},
+    network::message::Message,
+    symbolpool::SymbolPool,
+};
+
+//the counters and gauges of a node, exported on /metrics in the text format of
+//Prometheus. The gauges of the chains and the pools are read when scraped.
+
+//upper bounds in seconds of the buckets of the lock wait histogram
+pub const LOCK_WAIT_BUCKETS: [f64; 8] = [1e-6, 1e-5, 1e-4, 1e-3, 1e-2, 1e-1, 1.0, 10.0];
+
+//the tx blocks are not versa blocks, they are counted under this type
+pub const TX_BLOCK: &str = "transaction";
+
+//why a received block was dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RejectReason {
+    InvalidPow,
+    Duplicate,
+    Unavailable,
+    OverlappingAvaiSet,
+    UnknownParent,
+}
+
+impl RejectReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RejectReason::InvalidPow => "invalid_pow",
+            RejectReason::Duplicate => "duplicate",
+            RejectReason::Unavailable => "unavailable",
+            RejectReason::OverlappingAvaiSet => "overlapping_avai_set",
+            RejectReason::UnknownParent => "unknown_parent",
+        }
+    }
+}
+
+pub fn block_type(block: &VersaBlock) -> &'static str {
+    match block {
+        VersaBlock::PropBlock(_) => "proposer",
+        VersaBlock::ExAvaiBlock(_) => "exclusive_availability",
+        VersaBlock::InAvaiBlock(_) => "inclusive_availability",
+        VersaBlock::OrderBlock(_) => "ordering",
+    }
+}
+
+fn chain_label(chain: &ChainType) -> String {
+    match chain {
+        ChainType::Proposer => String::from("chain=\"proposer\""),
+        ChainType::Availability(shard_id) => format!("chain=\"availability\",shard=\"{}\"", shard_id),
+        ChainType::Ordering => String::from("chain=\"ordering\""),
+    }
+}
+
+#[derive(Debug)]
+struct Histogram {
+    buckets: Vec<u64>, //not cumulative, the last one counts the samples above all bounds
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Histogram {
+            buckets: vec![0; LOCK_WAIT_BUCKETS.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let index = LOCK_WAIT_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(LOCK_WAIT_BUCKETS.len());
+        self.buckets[index] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+//a snapshot of the metrics read from the shared state of the node
+pub struct Gauges {
+    pub chains: Vec<(ChainType, usize, f64)>, //(chain, height, forking rate)
+    pub mempool: MempoolStats,
+    pub requested_cmts: usize,
+    pub symbols: usize,
+}
+
+impl Gauges {
+    pub fn collect(multichain: &Multichain, mempool: MempoolStats, symbolpool: &SymbolPool) -> Self {
+        let chains = multichain
+            .get_chain_types()
+            .into_iter()
+            .filter_map(|chain| Some((
+                chain,
+                multichain.get_chain_height(&chain)?,
+                multichain.get_forking_rate(&chain)?,
+            )))
+            .collect();
+        Gauges {
+            chains,
+            mempool,
+            requested_cmts: symbolpool.get_num_requested_cmts(),
+            symbols: symbolpool.get_num_symbols(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Metrics {
+    blocks_mined: Mutex<BTreeMap<&'static str, u64>>,
+    blocks_received: Mutex<BTreeMap<&'static str, u64>>,
+    blocks_rejected: Mutex<BTreeMap<(&'static str, RejectReason), u64>>,
+    messages_received: Mutex<BTreeMap<&'static str, (u64, u64)>>, //(count, bytes)
+    messages_sent: Mutex<BTreeMap<&'static str, (u64, u64)>>,
+    unavailable_cmts: AtomicU64,
+    unavailable_avai_blocks: AtomicU64,
+    multichain_lock_wait: Mutex<Histogram>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            blocks_mined: Mutex::new(BTreeMap::new()),
+            blocks_received: Mutex::new(BTreeMap::new()),
+            blocks_rejected: Mutex::new(BTreeMap::new()),
+            messages_received: Mutex::new(BTreeMap::new()),
+            messages_sent: Mutex::new(BTreeMap::new()),
+            unavailable_cmts: AtomicU64::new(0),
+            unavailable_avai_blocks: AtomicU64::new(0),
+            multichain_lock_wait: Mutex::new(Histogram::new()),
+        }
+    }
+
+    pub fn block_mined(&self, block_type: &'static str) {
+        *self.blocks_mined.lock().unwrap().entry(block_type).or_insert(0) += 1;
+    }
+
+    pub fn block_received(&self, block_type: &'static str) {
+        *self.blocks_received.lock().unwrap().entry(block_type).or_insert(0) += 1;
+    }
+
+    pub fn block_rejected(&self, block_type: &'static str, reason: RejectReason) {
+        *self.blocks_rejected.lock().unwrap().entry((block_type, reason)).or_insert(0) += 1;
+    }
+
+    pub fn message_received(&self, msg: &Message, bytes: usize) {
+        let mut messages = self.messages_received.lock().unwrap();
+        let entry = messages.entry(msg.name()).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += bytes as u64;
+    }
+
+    pub fn message_sent(&self, msg: &Message, bytes: usize) {
+        let mut messages = self.messages_sent.lock().unwrap();
+        let entry = messages.entry(msg.name()).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += bytes as u64;
+    }
+
+    pub fn set_unavailable(&self, cmts: usize, avai_blocks: usize) {
+        self.unavailable_cmts.store(cmts as u64, Ordering::Relaxed);
+        self.unavailable_avai_blocks.store(avai_blocks as u64, Ordering::Relaxed);
+    }
+
+    //lock the multichain, recording how long the lock was waited for
+    pub fn lock_multichain<'a>(&self, multichain: &'a Mutex<Multichain>) -> MutexGuard<'a, Multichain> {
+        let start = Instant::now();
+        let guard = multichain.lock().unwrap();
+        self.multichain_lock_wait
+            .lock()
+            .unwrap()
+            .observe(start.elapsed().as_secs_f64());
+        guard
+    }
+
+    pub fn get_blocks_mined(&self, block_type: &str) -> u64 {
+        self.blocks_mined.lock().unwrap().get(block_type).copied().unwrap_or(0)
+    }
+
+    pub fn get_blocks_received(&self, block_type: &str) -> u64 {
+        self.blocks_received.lock().unwrap().get(block_type).copied().unwrap_or(0)
+    }
+
+    pub fn get_blocks_rejected(&self, block_type: &str, reason: RejectReason) -> u64 {
+        self.blocks_rejected
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|((t, r), _)| *t == block_type && *r == reason)
+            .map(|(_, n)| *n)
+            .unwrap_or(0)
+    }
+
+    //(count, bytes)
+    pub fn get_messages_received(&self, name: &str) -> (u64, u64) {
+        self.messages_received.lock().unwrap().get(name).copied().unwrap_or((0, 0))
+    }
+
+    pub fn get_messages_sent(&self, name: &str) -> (u64, u64) {
+        self.messages_sent.lock().unwrap().get(name).copied().unwrap_or((0, 0))
+    }
+
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "optchain_blocks_mined_total", "counter", "Blocks mined by this node.");
+        for (block_type, n) in self.blocks_mined.lock().unwrap().iter() {
+            let _ = writeln!(out, "optchain_blocks_mined_total{{type=\"{}\"}} {}", block_type, n);
+        }
+        header(&mut out, "optchain_blocks_received_total", "counter", "Blocks received from peers.");
+        for (block_type, n) in self.blocks_received.lock().unwrap().iter() {
+            let _ = writeln!(out, "optchain_blocks_received_total{{type=\"{}\"}} {}", block_type, n);
+        }
+        header(&mut out, "optchain_blocks_rejected_total", "counter", "Received blocks dropped, by reason.");
+        for ((block_type, reason), n) in self.blocks_rejected.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "optchain_blocks_rejected_total{{type=\"{}\",reason=\"{}\"}} {}",
+                block_type, reason.name(), n
+            );
+        }
+
+        header(&mut out, "optchain_chain_height", "gauge", "Height of the longest chain.");
+        for (chain, height, _) in gauges.chains.iter() {
+            let _ = writeln!(out, "optchain_chain_height{{{}}} {}", chain_label(chain), height);
+        }
+        header(&mut out, "optchain_chain_forking_rate", "gauge", "Blocks in the longest chain over all blocks of the chain.");
+        for (chain, _, rate) in gauges.chains.iter() {
+            let _ = writeln!(out, "optchain_chain_forking_rate{{{}}} {}", chain_label(chain), rate);
+        }
+
+        header(&mut out, "optchain_mempool_size", "gauge", "Tx blocks in the mempool.");
+        let _ = writeln!(out, "optchain_mempool_size {}", gauges.mempool.size);
+        header(&mut out, "optchain_mempool_capacity", "gauge", "Capacity of the mempool.");
+        let _ = writeln!(out, "optchain_mempool_capacity {}", gauges.mempool.capacity);
+        header(&mut out, "optchain_symbolpool_requested_cmts", "gauge", "Cmts whose symbols were requested.");
+        let _ = writeln!(out, "optchain_symbolpool_requested_cmts {}", gauges.requested_cmts);
+        header(&mut out, "optchain_symbolpool_symbols", "gauge", "Symbols in the symbol pool.");
+        let _ = writeln!(out, "optchain_symbolpool_symbols {}", gauges.symbols);
+        header(&mut out, "optchain_unavailable_cmts", "gauge", "Cmts with missing symbols referenced by availability blocks.");
+        let _ = writeln!(out, "optchain_unavailable_cmts {}", self.unavailable_cmts.load(Ordering::Relaxed));
+        header(&mut out, "optchain_unavailable_avai_blocks", "gauge", "Availability blocks waiting for symbols.");
+        let _ = writeln!(out, "optchain_unavailable_avai_blocks {}", self.unavailable_avai_blocks.load(Ordering::Relaxed));
+
+        for (direction, messages) in [("received", &self.messages_received), ("sent", &self.messages_sent)] {
+            let messages = messages.lock().unwrap();
+            let name = format!("optchain_messages_{}_total", direction);
+            header(&mut out, &name, "counter", &format!("Messages {} by type.", direction));
+            for (msg, (n, _)) in messages.iter() {
+                let _ = writeln!(out, "{}{{type=\"{}\"}} {}", name, msg, n);
+            }
+            let name = format!("optchain_message_bytes_{}_total", direction);
+            header(&mut out, &name, "counter", &format!("Bytes of the messages {} by type.", direction));
+            for (msg, (_, bytes)) in messages.iter() {
+                let _ = writeln!(out, "{}{{type=\"{}\"}} {}", name, msg, bytes);
+            }
+        }
+
+        let name = "optchain_multichain_lock_wait_seconds";
+        header(&mut out, name, "histogram", "Time waited for the lock of the multichain.");
+        let histogram = self.multichain_lock_wait.lock().unwrap();
+        let mut cumulative = 0;
+        for (bound, n) in LOCK_WAIT_BUCKETS.iter().zip(histogram.buckets.iter()) {
+            cumulative += n;
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+        let _ = writeln!(out, "{}_sum {}", name, histogram.sum);
+        let _ = writeln!(out, "{}_count {}", name, histogram.count);
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
@@ -1,16 +1,25 @@
 pub mod worker;
+pub mod simulated;
+pub mod rate;
+pub mod template;
 
 use log::{info};
 use crossbeam::channel::{
     unbounded, 
     Receiver, 
     Sender, 
-    TryRecvError
+    RecvTimeoutError,
 };
 use std::{
+    collections::HashSet,
     time::{self}, 
     thread, 
-    sync::{Arc, Mutex},
+    sync::{
+        Arc,
+        Mutex,
+        RwLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
 };
 use crate::{        
     optchain::{
@@ -22,18 +31,19 @@ use crate::{
             ordering_block::OrderingBlock, 
             proposer_block::ProposerBlock, 
             transaction_block::TransactionBlock, 
-            versa_block::VersaBlock
+            versa_block::{VersaBlock, VersaHash},
         }, 
+        clock::Clock,
         configuration::Configuration, 
         mempool::Mempool, 
         multichain::Multichain, 
         transaction::Transaction,
     }, types::{
         hash::{H256, Hashable},
-        random::Random,
     }
 };
 use rand::Rng;
+use rate::{RateLimiter, HashCounter, HashRateStats};
 
 enum ControlSignal {
     Start(u64), // the number controls the lambda of interval between block generation
@@ -43,10 +53,63 @@ enum ControlSignal {
 
 enum OperatingState {
     Paused,
-    Run(u64),
+    Run,
     ShutDown,
 }
 
+//the nonces a hashing thread tries before checking its template is still current
+const NONCES_PER_ROUND: u64 = 4096;
+//a running miner packs at most one template per interval on its current one, the
+//updates in between are carried out together at the end of it
+const MIN_REFRESH_INTERVAL: time::Duration = time::Duration::from_millis(200);
+//how long an idle hashing thread waits for a template
+const IDLE_INTERVAL: time::Duration = time::Duration::from_millis(10);
+
+//the block being mined by the hashing threads
+struct Template {
+    generation: u64,
+    block: Block,
+}
+
+//the state shared by the miner thread and the hashing threads
+struct Shared {
+    template: RwLock<Option<Arc<Template>>>, //None while paused or once a block is found on it
+    generation: AtomicU64,
+    lambda: AtomicU64,
+    exit: AtomicBool,
+    limiter: RateLimiter,
+    counter: HashCounter,
+}
+
+impl Shared {
+    fn current(&self) -> Option<Arc<Template>> {
+        self.template.read().unwrap().clone()
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::Acquire) == generation
+    }
+
+    //replace the template, the hashing threads drop the previous one
+    fn publish(&self, block: Option<Block>) {
+        let mut template = self.template.write().unwrap();
+        let generation = self.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        *template = block.map(|block| Arc::new(Template { generation, block }));
+    }
+
+    //stop mining a template once a block is found on it. False if it was already
+    //replaced or retired, e.g. by another thread finding a block at the same time
+    fn retire(&self, generation: u64) -> bool {
+        let mut template = self.template.write().unwrap();
+        if !self.is_current(generation) {
+            return false;
+        }
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        *template = None;
+        true
+    }
+}
+
 pub struct Context {
     /// Channel for receiving control signal
     control_chan: Receiver<ControlSignal>,
@@ -56,12 +119,18 @@ pub struct Context {
     mempool: Arc<Mutex<Mempool>>,
     // validator: Validator,
     config: Configuration,
+    shared: Arc<Shared>,
+    last_refresh: time::Instant,
+    stale: bool, //an update waits for the end of MIN_REFRESH_INTERVAL
 }
 
 #[derive(Clone)]
 pub struct Handle {
     /// Channel for sending signal to the miner thread
     control_chan: Sender<ControlSignal>,
+    /// Channel for handing the blocks mined elsewhere to the miner worker
+    finished_block_chan: Sender<MinerMessage>,
+    shared: Arc<Shared>,
 }
 
 pub fn new(multichain: &Arc<Mutex<Multichain>>, 
@@ -80,11 +149,23 @@ pub fn new(multichain: &Arc<Mutex<Multichain>>,
         multichain: Arc::clone(multichain),
         mempool: Arc::clone(mempool),
         // validator,
-        config: config.clone()
+        config: config.clone(),
+        shared: Arc::new(Shared {
+            template: RwLock::new(None),
+            generation: AtomicU64::new(0),
+            lambda: AtomicU64::new(0),
+            exit: AtomicBool::new(false),
+            limiter: RateLimiter::new(config.hash_rate),
+            counter: HashCounter::new(),
+        }),
+        last_refresh: time::Instant::now(),
+        stale: false,
     };
 
     let handle = Handle {
         control_chan: signal_chan_sender,
+        finished_block_chan: ctx.finished_block_chan.clone(),
+        shared: Arc::clone(&ctx.shared),
     };
 
     (ctx, handle, finished_block_receiver)
@@ -95,6 +176,177 @@ pub enum MinerMessage {
     TxBlk((TransactionBlock, BlockContent)),
 }
 
+//the kinds of block a pow hash can fall in, from the easiest to the hardest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    Tx,
+    Prop,
+    Order,
+    ExAvai,
+    InAvai,
+}
+
+//the kind of block mined by a pow hash, None if it is above every difficulty
+pub fn sortition(hash: &H256, config: &Configuration) -> Option<BlockKind> {
+    if *hash > config.tx_diff {
+        None
+    } else if *hash > config.prop_diff {
+        Some(BlockKind::Tx)
+    } else if *hash > config.order_diff {
+        Some(BlockKind::Prop)
+    } else if *hash > config.avai_diff {
+        Some(BlockKind::Order)
+    } else if *hash > config.in_avai_diff {
+        Some(BlockKind::ExAvai)
+    } else {
+        Some(BlockKind::InAvai)
+    }
+}
+
+impl BlockKind {
+    pub fn of_versa_hash(versa_hash: &VersaHash) -> Self {
+        match versa_hash {
+            VersaHash::PropHash(_) => BlockKind::Prop,
+            VersaHash::OrderHash(_) => BlockKind::Order,
+            VersaHash::ExHash(_) => BlockKind::ExAvai,
+            VersaHash::InHash(_) => BlockKind::InAvai,
+        }
+    }
+}
+
+//whether the pow hash of a block falls in the difficulty range of its kind, the
+//hash being the one the nonce gives
+pub fn meets_target(versa_hash: &VersaHash, config: &Configuration) -> bool {
+    sortition(&versa_hash.get_hash(), config) == Some(BlockKind::of_versa_hash(versa_hash))
+}
+
+//the same for a tx block, whose hash is the one of its header alone
+pub fn tx_blk_meets_target(tx_blk: &TransactionBlock, config: &Configuration) -> bool {
+    sortition(&H256::pow_hash(&tx_blk.hash(), tx_blk.get_nonce()), config) == Some(BlockKind::Tx)
+}
+
+//seal a hybrid block with a nonce as a block of the given kind
+pub fn seal(hybrid_block: &Block, nonce: u32, kind: BlockKind) -> MinerMessage {
+    let header = hybrid_block.get_header();
+    match kind {
+        BlockKind::Tx => MinerMessage::TxBlk((
+            TransactionBlock::new(header, nonce),
+            hybrid_block.get_content(),
+        )),
+        BlockKind::Prop => MinerMessage::VersaBlk(VersaBlock::PropBlock(ProposerBlock::new(
+            header,
+            nonce,
+            hybrid_block.get_prop_merkle_tree(),
+        ))),
+        BlockKind::Order => MinerMessage::VersaBlk(VersaBlock::OrderBlock(OrderingBlock::new(
+            header,
+            nonce,
+            hybrid_block.get_confirmed_avai_set(),
+        ))),
+        BlockKind::ExAvai => MinerMessage::VersaBlk(VersaBlock::ExAvaiBlock(AvailabilityBlock::new(
+            header,
+            nonce,
+            hybrid_block.get_avai_merkle_tree(),
+        ))),
+        BlockKind::InAvai => MinerMessage::VersaBlk(VersaBlock::InAvaiBlock(AvailabilityBlock::new(
+            header,
+            nonce,
+            hybrid_block.get_avai_merkle_tree(),
+        ))),
+    }
+}
+
+//pack a hybrid block on top of the current tips: the oldest tx blocks of the
+//mempool not proposed yet, the tx blocks waiting for availability and a constant
+//number of random transactions
+pub fn assemble_block(
+    multichain: &Arc<Mutex<Multichain>>,
+    mempool: &Arc<Mutex<Mempool>>,
+    config: &Configuration,
+) -> Block {
+    assemble_block_with(multichain, mempool, config, &mut rand::thread_rng(), &Clock::System)
+}
+
+//the same with the random transactions drawn from `rng` and the header stamped by
+//`clock`, so that a simulation packs the same block from the same seed
+pub fn assemble_block_with<R: Rng>(
+    multichain: &Arc<Mutex<Multichain>>,
+    mempool: &Arc<Mutex<Mempool>>,
+    config: &Configuration,
+    rng: &mut R,
+    clock: &Clock,
+) -> Block {
+    let prop_parent = multichain
+        .lock()
+        .unwrap()
+        .get_highest_prop_block();
+    let inter_parent = multichain
+        .lock()
+        .unwrap()
+        .get_highest_avai_block(config.shard_id);
+    let global_parents = multichain
+        .lock()
+        .unwrap()
+        .get_all_highest_avai_blocks();
+    let order_parent = multichain
+        .lock()
+        .unwrap()
+        .get_highest_order_block();
+
+    //the txs submitted to this node first, then random ones up to a constant number
+    let tx_num = config.num_symbol_per_block * config.symbol_size;
+    let mut pending = mempool
+        .lock()
+        .unwrap()
+        .peek_txs(tx_num)
+        .into_iter();
+    let txs: Vec<Vec<Transaction>> = (0..config.num_symbol_per_block)
+        .map(|_| {
+            (0..config.symbol_size)
+                .map(|_| pending.next().unwrap_or_else(|| Transaction::random_from(rng)))
+                .collect()
+        }).collect();
+
+    let old_prop_tx_set: HashSet<H256> = multichain
+        .lock()
+        .unwrap()
+        .get_prop_cmts(&prop_parent)
+        .iter()
+        .map(|x| x.hash())
+        .collect();
+    let prop_tx_set = mempool
+        .lock()
+        .unwrap()
+        .peek_tx_blks(config.prop_size, &old_prop_tx_set);
+
+    let avai_tx_set = match multichain
+        .lock()
+        .unwrap()
+        .get_avai_tx_blocks(config.avai_size)
+    {
+        Ok(enough_set) => enough_set,
+        Err(insufficient_set) => insufficient_set,
+    };
+
+    let confirmed_avai_set = multichain
+        .lock()
+        .unwrap()
+        .get_new_confirmed_avai_set();
+
+    Block::construct(
+        config.shard_id,
+        prop_parent,
+        inter_parent,
+        global_parents,
+        order_parent,
+        prop_tx_set,
+        avai_tx_set,
+        confirmed_avai_set,
+        txs,
+    )
+        .with_timestamp(clock.timestamp())
+}
+
 impl Handle {
     pub fn exit(&self) {
         self.control_chan.send(ControlSignal::Exit).unwrap();
@@ -106,8 +358,26 @@ impl Handle {
             .unwrap();
     }
 
+    //the tips or the mempool changed, the block in mining is packed again.
+    //Ignored when the miner does not run, e.g. in the simulator.
     pub fn update(&self) {
-        self.control_chan.send(ControlSignal::Update).unwrap();
+        let _ = self.control_chan.send(ControlSignal::Update);
+    }
+
+    //the hashes per second shared by the hashing threads, 0 means unlimited
+    pub fn set_hash_rate(&self, hash_rate: u64) {
+        self.shared.limiter.set_rate(hash_rate);
+    }
+
+    pub fn get_hash_rate_stats(&self) -> HashRateStats {
+        self.shared.counter.get_stats(self.shared.limiter.get_rate())
+    }
+
+    //a block mined by an external miner, inserted and relayed like a local one
+    pub fn submit(&self, message: MinerMessage) -> Result<(), String> {
+        self.finished_block_chan
+            .send(message)
+            .map_err(|_| String::from("the miner worker is not running"))
     }
 }
 
@@ -119,6 +389,21 @@ pub enum DoubleSpentType {
 
 impl Context {
     pub fn start(mut self) {
+        //the simulated arrivals are drawn by the miner thread itself
+        if self.config.simulated_pow.is_none() {
+            let num_threads = self.config.miner_threads.max(1) as u64;
+            for index in 0..num_threads {
+                let shared = Arc::clone(&self.shared);
+                let finished_block_chan = self.finished_block_chan.clone();
+                let config = self.config.clone();
+                thread::Builder::new()
+                    .name(format!("miner-{}", index))
+                    .spawn(move || {
+                        hash_loop(index, num_threads, shared, finished_block_chan, config);
+                    })
+                    .unwrap();
+            }
+        }
         thread::Builder::new()
             .name("miner".to_string())
             .spawn(move || {
@@ -128,263 +413,181 @@ impl Context {
         info!("Miner initialized into paused mode");
     }
 
-    fn po_w(&self, block_hash: H256, nonce: u32) -> H256 {
-        H256::pow_hash(&block_hash, nonce)
-    }
-
-
+    //react to the control signals and keep the template of the hashing threads
+    //on top of the current tips
     fn miner_loop(&mut self) {
-        // main mining loop
-        let mut pre_prop_parent = H256::default();
-        let mut pre_inter_parent = H256::default();
-        let mut pre_global_parents = H256::default();
-        let mut pre_order_parent = H256::default();
-        let mut pre_hybrid_block = Block::default();
         loop {
-            // check and react to control signals
-            // store the hash of parents in the previous round, 
-            // if the parents does not change, just need to change the nonce
-            // if the parents change, repackage the txs and generate the new consensus block
-            match self.operating_state {
-                OperatingState::Paused => {
-                    let signal = self.control_chan.recv().unwrap();
-                    match signal {
-                        ControlSignal::Exit => {
-                            info!("Miner shutting down");
-                            self.operating_state = OperatingState::ShutDown;
-                        }
-                        ControlSignal::Start(i) => {
-                            info!("Miner starting in continuous mode with lambda {}", i);
-                            self.operating_state = OperatingState::Run(i);
-                        }
-                        ControlSignal::Update => {
-                            // in paused state, don't need to update
-                        }
-                    };
-                    continue;
-                }
+            let signal = match self.operating_state {
+                OperatingState::Paused => self.control_chan.recv().unwrap(),
                 OperatingState::ShutDown => {
+                    self.shared.exit.store(true, Ordering::Release);
+                    self.shared.publish(None);
                     return;
                 }
-                _ => match self.control_chan.try_recv() {
-                    Ok(signal) => {
-                        match signal {
-                            ControlSignal::Exit => {
-                                info!("Miner shutting down");
-                                self.operating_state = OperatingState::ShutDown;
-                            }
-                            ControlSignal::Start(i) => {
-                                info!("Miner starting in continuous mode with lambda {}", i);
-                                self.operating_state = OperatingState::Run(i);
-                            }
-                            ControlSignal::Update => {
-                                unimplemented!()
+                OperatingState::Run => {
+                    let timeout = match self.config.simulated_pow {
+                        Some(sim_pow) => Some(sim_pow.next_arrival(&mut rand::thread_rng())),
+                        None if self.stale => Some(MIN_REFRESH_INTERVAL.saturating_sub(self.last_refresh.elapsed())),
+                        //once a block is found, wait for the update of the miner worker
+                        //which inserted it, so the next template is on the new tip
+                        None => None,
+                    };
+                    let signal = match timeout {
+                        Some(timeout) => self.control_chan.recv_timeout(timeout),
+                        None => self.control_chan.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    };
+                    match signal {
+                        Ok(signal) => signal,
+                        Err(RecvTimeoutError::Timeout) => {
+                            match self.config.simulated_pow {
+                                //a block arrives. A signal cancels the pending arrival, the
+                                //arrivals being memoryless the next one is simply drawn again
+                                Some(_) => self.mine_arrival(),
+                                None => self.refresh_template(),
                             }
-                        };
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => panic!("Miner control channel detached"),
                     }
-                    Err(TryRecvError::Empty) => {}
-                    Err(TryRecvError::Disconnected) => panic!("Miner control channel detached"),
-                },
+                }
+            };
+            let mut refresh = self.handle_signal(signal);
+            //a burst of updates, e.g. a batch of blocks, packs a single template
+            while let Ok(signal) = self.control_chan.try_recv() {
+                refresh |= self.handle_signal(signal);
             }
-            if let OperatingState::ShutDown = self.operating_state {
-                return;
+            match self.operating_state {
+                OperatingState::Run if refresh => self.request_refresh(),
+                OperatingState::Run => {}
+                _ => self.shared.publish(None),
             }
+        }
+    }
 
+    //return whether the template should be packed again
+    fn handle_signal(&mut self, signal: ControlSignal) -> bool {
+        match signal {
+            ControlSignal::Exit => {
+                info!("Miner shutting down");
+                self.operating_state = OperatingState::ShutDown;
+                self.shared.counter.pause();
+                false
+            }
+            ControlSignal::Start(i) => {
+                info!("Miner starting in continuous mode with lambda {}", i);
+                self.operating_state = OperatingState::Run;
+                self.shared.lambda.store(i, Ordering::Release);
+                self.shared.counter.start();
+                true
+            }
+            // in paused state, the template is packed when the miner starts
+            ControlSignal::Update => true,
+        }
+    }
 
-            if let OperatingState::Run(i) = self.operating_state {
-                if i != 0 {
-                    let interval = time::Duration::from_micros(i as u64);
-                    thread::sleep(interval);
-                }
-                let prop_parent = self.multichain
-                    .lock()
-                    .unwrap()
-                    .get_highest_prop_block();
-                let inter_parent = self.multichain
-                    .lock()
-                    .unwrap()
-                    .get_highest_avai_block(self.config.shard_id);
-                let global_parents = self.multichain
-                    .lock()
-                    .unwrap()
-                    .get_all_highest_avai_blocks();
-                let global_parents_hash = H256::multi_hash(&(
-                    global_parents.iter()
-                                  .map(|(hash, _)| hash.clone())
-                                  .collect()
-                ));
-                let order_parent = self.multichain
-                    .lock()
-                    .unwrap()
-                    .get_highest_order_block();
-                // let mut txs: Vec<Transaction> = vec![];
-                let txs: Vec<Vec<Transaction>>;
-                //check if parents have been change
-                if prop_parent != pre_prop_parent ||
-                    inter_parent != pre_inter_parent ||
-                    global_parents_hash != pre_global_parents ||
-                    order_parent != pre_order_parent {
-                    
-                    // randomly generate a constant number of transactions
-                    txs = (0..self.config.num_symbol_per_block)
-                                .into_iter()
-                                .map(|_| {
-                                    let sym: Vec<Transaction> = (0..self.config.symbol_size)
-                                        .into_iter()
-                                        .map(|_| Transaction::random())
-                                        .collect();
-                                    sym
-                                }).collect();
-                    
-                    let mut prop_tx_set: Vec<TransactionBlock> = vec![];
-                    let mut counter = 0;
-                    let old_prop_tx_set: Vec<H256> = self.multichain
-                        .lock()
-                        .unwrap()
-                        .get_prop_cmts(&prop_parent)
-                        .iter()
-                        .map(|x| x.hash())
-                        .collect();
-                        
-                    while counter < self.config.prop_size {
-                        match self.mempool
-                            .lock()
-                            .unwrap()
-                            .pop_one_tx_blk() 
-                        {
-                            Some(tx_blk) => {
-                                let tx_blk_hash = tx_blk.hash();
-                                if old_prop_tx_set.contains(&tx_blk_hash) {
-                                    continue;
-                                }
-                                prop_tx_set.push(tx_blk);
-                                counter += 1;
-                            }
-                            None => {
-                                break;
-                            }
-                        }
-                    }
-                    // let prop_tx_set = match self.mempool
-                    //     .lock()
-                    //     .unwrap()
-                    //     .get_tx_blocks(self.config.prop_size) 
-                    // {
-                    //     Ok(enough_set) => enough_set,
-                    //     Err(insufficient_set) => insufficient_set,
-                    // };
-
-                    let avai_tx_set = match self.multichain
-                        .lock()
-                        .unwrap()
-                        .get_avai_tx_blocks(self.config.avai_size) 
-                    {
-                        Ok(enough_set) => enough_set,
-                        Err(insufficient_set) => insufficient_set,
-                    };
+    //pack a template now when nothing is mined, e.g. once a block is found, else
+    //at most once per MIN_REFRESH_INTERVAL
+    fn request_refresh(&mut self) {
+        if self.shared.current().is_none() || self.last_refresh.elapsed() >= MIN_REFRESH_INTERVAL {
+            self.refresh_template();
+        } else {
+            self.stale = true;
+        }
+    }
+
+    //pack a block on the current tips and hand it to the hashing threads
+    fn refresh_template(&mut self) {
+        let block = assemble_block(&self.multichain, &self.mempool, &self.config);
+        self.shared.publish(Some(block));
+        self.last_refresh = time::Instant::now();
+        self.stale = false;
+    }
 
-                    let confirmed_avai_set = self.multichain
-                        .lock()
-                        .unwrap()
-                        .get_new_confirmed_avai_set();
-                    
-                    // let mut supposed_global_parents = global_parents.clone();
-                    // supposed_global_parents.retain(|x| x.1 != self.config.shard_id );
-                    // supposed_global_parents.push((vec![last_blk_hash.clone()], self.config.shard_id));
-                    let hybrid_block = Block::construct(
-                        self.config.shard_id,
-                        prop_parent.clone(),
-                        inter_parent.clone(),
-                        global_parents,
-                        order_parent.clone(),
-                        prop_tx_set,
-                        avai_tx_set,
-                        confirmed_avai_set,
-                        txs,
-                    );
-                    //info!("mines a block with parent {:?} of state size: {}", last_blk_hash, last_state.len());
-                    //update related information
-                    pre_prop_parent = prop_parent;
-                    pre_inter_parent = inter_parent;
-                    pre_global_parents = global_parents_hash;
-                    pre_order_parent = order_parent;
-                    pre_hybrid_block = hybrid_block;
+    //a simulated arrival: one nonce hashed against the easy targets is always a block
+    fn mine_arrival(&mut self) {
+        if self.stale {
+            self.refresh_template();
+        }
+        let template = match self.shared.current() {
+            Some(template) => template,
+            None => return,
+        };
+        let nonce: u32 = rand::thread_rng().gen();
+        let hash = H256::pow_hash(&template.block.hash(), nonce);
+        if let Some(kind) = sortition(&hash, &self.config) {
+            info!("mine a {:?} block {:?} in shard {}", kind, hash, self.config.shard_id);
+            if !self.shared.retire(template.generation) {
+                return;
+            }
+            self.finished_block_chan
+                .send(seal(&template.block, nonce, kind))
+                .unwrap();
+        }
+    }
+}
+
+//A hashing thread scans its own slice of the nonces of the current template, so
+//the threads never try the same nonce. Once its slice is exhausted, it moves to
+//a new extra nonce in the header, which gives a fresh slice.
+fn hash_loop(
+    index: u64,
+    num_threads: u64,
+    shared: Arc<Shared>,
+    finished_block_chan: Sender<MinerMessage>,
+    config: Configuration,
+) {
+    let num_nonces = u32::MAX as u64 + 1;
+    let first = index * (num_nonces / num_threads);
+    let last = match index + 1 == num_threads {
+        true => num_nonces,
+        false => (index + 1) * (num_nonces / num_threads),
+    };
+    while !shared.exit.load(Ordering::Acquire) {
+        let template = match shared.current() {
+            Some(template) => template,
+            None => {
+                thread::sleep(IDLE_INTERVAL);
+                continue;
+            }
+        };
+        let mut block = template.block.clone();
+        let mut extra_nonce: u32 = 0;
+        let mut nonce = first;
+        'template: while shared.is_current(template.generation) {
+            let lambda = shared.lambda.load(Ordering::Acquire);
+            let round_end = (nonce + shared.limiter.batch_size(NONCES_PER_ROUND)).min(last);
+            //wait for the hash rate budget of the round
+            let wait = shared.limiter.reserve(round_end - nonce);
+            if !wait.is_zero() {
+                thread::sleep(wait);
+                if !shared.is_current(template.generation) {
+                    break 'template;
                 }
-                
-                let nonce: u32 = rand::thread_rng().gen();
-                let hash_val = self.po_w(pre_hybrid_block.hash(), nonce);
-                //info!("block hash: {:?}", hash_val);
-                let tx_diff = self.config.tx_diff;
-                // let mut supposed_global_parents = global_parents.clone();
-                // supposed_global_parents.retain(|x| x.1 != self.config.shard_id );
-                // supposed_global_parents.push((vec![last_blk_hash.clone()], self.config.shard_id));
-                if hash_val <= tx_diff {
-                    if hash_val <= self.config.prop_diff {
-                        if hash_val <= self.config.order_diff {
-                            if hash_val <= self.config.avai_diff {
-                                if hash_val <= self.config.in_avai_diff {
-                                    info!("mine an inclusive availability block {:?} in shard {}", hash_val, self.config.shard_id);
-                                    let in_block = VersaBlock::InAvaiBlock(AvailabilityBlock::new(    
-                                        pre_hybrid_block.get_header(),
-                                        nonce,
-                                        pre_hybrid_block.get_avai_merkle_tree(),
-                                    ));
-                                    self.finished_block_chan
-                                        .send(MinerMessage::VersaBlk(in_block))
-                                        .unwrap();
-                                } else {
-                                    info!("mine an exclusive availability block {:?} in shard {}", hash_val, self.config.shard_id);
-                                    let ex_block = VersaBlock::ExAvaiBlock(AvailabilityBlock::new(    
-                                        pre_hybrid_block.get_header(),
-                                        nonce,
-                                        pre_hybrid_block.get_avai_merkle_tree(),
-                                    ));
-                                    self.finished_block_chan
-                                        .send(MinerMessage::VersaBlk(ex_block))
-                                        .unwrap();
-                                }
-                            } else {
-                                info!("mine an ordering block {:?}", hash_val);
-                                let order_block = VersaBlock::OrderBlock(OrderingBlock::new(    
-                                    pre_hybrid_block.get_header(),
-                                    nonce,
-                                    pre_hybrid_block.get_confirmed_avai_set(),
-                                ));
-                                self.finished_block_chan
-                                    .send(MinerMessage::VersaBlk(order_block))
-                                    .unwrap();
-                            }
-                        } else {
-                            info!("mine a proposer block {:?} in shard {}", hash_val, self.config.shard_id);
-                            let prop_block = VersaBlock::PropBlock(ProposerBlock::new(    
-                                pre_hybrid_block.get_header(),
-                                nonce,
-                                pre_hybrid_block.get_prop_merkle_tree(),
-                            ));
-                            self.finished_block_chan
-                                .send(MinerMessage::VersaBlk(prop_block))
-                                .unwrap();
-                            //leave the job of inserting new blocks to the workers
-                        }
-                    } else {
-                        info!("mine a transaction block {:?} in shard {}", hash_val, self.config.shard_id);
-                        let tx_block = TransactionBlock::new(
-                            pre_hybrid_block.get_header(),
-                            nonce
-                        );
-                        self.finished_block_chan
-                            .send(MinerMessage::TxBlk((tx_block, pre_hybrid_block.get_content())))
-                            .unwrap();
+            }
+            for n in nonce..round_end {
+                if lambda != 0 {
+                    thread::sleep(time::Duration::from_micros(lambda));
+                }
+                let hash = H256::pow_hash(&block.hash(), n as u32);
+                shared.counter.add(1);
+                if let Some(kind) = sortition(&hash, &config) {
+                    if shared.retire(template.generation) {
+                        info!("mine a {:?} block {:?} in shard {}", kind, hash, config.shard_id);
+                        //leave the job of inserting new blocks to the workers
+                        finished_block_chan.send(seal(&block, n as u32, kind)).unwrap();
                     }
-                    pre_prop_parent = H256::default();
-                    pre_inter_parent = H256::default();
-                    pre_global_parents = H256::default();
-                    pre_hybrid_block = Block::default();
-                } else {
-                    //no block is mined
+                    break 'template;
                 }
-
-                
+                //a slow miner notices a new template without finishing its round
+                if lambda != 0 && !shared.is_current(template.generation) {
+                    break 'template;
+                }
+            }
+            nonce = round_end;
+            if nonce == last {
+                extra_nonce = extra_nonce.wrapping_add(1);
+                block = template.block.with_extra_nonce(extra_nonce);
+                nonce = first;
             }
         }
     }
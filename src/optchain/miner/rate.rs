@@ -0,0 +1,127 @@
+use serde::Serialize;
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+//Paces the hashing threads to a budget of hashes per second. A thread reserves
+//its next batch of hashes and waits until the budget allows it, so the threads
+//share one budget whatever their number.
+pub struct RateLimiter {
+    rate: AtomicU64, //hashes per second, 0 means unlimited
+    next: Mutex<Instant>, //when the hashes reserved so far are spent
+}
+
+impl RateLimiter {
+    pub fn new(rate: u64) -> Self {
+        RateLimiter {
+            rate: AtomicU64::new(rate),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn get_rate(&self) -> u64 {
+        self.rate.load(Ordering::Acquire)
+    }
+
+    //the reservations made under the previous rate are forgotten
+    pub fn set_rate(&self, rate: u64) {
+        let mut next = self.next.lock().unwrap();
+        self.rate.store(rate, Ordering::Release);
+        *next = Instant::now();
+    }
+
+    //the hashes worth about 10ms of the budget, so that a slow miner still
+    //notices a new template quickly
+    pub fn batch_size(&self, max: u64) -> u64 {
+        match self.get_rate() {
+            0 => max,
+            rate => (rate / 100).clamp(1, max),
+        }
+    }
+
+    //reserve n hashes and return how long to wait before doing them
+    pub fn reserve(&self, n: u64) -> Duration {
+        let rate = self.get_rate();
+        if rate == 0 {
+            return Duration::ZERO;
+        }
+        let now = Instant::now();
+        let mut next = self.next.lock().unwrap();
+        let start = (*next).max(now);
+        *next = start + Duration::from_secs_f64(n as f64 / rate as f64);
+        start - now
+    }
+}
+
+//the hashes done by the miner since it was last started
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HashRateStats {
+    pub target_hash_rate: u64, //0 means unlimited
+    pub hashes: u64,
+    pub elapsed: f64, //seconds of mining
+    pub hash_rate: f64, //achieved hashes per second
+}
+
+pub struct HashCounter {
+    hashes: AtomicU64,
+    started: Mutex<Option<Instant>>, //None while the miner is paused
+    elapsed: Mutex<Duration>, //the mining time before the last start
+}
+
+impl Default for HashCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HashCounter {
+    pub fn new() -> Self {
+        HashCounter {
+            hashes: AtomicU64::new(0),
+            started: Mutex::new(None),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn add(&self, n: u64) {
+        self.hashes.fetch_add(n, Ordering::Relaxed);
+    }
+
+    //a new run of the miner counts from zero
+    pub fn start(&self) {
+        let mut started = self.started.lock().unwrap();
+        *self.elapsed.lock().unwrap() = Duration::ZERO;
+        self.hashes.store(0, Ordering::Relaxed);
+        *started = Some(Instant::now());
+    }
+
+    pub fn pause(&self) {
+        let mut started = self.started.lock().unwrap();
+        if let Some(at) = started.take() {
+            *self.elapsed.lock().unwrap() += at.elapsed();
+        }
+    }
+
+    pub fn get_stats(&self, target_hash_rate: u64) -> HashRateStats {
+        let started = self.started.lock().unwrap();
+        let mut elapsed = *self.elapsed.lock().unwrap();
+        if let Some(at) = *started {
+            elapsed += at.elapsed();
+        }
+        let hashes = self.hashes.load(Ordering::Relaxed);
+        let elapsed = elapsed.as_secs_f64();
+        HashRateStats {
+            target_hash_rate,
+            hashes,
+            elapsed,
+            hash_rate: match elapsed > 0.0 {
+                true => hashes as f64 / elapsed,
+                false => 0.0,
+            },
+        }
+    }
+}
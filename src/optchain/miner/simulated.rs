@@ -0,0 +1,111 @@
+use crate::{
+    types::hash::H256,
+    optchain::configuration::Configuration,
+};
+use rand::Rng;
+use std::time::Duration;
+
+//Simulated proof of work: the blocks of the network arrive as Poisson processes
+//of given rates, of which a node mines its hash power share. At each arrival one
+//nonce is hashed against easy targets splitting the hash space in proportion to
+//the rates, so the kind of the block follows the configured probabilities and
+//its nonce can still be checked against the targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulatedPow {
+    pub hash_power: f64, //share of the hash power of the network, in (0, 1]
+    //blocks per second of the whole network
+    pub tx_rate: f64,
+    pub prop_rate: f64,
+    pub order_rate: f64,
+    pub avai_rate: f64, //exclusive availability blocks
+    pub in_avai_rate: f64,
+}
+
+impl Default for SimulatedPow {
+    fn default() -> Self {
+        SimulatedPow {
+            hash_power: 1.0,
+            tx_rate: 0.0,
+            prop_rate: 0.0,
+            order_rate: 0.0,
+            avai_rate: 0.0,
+            in_avai_rate: 0.0,
+        }
+    }
+}
+
+//the target below which a uniform hash falls with the given probability
+fn target_of(probability: f64) -> H256 {
+    let mut bytes = [0xffu8; 32];
+    if probability < 1.0 {
+        let higher = (probability.max(0.0) * u128::MAX as f64) as u128;
+        bytes[0..16].copy_from_slice(&higher.to_be_bytes());
+    }
+    bytes.into()
+}
+
+impl SimulatedPow {
+    //blocks per second mined by this node
+    pub fn get_rate(&self) -> f64 {
+        self.hash_power
+            * (self.tx_rate + self.prop_rate + self.order_rate + self.avai_rate + self.in_avai_rate)
+    }
+
+    //the time to the next block of this node, exponentially distributed
+    pub fn next_arrival<R: Rng>(&self, rng: &mut R) -> Duration {
+        let u: f64 = rng.gen();
+        Duration::from_secs_f64(-(1.0 - u).ln() / self.get_rate())
+    }
+
+    //set the difficulties so that a hash is always a block, each kind with a
+    //probability proportional to its rate
+    pub fn apply_targets(&self, config: &mut Configuration) {
+        let total = self.tx_rate + self.prop_rate + self.order_rate + self.avai_rate + self.in_avai_rate;
+        let mut cumulative = self.in_avai_rate / total;
+        config.in_avai_diff = target_of(cumulative);
+        cumulative += self.avai_rate / total;
+        config.avai_diff = target_of(cumulative);
+        cumulative += self.order_rate / total;
+        config.order_diff = target_of(cumulative);
+        cumulative += self.prop_rate / total;
+        config.prop_diff = target_of(cumulative);
+        config.tx_diff = target_of(1.0);
+    }
+}
+
+//e.g. "power=0.25,tx=20,prop=1,order=1,avai=2,inAvai=0.5", the rates being the
+//blocks per second of the whole network
+impl std::str::FromStr for SimulatedPow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sim_pow = SimulatedPow::default();
+        for part in s.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or(format!("simulated pow parameter {} is not of the form key=value", part))?;
+            let value: f64 = value
+                .parse()
+                .map_err(|e| format!("cannot parse {}: {}", part, e))?;
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("{} must be a non-negative number", key));
+            }
+            match key {
+                "power" => sim_pow.hash_power = value,
+                "tx" => sim_pow.tx_rate = value,
+                "prop" => sim_pow.prop_rate = value,
+                "order" => sim_pow.order_rate = value,
+                "avai" => sim_pow.avai_rate = value,
+                "inAvai" => sim_pow.in_avai_rate = value,
+                _ => return Err(format!("unknown simulated pow parameter {}, expected power, tx, prop, order, avai or inAvai", key)),
+            }
+        }
+        if sim_pow.hash_power <= 0.0 || sim_pow.hash_power > 1.0 {
+            return Err(format!("hash power share {} is not in (0, 1]", sim_pow.hash_power));
+        }
+        if sim_pow.get_rate() <= 0.0 {
+            return Err(String::from("at least one block rate must be positive"));
+        }
+        Ok(sim_pow)
+    }
+}
@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    time::UNIX_EPOCH,
+};
+use crate::{
+    optchain::{
+        block::{Block, Info},
+        configuration::Configuration,
+        miner::{sortition, seal, MinerMessage},
+    },
+    types::hash::{H256, Hashable},
+};
+
+//the templates kept for submissions, the older ones are dropped
+pub const MAX_TEMPLATES: usize = 16;
+
+//the difficulties of the sortition, a pow hash below several targets is a block
+//of the kind of the lowest one
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Targets {
+    pub tx: String,
+    pub prop: String,
+    pub order: String,
+    pub avai: String,
+    pub in_avai: String,
+}
+
+//A hybrid block for an external miner. The pow hash of a nonce is the sha256 of
+//pow_base followed by the nonce as 4 big-endian bytes, and the hashes are hex
+//strings compared as big-endian integers.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BlockTemplate {
+    pub id: u64,
+    pub pow_base: String,
+    pub shard_id: usize,
+    pub prop_parent: String,
+    pub inter_parent: String,
+    pub global_parents: Vec<(String, usize)>,
+    pub order_parent: String,
+    pub prop_root: String,
+    pub avai_root: String,
+    pub order_root: String,
+    pub cmt_root: String,
+    pub timestamp: u128, //ms since the epoch
+    pub extra_nonce: u32,
+    pub targets: Targets,
+}
+
+impl BlockTemplate {
+    fn new(id: u64, block: &Block, config: &Configuration) -> Self {
+        let header = block.get_header();
+        BlockTemplate {
+            id,
+            pow_base: block.hash().to_string(),
+            shard_id: header.get_shard_id(),
+            prop_parent: header.get_prop_parent().to_string(),
+            inter_parent: header.get_inter_parent().to_string(),
+            global_parents: header
+                .get_global_parents()
+                .iter()
+                .map(|(hash, shard_id)| (hash.to_string(), *shard_id))
+                .collect(),
+            order_parent: header.get_order_parent().to_string(),
+            prop_root: header.get_prop_root().to_string(),
+            avai_root: header.get_avai_root().to_string(),
+            order_root: header.get_order_root().to_string(),
+            cmt_root: header.get_cmt_root().to_string(),
+            timestamp: header
+                .get_timestamp()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0),
+            extra_nonce: header.get_extra_nonce(),
+            targets: Targets {
+                tx: config.tx_diff.to_string(),
+                prop: config.prop_diff.to_string(),
+                order: config.order_diff.to_string(),
+                avai: config.avai_diff.to_string(),
+                in_avai: config.in_avai_diff.to_string(),
+            },
+        }
+    }
+}
+
+//The templates handed to external miners, so that a submitted nonce is sealed
+//into the same block as the one it was found on.
+pub struct TemplatePool {
+    next_id: u64,
+    templates: VecDeque<(u64, Block)>, //oldest first
+}
+
+impl Default for TemplatePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TemplatePool {
+    pub fn new() -> Self {
+        TemplatePool {
+            next_id: 0,
+            templates: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    pub fn insert(&mut self, block: Block, config: &Configuration) -> BlockTemplate {
+        let id = self.next_id;
+        self.next_id += 1;
+        let template = BlockTemplate::new(id, &block, config);
+        self.templates.push_back((id, block));
+        while self.templates.len() > MAX_TEMPLATES {
+            self.templates.pop_front();
+        }
+        template
+    }
+
+    //seal the block of a template with a nonce, as the miner does. A template
+    //yields a single block, it is dropped once a nonce is accepted
+    pub fn submit(&mut self, id: u64, nonce: u32, config: &Configuration) -> Result<MinerMessage, String> {
+        let index = self.templates
+            .iter()
+            .position(|(template_id, _)| *template_id == id)
+            .ok_or(format!("unknown or expired template {}", id))?;
+        let block = &self.templates[index].1;
+        let hash = H256::pow_hash(&block.hash(), nonce);
+        let kind = sortition(&hash, config)
+            .ok_or(format!("pow hash {} of nonce {} is above every target", hash, nonce))?;
+        let message = seal(block, nonce, kind);
+        self.templates.remove(index);
+        Ok(message)
+    }
+}
@@ -1,10 +1,11 @@
 use crossbeam::channel::Receiver;
 use log::{info};
 use crate::{
+    types::hash::Hashable,
     optchain::{
         block::{
             Info,
-            // Content,
+            Content,
             versa_block::{
                 VersaBlock,
                 VersaHash,
@@ -13,11 +14,19 @@ use crate::{
         network::{
             server::Handle as ServerHandle,
             message::Message,
+            relay::RelayClass,
+            compact::CompactBlock,
+            worker::WorkerContext,
+        },
+        multichain::{ChainType, Multichain},
+        miner::{
+            MinerMessage,
+            Handle as MinerHandle,
         },
-        multichain::Multichain,
-        miner::MinerMessage,
         configuration::Configuration,
-        mempool::Mempool,
+        events::{self, ChainEvent, EventBus},
+        metrics::{self, Metrics},
+        mempool::{Mempool, LOCAL_PRIORITY},
         symbolpool::{
             SymbolPool,
             SymbolIndex,
@@ -38,24 +47,26 @@ pub struct Worker {
     mempool: Arc<Mutex<Mempool>>,
     symbolpool: Arc<Mutex<SymbolPool>>,
     config: Configuration,
+    miner: MinerHandle,
+    metrics: Arc<Metrics>,
+    events: Arc<EventBus>,
 }
 
 impl Worker {
     pub fn new(
-        server: &ServerHandle,
         finished_block_chan: Receiver<MinerMessage>,
-        multichain: &Arc<Mutex<Multichain>>,
-        mempool: &Arc<Mutex<Mempool>>,
-        symbolpool: &Arc<Mutex<SymbolPool>>,
-        config: &Configuration,
+        ctx: &WorkerContext,
     ) -> Self {
         Self {
-            server: server.clone(),
+            server: ctx.server.clone(),
             finished_block_chan,
-            multichain: Arc::clone(multichain),
-            mempool: Arc::clone(mempool),
-            symbolpool: Arc::clone(symbolpool),
-            config: config.clone(),
+            multichain: Arc::clone(&ctx.multichain),
+            mempool: Arc::clone(&ctx.mempool),
+            symbolpool: Arc::clone(&ctx.symbolpool),
+            config: ctx.config.clone(),
+            miner: ctx.miner.clone(),
+            metrics: Arc::clone(&ctx.metrics),
+            events: Arc::clone(&ctx.events),
         }
     }
 
@@ -69,127 +80,138 @@ impl Worker {
         info!("Miner initialized into paused mode");
     }
 
+    //insert a mined block in the chain of its parent and publish what changed
+    fn insert_mined(&self, versa_block: &VersaBlock, parent: VersaHash, shard_id: usize) {
+        let chain = ChainType::of_parent(&parent, shard_id);
+        let mut multichain = self.metrics.lock_multichain(&self.multichain);
+        let old_tip = multichain.get_tip(&chain);
+        match multichain.insert_block_with_parent(versa_block.clone(), &parent, shard_id) {
+            Ok(_) => {
+                if let Some(old_tip) = old_tip {
+                    let chain_events = events::insertion_events(
+                        &self.events,
+                        &multichain,
+                        chain,
+                        versa_block,
+                        &parent.get_hash(),
+                        &old_tip,
+                    );
+                    let (adopted, abandoned) = match (chain, multichain.get_tip(&chain)) {
+                        (ChainType::Proposer, Some(new_tip)) if new_tip != old_tip => {
+                            multichain.get_prop_fork_tx_blks(&old_tip, &new_tip)
+                        }
+                        _ => (vec![], vec![]),
+                    };
+                    drop(multichain);
+                    self.events.publish_all(chain_events);
+                    if !adopted.is_empty() || !abandoned.is_empty() {
+                        self.mempool.lock().unwrap().follow_prop_fork(&adopted, abandoned);
+                    }
+                }
+            }
+            Err(e) => {
+                info!("inserting myself fail: {}", e);
+            }
+        }
+    }
+
+    //push a mined block, as a compact block when enabled
+    fn relay_block(&self, versa_block: VersaBlock) {
+        let class = RelayClass::of_block(&versa_block);
+        let msg = match (self.config.compact_blocks, CompactBlock::from_block(&versa_block)) {
+            (true, Some(compact)) => Message::CompactBlocks(vec![compact]),
+            _ => Message::Blocks(vec![versa_block]),
+        };
+        self.server.relay(msg, class);
+    }
+
     fn worker_loop(&mut self) {
         loop {
             let message = self.finished_block_chan
                 .recv()
                 .expect("Receive finished block error");
-             
-            match message {
-                MinerMessage::TxBlk((tx_block, content)) => {
-                    let cmt_root = tx_block.get_cmt_root();
-                    self.mempool.lock()
-                                .unwrap()
-                                .insert_tx_blk(tx_block.clone());
-                    //request all symbols of it
-                    let indexs: Vec<usize> = (0..self.config.num_symbol_per_block).collect();
+            self.handle_mined(message);
+        }
+    }
+
+    //insert a block mined locally and relay it
+    pub fn handle_mined(&mut self, message: MinerMessage) {
+        match message {
+            MinerMessage::TxBlk((tx_block, content)) => {
+                self.metrics.block_mined(metrics::TX_BLOCK);
+                self.events.publish(ChainEvent::BlockMined {
+                    block_type: metrics::TX_BLOCK.to_string(),
+                    hash: tx_block.hash().to_string(),
+                    chain: None,
+                });
+                let cmt_root = tx_block.get_cmt_root();
+                let mut mempool = self.mempool.lock().unwrap();
+                mempool.insert_tx_blk_with_priority(tx_block.clone(), LOCAL_PRIORITY);
+                //the submitted txs it packs are no longer pending
+                mempool.remove_txs(&content.get_txs());
+                drop(mempool);
+                //request all symbols of it
+                let indexs: Vec<usize> = (0..self.config.num_symbol_per_block).collect();
+                self.symbolpool.lock()
+                               .unwrap()
+                               .request_symbols(&cmt_root, self.config.shard_id, indexs.clone())
+                               .unwrap();
+                //insert all symbols to the symbolpool
+                for index in indexs {
+                    let symbol_index = SymbolIndex::new(cmt_root, index);
+                    let symbol_txs = content.get_symbol_txs(index).unwrap();
+                    let symbol = Symbol::new(
+                        symbol_index, 
+                        symbol_txs, 
+                        content.get_symbol_merkle_proof(index),
+                        &self.config,
+                    );
                     self.symbolpool.lock()
                                    .unwrap()
-                                   .request_symbols(&cmt_root, indexs.clone())
+                                   .insert_symbol(symbol)
                                    .unwrap();
-                    //insert all symbols to the symbolpool
-                    for index in indexs {
-                        let symbol_index = SymbolIndex::new(cmt_root.clone(), index);
-                        let symbol_txs = content.get_symbol_txs(index).unwrap();
-                        let symbol = Symbol::new(
-                            symbol_index, 
-                            symbol_txs, 
-                            content.get_symbol_merkle_proof(index),
-                            &self.config,
-                        );
-                        self.symbolpool.lock()
-                                       .unwrap()
-                                       .insert_symbol(symbol)
-                                       .unwrap();
-                    }
-                    //
-                    self.server.broadcast(Message::TxBlocks(vec![tx_block]));
                 }
-                MinerMessage::VersaBlk(versa_block) => {
-                    match versa_block.clone() {
-                        VersaBlock::InAvaiBlock(avai_block) => {
-                            let global_parents = avai_block.get_global_parents();
-                            for (inter_parent, shard_id) in global_parents {
-                                match self.multichain
-                                    .lock()
-                                    .unwrap()
-                                    .insert_block_with_parent(
-                                    versa_block.clone(),
-                                    &VersaHash::InHash(inter_parent),
-                                    shard_id
-                                ) {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        info!("inserting myself fail: {}", e);    
-                                    }
-                                }
-                            }
-                            self.server.broadcast(
-                                Message::Blocks(vec![versa_block])
-                            );
-                        }
-                        VersaBlock::ExAvaiBlock(avai_block) => {
-                            //exclusive avaialbility block
-                            let inter_parent = avai_block.get_inter_parent();
-                            match self.multichain
-                                .lock()
-                                .unwrap()
-                                .insert_block_with_parent(
-                                versa_block.clone(),
-                                &VersaHash::ExHash(inter_parent),
-                                self.config.shard_id
-                            ) {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    info!("inserting myself fail: {}", e);
-                                }
-                            }
-                            self.server.broadcast(
-                                Message::Blocks(vec![versa_block])
-                            );
-                        }
-                        VersaBlock::PropBlock(prop_block) => {
-                            let prop_parent = prop_block.get_prop_parent();
-                            match self.multichain
-                                .lock()
-                                .unwrap()
-                                .insert_block_with_parent(
-                                versa_block.clone(),
-                                &VersaHash::PropHash(prop_parent),
-                                self.config.shard_id
-                            ) {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    info!("inserting myself fail: {}", e);
-                                }
-                            }
-                            self.server.broadcast(
-                                Message::Blocks(vec![versa_block])
-                            );
-                        }
-                        VersaBlock::OrderBlock(order_block) => {
-                            let order_parent = order_block.get_order_parent();
-                            match self.multichain
-                                .lock()
-                                .unwrap()
-                                .insert_block_with_parent(
-                                versa_block.clone(),
-                                &VersaHash::OrderHash(order_parent),
-                                self.config.shard_id
-                            ) {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    info!("inserting myself fail: {}", e);
-                                }
-                            }
-                            self.server.broadcast(
-                                Message::Blocks(vec![versa_block])
-                            );
+                //
+                self.server.relay(
+                    Message::TxBlocks(vec![tx_block]),
+                    RelayClass::TxBlock(self.config.shard_id)
+                );
+            }
+            MinerMessage::VersaBlk(versa_block) => {
+                self.metrics.block_mined(metrics::block_type(&versa_block));
+                self.events.publish(ChainEvent::BlockMined {
+                    block_type: metrics::block_type(&versa_block).to_string(),
+                    hash: versa_block.hash().to_string(),
+                    chain: Some(events::chain_of_block(&versa_block)),
+                });
+                match versa_block.clone() {
+                    VersaBlock::InAvaiBlock(avai_block) => {
+                        let global_parents = avai_block.get_global_parents();
+                        for (inter_parent, shard_id) in global_parents {
+                            self.insert_mined(&versa_block, VersaHash::InHash(inter_parent), shard_id);
                         }
+                        self.relay_block(versa_block);
                     }
-                }    
-            }
-
+                    VersaBlock::ExAvaiBlock(avai_block) => {
+                        //exclusive avaialbility block
+                        let inter_parent = avai_block.get_inter_parent();
+                        self.insert_mined(&versa_block, VersaHash::ExHash(inter_parent), self.config.shard_id);
+                        self.relay_block(versa_block);
+                    }
+                    VersaBlock::PropBlock(prop_block) => {
+                        let prop_parent = prop_block.get_prop_parent();
+                        self.insert_mined(&versa_block, VersaHash::PropHash(prop_parent), self.config.shard_id);
+                        self.relay_block(versa_block);
+                    }
+                    VersaBlock::OrderBlock(order_block) => {
+                        let order_parent = order_block.get_order_parent();
+                        self.insert_mined(&versa_block, VersaHash::OrderHash(order_parent), self.config.shard_id);
+                        self.relay_block(versa_block);
+                    }
+                }
+            }    
         }
+        //the hashing threads stopped on the mined block, pack the next one
+        self.miner.update();
     }
 }
@@ -1,17 +1,22 @@
 pub mod api;
 pub mod block;
 pub mod blockchain;
+pub mod clock;
 pub mod miner;
 pub mod network;
 pub mod transaction;
 pub mod configuration;
+pub mod events;
 // pub mod validator;
 pub mod mempool;
+pub mod metrics;
 pub mod multichain;
 pub mod symbolpool;
+pub mod simulator;
 
 use crate::{
     types::{
+        options::Options,
         hash::{
             H256,
             Hashable,
@@ -21,6 +26,8 @@ use crate::{
     optchain::{
         configuration::Configuration,
         mempool::Mempool,
+        events::EventBus,
+        metrics::Metrics,
         block::{
             proposer_block::ProposerBlock,
             availability_block::AvailabilityBlock,
@@ -31,12 +38,18 @@ use crate::{
         },
         network::{
             server as NetworkServer,
-            worker::Worker as NetworkWorker,
+            worker::{Worker as NetworkWorker, WorkerContext},
+            sync::{SyncManager, SYNC_CHECK_INTERVAL},
+            handshake::Handshake,
+        },
+        api::{
+            Server as ApiServer,
+            rpc::RpcContext,
         },
-        api::Server as ApiServer,
         miner::{
             self as Miner,
             worker::Worker as MinerWorker,
+            template::TemplatePool,
         },
         blockchain::Blockchain as Blockchain,
         multichain::Multichain,
@@ -60,7 +73,7 @@ use crate::{
 use smol::channel;
 use log::{error, info};
 use std::{
-    collections::HashMap, convert::TryInto, net, num::ParseIntError, process, sync::{Arc, Mutex}, thread, time
+    net, num::ParseIntError, process, sync::{Arc, Mutex}, thread, time
 };
 // use env_logger::Env;
 
@@ -71,266 +84,131 @@ pub fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
         .collect()
 }
 
+//the header of the genesis blocks of a shard, committing to the genesis file if any
+fn genesis_header(config: &Configuration, shard_id: usize) -> BlockHeader {
+    match config.genesis.as_ref() {
+        Some(genesis) => BlockHeader::create(
+            shard_id,
+            H256::default(), //prop_parent
+            H256::default(), //inter_parent
+            vec![], //global_parents
+            H256::default(), //order_parent
+            H256::default(), //prop_root
+            H256::default(), //avai_root
+            H256::default(), //order_root
+            genesis.commitment(), //cmt_root
+            genesis.get_timestamp(),
+        ),
+        None => {
+            let mut header = BlockHeader::default();
+            header.set_shard_id(shard_id);
+            header
+        }
+    }
+}
+
+//the chains of a new node, starting from the genesis blocks shared by every node
+pub fn new_multichain(config: &Configuration) -> Multichain {
+    //the nonce avoids the same block as the availability genesis of shard 0
+    let prop_genesis_block = VersaBlock::PropBlock(ProposerBlock::new(
+        genesis_header(config, 0),
+        1,
+        MerkleTree::<TransactionBlock>::new((vec![]).as_slice()),
+    ));
+    let prop_chain = Blockchain::new(prop_genesis_block, config);
+
+    let mut genesis_avai_set: Vec<(H256, u32)> = vec![];
+    let avai_chains: Vec<Blockchain> = (0..config.shard_num)
+        .map(|i| {
+            let avai_block = AvailabilityBlock::new(
+                genesis_header(config, i),
+                0,
+                MerkleTree::<TransactionBlock>::new((vec![]).as_slice())
+            );
+            let avai_genesis_block = VersaBlock::ExAvaiBlock(avai_block);
+            genesis_avai_set.push((avai_genesis_block.hash(), i as u32));
+            Blockchain::new(avai_genesis_block, config)
+        })
+        .collect();
+    // let chains_ref: Vec<&Arc<Mutex<Blockchain>>> = avai_chains
+    //     .iter()
+    //     .collect();
+    let ordering_genesis_block = VersaBlock::OrderBlock(OrderingBlock::new(
+        genesis_header(config, 0),
+        0,
+        genesis_avai_set.clone(),
+    ));
+    let ordering_chain = Blockchain::new(ordering_genesis_block, config);
+    Multichain::new(
+        prop_chain, 
+        avai_chains, 
+        ordering_chain, 
+        config
+    )
+}
+
 pub fn start(sub_com: &clap::ArgMatches) {
 
+    // the options of the command line over the ones of the config file
+    let options = Options::load(sub_com).unwrap_or_else(|e| {
+        error!("Error loading the configuration: {}", e);
+        process::exit(1);
+    });
+
     // parse p2p server address
-    let p2p_addr = sub_com
-        .value_of("peer_addr")
-        .unwrap()
-        .parse::<net::SocketAddr>()
+    let p2p_addr = options
+        .parse::<net::SocketAddr>("peer_addr")
         .unwrap_or_else(|e| {
             error!("Error parsing P2P server address: {}", e);
             process::exit(1);
         });
 
     // parse api server address
-    let api_addr = sub_com
-        .value_of("api_addr")
-        .unwrap()
-        .parse::<net::SocketAddr>()
+    let api_addr = options
+        .parse::<net::SocketAddr>("api_addr")
         .unwrap_or_else(|e| {
             error!("Error parsing API server address: {}", e);
             process::exit(1);
         });
-    //parse the shard id
-    let shard_id = sub_com
-        .value_of("shard_id")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the shard id: {}", e);
-            process::exit(1);
-        });
-    //parse the shard id
-    let node_id = sub_com
-        .value_of("node_id")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the node id: {}", e);
-            process::exit(1);
-        });
-    //parse the shard id
-    let exper_number = sub_com
-        .value_of("exper_number")
-        .unwrap()
-        .parse::<usize>()
+    let p2p_workers = options
+        .parse::<usize>("p2p_workers")
         .unwrap_or_else(|e| {
-            error!("Error parsing the experiment number: {}", e);
-            process::exit(1);
-        });
-    //parse the shard id
-    let exper_iter = sub_com
-        .value_of("exper_iter")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the experiment iter: {}", e);
-            process::exit(1);
-        });
-    let shard_num = sub_com
-        .value_of("shard_num")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the shard_num number: {}", e);
-            process::exit(1);
-        });
-    let shard_size = sub_com
-        .value_of("shard_size")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the shard size: {}", e);
-            process::exit(1);
-        });
-    let block_size = sub_com
-        .value_of("block_size")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the block size: {}", e);
-            process::exit(1);
-        });
-    let symbol_size = sub_com
-        .value_of("symbol_size")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the symbol size: {}", e);
-            process::exit(1);
-        });
-    let prop_size = sub_com
-        .value_of("prop_size")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the prop size: {}", e);
-            process::exit(1);
-        });
-    let avai_size = sub_com
-        .value_of("avai_size")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the avai size: {}", e);
-            process::exit(1);
-        });
-    let ex_req_num = sub_com
-        .value_of("ex_req_num")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the ex_req_num: {}", e);
-            process::exit(1);
-        });
-    let in_req_num = sub_com
-        .value_of("in_req_num")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the in_req_num: {}", e);
-            process::exit(1);
-        });
-    let confirmation_depth = sub_com
-        .value_of("confirmation_depth")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the confirmation depth: {}", e);
-            process::exit(1);
-        });
-    let tx_diff = sub_com
-        .value_of("tx_diff")
-        .unwrap()
-        .parse::<String>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the tx_diff: {}", e);
-            process::exit(1);
-        });
-    let prop_diff = sub_com
-        .value_of("prop_diff")
-        .unwrap()
-        .parse::<String>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the prof_diff: {}", e);
-            process::exit(1);
-        });
-    let avai_diff = sub_com
-        .value_of("avai_diff")
-        .unwrap()
-        .parse::<String>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the avai_diff: {}", e);
-            process::exit(1);
-        });
-    let in_avai_diff = sub_com
-        .value_of("in_avai_diff")
-        .unwrap()
-        .parse::<String>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the in_avai_diff: {}", e);
-            process::exit(1);
-        });
-    let order_diff = sub_com
-        .value_of("order_diff")
-        .unwrap()
-        .parse::<String>()
-        .unwrap_or_else(|e| {
-            error!("Error parsing the order_diff: {}", e);
+            error!("Error parsing P2P workers: {}", e);
             process::exit(1);
         });
-    let p2p_workers = sub_com
-        .value_of("p2p_workers")
-        .unwrap()
-        .parse::<usize>()
+    let known_peers = options
+        .values_of("known_peer")
         .unwrap_or_else(|e| {
-            error!("Error parsing P2P workers: {}", e);
+            error!("Error parsing the known peers: {}", e);
             process::exit(1);
         });
-    
-    
-    let mut config = Configuration::new();
-    let tx_diff_bytes: [u8; 32] = decode_hex(tx_diff.as_str())
-        .unwrap()
-        .try_into().unwrap();
-    let prop_diff_bytes: [u8; 32] = decode_hex(prop_diff.as_str())
-        .unwrap()
-        .try_into().unwrap();
-    let avai_diff_bytes: [u8; 32] = decode_hex(avai_diff.as_str())
-        .unwrap()
-        .try_into().unwrap();
-    let in_avai_diff_bytes: [u8; 32] = decode_hex(in_avai_diff.as_str())
-        .unwrap()
-        .try_into().unwrap();
-    let order_diff_bytes: [u8; 32] = decode_hex(order_diff.as_str())
-        .unwrap()
-        .try_into().unwrap();
-    let tx_diff_hash: H256 = tx_diff_bytes.into();
-    let prop_diff_hash: H256 = prop_diff_bytes.into();
-    let avai_diff_hash: H256 = avai_diff_bytes.into();
-    let in_avai_diff_hash: H256 = in_avai_diff_bytes.into();
-    let order_diff_hash: H256 = order_diff_bytes.into();
-    config.tx_diff = tx_diff_hash;
-    config.prop_diff = prop_diff_hash;
-    config.avai_diff = avai_diff_hash;
-    config.in_avai_diff = in_avai_diff_hash;
-    config.order_diff = order_diff_hash;
-    config.block_size = block_size as usize;
-    config.symbol_size = symbol_size as usize;
-    assert!(block_size % symbol_size == 0);
-    config.num_symbol_per_block = block_size / symbol_size;
-    config.prop_size = prop_size as usize;
-    config.avai_size = avai_size as usize;
-    config.ex_req_num = ex_req_num as usize;
-    config.in_req_num = in_req_num as usize;
-    config.k = confirmation_depth as usize;
-    config.shard_id = shard_id as usize;
-    config.node_id = node_id as usize;
-    config.exper_number = exper_number as usize;
-    config.exper_iter = exper_iter as usize;
-    config.shard_num = shard_num as usize;
-    config.shard_size = shard_size as usize;
+
+    let config = Configuration::from_options(&options).unwrap_or_else(|e| {
+        error!("Error parsing the configuration: {}", e);
+        process::exit(1);
+    });
+    let dump_config = options.is_present("dump_config").unwrap_or(false);
+    if let Err(e) = options.check_unknown() {
+        error!("Error loading the configuration: {}", e);
+        process::exit(1);
+    }
+    if let Err(errors) = config.validate() {
+        for e in errors {
+            error!("Invalid configuration: {}", e);
+        }
+        process::exit(1);
+    }
+    if dump_config {
+        println!("{}", options.dump());
+        return;
+    }
     // let shard_id = format!("{:x}", shard_id);
     info!("configuration: {:?}", config);
 
     // let api_port: u16 = api_addr.port();
-    let prop_genesis_block = VersaBlock::PropBlock(ProposerBlock::default());
-    let prop_chain = Blockchain::new(prop_genesis_block, &config);
-
-    let mut genesis_avai_set: Vec<(H256, u32)> = vec![];
-    let avai_chains: Vec<Blockchain> = (0..config.shard_num)
-        .into_iter()
-        .map(|i| {
-            let mut header = BlockHeader::default();
-            header.set_shard_id(i);
-            let avai_block = AvailabilityBlock::new(
-                header,
-                0,
-                MerkleTree::<TransactionBlock>::new((vec![]).as_slice())
-            );
-            let avai_genesis_block = VersaBlock::ExAvaiBlock(avai_block);
-            genesis_avai_set.push((avai_genesis_block.hash(), i as u32));
-            Blockchain::new(avai_genesis_block, &config)
-        })
-        .collect();
-    // let chains_ref: Vec<&Arc<Mutex<Blockchain>>> = avai_chains
-    //     .iter()
-    //     .collect();
-    let ordering_genesis_block = VersaBlock::OrderBlock(OrderingBlock::new(
-        BlockHeader::default(),
-        0,
-        genesis_avai_set.clone(),
-    ));
-    let ordering_chain = Blockchain::new(ordering_genesis_block, &config);
     let multichain = Arc::new(
         Mutex::new(
-            Multichain::new(
-                prop_chain, 
-                avai_chains, 
-                ordering_chain, 
-                &config
-            )
+            new_multichain(&config)
         )
     );
 
@@ -346,38 +224,49 @@ pub fn start(sub_com: &clap::ArgMatches) {
         )
     );
 
+    let sync_manager = Arc::new(
+        Mutex::new(
+            SyncManager::new()
+        )
+    );
+
+    // the counters exported on /metrics
+    let metrics = Arc::new(Metrics::new());
+    // the chain events streamed on /events
+    let events = Arc::new(EventBus::new());
+
     // create channels between server and worker
     let (msg_tx, msg_rx) = channel::bounded(10000);
 
     // start the p2p server
-    let (server_ctx, server) = NetworkServer::new(p2p_addr, msg_tx, config.shard_id).unwrap();
+    let handshake = Handshake::new(
+        &config,
+        multichain.lock().unwrap().get_genesis_hashes(),
+        p2p_addr,
+    );
+    let (server_ctx, server) = NetworkServer::new(p2p_addr, msg_tx, handshake, &config, &metrics).unwrap();
     server_ctx.start().unwrap();
     
+    // create the miner, the workers tell it when the tips or the mempool change
+    let (miner_ctx, miner, finished_block_chan) = Miner::new(&multichain, &mempool, &config);
+
     // start the worker
-    let worker_ctx = NetworkWorker::new(
-        p2p_workers,
-        msg_rx,
-        &server,
-        &multichain,
-        &mempool,
-        &symbolpool,
-        &config,
-        &Arc::new(Mutex::new(HashMap::new())), // pending requests
-        &Arc::new(Mutex::new(HashMap::new())), // unavailable_cmt2avai_blocks
-        &Arc::new(Mutex::new(HashMap::new())), // unavailable_avai_block2cmts
-    );
+    let shared_ctx = WorkerContext {
+        server: server.clone(),
+        multichain: Arc::clone(&multichain),
+        mempool: Arc::clone(&mempool),
+        symbolpool: Arc::clone(&symbolpool),
+        sync_manager: Arc::clone(&sync_manager),
+        config: config.clone(),
+        miner: miner.clone(),
+        metrics: Arc::clone(&metrics),
+        events: Arc::clone(&events),
+    };
+    let worker_ctx = NetworkWorker::new(p2p_workers, msg_rx, &shared_ctx);
     worker_ctx.start();
 
     // start the miner
-    let (miner_ctx, miner, finished_block_chan) = Miner::new(&multichain, &mempool, &config);
-    let miner_worker_ctx = MinerWorker::new(
-        &server, 
-        finished_block_chan, 
-        &multichain,
-        &mempool,
-        &symbolpool,
-        &config,
-    );
+    let miner_worker_ctx = MinerWorker::new(finished_block_chan, &shared_ctx);
     miner_ctx.start();
     miner_worker_ctx.start();
 
@@ -393,8 +282,7 @@ pub fn start(sub_com: &clap::ArgMatches) {
 
     
     // connect to known peers
-    if let Some(known_peers) = sub_com.values_of("known_peer") {
-        let known_peers: Vec<String> = known_peers.map(|x| x.to_owned()).collect();
+    if !known_peers.is_empty() {
         let server = server.clone();
         thread::spawn(move || {
             for peer in known_peers {
@@ -411,6 +299,11 @@ pub fn start(sub_com: &clap::ArgMatches) {
                             info!("Connected to outgoing peer {}", &addr);
                             break;
                         }
+                        //the peer runs another network, retrying is useless
+                        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                            error!("Error connecting to peer {}: {}", addr, e);
+                            break;
+                        }
                         Err(e) => {
                             error!(
                                 "Error connecting to peer {}, retrying in one second: {}",
@@ -426,15 +319,50 @@ pub fn start(sub_com: &clap::ArgMatches) {
 
     }
 
+    // download the chains from a connected peer before relaying blocks
+    {
+        let server = server.clone();
+        let multichain = Arc::clone(&multichain);
+        let sync_manager = Arc::clone(&sync_manager);
+        thread::Builder::new()
+            .name("sync".to_string())
+            .spawn(move || loop {
+                thread::sleep(SYNC_CHECK_INTERVAL);
+                let connected: Vec<net::SocketAddr> = server
+                    .get_peers()
+                    .iter()
+                    .map(|info| info.addr)
+                    .collect();
+                let (next, is_done) = {
+                    let mut sync_manager = sync_manager.lock().unwrap();
+                    let next = sync_manager.drive(&multichain.lock().unwrap(), &connected, time::Instant::now());
+                    (next, sync_manager.is_done())
+                };
+                if let Some((peer, requests)) = next {
+                    info!("Syncing the chains from peer {}", peer);
+                    for request in requests {
+                        server.send(peer, request);
+                    }
+                }
+                if is_done {
+                    break;
+                }
+            })
+            .unwrap();
+    }
+
     // start the API server
-    ApiServer::start(
-        api_addr,
-        &miner,
-        &server,
-        &multichain,
-        &mempool,
-        &config,
-    );
+    let api_ctx = RpcContext {
+        miner,
+        network: server,
+        multichain,
+        mempool,
+        symbolpool,
+        sync_manager,
+        templates: Arc::new(Mutex::new(TemplatePool::new())),
+        config,
+    };
+    ApiServer::start(api_addr, api_ctx, &metrics, &events);
 
     loop {
         std::thread::park();
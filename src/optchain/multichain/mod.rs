@@ -20,7 +20,11 @@ use crate::{
 //     // sync::{Arc, Mutex},
 //     collections::BTreeSet,
 // };
-use std::time::{SystemTime};
+use std::{
+    collections::HashSet,
+    time::{SystemTime},
+};
+use serde::{Serialize, Deserialize};
 
 impl Hashable for (H256, u32) {
     fn hash(&self) -> H256 {
@@ -28,6 +32,24 @@ impl Hashable for (H256, u32) {
     }
 }
 
+//identifies one of the chains maintained by the multichain
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChainType {
+    Proposer,
+    Availability(u32), //shard id
+    Ordering,
+}
+
+impl ChainType {
+    //the chain a block is inserted in, given the parent it is inserted under
+    pub fn of_parent(parent: &VersaHash, shard_id: usize) -> ChainType {
+        match parent {
+            VersaHash::PropHash(_) => ChainType::Proposer,
+            VersaHash::ExHash(_) | VersaHash::InHash(_) => ChainType::Availability(shard_id as u32),
+            VersaHash::OrderHash(_) => ChainType::Ordering,
+        }
+    }
+}
 
 pub struct Multichain {
     pub config: Configuration,
@@ -192,6 +214,113 @@ impl Multichain {
     }
 
 
+    pub fn get_chain_types(&self) -> Vec<ChainType> {
+        let mut chains = vec![ChainType::Proposer];
+        chains.extend((0..self.config.shard_num).map(|i| ChainType::Availability(i as u32)));
+        chains.push(ChainType::Ordering);
+        chains
+    }
+
+    fn get_chain(&self, chain: &ChainType) -> Option<&Blockchain> {
+        match chain {
+            ChainType::Proposer => Some(&self.proposer_chain),
+            ChainType::Availability(shard_id) => self.availability_chains.get(*shard_id as usize),
+            ChainType::Ordering => Some(&self.ordering_chain),
+        }
+    }
+
+    pub fn get_block_locator(&self, chain: &ChainType) -> Vec<H256> {
+        match self.get_chain(chain) {
+            Some(c) => c.get_block_locator(),
+            None => vec![],
+        }
+    }
+
+    pub fn get_blocks_after(&self, chain: &ChainType, locator: &[H256], max: usize) -> Vec<VersaBlock> {
+        match self.get_chain(chain) {
+            Some(c) => c.get_blocks_after(locator, max),
+            None => vec![],
+        }
+    }
+
+    pub fn get_genesis_hashes(&self) -> Vec<(ChainType, H256)> {
+        self.get_chain_types()
+            .into_iter()
+            .filter_map(|chain| self.get_chain(&chain)
+                .map(|c| (chain, c.get_genesis_block().hash())))
+            .collect()
+    }
+
+    pub fn get_chain_height(&self, chain: &ChainType) -> Option<usize> {
+        self.get_chain(chain).map(|c| c.height)
+    }
+
+    pub fn get_block_height(&self, chain: &ChainType, hash: &H256) -> Option<usize> {
+        self.get_chain(chain).and_then(|c| c.get_block_height(hash))
+    }
+
+    pub fn get_block(&self, chain: &ChainType, hash: &H256) -> Option<VersaBlock> {
+        self.get_chain(chain).and_then(|c| c.get_block(hash))
+    }
+
+    pub fn get_fork(&self, chain: &ChainType, old_tip: &H256, new_tip: &H256) -> Option<(Vec<H256>, Vec<H256>)> {
+        self.get_chain(chain).and_then(|c| c.get_fork(old_tip, new_tip))
+    }
+
+    //the tx blocks the proposer chain references once its tip moved from `old_tip`
+    //to `new_tip`, and the ones it stopped referencing
+    pub fn get_prop_fork_tx_blks(&self, old_tip: &H256, new_tip: &H256) -> (Vec<TransactionBlock>, Vec<TransactionBlock>) {
+        let (abandoned, adopted) = match self.get_fork(&ChainType::Proposer, old_tip, new_tip) {
+            Some(fork) => fork,
+            None => return (vec![], vec![]),
+        };
+        let tx_blks_of = |hashes: &[H256]| -> Vec<TransactionBlock> {
+            hashes
+                .iter()
+                .filter_map(|hash| self.get_proposer_block(hash))
+                .flat_map(|blk| blk.get_tx_blocks())
+                .collect()
+        };
+        let adopted = tx_blks_of(&adopted);
+        let kept: HashSet<H256> = adopted.iter().map(|x| x.hash()).collect();
+        let abandoned = tx_blks_of(&abandoned)
+            .into_iter()
+            .filter(|x| !kept.contains(&x.hash()))
+            .collect();
+        (adopted, abandoned)
+    }
+
+    pub fn get_forking_rate(&self, chain: &ChainType) -> Option<f64> {
+        self.get_chain(chain).map(|c| c.get_forking_rate())
+    }
+
+    pub fn get_tip(&self, chain: &ChainType) -> Option<H256> {
+        self.get_chain(chain).map(|c| c.tip())
+    }
+
+    //ordered from genesis to the tip
+    pub fn all_blocks_in_longest_chain(&self, chain: &ChainType) -> Vec<H256> {
+        match self.get_chain(chain) {
+            Some(c) => c.all_blocks_in_longest_chain(),
+            None => vec![],
+        }
+    }
+
+    //the chains storing a block, an inclusive availability block being in several
+    pub fn find_block(&self, hash: &H256) -> Vec<ChainType> {
+        self.get_chain_types()
+            .into_iter()
+            .filter(|chain| self.contains_block(chain, hash))
+            .collect()
+    }
+
+    pub fn contains_block(&self, chain: &ChainType, hash: &H256) -> bool {
+        match self.get_chain(chain) {
+            Some(c) => c.get_block_height(hash).is_some(),
+            None => false,
+        }
+    }
+
     pub fn all_blocks_in_longest_proposer_chain(&self) -> Vec<H256> {
         self.proposer_chain
             .all_blocks_in_longest_chain()
@@ -223,6 +352,15 @@ impl Multichain {
         self.proposer_chain
             .get_tx_blk_in_longest_chain(blk_hash)
     }
+    //a tx block of any stored proposer or availability block
+    pub fn get_tx_blk(&self, tx_blk_hash: &H256) -> Option<TransactionBlock> {
+        self.proposer_chain
+            .get_tx_blk(tx_blk_hash)
+            .or_else(|| self.availability_chains
+                .iter()
+                .find_map(|chain| chain.get_tx_blk(tx_blk_hash)))
+    }
+
     pub fn get_highest_prop_block(&self) -> H256 {
         self.proposer_chain
             .tip()
@@ -321,6 +459,12 @@ impl Multichain {
         } 
     }
 
+    //look the availability block up in every shard
+    pub fn get_avai_block(&self, hash: &H256) -> Option<AvailabilityBlock> {
+        (0..self.availability_chains.len())
+            .find_map(|shard_id| self.get_avai_block_by_shard(hash, shard_id))
+    }
+
     pub fn get_prop_size(&self) -> usize {
         self.proposer_chain.size()
     }
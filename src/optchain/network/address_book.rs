@@ -0,0 +1,216 @@
+use serde::{Serialize, Deserialize};
+use log::{info, warn};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    net,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+//the maximal number of addresses sent in one Peers message
+pub const MAX_PEER_ADDRS: usize = 1000;
+//an address failing this many connections in a row is forgotten
+const MAX_FAILURES: u32 = 5;
+
+//the listening address of a node, as gossiped between peers
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PeerAddr {
+    pub addr: net::SocketAddr,
+    pub shard_id: usize,
+    pub node_id: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct AddrEntry {
+    peer_addr: PeerAddr,
+    last_seen: u64, //unix time in seconds of the last successful connection
+    failures: u32, //consecutive failed connections
+    #[serde(default)]
+    confirmed: bool, //a handshake over this address told us its shard
+}
+
+//the peers this node has heard of, learned from handshakes and Peers messages
+pub struct AddressBook {
+    entries: HashMap<net::SocketAddr, AddrEntry>,
+    path: Option<String>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl AddressBook {
+    //load the address book stored at `path`, if any
+    pub fn new(path: Option<String>) -> Self {
+        let mut entries: HashMap<net::SocketAddr, AddrEntry> = HashMap::new();
+        if let Some(p) = path.as_ref() {
+            if let Ok(content) = fs::read_to_string(p) {
+                match serde_json::from_str::<Vec<AddrEntry>>(&content) {
+                    Ok(stored) => {
+                        for entry in stored {
+                            entries.insert(entry.peer_addr.addr, entry);
+                        }
+                        info!("Loaded {} peer addresses from {}", entries.len(), p);
+                    }
+                    Err(e) => warn!("Ignoring corrupted address book {}: {}", p, e),
+                }
+            }
+        }
+        AddressBook {
+            entries,
+            path,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, addr: &net::SocketAddr) -> bool {
+        self.entries.contains_key(addr)
+    }
+
+    //learn an address as gossiped or claimed by a peer, return whether it was new.
+    //the claim does not override what a handshake over the address told us
+    pub fn add(&mut self, peer_addr: PeerAddr) -> bool {
+        match self.entries.get_mut(&peer_addr.addr) {
+            Some(entry) => {
+                if !entry.confirmed {
+                    entry.peer_addr = peer_addr;
+                }
+                false
+            }
+            None => {
+                self.entries.insert(peer_addr.addr, AddrEntry {
+                    peer_addr,
+                    last_seen: 0,
+                    failures: 0,
+                    confirmed: false,
+                });
+                true
+            }
+        }
+    }
+
+    pub fn is_confirmed(&self, addr: &net::SocketAddr) -> bool {
+        self.entries
+            .get(addr)
+            .map(|entry| entry.confirmed)
+            .unwrap_or(false)
+    }
+
+    //a connection to the address succeeded, and its handshake says the node
+    //listening there is `peer_addr`
+    pub fn mark_seen(&mut self, peer_addr: PeerAddr) {
+        let addr = peer_addr.addr;
+        let entry = self.entries.entry(addr).or_insert(AddrEntry {
+            peer_addr: peer_addr.clone(),
+            last_seen: 0,
+            failures: 0,
+            confirmed: false,
+        });
+        entry.peer_addr = peer_addr;
+        entry.last_seen = now_secs();
+        entry.failures = 0;
+        entry.confirmed = true;
+    }
+
+    //a connection to the address failed
+    pub fn mark_failed(&mut self, addr: &net::SocketAddr) {
+        let forget = match self.entries.get_mut(addr) {
+            Some(entry) => {
+                entry.failures += 1;
+                entry.failures >= MAX_FAILURES
+            }
+            None => false,
+        };
+        if forget {
+            self.entries.remove(addr);
+        }
+    }
+
+    //the addresses to gossip, most recently seen first
+    pub fn get_peer_addrs(&self, max: usize) -> Vec<PeerAddr> {
+        let mut entries: Vec<&AddrEntry> = self.entries.values().collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_seen));
+        entries.into_iter()
+            .take(max)
+            .map(|entry| entry.peer_addr.clone())
+            .collect()
+    }
+
+    //pick addresses to connect to. `outbound` is the number of outgoing connections
+    //per shard, `connected` the addresses to skip. Shards below `per_shard` are served
+    //first, then any shard until `max_outbound` connections.
+    pub fn select_candidates(
+        &self,
+        outbound: &HashMap<usize, usize>,
+        connected: &HashSet<net::SocketAddr>,
+        max_outbound: usize,
+        per_shard: usize,
+    ) -> Vec<PeerAddr> {
+        let mut total: usize = outbound.values().sum();
+        let mut shard_counts = outbound.clone();
+        let mut entries: Vec<&AddrEntry> = self.entries
+            .values()
+            .filter(|entry| !connected.contains(&entry.peer_addr.addr))
+            .collect();
+        //prefer reliable and recently seen peers
+        entries.sort_by(|a, b| a.failures
+            .cmp(&b.failures)
+            .then(b.last_seen.cmp(&a.last_seen)));
+
+        let mut candidates: Vec<PeerAddr> = vec![];
+        for entry in entries.iter() {
+            if total >= max_outbound {
+                return candidates;
+            }
+            let count = shard_counts.entry(entry.peer_addr.shard_id).or_insert(0);
+            if *count < per_shard {
+                *count += 1;
+                total += 1;
+                candidates.push(entry.peer_addr.clone());
+            }
+        }
+        for entry in entries.iter() {
+            if total >= max_outbound {
+                break;
+            }
+            if !candidates.contains(&entry.peer_addr) {
+                total += 1;
+                candidates.push(entry.peer_addr.clone());
+            }
+        }
+        candidates
+    }
+
+    pub fn save(&self) {
+        let path = match self.path.as_ref() {
+            Some(p) => p,
+            None => return,
+        };
+        if let Some(dir) = Path::new(path).parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                warn!("Error creating directory for the address book: {}", e);
+                return;
+            }
+        }
+        let entries: Vec<&AddrEntry> = self.entries.values().collect();
+        match serde_json::to_string_pretty(&entries) {
+            Ok(content) => {
+                if let Err(e) = fs::write(path, content) {
+                    warn!("Error writing the address book {}: {}", path, e);
+                }
+            }
+            Err(e) => warn!("Error serializing the address book: {}", e),
+        }
+    }
+}
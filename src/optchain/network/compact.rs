@@ -0,0 +1,218 @@
+use serde::{Serialize, Deserialize};
+use crate::{
+    types::{
+        hash::{H256, Hashable},
+        merkle::MerkleTree,
+    },
+    optchain::block::{
+        Info,
+        BlockHeader,
+        proposer_block::ProposerBlock,
+        availability_block::AvailabilityBlock,
+        transaction_block::TransactionBlock,
+        versa_block::{
+            VersaBlock,
+            VersaHash,
+        },
+    },
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    net,
+};
+
+//the maximal number of compact blocks waiting for their missing tx blocks
+pub const MAX_PENDING_COMPACT_BLOCKS: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactKind {
+    Prop,
+    ExAvai,
+    InAvai,
+}
+
+//A proposer or availability block whose tx set is replaced by the hashes of its
+//tx blocks, which the receiver usually got already through the TxBlocks gossip.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompactBlock {
+    kind: CompactKind,
+    header: BlockHeader,
+    nonce: u32,
+    tx_blk_hashes: Vec<H256>,
+}
+
+impl CompactBlock {
+    //ordering blocks carry no tx blocks and are always relayed in full
+    pub fn from_block(block: &VersaBlock) -> Option<Self> {
+        let kind = match block {
+            VersaBlock::PropBlock(_) => CompactKind::Prop,
+            VersaBlock::ExAvaiBlock(_) => CompactKind::ExAvai,
+            VersaBlock::InAvaiBlock(_) => CompactKind::InAvai,
+            VersaBlock::OrderBlock(_) => return None,
+        };
+        Some(CompactBlock {
+            kind,
+            header: block.get_header(),
+            nonce: block.get_nonce(),
+            tx_blk_hashes: block
+                .get_tx_blocks()
+                .iter()
+                .map(|tx_blk| tx_blk.hash())
+                .collect(),
+        })
+    }
+
+    pub fn get_versa_hash(&self) -> VersaHash {
+        let hash = H256::pow_hash(&self.header.hash(), self.nonce);
+        match self.kind {
+            CompactKind::Prop => VersaHash::PropHash(hash),
+            CompactKind::ExAvai => VersaHash::ExHash(hash),
+            CompactKind::InAvai => VersaHash::InHash(hash),
+        }
+    }
+
+    pub fn get_tx_blk_hashes(&self) -> &[H256] {
+        &self.tx_blk_hashes
+    }
+
+    //rebuild the full block, checking the tx blocks against the root in the header
+    pub fn reconstruct(&self, tx_blks: Vec<TransactionBlock>) -> Result<VersaBlock, String> {
+        if tx_blks.len() != self.tx_blk_hashes.len()
+            || tx_blks.iter().zip(self.tx_blk_hashes.iter()).any(|(b, h)| b.hash() != *h)
+        {
+            return Err(String::from("tx blocks do not match the compact block"));
+        }
+        let tx_set = MerkleTree::<TransactionBlock>::new(tx_blks.as_slice());
+        let (block, root) = match self.kind {
+            CompactKind::Prop => (
+                VersaBlock::PropBlock(ProposerBlock::new(self.header.clone(), self.nonce, tx_set.clone())),
+                self.header.get_prop_root(),
+            ),
+            CompactKind::ExAvai => (
+                VersaBlock::ExAvaiBlock(AvailabilityBlock::new(self.header.clone(), self.nonce, tx_set.clone())),
+                self.header.get_avai_root(),
+            ),
+            CompactKind::InAvai => (
+                VersaBlock::InAvaiBlock(AvailabilityBlock::new(self.header.clone(), self.nonce, tx_set.clone())),
+                self.header.get_avai_root(),
+            ),
+        };
+        if tx_set.root() != root {
+            return Err(format!("tx set of {:?} does not match its header", self.get_versa_hash()));
+        }
+        Ok(block)
+    }
+}
+
+//a compact block waiting for some of its tx blocks
+struct PendingBlock {
+    compact: CompactBlock,
+    tx_blks: Vec<Option<TransactionBlock>>,
+}
+
+//The compact blocks received from peers whose tx blocks are not all known locally.
+//The oldest ones are dropped first, their full block is then requested again.
+#[derive(Default)]
+pub struct CompactPool {
+    pending: HashMap<VersaHash, PendingBlock>,
+    order: VecDeque<VersaHash>,
+    //the peers asked for missing tx blocks, kept after the block is rebuilt or
+    //dropped so that a late answer is not taken for unsolicited data
+    requested: HashSet<(VersaHash, net::SocketAddr)>,
+    request_order: VecDeque<(VersaHash, net::SocketAddr)>,
+}
+
+impl CompactPool {
+    pub fn new() -> Self {
+        CompactPool {
+            pending: HashMap::new(),
+            order: VecDeque::new(),
+            requested: HashSet::new(),
+            request_order: VecDeque::new(),
+        }
+    }
+
+    pub fn mark_requested(&mut self, versa_hash: &VersaHash, peer: net::SocketAddr) {
+        let key = (versa_hash.clone(), peer);
+        if !self.requested.insert(key.clone()) {
+            return;
+        }
+        self.request_order.push_back(key);
+        while self.request_order.len() > MAX_PENDING_COMPACT_BLOCKS {
+            if let Some(old) = self.request_order.pop_front() {
+                self.requested.remove(&old);
+            }
+        }
+    }
+
+    //whether the peer was asked for tx blocks of the block, forgetting the request
+    pub fn take_request(&mut self, versa_hash: &VersaHash, peer: &net::SocketAddr) -> bool {
+        let key = (versa_hash.clone(), *peer);
+        if !self.requested.remove(&key) {
+            return false;
+        }
+        self.request_order.retain(|k| *k != key);
+        true
+    }
+
+    pub fn contains(&self, versa_hash: &VersaHash) -> bool {
+        self.pending.contains_key(versa_hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    //keep a compact block whose tx blocks are partly known, and return the indexs
+    //of the missing ones
+    pub fn insert(&mut self, compact: CompactBlock, tx_blks: Vec<Option<TransactionBlock>>) -> Vec<usize> {
+        let missing: Vec<usize> = tx_blks
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.is_none())
+            .map(|(i, _)| i)
+            .collect();
+        let versa_hash = compact.get_versa_hash();
+        if self.pending.insert(versa_hash.clone(), PendingBlock { compact, tx_blks }).is_none() {
+            self.order.push_back(versa_hash);
+        }
+        while self.order.len() > MAX_PENDING_COMPACT_BLOCKS {
+            if let Some(old) = self.order.pop_front() {
+                self.pending.remove(&old);
+            }
+        }
+        missing
+    }
+
+    //fill a pending block with the tx blocks returned by a peer. Once complete, the
+    //block is removed and rebuilt.
+    pub fn fill(&mut self, versa_hash: &VersaHash, tx_blks: Vec<TransactionBlock>) -> Option<Result<VersaBlock, String>> {
+        let pending = self.pending.get_mut(versa_hash)?;
+        let by_hash: HashMap<H256, TransactionBlock> = tx_blks
+            .into_iter()
+            .map(|b| (b.hash(), b))
+            .collect();
+        for (slot, hash) in pending.tx_blks.iter_mut().zip(pending.compact.tx_blk_hashes.iter()) {
+            if slot.is_none() {
+                *slot = by_hash.get(hash).cloned();
+            }
+        }
+        if pending.tx_blks.iter().any(|b| b.is_none()) {
+            return Some(Err(format!("tx blocks of {:?} are still missing", versa_hash)));
+        }
+        let pending = self.pending.remove(versa_hash).unwrap();
+        self.order.retain(|h| h != versa_hash);
+        let tx_blks = pending.tx_blks.into_iter().flatten().collect();
+        Some(pending.compact.reconstruct(tx_blks))
+    }
+
+    pub fn remove(&mut self, versa_hash: &VersaHash) {
+        if self.pending.remove(versa_hash).is_some() {
+            self.order.retain(|h| h != versa_hash);
+        }
+    }
+}
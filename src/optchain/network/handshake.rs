@@ -0,0 +1,185 @@
+use serde::{Serialize, Deserialize};
+use crate::{
+    types::hash::H256,
+    optchain::{
+        configuration::Configuration,
+        multichain::ChainType,
+        network::{
+            peer,
+            misbehavior::{self, Score},
+        },
+    },
+};
+use futures::io::{AsyncReadExt, AsyncWriteExt};
+use smol::Async;
+use std::{
+    io::{Error, ErrorKind},
+    net,
+    time::{Duration, Instant},
+};
+
+//bump it whenever the wire format of the messages changes
+pub const PROTOCOL_VERSION: u32 = 3;
+//a handshake is small, a bigger frame means the peer is not an optchain node
+const MAX_HANDSHAKE_SIZE: usize = 64 * 1024;
+//how long a new connection may take to complete the handshake
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+//the consensus parameters two nodes must agree on to accept each other's blocks
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChainParameters {
+    pub tx_diff: H256,
+    pub prop_diff: H256,
+    pub avai_diff: H256,
+    pub in_avai_diff: H256,
+    pub order_diff: H256,
+    pub block_size: usize,
+    pub symbol_size: usize,
+    pub prop_size: usize,
+    pub avai_size: usize,
+    pub shard_num: usize,
+    pub shard_size: usize,
+    pub k: usize,
+}
+
+impl ChainParameters {
+    pub fn from_config(config: &Configuration) -> Self {
+        ChainParameters {
+            tx_diff: config.tx_diff,
+            prop_diff: config.prop_diff,
+            avai_diff: config.avai_diff,
+            in_avai_diff: config.in_avai_diff,
+            order_diff: config.order_diff,
+            block_size: config.block_size,
+            symbol_size: config.symbol_size,
+            prop_size: config.prop_size,
+            avai_size: config.avai_size,
+            shard_num: config.shard_num,
+            shard_size: config.shard_size,
+            k: config.k,
+        }
+    }
+}
+
+//the first frame exchanged on every connection, before any Message
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Handshake {
+    pub version: u32,
+    pub genesis_hashes: Vec<(ChainType, H256)>,
+    pub params: ChainParameters,
+    pub shard_id: usize,
+    pub node_id: usize,
+    pub listen_addr: net::SocketAddr,
+}
+
+impl Handshake {
+    pub fn new(
+        config: &Configuration,
+        genesis_hashes: Vec<(ChainType, H256)>,
+        listen_addr: net::SocketAddr,
+    ) -> Self {
+        Handshake {
+            version: PROTOCOL_VERSION,
+            genesis_hashes,
+            params: ChainParameters::from_config(config),
+            shard_id: config.shard_id,
+            node_id: config.node_id,
+            listen_addr,
+        }
+    }
+
+    //check whether the remote node runs the same network as this one
+    pub fn check(&self, remote: &Handshake) -> Result<(), String> {
+        if remote.version != self.version {
+            return Err(format!("protocol version {} mismatches ours {}", remote.version, self.version));
+        }
+        if remote.params != self.params {
+            return Err(format!("chain parameters {:?} mismatch ours {:?}", remote.params, self.params));
+        }
+        if remote.genesis_hashes != self.genesis_hashes {
+            return Err(String::from("genesis blocks mismatch ours"));
+        }
+        if remote.shard_id >= self.params.shard_num {
+            return Err(format!("shard id {} is out of range", remote.shard_id));
+        }
+        Ok(())
+    }
+
+    //write the handshake as a length-prefixed frame, like the messages
+    pub async fn write_to(&self, stream: &mut Async<net::TcpStream>) -> std::io::Result<()> {
+        let payload = bincode::serialize(self)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        stream.write_all(&(payload.len() as u32).to_be_bytes()).await?;
+        stream.write_all(&payload).await?;
+        stream.flush().await
+    }
+
+    pub async fn read_from(stream: &mut Async<net::TcpStream>) -> std::io::Result<Self> {
+        let mut size_buffer = [0u8; std::mem::size_of::<u32>()];
+        stream.read_exact(&mut size_buffer).await?;
+        let size = u32::from_be_bytes(size_buffer) as usize;
+        if size > MAX_HANDSHAKE_SIZE {
+            return Err(Error::new(ErrorKind::InvalidData, format!("handshake of {} bytes is too large", size)));
+        }
+        let mut payload = vec![0u8; size];
+        stream.read_exact(&mut payload).await?;
+        bincode::deserialize(&payload)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+//what we know about a connected peer
+#[derive(Serialize, Debug, Clone)]
+pub struct PeerInfo {
+    pub addr: net::SocketAddr,
+    pub listen_addr: net::SocketAddr,
+    pub shard_id: usize,
+    pub node_id: usize,
+    pub version: u32,
+    pub direction: peer::Direction,
+    pub rtt_ms: Option<u64>, //round trip time of the last answered ping
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub last_seen: u64, //unix time in seconds of the last received message
+    pub misbehavior_score: u32, //the decayed score when the info was taken
+    #[serde(skip)]
+    pub ban_key: net::SocketAddr,
+    #[serde(skip)]
+    pub conn_id: u64, //tells a connection from a later one to the same address
+    #[serde(skip)]
+    pub pending_ping: Option<(String, Instant)>,
+    #[serde(skip)]
+    pub score: Score,
+}
+
+impl PeerInfo {
+    pub fn new(
+        addr: net::SocketAddr,
+        handshake: &Handshake,
+        direction: peer::Direction,
+        conn_id: u64,
+    ) -> Self {
+        let ban_key = misbehavior::ban_key(&handshake.listen_addr, addr.ip());
+        PeerInfo {
+            addr,
+            listen_addr: handshake.listen_addr,
+            shard_id: handshake.shard_id,
+            node_id: handshake.node_id,
+            version: handshake.version,
+            direction,
+            rtt_ms: None,
+            bytes_in: 0,
+            bytes_out: 0,
+            last_seen: 0,
+            misbehavior_score: 0,
+            ban_key,
+            conn_id,
+            pending_ping: None,
+            score: Score::new(Instant::now()),
+        }
+    }
+
+    pub fn is_outgoing(&self) -> bool {
+        self.direction == peer::Direction::Outgoing
+    }
+}
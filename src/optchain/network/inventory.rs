@@ -0,0 +1,206 @@
+use crate::{
+    types::hash::{H256, Hashable},
+    optchain::{
+        block::versa_block::VersaHash,
+        clock::Clock,
+        network::message::Message,
+    },
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+//the number of items remembered per peer, the oldest are forgotten first
+pub const MAX_KNOWN_ITEMS: usize = 50000;
+//a request not answered within this delay may be sent to another peer
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+//an item announced, requested or relayed between peers
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InvItem {
+    Block(VersaHash),
+    TxBlock(H256),
+    Symbol(H256), //hash of the symbol index
+}
+
+impl InvItem {
+    //the items a message shows its sender has
+    pub fn from_message(msg: &Message) -> Vec<InvItem> {
+        match msg {
+            Message::NewBlockHash(hashes) => hashes
+                .iter()
+                .map(|h| InvItem::Block(h.clone()))
+                .collect(),
+            Message::Blocks(blocks) => blocks
+                .iter()
+                .map(|b| InvItem::Block(b.get_versa_hash()))
+                .collect(),
+            Message::CompactBlocks(compacts) => compacts
+                .iter()
+                .map(|c| InvItem::Block(c.get_versa_hash()))
+                .collect(),
+            Message::NewTxBlockHash(hashes) => hashes
+                .iter()
+                .map(|h| InvItem::TxBlock(*h))
+                .collect(),
+            Message::TxBlocks(tx_blks) => tx_blks
+                .iter()
+                .map(|b| InvItem::TxBlock(b.hash()))
+                .collect(),
+            Message::NewSymbols(indexs) => indexs
+                .iter()
+                .map(|i| InvItem::Symbol(i.hash()))
+                .collect(),
+            Message::Symbols(symbols) => symbols
+                .iter()
+                .map(|s| InvItem::Symbol(s.get_index().hash()))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+//the items a peer is known to have, because it sent or received them
+pub struct KnownInventory {
+    items: HashSet<InvItem>,
+    order: VecDeque<InvItem>,
+    capacity: usize,
+}
+
+impl Default for KnownInventory {
+    fn default() -> Self {
+        Self::new(MAX_KNOWN_ITEMS)
+    }
+}
+
+impl KnownInventory {
+    pub fn new(capacity: usize) -> Self {
+        KnownInventory {
+            items: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn contains(&self, item: &InvItem) -> bool {
+        self.items.contains(item)
+    }
+
+    pub fn insert(&mut self, item: InvItem) {
+        if self.items.insert(item.clone()) {
+            self.order.push_back(item);
+            while self.order.len() > self.capacity {
+                if let Some(old) = self.order.pop_front() {
+                    self.items.remove(&old);
+                }
+            }
+        }
+    }
+
+    //the announcement restricted to the items the peer does not know, which are
+    //then marked as known. Other messages are returned unchanged.
+    pub fn filter_announcement(&mut self, msg: &Message) -> Option<Message> {
+        let filtered = match msg {
+            Message::NewBlockHash(hashes) => {
+                let hashes: Vec<VersaHash> = hashes
+                    .iter()
+                    .filter(|h| !self.contains(&InvItem::Block((*h).clone())))
+                    .cloned()
+                    .collect();
+                match hashes.is_empty() {
+                    true => None,
+                    false => Some(Message::NewBlockHash(hashes)),
+                }
+            }
+            Message::NewTxBlockHash(hashes) => {
+                let hashes: Vec<H256> = hashes
+                    .iter()
+                    .filter(|h| !self.contains(&InvItem::TxBlock(**h)))
+                    .cloned()
+                    .collect();
+                match hashes.is_empty() {
+                    true => None,
+                    false => Some(Message::NewTxBlockHash(hashes)),
+                }
+            }
+            Message::TxBlocks(tx_blks) => {
+                let tx_blks: Vec<_> = tx_blks
+                    .iter()
+                    .filter(|b| !self.contains(&InvItem::TxBlock(b.hash())))
+                    .cloned()
+                    .collect();
+                match tx_blks.is_empty() {
+                    true => None,
+                    false => Some(Message::TxBlocks(tx_blks)),
+                }
+            }
+            Message::NewSymbols(indexs) => {
+                let indexs: Vec<_> = indexs
+                    .iter()
+                    .filter(|i| !self.contains(&InvItem::Symbol(i.hash())))
+                    .cloned()
+                    .collect();
+                match indexs.is_empty() {
+                    true => None,
+                    false => Some(Message::NewSymbols(indexs)),
+                }
+            }
+            _ => return Some(msg.clone()),
+        };
+        if let Some(msg) = filtered.as_ref() {
+            for item in InvItem::from_message(msg) {
+                self.insert(item);
+            }
+        }
+        filtered
+    }
+}
+
+//the items requested from some peer and not received yet, shared by the workers
+//so that the same item is not requested from every peer announcing it
+#[derive(Default)]
+pub struct InFlightRequests {
+    requests: HashMap<InvItem, Instant>,
+    clock: Clock,
+}
+
+impl InFlightRequests {
+    pub fn new() -> Self {
+        Self::with_clock(Clock::System)
+    }
+
+    //the requests time out on the given clock, e.g. the virtual one of a simulation
+    pub fn with_clock(clock: Clock) -> Self {
+        InFlightRequests {
+            requests: HashMap::new(),
+            clock,
+        }
+    }
+
+    //keep the items not requested recently and mark them as requested
+    pub fn filter_new<T, F>(&mut self, items: Vec<T>, to_item: F) -> Vec<T>
+    where
+        F: Fn(&T) -> InvItem,
+    {
+        let now = self.clock.now();
+        self.requests.retain(|_, sent_at| now.duration_since(*sent_at) < REQUEST_TIMEOUT);
+        items
+            .into_iter()
+            .filter(|x| {
+                let item = to_item(x);
+                match self.requests.contains_key(&item) {
+                    true => false,
+                    false => {
+                        self.requests.insert(item, now);
+                        true
+                    }
+                }
+            })
+            .collect()
+    }
+
+    pub fn received(&mut self, item: &InvItem) {
+        self.requests.remove(item);
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Serialize, Deserialize};
+use futures::{
+    channel::mpsc,
+    stream::StreamExt,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    net,
+    time::{Duration, Instant},
+};
+
+//the delay and the bandwidth of the link to one peer
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct LinkSpec {
+    #[serde(default)]
+    pub delay: u64, //one-way propagation delay in ms
+    #[serde(default)]
+    pub bandwidth: f64, //in Mbps, 0 means unlimited
+}
+
+impl LinkSpec {
+    pub fn is_ideal(&self) -> bool {
+        self.delay == 0 && self.bandwidth <= 0.0
+    }
+
+    pub fn get_delay(&self) -> Duration {
+        Duration::from_millis(self.delay)
+    }
+
+    //in bytes per second
+    pub fn get_rate(&self) -> Option<f64> {
+        match self.bandwidth > 0.0 {
+            true => Some(self.bandwidth * 1_000_000.0 / 8.0),
+            false => None,
+        }
+    }
+}
+
+//Emulated network conditions, read from the same JSON as scripts/config.json: a
+//propagation delay for every link and a bandwidth matrix indexed by the shards
+//of both ends. Links to given peers can be overridden by their listening address.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LinkEmulation {
+    #[serde(default)]
+    pub propagation_delay: u64, //in ms
+    #[serde(default)]
+    pub bandwidths: Vec<Vec<f64>>, //in Mbps, 0 means unlimited
+    #[serde(default)]
+    pub peer_links: HashMap<net::SocketAddr, LinkSpec>,
+}
+
+impl LinkEmulation {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path, e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("cannot parse {}: {}", path, e))
+    }
+
+    //the link from a node of `local_shard` to a peer
+    pub fn get_link(&self, local_shard: usize, remote_shard: usize, remote_addr: &net::SocketAddr) -> LinkSpec {
+        if let Some(spec) = self.peer_links.get(remote_addr) {
+            return *spec;
+        }
+        let bandwidth = self.bandwidths
+            .get(local_shard)
+            .and_then(|row| row.get(remote_shard))
+            .cloned()
+            .unwrap_or(0.0);
+        LinkSpec {
+            delay: self.propagation_delay,
+            bandwidth,
+        }
+    }
+}
+
+//a token bucket allowing bursts of `capacity` bytes, then `rate` bytes per second
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, capacity: f64, now: Instant) -> Self {
+        TokenBucket {
+            rate,
+            capacity,
+            tokens: capacity,
+            last: now,
+        }
+    }
+
+    //take the tokens of a frame and return how long to wait before sending it.
+    //A frame larger than the bucket puts it in debt, delaying the next ones.
+    pub fn reserve(&mut self, bytes: usize, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.tokens -= bytes as f64;
+        match self.tokens >= 0.0 {
+            true => Duration::ZERO,
+            false => Duration::from_secs_f64(-self.tokens / self.rate),
+        }
+    }
+}
+
+//shape the frames written to a peer: each frame waits for the bandwidth, then
+//reaches the writer after the propagation delay. Frames are not held back by the
+//delay of the previous ones, as on a real link.
+pub async fn shape_bandwidth(
+    mut input: mpsc::UnboundedReceiver<Vec<u8>>,
+    output: mpsc::UnboundedSender<(Instant, Vec<u8>)>,
+    spec: LinkSpec,
+) {
+    //bursts of 100ms worth of traffic
+    let mut bucket = spec
+        .get_rate()
+        .map(|rate| TokenBucket::new(rate, rate / 10.0, Instant::now()));
+    while let Some(frame) = input.next().await {
+        if let Some(bucket) = bucket.as_mut() {
+            let wait = bucket.reserve(frame.len() + 4, Instant::now());
+            if !wait.is_zero() {
+                smol::Timer::after(wait).await;
+            }
+        }
+        if output.unbounded_send((Instant::now() + spec.get_delay(), frame)).is_err() {
+            break;
+        }
+    }
+}
+
+pub async fn delay_frames(
+    mut input: mpsc::UnboundedReceiver<(Instant, Vec<u8>)>,
+    output: mpsc::UnboundedSender<Vec<u8>>,
+) {
+    while let Some((due, frame)) = input.next().await {
+        if due > Instant::now() {
+            smol::Timer::at(due).await;
+        }
+        if output.unbounded_send(frame).is_err() {
+            break;
+        }
+    }
+}
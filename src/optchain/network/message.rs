@@ -1,4 +1,5 @@
 use serde::{Serialize, Deserialize};
+use bincode::Options;
 
 use crate::{
         types::{
@@ -13,6 +14,12 @@ use crate::{
             }
         },
         symbolpool::{SymbolIndex, Symbol},
+        multichain::ChainType,
+        network::{
+            sync::SyncHeader,
+            address_book::PeerAddr,
+            compact::CompactBlock,
+        },
     }
 };
 
@@ -20,6 +27,9 @@ use crate::{
 pub enum Message {
     Ping(String),
     Pong(String),
+    //Peer discovery
+    GetPeers,
+    Peers(Vec<PeerAddr>),
     //Exclusive Block
     NewTxBlockHash(Vec<H256>),
     GetTxBlocks(Vec<H256>),
@@ -28,6 +38,14 @@ pub enum Message {
     NewBlockHash(Vec<VersaHash>),
     GetBlocks(Vec<VersaHash>),
     Blocks(Vec<VersaBlock>),
+    //Compact relay of proposer and availability blocks
+    GetCompactBlocks(Vec<VersaHash>), //answered with CompactBlocks, or Blocks for ordering blocks
+    CompactBlocks(Vec<CompactBlock>),
+    GetBlockTxs((VersaHash, Vec<usize>)), //(block, indexs of the missing tx blocks)
+    BlockTxs((VersaHash, Vec<TransactionBlock>)),
+    //Header-first sync
+    GetHeaders((ChainType, Vec<H256>)), //(chain, block locator)
+    Headers((ChainType, usize, Vec<SyncHeader>)), //(chain, peer tip height, headers)
     //Data Availability Sample
     NewSymbols(Vec<SymbolIndex>),
     GetSymbols(Vec<SymbolIndex>), //(cmt_root: H256, tx_index)
@@ -36,3 +54,47 @@ pub enum Message {
     //missing block
     // NewMissBlockHash((Vec<H256>, u32)),
 }
+
+//the largest frame a peer may send; it also bounds the allocations when decoding
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+impl Message {
+    //decode a frame received from a peer, which cannot be trusted
+    pub fn decode(bytes: &[u8]) -> Result<Message, String> {
+        if bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(format!("message of {} bytes is too large", bytes.len()));
+        }
+        //the same encoding as bincode::serialize, with a size limit
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(MAX_MESSAGE_SIZE as u64)
+            .deserialize(bytes)
+            .map_err(|e| format!("malformed message: {}", e))
+    }
+
+    //the name of the variant, used as a label of the metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            Message::Ping(_) => "Ping",
+            Message::Pong(_) => "Pong",
+            Message::GetPeers => "GetPeers",
+            Message::Peers(_) => "Peers",
+            Message::NewTxBlockHash(_) => "NewTxBlockHash",
+            Message::GetTxBlocks(_) => "GetTxBlocks",
+            Message::TxBlocks(_) => "TxBlocks",
+            Message::NewBlockHash(_) => "NewBlockHash",
+            Message::GetBlocks(_) => "GetBlocks",
+            Message::Blocks(_) => "Blocks",
+            Message::GetCompactBlocks(_) => "GetCompactBlocks",
+            Message::CompactBlocks(_) => "CompactBlocks",
+            Message::GetBlockTxs(_) => "GetBlockTxs",
+            Message::BlockTxs(_) => "BlockTxs",
+            Message::GetHeaders(_) => "GetHeaders",
+            Message::Headers(_) => "Headers",
+            Message::NewSymbols(_) => "NewSymbols",
+            Message::GetSymbols(_) => "GetSymbols",
+            Message::Symbols(_) => "Symbols",
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    net,
+    time::{Duration, Instant},
+};
+
+//a connection reaching this score is dropped and its address banned
+pub const BAN_THRESHOLD: u32 = 100;
+pub const BAN_DURATION: Duration = Duration::from_secs(600);
+//a score loses one point per interval, so that rare honest mistakes never add up to a ban
+pub const SCORE_DECAY_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misbehavior {
+    MalformedMessage, //the frame cannot be decoded
+    OversizedMessage, //the frame is larger than MAX_MESSAGE_SIZE
+    InvalidPow, //a block whose hash does not match its header and nonce
+    InvalidSymbol, //a symbol failing its merkle proof
+    InvalidHeaders, //headers not linked or not connected to our chain
+    UnsolicitedData, //data we did not ask for
+}
+
+impl Misbehavior {
+    pub fn score(&self) -> u32 {
+        match self {
+            Misbehavior::MalformedMessage => 50,
+            Misbehavior::OversizedMessage => BAN_THRESHOLD,
+            //an honest peer may relay a block mined before a change of target
+            Misbehavior::InvalidPow => 50,
+            Misbehavior::InvalidSymbol => 20,
+            Misbehavior::InvalidHeaders => 50,
+            Misbehavior::UnsolicitedData => 2,
+        }
+    }
+}
+
+//the misbehavior score of a connection, decaying over time
+#[derive(Debug, Clone)]
+pub struct Score {
+    points: u32,
+    updated: Instant,
+}
+
+impl Score {
+    pub fn new(now: Instant) -> Self {
+        Score {
+            points: 0,
+            updated: now,
+        }
+    }
+
+    fn decay(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.updated).as_secs() / SCORE_DECAY_INTERVAL.as_secs()
+    }
+
+    pub fn get(&self, now: Instant) -> u32 {
+        self.points.saturating_sub(self.decay(now).min(u32::MAX as u64) as u32)
+    }
+
+    //add the points of a misbehavior and return the new score
+    pub fn add(&mut self, points: u32, now: Instant) -> u32 {
+        let decay = self.decay(now);
+        let current = self.get(now);
+        //the part of an interval not decayed yet still counts
+        self.updated = if current == 0 {
+            now
+        } else {
+            self.updated + SCORE_DECAY_INTERVAL * decay as u32
+        };
+        self.points = current.saturating_add(points);
+        self.points
+    }
+}
+
+//the key a peer is banned under: its IP, or its listening address for loopback peers,
+//so that one misbehaving node of a local experiment does not ban all the others
+pub fn ban_key(listen_addr: &net::SocketAddr, remote_ip: net::IpAddr) -> net::SocketAddr {
+    if remote_ip.is_loopback() {
+        *listen_addr
+    } else {
+        net::SocketAddr::new(remote_ip, 0)
+    }
+}
+
+#[derive(Default)]
+pub struct BanList {
+    banned: HashMap<net::SocketAddr, Instant>, //key -> end of the ban
+}
+
+impl BanList {
+    pub fn new() -> Self {
+        BanList {
+            banned: HashMap::new(),
+        }
+    }
+
+    pub fn ban(&mut self, key: net::SocketAddr, duration: Duration) {
+        self.banned.insert(key, Instant::now() + duration);
+    }
+
+    pub fn is_banned(&mut self, key: &net::SocketAddr) -> bool {
+        match self.banned.get(key) {
+            Some(end) if *end > Instant::now() => true,
+            Some(_) => {
+                self.banned.remove(key);
+                false
+            }
+            None => false,
+        }
+    }
+}
@@ -1,4 +1,12 @@
+pub mod address_book;
+pub mod compact;
+pub mod handshake;
+pub mod inventory;
+pub mod link;
 pub mod message;
+pub mod misbehavior;
 pub mod peer;
+pub mod relay;
 pub mod server;
 pub mod worker;
+pub mod sync;
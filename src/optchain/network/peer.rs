@@ -1,30 +1,105 @@
-use super::message::Message;
+use super::message::{Message, MAX_MESSAGE_SIZE};
+use crate::optchain::metrics::Metrics;
 use futures::{channel::mpsc, sink::SinkExt};
-use log::trace;
+use log::{trace, warn};
+use serde::Serialize;
 use smol::Async;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub fn new(
     stream: &Async<std::net::TcpStream>,
+    metrics: &Arc<Metrics>,
 ) -> std::io::Result<(mpsc::UnboundedReceiver<Vec<u8>>, Handle)> {
     let (write_sender, write_receiver) = mpsc::unbounded();
     let addr = stream.get_ref().peer_addr()?;
     let handle = Handle {
         write_queue: write_sender,
         addr,
+        traffic: Arc::new(Traffic::new()),
+        metrics: Arc::clone(metrics),
+        stream: Some(Arc::new(stream.get_ref().try_clone()?)),
     };
     Ok((write_receiver, handle))
 }
 
-#[derive(Copy, Clone)]
+//a peer without a socket, whose frames are read from the write queue by an
+//in-process transport such as the simulator
+pub fn new_simulated(
+    addr: std::net::SocketAddr,
+    metrics: &Arc<Metrics>,
+) -> (mpsc::UnboundedReceiver<Vec<u8>>, Handle) {
+    let (write_sender, write_receiver) = mpsc::unbounded();
+    let handle = Handle {
+        write_queue: write_sender,
+        addr,
+        traffic: Arc::new(Traffic::new()),
+        metrics: Arc::clone(metrics),
+        stream: None,
+    };
+    (write_receiver, handle)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
 pub enum Direction {
     Incoming,
     Outgoing,
 }
 
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+//the traffic of a connection, shared by its handles and its reader task
+#[derive(Debug)]
+pub struct Traffic {
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    last_recv: AtomicU64, //unix time in seconds of the last received frame
+}
+
+impl Traffic {
+    fn new() -> Self {
+        Traffic {
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            last_recv: AtomicU64::new(now_secs()),
+        }
+    }
+
+    pub fn record_recv(&self, bytes: usize) {
+        self.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.last_recv.store(now_secs(), Ordering::Relaxed);
+    }
+
+    pub fn get_bytes_in(&self) -> u64 {
+        self.bytes_in.load(Ordering::Relaxed)
+    }
+
+    pub fn get_bytes_out(&self) -> u64 {
+        self.bytes_out.load(Ordering::Relaxed)
+    }
+
+    pub fn get_last_recv(&self) -> u64 {
+        self.last_recv.load(Ordering::Relaxed)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Handle {
     addr: std::net::SocketAddr,
     write_queue: mpsc::UnboundedSender<Vec<u8>>,
+    traffic: Arc<Traffic>,
+    metrics: Arc<Metrics>, //of the local node
+    stream: Option<Arc<std::net::TcpStream>>,
 }
 
 #[cfg(any(test, feature = "test_utilities"))]
@@ -35,6 +110,14 @@ pub struct TestReceiver {
 impl Handle {
     pub fn write(&mut self, msg: Message) {
         let buffer = bincode::serialize(&msg).unwrap();
+        //the peer would drop the connection
+        if buffer.len() > MAX_MESSAGE_SIZE {
+            warn!("Not sending a message of {} bytes to {}", buffer.len(), self.addr);
+            return;
+        }
+        //count the frame header too
+        self.traffic.bytes_out.fetch_add(buffer.len() as u64 + 4, Ordering::Relaxed);
+        self.metrics.message_sent(&msg, buffer.len());
         smol::block_on(async move {
             if self.write_queue.send(buffer).await.is_err() {
                 trace!("Trying to send to disconnected peer");
@@ -46,6 +129,17 @@ impl Handle {
         &self.addr
     }
 
+    pub fn traffic(&self) -> &Traffic {
+        &self.traffic
+    }
+
+    //close the connection, the reader and writer tasks then stop
+    pub fn shutdown(&self) {
+        if let Some(stream) = self.stream.as_ref() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+
     // #[cfg(any(test,test_utilities))]
     #[cfg(any(test))]
     pub fn test_handle() -> (Handle, TestReceiver) {
@@ -53,6 +147,9 @@ impl Handle {
         (Handle {
             addr: std::net::SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 1)), 12321),
             write_queue: s,
+            traffic: Arc::new(Traffic::new()),
+            metrics: Arc::new(Metrics::new()),
+            stream: None,
         },
         TestReceiver {
             r
@@ -0,0 +1,140 @@
+use serde::Serialize;
+use crate::optchain::block::{
+    Info,
+    versa_block::VersaBlock,
+};
+
+//the peers an item of some class is relayed to
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayScope {
+    All, //every connected peer
+    Origin, //the peers of the shard the item belongs to
+}
+
+impl std::str::FromStr for RelayScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(RelayScope::All),
+            "origin" => Ok(RelayScope::Origin),
+            _ => Err(format!("unknown relay scope {}, expected all or origin", s)),
+        }
+    }
+}
+
+//the class of a relayed message, with the shard its items belong to when known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayClass {
+    TxBlock(usize),
+    Proposer,
+    Ordering,
+    ExAvailability(Option<usize>),
+    InAvailability(Option<usize>),
+    Symbol(Option<usize>), //the shard of the tx block the symbol is taken from
+}
+
+impl RelayClass {
+    pub fn of_block(block: &VersaBlock) -> Self {
+        match block {
+            VersaBlock::PropBlock(_) => RelayClass::Proposer,
+            VersaBlock::OrderBlock(_) => RelayClass::Ordering,
+            VersaBlock::ExAvaiBlock(avai_block) => RelayClass::ExAvailability(Some(avai_block.get_shard_id())),
+            VersaBlock::InAvaiBlock(avai_block) => RelayClass::InAvailability(Some(avai_block.get_shard_id())),
+        }
+    }
+}
+
+//Where each class of message is gossiped. Proposer and ordering blocks are global,
+//inclusive availability blocks are referenced by every shard, while exclusive
+//availability blocks and symbols mostly matter to the shard they belong to. Tx
+//blocks go wherever the blocks carrying them go, since a compact block is rebuilt
+//from the tx blocks its receiver already has. Anything not pushed to a node can
+//still be pulled, since a missing parent or symbol is requested from the peers.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayPolicy {
+    pub tx_block: RelayScope,
+    pub proposer: RelayScope,
+    pub ordering: RelayScope,
+    pub ex_availability: RelayScope,
+    pub in_availability: RelayScope,
+    pub symbol: RelayScope,
+}
+
+impl Default for RelayPolicy {
+    fn default() -> Self {
+        Self::flood()
+    }
+}
+
+impl RelayPolicy {
+    pub fn shard_aware() -> Self {
+        RelayPolicy {
+            tx_block: RelayScope::All,
+            proposer: RelayScope::All,
+            ordering: RelayScope::All,
+            ex_availability: RelayScope::Origin,
+            in_availability: RelayScope::All,
+            symbol: RelayScope::Origin,
+        }
+    }
+
+    //every message goes to every peer
+    pub fn flood() -> Self {
+        RelayPolicy {
+            tx_block: RelayScope::All,
+            proposer: RelayScope::All,
+            ordering: RelayScope::All,
+            ex_availability: RelayScope::All,
+            in_availability: RelayScope::All,
+            symbol: RelayScope::All,
+        }
+    }
+
+    //the shard whose peers the message should go to, None meaning all the peers
+    pub fn target_shard(&self, class: &RelayClass) -> Option<usize> {
+        let (scope, shard_id) = match class {
+            RelayClass::TxBlock(shard_id) => (self.tx_block, Some(*shard_id)),
+            RelayClass::Proposer => (self.proposer, None),
+            RelayClass::Ordering => (self.ordering, None),
+            RelayClass::ExAvailability(shard_id) => (self.ex_availability, *shard_id),
+            RelayClass::InAvailability(shard_id) => (self.in_availability, *shard_id),
+            RelayClass::Symbol(shard_id) => (self.symbol, *shard_id),
+        };
+        match scope {
+            RelayScope::All => None,
+            RelayScope::Origin => shard_id,
+        }
+    }
+}
+
+//"flood" or "shard", the latter optionally followed by per-class overrides,
+//e.g. "shard,symbol=all,exAvai=all"
+impl std::str::FromStr for RelayPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(|x| x.trim());
+        let mut policy = match parts.next() {
+            Some("flood") => RelayPolicy::flood(),
+            Some("shard") => RelayPolicy::shard_aware(),
+            _ => return Err(format!("unknown relay policy {}, expected flood or shard", s)),
+        };
+        for part in parts {
+            let (class, scope) = part
+                .split_once('=')
+                .ok_or(format!("relay override {} is not of the form class=scope", part))?;
+            let scope: RelayScope = scope.parse()?;
+            match class {
+                "tx" => policy.tx_block = scope,
+                "prop" => policy.proposer = scope,
+                "order" => policy.ordering = scope,
+                "exAvai" => policy.ex_availability = scope,
+                "inAvai" => policy.in_availability = scope,
+                "symbol" => policy.symbol = scope,
+                _ => return Err(format!("unknown relay class {}, expected tx, prop, order, exAvai, inAvai or symbol", class)),
+            }
+        }
+        Ok(policy)
+    }
+}
@@ -1,8 +1,41 @@
 use crate::{
-    types::address::Address,
-    optchain::network::{
-        peer,
-        message::Message,
+    optchain::{
+        network::{
+            peer,
+            message::Message,
+            handshake::{
+                Handshake,
+                PeerInfo,
+                HANDSHAKE_TIMEOUT,
+            },
+            address_book::{
+                AddressBook,
+                PeerAddr,
+                MAX_PEER_ADDRS,
+            },
+            message::MAX_MESSAGE_SIZE,
+            relay::{
+                RelayClass,
+                RelayPolicy,
+            },
+            link::{
+                self,
+                LinkEmulation,
+            },
+            inventory::{
+                InvItem,
+                KnownInventory,
+            },
+            misbehavior::{
+                self,
+                Misbehavior,
+                BanList,
+                BAN_THRESHOLD,
+                BAN_DURATION,
+            },
+        },
+        configuration::Configuration,
+        metrics::Metrics,
     },
 };
 
@@ -18,21 +51,49 @@ use futures::{
         BufReader, 
         BufWriter,
     },
-    channel::oneshot, stream::StreamExt
+    channel::{mpsc, oneshot}, stream::StreamExt
 };
 use smol::{Async, Executor};
-use log::{info, trace};
+use log::{info, trace, warn};
 use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
     net,
-    sync::Arc,
+    sync::{Arc, Mutex},
     thread,
+    time::{self, Instant, SystemTime, UNIX_EPOCH},
 };
 
+//how often the peers are pinged and the outbound connections checked against their targets
+const PEER_MAINTENANCE_INTERVAL: time::Duration = time::Duration::from_secs(5);
+//a peer silent for this long is considered dead
+const PEER_TIMEOUT_SECS: u64 = 30;
+//the upper bound of the exponential backoff between two reconnections
+const MAX_RECONNECT_DELAY_SECS: u64 = 64;
+//how long an outgoing TCP connection may take to be established
+const CONNECT_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+//the reconnection state of a peer given with --connect or the API
+struct Reconnect {
+    failures: u32, //reset once the peer answers a ping, so a flapping peer keeps backing off
+    next_attempt: Instant,
+}
+
+impl Reconnect {
+    fn back_off(&mut self) {
+        self.failures += 1;
+        let delay = MAX_RECONNECT_DELAY_SECS.min(1 << self.failures.min(6));
+        self.next_attempt = Instant::now() + time::Duration::from_secs(delay);
+    }
+}
+
 
 pub fn new(
     addr: std::net::SocketAddr,
     msg_sink: smol::channel::Sender<(Vec<u8>, peer::Handle)>,
-    shard_id: usize,
+    handshake: Handshake,
+    config: &Configuration,
+    metrics: &Arc<Metrics>,
 ) -> std::io::Result<(Context, Handle)> {
     let (control_signal_sender, control_signal_receiver) = smol::channel::bounded(10000);
     let handle = Handle {
@@ -41,11 +102,28 @@ pub fn new(
     let ctx = Context {
         peers: std::collections::HashMap::new(),
         peers_by_shard: std::collections::HashMap::new(),
+        peer_infos: std::collections::HashMap::new(),
         addr,
         control_chan: control_signal_receiver,
         control_sender: control_signal_sender,
         new_msg_chan: msg_sink,
-        shard_id,
+        handshake,
+        address_book: AddressBook::new(Some(format!(
+            "{}/node(shard-{},index-{})/peers.json",
+            config.data_dir,
+            config.shard_id,
+            config.node_id
+        ))),
+        max_outbound: config.max_outbound,
+        outbound_per_shard: config.outbound_per_shard,
+        persistent_peers: HashMap::new(),
+        connecting: HashSet::new(),
+        next_conn_id: 0,
+        ban_list: Arc::new(Mutex::new(BanList::new())),
+        known_inventory: HashMap::new(),
+        relay_policy: config.relay_policy,
+        link_emulation: config.link_emulation.clone(),
+        metrics: Arc::clone(metrics),
     };
     Ok((ctx, handle))
 }
@@ -53,11 +131,23 @@ pub fn new(
 pub struct Context {
     peers: std::collections::HashMap<std::net::SocketAddr, peer::Handle>,
     peers_by_shard: std::collections::HashMap<usize, Vec<std::net::SocketAddr>>,
+    peer_infos: std::collections::HashMap<std::net::SocketAddr, PeerInfo>,
     addr: std::net::SocketAddr,
     control_chan: smol::channel::Receiver<ControlSignal>,
     control_sender: smol::channel::Sender<ControlSignal>,
     new_msg_chan: smol::channel::Sender<(Vec<u8>, peer::Handle)>,
-    shard_id: usize,
+    handshake: Handshake,
+    address_book: AddressBook,
+    max_outbound: usize,
+    outbound_per_shard: usize,
+    persistent_peers: HashMap<std::net::SocketAddr, Reconnect>,
+    connecting: HashSet<std::net::SocketAddr>, //outgoing connections not set up yet
+    next_conn_id: u64,
+    ban_list: Arc<Mutex<BanList>>,
+    known_inventory: HashMap<std::net::SocketAddr, KnownInventory>,
+    relay_policy: RelayPolicy,
+    link_emulation: Option<LinkEmulation>,
+    metrics: Arc<Metrics>,
 }
 
 impl Context {
@@ -67,6 +157,7 @@ impl Context {
         let listener = Async::<net::TcpListener>::bind(self.addr)?;
         info!("P2P server listening at {}", self.addr);
         let control_chan = self.control_sender.clone();
+        let maintenance_chan = self.control_sender.clone();
         let ex = Executor::new();
         let ex = Arc::new(ex);
         let ex_clone = ex.clone();
@@ -79,6 +170,13 @@ impl Context {
         })
             .detach();
         thread::spawn(move || smol::block_on(ex.run(futures::future::pending::<()>())));
+        // periodically check the peers' health and top up the outbound connections
+        thread::spawn(move || loop {
+            thread::sleep(PEER_MAINTENANCE_INTERVAL);
+            if smol::block_on(maintenance_chan.send(ControlSignal::MaintainPeers)).is_err() {
+                break;
+            }
+        });
         return Ok(());
     }
 
@@ -103,127 +201,466 @@ impl Context {
             match ctrl {
                 ControlSignal::ConnectNewPeer(addr, result_chan) => {
                     trace!("Processing ConnectNewPeer command");
-                    let handle = self.connect(&addr, ex.clone()).await;
-                    result_chan.send(handle).unwrap();
+                    self.connect(&addr, PeerOrigin::Requested(result_chan), &ex);
                 }
                 ControlSignal::BroadcastMessage(msg) => {
                     trace!("Processing BroadcastMessage command");
-                    let mut rng = thread_rng();
-
-                    // Collect mutable references to the peer handles into a vector
-                    let mut peer_handles: Vec<_> = self.peers.iter_mut().collect();
-
-                    // Shuffle the order
-                    peer_handles.shuffle(&mut rng);
-
-                    // Now iterate in random order
-                    for (_, hd) in peer_handles {
-                        hd.write(msg.clone());
-                    }
-                    // for (_, hd) in self.peers.iter_mut() {
-                    //     hd.write(msg.clone());
-                    // }
+                    let addrs: Vec<std::net::SocketAddr> = self.peers.keys().cloned().collect();
+                    self.send_to_peers(addrs, &msg);
                 }
                 ControlSignal::BroadcastMessageWithShard((msg, shard_id)) => {
                     trace!("Processing BroadcastMessageWithShard command");
-                    let shard_id = shard_id as usize;
-                    
-                    // match self.peers_by_shard.get_mut(&shard_id) {
-                    //     Some(mut peers) => {
-                    //         for peer in peers.iter_mut() {
-                    //         let mut hd = self.peers.get_mut(peer).unwrap();
-                    //             hd.write(msg.clone());
-                    //         }
-                    //     }
-                    //     None => {}
-                    // }
-                    match self.peers_by_shard.get_mut(&shard_id) {
-                        Some(peers) => {
-                            for peer in peers.iter_mut() {
-                            let hd = self.peers.get_mut(peer).unwrap();
-                                hd.write(msg.clone());
-                            }
-                        }
-                        None => {}
-                    }
-                    
+                    let addrs = self.peers_by_shard
+                        .get(&shard_id)
+                        .cloned()
+                        .unwrap_or_default();
+                    self.send_to_peers(addrs, &msg);
+                }
+                ControlSignal::Relay((msg, class)) => {
+                    trace!("Processing Relay command");
+                    let shard_peers = self.relay_policy
+                        .target_shard(&class)
+                        .and_then(|shard_id| self.peers_by_shard.get(&shard_id))
+                        .filter(|addrs| !addrs.is_empty())
+                        .cloned();
+                    //flood when we know no peer of the shard, so that the item is not lost
+                    let addrs = shard_peers
+                        .unwrap_or_else(|| self.peers.keys().cloned().collect());
+                    self.send_to_peers(addrs, &msg);
                 }
                 ControlSignal::GetNewPeer(stream) => {
                     trace!("Processing GetNewPeer command");
-                    self.accept(stream, ex.clone()).await?;
+                    match stream.get_ref().peer_addr() {
+                        Ok(addr) => self.spawn_handshake(
+                            smol::future::ready(Ok(stream)),
+                            addr,
+                            PeerOrigin::Incoming,
+                            &ex,
+                        ),
+                        Err(e) => warn!("Rejecting incoming peer: {}", e),
+                    }
+                }
+                ControlSignal::Handshaken(handshaken) => {
+                    let (addr, origin, result) = *handshaken;
+                    trace!("Processing Handshaken({})", addr);
+                    if !matches!(origin, PeerOrigin::Incoming) {
+                        self.connecting.remove(&addr);
+                    }
+                    let result = match result {
+                        Ok((stream, remote)) => self.add_peer(stream, &origin, remote, ex.clone()).await,
+                        Err(e) => Err(e),
+                    };
+                    self.finish_connect(&addr, origin, result);
                 }
-                ControlSignal::DroppedPeer(addr) => {
+                ControlSignal::DroppedPeer((addr, conn_id)) => {
                     trace!("Processing DroppedPeer({})", addr);
-                    self.peers.remove(&addr);
+                    //both tasks of a connection report it, and the address may already
+                    //belong to a new connection
+                    let is_current = self.peer_infos
+                        .get(&addr)
+                        .map(|info| info.conn_id == conn_id)
+                        .unwrap_or(false);
+                    if !is_current {
+                        continue;
+                    }
+                    if let Some(hd) = self.peers.remove(&addr) {
+                        hd.shutdown();
+                    }
+                    self.known_inventory.remove(&addr);
+                    if let Some(info) = self.peer_infos.remove(&addr) {
+                        if let Some(addrs) = self.peers_by_shard.get_mut(&info.shard_id) {
+                            addrs.retain(|x| *x != addr);
+                        }
+                    }
+                    if let Some(reconnect) = self.persistent_peers.get_mut(&addr) {
+                        reconnect.back_off();
+                    }
                     info!("Peer {} disconnected", addr);
                 }
-                ControlSignal::SendToPeer((_receiver, _msg)) => {
-                    unimplemented!()
+                ControlSignal::Pong((addr, nonce)) => {
+                    trace!("Processing Pong({})", addr);
+                    if let Some(info) = self.peer_infos.get_mut(&addr) {
+                        if let Some((sent_nonce, sent_at)) = info.pending_ping.take() {
+                            if sent_nonce == nonce {
+                                info.rtt_ms = Some(sent_at.elapsed().as_millis() as u64);
+                                if let Some(reconnect) = self.persistent_peers.get_mut(&addr) {
+                                    reconnect.failures = 0;
+                                }
+                            } else {
+                                info.pending_ping = Some((sent_nonce, sent_at));
+                            }
+                        }
+                    }
+                }
+                ControlSignal::MarkKnown((addr, items)) => {
+                    if let Some(known) = self.known_inventory.get_mut(&addr) {
+                        for item in items {
+                            known.insert(item);
+                        }
+                    }
+                }
+                ControlSignal::Misbehaving((addr, misbehavior)) => {
+                    trace!("Processing Misbehaving({})", addr);
+                    self.punish(&addr, misbehavior);
+                }
+                ControlSignal::GetPeers(result_chan) => {
+                    trace!("Processing GetPeers command");
+                    let _ = result_chan.send(self.get_peers());
+                }
+                ControlSignal::GetPeerAddrs(result_chan) => {
+                    trace!("Processing GetPeerAddrs command");
+                    let _ = result_chan.send(self.address_book.get_peer_addrs(MAX_PEER_ADDRS));
+                }
+                ControlSignal::AddPeerAddrs(peer_addrs) => {
+                    trace!("Processing AddPeerAddrs command");
+                    self.add_peer_addrs(peer_addrs);
+                }
+                ControlSignal::MaintainPeers => {
+                    trace!("Processing MaintainPeers command");
+                    self.check_peer_health();
+                    self.reconnect_peers(&ex);
+                    self.maintain_peers(&ex);
+                }
+                ControlSignal::SendToPeer((addr, msg)) => {
+                    trace!("Processing SendToPeer({})", addr);
+                    if let Some(hd) = self.peers.get_mut(&addr) {
+                        hd.write(msg);
+                    }
                 }
             }
         }
         return Ok(());
     }
 
-    /// Connect to a peer, and register this peer
-    async fn connect(
+    /// Connect to a peer; the connection, the handshake and the registration go on
+    /// without the control loop
+    fn connect(
         &mut self,
         addr: &std::net::SocketAddr,
-        ex: Arc<Executor<'_>>,
-    ) -> std::io::Result<peer::Handle> {
+        origin: PeerOrigin,
+        ex: &Arc<Executor<'_>>,
+    ) {
+        if let Some(hd) = self.peers.get(addr) {
+            let hd = hd.clone();
+            self.finish_connect(addr, origin, Ok(hd));
+            return;
+        }
+        if self.ban_list.lock().unwrap().is_banned(&misbehavior::ban_key(addr, addr.ip())) {
+            let e = std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("peer {} is banned", addr),
+            );
+            self.finish_connect(addr, origin, Err(e));
+            return;
+        }
         info!("Establishing connection to peer {}", addr);
-        let mut stream = Async::<std::net::TcpStream>::connect(addr.clone()).await?;
-        
-        //send the shard id to the outgoing peer
-        let shard_id = self.shard_id as u32;
-        let serialized_shard_id = shard_id.to_be_bytes();
-        stream.write_all(&serialized_shard_id).await?; 
-        //receive the shard id from incoming peer
-        let mut buffer = [0u8; std::mem::size_of::<u32>()];
-        let _ = stream.read_exact(&mut buffer).await?;
-        let received_shard_id = u32::from_be_bytes(buffer);
-        info!("Connecting node's shard id: {}", received_shard_id);
-
-        // register the new peer
-        self.register(stream, peer::Direction::Outgoing, ex, received_shard_id as usize).await
+        self.connecting.insert(*addr);
+        let stream = with_timeout(Async::<std::net::TcpStream>::connect(*addr), CONNECT_TIMEOUT);
+        self.spawn_handshake(stream, *addr, origin, ex);
+    }
+
+    //a slow or silent peer must not stall the control loop, so the connection
+    //and the handshake run on their own task, which reports back with
+    //ControlSignal::Handshaken
+    fn spawn_handshake(
+        &self,
+        stream: impl Future<Output = std::io::Result<Async<net::TcpStream>>> + Send + 'static,
+        addr: std::net::SocketAddr,
+        origin: PeerOrigin,
+        ex: &Arc<Executor<'_>>,
+    ) {
+        let ours = self.handshake.clone();
+        let ban_list = Arc::clone(&self.ban_list);
+        let control_chan = self.control_sender.clone();
+        ex.spawn(async move {
+            let incoming = matches!(origin, PeerOrigin::Incoming);
+            let result = match stream.await {
+                Ok(stream) => with_timeout(
+                    exchange_handshakes(stream, addr, &ours, incoming, &ban_list),
+                    HANDSHAKE_TIMEOUT,
+                ).await,
+                Err(e) => Err(e),
+            };
+            let _ = control_chan
+                .send(ControlSignal::Handshaken(Box::new((addr, origin, result))))
+                .await;
+        })
+            .detach();
     }
 
-    async fn accept(
+    /// register a peer whose handshake succeeded
+    async fn add_peer(
         &mut self,
-        mut stream: Async<net::TcpStream>,
+        stream: Async<net::TcpStream>,
+        origin: &PeerOrigin,
+        remote: Handshake,
         ex: Arc<Executor<'_>>,
-    ) -> std::io::Result<()> {
-        //receive the shard id from incoming peer
-        let mut buffer = [0u8; std::mem::size_of::<u32>()];
-        let _ = stream.read_exact(&mut buffer).await?;
-        let received_shard_id = u32::from_be_bytes(buffer);
-        //send the shard id to the outgoing peer
-        let shard_id = self.shard_id as u32;
-        let serialized_shard_id = shard_id.to_be_bytes();
-        stream.write_all(&serialized_shard_id).await?; 
-        info!("Coming node's shard id: {}", received_shard_id);
-
-        self.register(stream, peer::Direction::Incoming, ex, received_shard_id as usize).await?;
-        Ok(())
+    ) -> std::io::Result<peer::Handle> {
+        if let PeerOrigin::Incoming = origin {
+            info!("Coming node's shard id: {}", remote.shard_id);
+            return self.register(stream, peer::Direction::Incoming, ex, remote).await;
+        }
+        //another connection to the peer may have completed meanwhile
+        let addr = stream.get_ref().peer_addr()?;
+        if let Some(hd) = self.peers.get(&addr) {
+            return Ok(hd.clone());
+        }
+        info!("Connecting node's shard id: {}", remote.shard_id);
+        // register the new peer and ask it for more peers
+        let mut handle = self.register(stream, peer::Direction::Outgoing, ex, remote).await?;
+        handle.write(Message::GetPeers);
+        Ok(handle)
+    }
+
+    /// tell whoever asked for the connection how it went
+    fn finish_connect(
+        &mut self,
+        addr: &std::net::SocketAddr,
+        origin: PeerOrigin,
+        result: std::io::Result<peer::Handle>,
+    ) {
+        match origin {
+            PeerOrigin::Incoming => {
+                //a failed handshake only drops this peer
+                if let Err(e) = result {
+                    warn!("Rejecting incoming peer {}: {}", addr, e);
+                }
+            }
+            PeerOrigin::Requested(result_chan) => {
+                //peers asked for explicitly are reconnected whenever they drop
+                self.update_reconnect(addr, result.is_ok());
+                let _ = result_chan.send(result);
+            }
+            PeerOrigin::Reconnect => {
+                match result.as_ref() {
+                    Ok(_) => info!("Reconnected to peer {}", addr),
+                    Err(e) => info!("Error reconnecting to peer {}: {}", addr, e),
+                }
+                self.update_reconnect(addr, result.is_ok());
+            }
+            PeerOrigin::Known(gossiped) => {
+                let info = match result {
+                    Ok(hd) => self.peer_infos.get(hd.addr()),
+                    Err(e) => {
+                        info!("Error connecting to known peer {}: {}", addr, e);
+                        self.address_book.mark_failed(addr);
+                        return;
+                    }
+                };
+                //the gossiped shard is only trusted once the handshake confirms it
+                if let Some(info) = info {
+                    if info.listen_addr != gossiped.addr {
+                        warn!("Known peer {} listens at {}", addr, info.listen_addr);
+                        self.address_book.mark_failed(addr);
+                    } else if info.shard_id != gossiped.shard_id {
+                        warn!("Known peer {} is in shard {}, not {}", addr, info.shard_id, gossiped.shard_id);
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_reconnect(&mut self, addr: &std::net::SocketAddr, connected: bool) {
+        let reconnect = self.persistent_peers
+            .entry(*addr)
+            .or_insert(Reconnect {
+                failures: 0,
+                next_attempt: Instant::now(),
+            });
+        if !connected {
+            reconnect.back_off();
+        }
+    }
+
+    /// reconnect the dropped persistent peers whose backoff has expired, the
+    /// connections being set up on their own tasks
+    fn reconnect_peers(&mut self, ex: &Arc<Executor<'_>>) {
+        let now = Instant::now();
+        let due: Vec<std::net::SocketAddr> = self.persistent_peers
+            .iter()
+            .filter(|(addr, reconnect)| !self.peers.contains_key(*addr)
+                && !self.connecting.contains(*addr)
+                && reconnect.next_attempt <= now)
+            .map(|(addr, _)| *addr)
+            .collect();
+        for addr in due {
+            self.connect(&addr, PeerOrigin::Reconnect, ex);
+        }
+    }
+
+    /// drop the silent peers and ping the others to measure their round trip time
+    fn check_peer_health(&mut self) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        for (addr, hd) in self.peers.iter_mut() {
+            if now.saturating_sub(hd.traffic().get_last_recv()) > PEER_TIMEOUT_SECS {
+                //the reader and writer tasks then report the dropped peer
+                warn!("Peer {} timed out", addr);
+                hd.shutdown();
+                continue;
+            }
+            let nonce = rand::random::<u64>().to_string();
+            if let Some(info) = self.peer_infos.get_mut(addr) {
+                info.pending_ping = Some((nonce.clone(), Instant::now()));
+            }
+            hd.write(Message::Ping(nonce));
+        }
+    }
+
+    /// add to the misbehavior score of a peer, and ban it once the threshold is reached
+    fn punish(&mut self, addr: &std::net::SocketAddr, misbehavior: Misbehavior) {
+        let info = match self.peer_infos.get_mut(addr) {
+            Some(info) => info,
+            None => return,
+        };
+        info.misbehavior_score = info.score.add(misbehavior.score(), Instant::now());
+        warn!("Peer {} misbehaved: {:?}, score {}", addr, misbehavior, info.misbehavior_score);
+        if info.misbehavior_score >= BAN_THRESHOLD {
+            warn!("Banning peer {} for {:?}", info.ban_key, BAN_DURATION);
+            self.ban_list.lock().unwrap().ban(info.ban_key, BAN_DURATION);
+            if let Some(hd) = self.peers.get(addr) {
+                hd.shutdown();
+            }
+        }
+    }
+
+    fn get_peers(&self) -> Vec<PeerInfo> {
+        self.peer_infos
+            .values()
+            .map(|info| {
+                let mut info = info.clone();
+                info.misbehavior_score = info.score.get(Instant::now());
+                if let Some(hd) = self.peers.get(&info.addr) {
+                    let traffic = hd.traffic();
+                    info.bytes_in = traffic.get_bytes_in();
+                    info.bytes_out = traffic.get_bytes_out();
+                    info.last_seen = traffic.get_last_recv();
+                }
+                info
+            })
+            .collect()
+    }
+
+    /// learn gossiped addresses of nodes running the same network
+    fn add_peer_addrs(&mut self, peer_addrs: Vec<PeerAddr>) {
+        let mut new_addrs: usize = 0;
+        for peer_addr in peer_addrs.into_iter().take(MAX_PEER_ADDRS) {
+            if peer_addr.shard_id >= self.handshake.params.shard_num
+                || peer_addr.addr == self.handshake.listen_addr {
+                continue;
+            }
+            if self.address_book.add(peer_addr) {
+                new_addrs += 1;
+            }
+        }
+        if new_addrs > 0 {
+            info!("Learned {} new peer addresses", new_addrs);
+        }
+    }
+
+    /// connect to known addresses until the outbound targets are met, overall and per shard
+    fn maintain_peers(&mut self, ex: &Arc<Executor<'_>>) {
+        let mut outbound: HashMap<usize, usize> = HashMap::new();
+        let mut connected: HashSet<std::net::SocketAddr> = HashSet::new();
+        connected.insert(self.addr);
+        connected.insert(self.handshake.listen_addr);
+        connected.extend(self.connecting.iter());
+        for info in self.peer_infos.values() {
+            connected.insert(info.addr);
+            connected.insert(info.listen_addr);
+            if info.is_outgoing() {
+                *outbound.entry(info.shard_id).or_insert(0) += 1;
+            }
+        }
+        let candidates = self.address_book.select_candidates(
+            &outbound,
+            &connected,
+            self.max_outbound,
+            self.outbound_per_shard,
+        );
+        //the connections being set up count once their handshakes complete
+        let num_outbound = outbound.values().sum::<usize>() + candidates.len();
+        for candidate in candidates {
+            self.connect(&candidate.addr.clone(), PeerOrigin::Known(candidate), ex);
+        }
+        //not enough known addresses, ask the neighbors
+        if num_outbound < self.max_outbound {
+            for hd in self.peers.values_mut() {
+                hd.write(Message::GetPeers);
+            }
+        }
+        self.address_book.save();
+    }
+
+    //send the message to the peers in random order, skipping the items each
+    //peer already has
+    fn send_to_peers(&mut self, mut addrs: Vec<std::net::SocketAddr>, msg: &Message) {
+        addrs.shuffle(&mut thread_rng());
+        for addr in addrs {
+            let hd = match self.peers.get_mut(&addr) {
+                Some(hd) => hd,
+                None => continue,
+            };
+            let peer_msg = match self.known_inventory.get_mut(&addr) {
+                Some(known) => known.filter_announcement(msg),
+                None => Some(msg.clone()),
+            };
+            if let Some(peer_msg) = peer_msg {
+                hd.write(peer_msg);
+            }
+        }
     }
 
     async fn register(
         &mut self,
         stream: Async<net::TcpStream>,
-        _direction: peer::Direction,
+        direction: peer::Direction,
         ex: Arc<Executor<'_>>,
-        shard_id: usize,
+        remote: Handshake,
     ) -> std::io::Result<peer::Handle> {
-        let (mut write_queue, handle) = peer::new(&stream)?;
+        let shard_id = remote.shard_id;
+        let (mut write_queue, handle) = peer::new(&stream, &self.metrics)?;
+        //emulate the delay and the bandwidth of the link to this peer
+        let link_spec = self.link_emulation
+            .as_ref()
+            .map(|l| l.get_link(self.handshake.shard_id, shard_id, &remote.listen_addr))
+            .filter(|spec| !spec.is_ideal());
+        if let Some(spec) = link_spec {
+            let (shaped_sender, shaped_receiver) = mpsc::unbounded();
+            let (delayed_sender, delayed_receiver) = mpsc::unbounded();
+            ex.spawn(link::shape_bandwidth(write_queue, shaped_sender, spec)).detach();
+            ex.spawn(link::delay_frames(shaped_receiver, delayed_sender)).detach();
+            write_queue = delayed_receiver;
+        }
 
         let stream = AsyncArc::new(stream);
         let new_msg_chan = self.new_msg_chan.clone();
         let handle_copy = handle.clone();
         let control_chan = self.control_sender.clone();
+        let drop_chan = self.control_sender.clone();
         let addr = stream.get_ref().peer_addr()?;
+        let conn_id = self.next_conn_id;
+        self.next_conn_id += 1;
 
         // insert the peer handle so that we can broadcast to this guy later
         self.peers.insert(addr.clone(), handle.clone());
+        let info = PeerInfo::new(addr, &remote, direction, conn_id);
+        info!("Registered peer {:?}", info);
+        let peer_addr = PeerAddr {
+            addr: remote.listen_addr,
+            shard_id,
+            node_id: remote.node_id,
+        };
+        //the claimed listening address is only confirmed if we reached the peer there
+        if direction == peer::Direction::Outgoing && remote.listen_addr == addr {
+            self.address_book.mark_seen(peer_addr);
+        } else {
+            self.address_book.add(peer_addr);
+        }
+        self.peer_infos.insert(addr, info);
+        self.known_inventory.insert(addr, KnownInventory::default());
         // update the peers by shard map
         match self.peers_by_shard.get(&shard_id) {
             Some(old_addrs) => {
@@ -243,14 +680,16 @@ impl Context {
             let mut size_buffer: [u8; 4] = [0; 4];
             // the buffer to store the message content
             let mut msg_buffer: Vec<u8> = vec![];
-            loop {
-                // first, read exactly 4 bytes to get the frame header
-                let msg_size = match reader.read_exact(&mut size_buffer).await {
-                    Ok(_) => u32::from_be_bytes(size_buffer),
-                    Err(_) => {
-                        break;
-                    }
-                };
+            // first, read exactly 4 bytes to get the frame header
+            while reader.read_exact(&mut size_buffer).await.is_ok() {
+                let msg_size = u32::from_be_bytes(size_buffer);
+                // do not allocate whatever the peer claims
+                if msg_size as usize > MAX_MESSAGE_SIZE {
+                    let _ = drop_chan
+                        .send(ControlSignal::Misbehaving((addr, Misbehavior::OversizedMessage)))
+                        .await;
+                    break;
+                }
                 // then, read exactly msg_size bytes to get the whole message
                 if msg_buffer.len() < msg_size as usize {
                     msg_buffer.resize(msg_size as usize, 0);
@@ -260,6 +699,7 @@ impl Context {
                     .await
                 {
                     Ok(_) => {
+                        handle_copy.traffic().record_recv(msg_size as usize + size_buffer.len());
                         let new_payload: Vec<u8> = msg_buffer[0..msg_size as usize].to_vec();
                         new_msg_chan
                             .send((new_payload, handle_copy.clone()))
@@ -272,16 +712,17 @@ impl Context {
                 }
             }
             // the peer is disconnected
+            let _ = drop_chan
+                .send(ControlSignal::DroppedPeer((addr, conn_id)))
+                .await;
         })
             .detach();
 
         // second, start a task that keeps writing to this guy
         let mut writer = BufWriter::new(stream.clone());
         ex.spawn(async move {
-            loop {
-                // first, get a message to write from the queue
-                let new_msg = write_queue.next().await.unwrap();
-
+            // first, get a message to write from the queue
+            while let Some(new_msg) = write_queue.next().await {
                 // second, encode the length of the message
                 let size_buffer = (new_msg.len() as u32).to_be_bytes();
 
@@ -306,10 +747,9 @@ impl Context {
                 }
             }
             // the peer is disconnected
-            control_chan
-                .send(ControlSignal::DroppedPeer(addr))
-                .await
-                .unwrap();
+            let _ = control_chan
+                .send(ControlSignal::DroppedPeer((addr, conn_id)))
+                .await;
         })
             .detach();
 
@@ -317,10 +757,66 @@ impl Context {
     }
 }
 
+//exchange the handshakes on a new connection, the outgoing side introducing itself first
+async fn exchange_handshakes(
+    mut stream: Async<net::TcpStream>,
+    addr: std::net::SocketAddr,
+    ours: &Handshake,
+    incoming: bool,
+    ban_list: &Mutex<BanList>,
+) -> HandshakeResult {
+    let remote = if incoming {
+        let remote = Handshake::read_from(&mut stream).await?;
+        if ban_list.lock().unwrap().is_banned(&misbehavior::ban_key(&remote.listen_addr, addr.ip())) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("peer {} is banned", addr),
+            ));
+        }
+        //reply even on mismatch so that the outgoing side can tell why it is dropped
+        ours.write_to(&mut stream).await?;
+        remote
+    } else {
+        ours.write_to(&mut stream).await?;
+        Handshake::read_from(&mut stream).await?
+    };
+    if let Err(e) = ours.check(&remote) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("handshake with peer {} failed: {}", addr, e),
+        ));
+    }
+    Ok((stream, remote))
+}
+
+async fn with_timeout<T>(
+    fut: impl Future<Output = std::io::Result<T>>,
+    timeout: time::Duration,
+) -> std::io::Result<T> {
+    smol::future::or(fut, async move {
+        smol::Timer::after(timeout).await;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("timed out after {:?}", timeout),
+        ))
+    })
+        .await
+}
+
 #[derive(Clone)]
 pub struct Handle {
     control_chan: smol::channel::Sender<ControlSignal>,
 }
+
+//a handle not backed by a server context: its control signals are returned to
+//the caller, e.g. the simulator, which carries the messages itself
+pub fn new_detached() -> (smol::channel::Receiver<ControlSignal>, Handle) {
+    let (control_signal_sender, control_signal_receiver) = smol::channel::unbounded();
+    let handle = Handle {
+        control_chan: control_signal_sender,
+    };
+    (control_signal_receiver, handle)
+}
 //#[cfg(any(test,test_utilities))]
 //pub struct TestReceiver{
 //    control_chan: smol::channel::Receiver<ControlSignal>,
@@ -356,7 +852,42 @@ impl Handle {
         smol::block_on(self.control_chan.send(ControlSignal::BroadcastMessageWithShard((msg, shard_id)))).unwrap();
     }
 
-    pub fn send(&self, receiver: Address, msg: Message) {
+    //send the message to the peers chosen by the relay policy for its class
+    pub fn relay(&self, msg: Message, class: RelayClass) {
+        smol::block_on(self.control_chan.send(ControlSignal::Relay((msg, class)))).unwrap();
+    }
+
+    pub fn get_peer_addrs(&self) -> Vec<PeerAddr> {
+        let (sender, receiver) = oneshot::channel();
+        smol::block_on(self.control_chan.send(ControlSignal::GetPeerAddrs(sender))).unwrap();
+        smol::block_on(receiver).unwrap_or_default()
+    }
+
+    pub fn add_peer_addrs(&self, peer_addrs: Vec<PeerAddr>) {
+        smol::block_on(self.control_chan.send(ControlSignal::AddPeerAddrs(peer_addrs))).unwrap();
+    }
+
+    pub fn record_pong(&self, addr: std::net::SocketAddr, nonce: String) {
+        smol::block_on(self.control_chan.send(ControlSignal::Pong((addr, nonce)))).unwrap();
+    }
+
+    //the peer has these items, they need not be announced to it
+    pub fn mark_known(&self, addr: std::net::SocketAddr, items: Vec<InvItem>) {
+        smol::block_on(self.control_chan.send(ControlSignal::MarkKnown((addr, items)))).unwrap();
+    }
+
+    pub fn report_misbehavior(&self, addr: std::net::SocketAddr, misbehavior: Misbehavior) {
+        smol::block_on(self.control_chan.send(ControlSignal::Misbehaving((addr, misbehavior)))).unwrap();
+    }
+
+    pub fn get_peers(&self) -> Vec<PeerInfo> {
+        let (sender, receiver) = oneshot::channel();
+        smol::block_on(self.control_chan.send(ControlSignal::GetPeers(sender))).unwrap();
+        smol::block_on(receiver).unwrap_or_default()
+    }
+
+    //send the message to one connected peer, dropped if it is gone
+    pub fn send(&self, receiver: std::net::SocketAddr, msg: Message) {
         smol::block_on(self.control_chan.send(ControlSignal::SendToPeer((receiver, msg)))).unwrap();
     }
 
@@ -369,7 +900,17 @@ impl Handle {
    // }
 }
 
-enum ControlSignal {
+type HandshakeResult = std::io::Result<(Async<net::TcpStream>, Handshake)>;
+
+//who a connection is set up for, told of the outcome once its handshake completes
+pub enum PeerOrigin {
+    Incoming,
+    Requested(oneshot::Sender<std::io::Result<peer::Handle>>), //--connect or the API
+    Reconnect, //a dropped persistent peer
+    Known(PeerAddr), //an address of the address book, as gossiped
+}
+
+pub enum ControlSignal {
     ConnectNewPeer(
         std::net::SocketAddr,
         oneshot::Sender<std::io::Result<peer::Handle>>,
@@ -377,6 +918,16 @@ enum ControlSignal {
     BroadcastMessage(Message),
     BroadcastMessageWithShard((Message, usize)),
     GetNewPeer(Async<net::TcpStream>),
-    DroppedPeer(std::net::SocketAddr),
-    SendToPeer((Address,Message)),
+    DroppedPeer((std::net::SocketAddr, u64)), //(address, connection id)
+    SendToPeer((std::net::SocketAddr, Message)),
+    GetPeerAddrs(oneshot::Sender<Vec<PeerAddr>>),
+    AddPeerAddrs(Vec<PeerAddr>),
+    MaintainPeers,
+    Pong((std::net::SocketAddr, String)),
+    GetPeers(oneshot::Sender<Vec<PeerInfo>>),
+    Misbehaving((std::net::SocketAddr, Misbehavior)),
+    MarkKnown((std::net::SocketAddr, Vec<InvItem>)),
+    Relay((Message, RelayClass)),
+    //boxed, the handshake result dwarfs the other signals
+    Handshaken(Box<(std::net::SocketAddr, PeerOrigin, HandshakeResult)>),
 }
@@ -0,0 +1,383 @@
+use serde::{Serialize, Deserialize};
+use crate::{
+    types::hash::{H256, Hashable},
+    optchain::{
+        block::{
+            Info,
+            BlockHeader,
+            versa_block::{
+                VersaBlock,
+                VersaHash,
+            },
+        },
+        configuration::Configuration,
+        miner::meets_target,
+        multichain::{
+            Multichain,
+            ChainType,
+        },
+        network::{
+            message::Message,
+            peer,
+        },
+    },
+};
+use std::{
+    collections::{HashMap, HashSet},
+    net,
+    time::{Duration, Instant},
+};
+
+//the maximal number of headers returned for one GetHeaders request
+pub const HEADER_BATCH_SIZE: usize = 500;
+//the maximal number of block bodies requested in one GetBlocks message
+pub const BODY_BATCH_SIZE: usize = 50;
+//how long the sync peer may send nothing useful before another peer is tried
+pub const SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+//how often the sync peer is checked
+pub const SYNC_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+//the header of a block together with its nonce, which is enough to check the PoW
+//and the linkage of a chain without downloading the tx sets
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncHeader {
+    versa_hash: VersaHash,
+    header: BlockHeader,
+    nonce: u32,
+}
+
+impl SyncHeader {
+    pub fn from_block(block: &VersaBlock) -> Self {
+        SyncHeader {
+            versa_hash: block.get_versa_hash(),
+            header: block.get_header(),
+            nonce: block.get_nonce(),
+        }
+    }
+
+    pub fn get_versa_hash(&self) -> VersaHash {
+        self.versa_hash.clone()
+    }
+
+    pub fn verify_hash(&self) -> bool {
+        H256::pow_hash(&self.header.hash(), self.nonce) == self.versa_hash.get_hash()
+    }
+
+    //the pow hash meets the difficulty of the kind of the block
+    pub fn verify_target(&self, config: &Configuration) -> bool {
+        meets_target(&self.versa_hash, config)
+    }
+
+    //the parent of the block in the given chain
+    pub fn get_parent(&self, chain: &ChainType) -> Option<H256> {
+        match (&self.versa_hash, chain) {
+            (VersaHash::PropHash(_), ChainType::Proposer) => Some(self.header.get_prop_parent()),
+            (VersaHash::OrderHash(_), ChainType::Ordering) => Some(self.header.get_order_parent()),
+            (VersaHash::ExHash(_), ChainType::Availability(_)) => Some(self.header.get_inter_parent()),
+            (VersaHash::InHash(_), ChainType::Availability(shard_id)) => self.header
+                .get_global_parents()
+                .into_iter()
+                .find(|(_, id)| *id == *shard_id as usize)
+                .map(|(hash, _)| hash),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStatus {
+    Idle, //no sync has been started
+    Headers, //downloading headers
+    Bodies, //all headers are downloaded, waiting for block bodies and their symbols
+    Synced, //relay mode
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ChainProgress {
+    pub chain: ChainType,
+    pub status: SyncStatus,
+    pub local_height: usize,
+    pub peer_height: usize,
+    pub headers_received: usize,
+    pub bodies_requested: usize,
+    pub bodies_pending: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SyncProgress {
+    pub status: SyncStatus,
+    pub chains: Vec<ChainProgress>,
+}
+
+struct ChainSync {
+    status: SyncStatus,
+    peer_height: usize,
+    headers_received: usize,
+    bodies_requested: usize,
+    pending_bodies: HashSet<VersaHash>,
+    last_header: Option<H256>, //the last header accepted, which the next batch starts from
+}
+
+impl ChainSync {
+    fn new() -> Self {
+        ChainSync {
+            status: SyncStatus::Idle,
+            peer_height: 0,
+            headers_received: 0,
+            bodies_requested: 0,
+            pending_bodies: HashSet::new(),
+            last_header: None,
+        }
+    }
+}
+
+//Header-first initial block download. A joining node asks one connected peer for
+//the headers of every chain from its block locators, checks them, then downloads
+//the missing bodies. A sync peer that stalls is replaced by another connected one.
+//Block announcements received meanwhile are deferred until all chains are synced,
+//after which the node switches to relay mode.
+pub struct SyncManager {
+    chains: HashMap<ChainType, ChainSync>,
+    deferred_announcements: Vec<(Vec<VersaHash>, peer::Handle)>,
+    sync_peer: Option<net::SocketAddr>,
+    failed_peers: HashSet<net::SocketAddr>,
+    last_progress: Instant,
+}
+
+impl Default for SyncManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SyncManager {
+    pub fn new() -> Self {
+        SyncManager {
+            chains: HashMap::new(),
+            deferred_announcements: vec![],
+            sync_peer: None,
+            failed_peers: HashSet::new(),
+            last_progress: Instant::now(),
+        }
+    }
+
+    //the GetHeaders requests starting the sync of every chain with the peer
+    pub fn start(&mut self, multichain: &Multichain, peer: net::SocketAddr) -> Vec<Message> {
+        self.sync_peer = Some(peer);
+        self.last_progress = Instant::now();
+        let mut requests: Vec<Message> = vec![];
+        for chain in multichain.get_chain_types() {
+            let mut chain_sync = ChainSync::new();
+            chain_sync.status = SyncStatus::Headers;
+            self.chains.insert(chain, chain_sync);
+            requests.push(Message::GetHeaders((chain, multichain.get_block_locator(&chain))));
+        }
+        requests
+    }
+
+    //whether a GetHeaders request of the chain is pending
+    pub fn is_expecting_headers(&self, chain: &ChainType) -> bool {
+        self.chains
+            .get(chain)
+            .map(|c| c.status == SyncStatus::Headers)
+            .unwrap_or(false)
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        self.chains
+            .values()
+            .any(|c| c.status == SyncStatus::Headers || c.status == SyncStatus::Bodies)
+    }
+
+    //the sync has been started and is over
+    pub fn is_done(&self) -> bool {
+        !self.chains.is_empty() && !self.is_syncing()
+    }
+
+    pub fn get_sync_peer(&self) -> Option<net::SocketAddr> {
+        self.sync_peer
+    }
+
+    pub fn is_stalled(&self, now: Instant) -> bool {
+        self.is_syncing() && now.saturating_duration_since(self.last_progress) > SYNC_TIMEOUT
+    }
+
+    //start the sync with a connected peer, or restart it with another one when the
+    //sync peer stalls; with no other peer left the node gives up the download and
+    //catches up from the announcements instead. Returns the peer to send the
+    //requests to.
+    pub fn drive(
+        &mut self,
+        multichain: &Multichain,
+        connected: &[net::SocketAddr],
+        now: Instant,
+    ) -> Option<(net::SocketAddr, Vec<Message>)> {
+        if !self.chains.is_empty() && !self.is_stalled(now) {
+            return None;
+        }
+        if let Some(stalled) = self.sync_peer.take() {
+            self.failed_peers.insert(stalled);
+        }
+        match connected.iter().find(|addr| !self.failed_peers.contains(*addr)) {
+            Some(peer) => Some((*peer, self.start(multichain, *peer))),
+            None => {
+                if !self.chains.is_empty() {
+                    for chain_sync in self.chains.values_mut() {
+                        chain_sync.status = SyncStatus::Synced;
+                        chain_sync.pending_bodies.clear();
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    //whether a batch of headers starts from the local chain or from the last header
+    //accepted, whose body may not be inserted yet
+    pub fn connects(&self, chain: &ChainType, headers: &[SyncHeader], multichain: &Multichain) -> bool {
+        let parent = match headers.first().and_then(|h| h.get_parent(chain)) {
+            Some(parent) => parent,
+            None => return true,
+        };
+        multichain.contains_block(chain, &parent)
+            || self.chains.get(chain).and_then(|c| c.last_header) == Some(parent)
+    }
+
+    //check a batch of headers returned by a peer. `known_parent` tells whether the
+    //parent of the first header is already in the local chain.
+    pub fn check_headers(
+        chain: &ChainType,
+        headers: &[SyncHeader],
+        known_parent: bool,
+        config: &Configuration,
+    ) -> Result<(), String> {
+        if !headers.is_empty() && !known_parent {
+            return Err(String::from("headers do not connect to the local chain"));
+        }
+        for (i, header) in headers.iter().enumerate() {
+            if !header.verify_hash() {
+                return Err(format!("incorrect hash of header {:?}", header.versa_hash));
+            }
+            if !header.verify_target(config) {
+                return Err(format!("header {:?} does not meet the difficulty of its kind", header.versa_hash));
+            }
+            let parent = match header.get_parent(chain) {
+                Some(parent) => parent,
+                None => return Err(format!("header {:?} does not belong to {:?}", header.versa_hash, chain)),
+            };
+            if i > 0 && parent != headers[i - 1].versa_hash.get_hash() {
+                return Err(format!("header {:?} is not linked to the previous one", header.versa_hash));
+            }
+        }
+        Ok(())
+    }
+
+    //record a checked batch of headers whose bodies in `missing` are not stored locally,
+    //and return the requests to send to the peer
+    pub fn on_headers(
+        &mut self,
+        chain: &ChainType,
+        peer_height: usize,
+        headers: &[SyncHeader],
+        missing: Vec<VersaHash>,
+    ) -> Vec<Message> {
+        let chain_sync = match self.chains.get_mut(chain) {
+            Some(c) if c.status == SyncStatus::Headers => c,
+            //unsolicited headers
+            _ => return vec![],
+        };
+        self.last_progress = Instant::now();
+        chain_sync.peer_height = peer_height;
+        chain_sync.headers_received += headers.len();
+        chain_sync.bodies_requested += missing.len();
+        let mut requests: Vec<Message> = missing
+            .chunks(BODY_BATCH_SIZE)
+            .map(|batch| Message::GetBlocks(batch.to_vec()))
+            .collect();
+        chain_sync.pending_bodies.extend(missing);
+        if let Some(last) = headers.last() {
+            chain_sync.last_header = Some(last.versa_hash.get_hash());
+        }
+
+        if headers.len() >= HEADER_BATCH_SIZE {
+            let last = headers.last().unwrap().versa_hash.get_hash();
+            requests.push(Message::GetHeaders((*chain, vec![last])));
+        } else if chain_sync.pending_bodies.is_empty() {
+            chain_sync.status = SyncStatus::Synced;
+        } else {
+            chain_sync.status = SyncStatus::Bodies;
+        }
+        requests
+    }
+
+    //a block has been inserted in the multichain
+    pub fn on_block_inserted(&mut self, versa_hash: &VersaHash) {
+        for chain_sync in self.chains.values_mut() {
+            if !chain_sync.pending_bodies.remove(versa_hash) {
+                continue;
+            }
+            self.last_progress = Instant::now();
+            if chain_sync.status == SyncStatus::Bodies && chain_sync.pending_bodies.is_empty() {
+                chain_sync.status = SyncStatus::Synced;
+            }
+        }
+    }
+
+    //keep a block announcement to handle it once the sync is finished
+    pub fn defer_announcement(&mut self, hashes: Vec<VersaHash>, peer: peer::Handle) {
+        self.deferred_announcements.push((hashes, peer));
+    }
+
+    //return the deferred announcements once the node is in relay mode
+    pub fn take_deferred_announcements(&mut self) -> Vec<(Vec<VersaHash>, peer::Handle)> {
+        if self.is_syncing() {
+            vec![]
+        } else {
+            std::mem::take(&mut self.deferred_announcements)
+        }
+    }
+
+    pub fn get_progress(&self, multichain: &Multichain) -> SyncProgress {
+        let chains: Vec<ChainProgress> = multichain
+            .get_chain_types()
+            .into_iter()
+            .map(|chain| {
+                let local_height = multichain.get_chain_height(&chain).unwrap_or(0);
+                match self.chains.get(&chain) {
+                    Some(c) => ChainProgress {
+                        chain,
+                        status: c.status,
+                        local_height,
+                        peer_height: c.peer_height,
+                        headers_received: c.headers_received,
+                        bodies_requested: c.bodies_requested,
+                        bodies_pending: c.pending_bodies.len(),
+                    },
+                    None => ChainProgress {
+                        chain,
+                        status: SyncStatus::Idle,
+                        local_height,
+                        peer_height: 0,
+                        headers_received: 0,
+                        bodies_requested: 0,
+                        bodies_pending: 0,
+                    },
+                }
+            })
+            .collect();
+        let status = if self.is_syncing() {
+            match chains.iter().any(|c| c.status == SyncStatus::Headers) {
+                true => SyncStatus::Headers,
+                false => SyncStatus::Bodies,
+            }
+        } else if self.chains.is_empty() {
+            SyncStatus::Idle
+        } else {
+            SyncStatus::Synced
+        };
+        SyncProgress {
+            status,
+            chains,
+        }
+    }
+}
@@ -7,6 +7,21 @@ use crate::{
             message::Message,
             peer,
             server::Handle as ServerHandle,
+            misbehavior::Misbehavior,
+            inventory::{
+                InvItem,
+                InFlightRequests,
+            },
+            relay::RelayClass,
+            compact::{
+                CompactBlock,
+                CompactPool,
+            },
+            sync::{
+                SyncManager,
+                SyncHeader,
+                HEADER_BATCH_SIZE,
+            },
         },
         block::{
             Info, 
@@ -16,10 +31,17 @@ use crate::{
                 VersaHash,
             }
         },
+        clock::Clock,
         configuration::Configuration,
+        events::{self, ChainEvent, EventBus},
+        metrics::{self, Metrics, RejectReason},
+        miner::{Handle as MinerHandle, meets_target, tx_blk_meets_target},
         // validator::{Validator},
         mempool::Mempool,
-        multichain::Multichain,
+        multichain::{
+            Multichain,
+            ChainType,
+        },
         symbolpool::{
             SymbolPool,
             SymbolIndex,
@@ -30,10 +52,35 @@ use crate::{
 use log::{debug, warn, error, info};
 use std::{
     thread,
-    sync::{Arc,Mutex},
-    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+    collections::{BTreeMap, HashMap, VecDeque},
 };
 
+//take a tx block from a peer or a client into the mempool: false when it is already
+//pending or referenced by the longest proposer chain, an error without the work of
+//a tx block
+pub fn accept_tx_blk(
+    multichain: &Mutex<Multichain>,
+    mempool: &Mutex<Mempool>,
+    tx_blk: &TransactionBlock,
+    config: &Configuration,
+) -> Result<bool, String> {
+    let hash = tx_blk.hash();
+    if !tx_blk_meets_target(tx_blk, config) {
+        return Err(format!("tx block {:?} does not meet the tx block target", hash));
+    }
+    {
+        let mempool = mempool.lock().unwrap();
+        if mempool.check(&hash) || mempool.is_referenced(&hash) {
+            return Ok(false);
+        }
+    }
+    if multichain.lock().unwrap().get_tx_blk_in_longest_proposer_chain(&hash).is_some() {
+        return Ok(false);
+    }
+    Ok(mempool.lock().unwrap().insert_tx_blk(tx_blk.clone()))
+}
+
 //#[cfg(any(test,test_utilities))]
 //use super::peer::TestReceiver as PeerTestReceiver;
 //#[cfg(any(test,test_utilities))]
@@ -46,11 +93,31 @@ pub struct Worker {
     multichain: Arc<Mutex<Multichain>>,
     mempool: Arc<Mutex<Mempool>>,
     symbolpool: Arc<Mutex<SymbolPool>>,
+    sync_manager: Arc<Mutex<SyncManager>>,
+    in_flight: Arc<Mutex<InFlightRequests>>,
+    compact_pool: Arc<Mutex<CompactPool>>,
     config: Configuration,
+    miner: MinerHandle,
     // validator: Validator,
     blk_buff: Arc<Mutex<HashMap<VersaHash, Vec<VersaBlock>>>>,
     unavailable_cmt2avai_blocks: Arc<Mutex<HashMap<H256, Vec<VersaBlock>>>>, //cmt -> avai blocks containing cmt
-    unavailable_avai_block2cmts: Arc<Mutex<HashMap<H256, Vec<H256>>>> // avai block hash -> cmts
+    unavailable_avai_block2cmts: Arc<Mutex<HashMap<H256, Vec<H256>>>>, // avai block hash -> cmts
+    metrics: Arc<Metrics>,
+    events: Arc<EventBus>,
+}
+
+//the state of the node the network and the miner workers share
+#[derive(Clone)]
+pub struct WorkerContext {
+    pub server: ServerHandle,
+    pub multichain: Arc<Mutex<Multichain>>,
+    pub mempool: Arc<Mutex<Mempool>>,
+    pub symbolpool: Arc<Mutex<SymbolPool>>,
+    pub sync_manager: Arc<Mutex<SyncManager>>,
+    pub config: Configuration,
+    pub miner: MinerHandle,
+    pub metrics: Arc<Metrics>,
+    pub events: Arc<EventBus>,
 }
 
 // pub type SampleIndex = (H256, u32, u32); //block_hash, tx_index, shard_id
@@ -60,29 +127,33 @@ impl Worker {
     pub fn new(
         num_worker: usize,
         msg_src: smol::channel::Receiver<(Vec<u8>, peer::Handle)>,
-        server: &ServerHandle,
-        multichain: &Arc<Mutex<Multichain>>,
-        mempool: &Arc<Mutex<Mempool>>,
-        symbolpool: &Arc<Mutex<SymbolPool>>,
-        config: &Configuration,
-        blk_buff: &Arc<Mutex<HashMap<VersaHash, Vec<VersaBlock>>>>,
-        unavailable_cmt2avai_blocks: &Arc<Mutex<HashMap<H256, Vec<VersaBlock>>>>, //cmt -> avai blocks containing cmt
-        unavailable_avai_block2cmts: &Arc<Mutex<HashMap<H256, Vec<H256>>>> // avai block hash -> cmts
+        ctx: &WorkerContext,
     ) -> Self {
         Self {
             msg_chan: msg_src,
             num_worker,
-            server: server.clone(),
-            multichain: Arc::clone(multichain),
-            blk_buff: Arc::clone(blk_buff),
-            mempool: Arc::clone(mempool),
-            symbolpool: Arc::clone(symbolpool),
-            config: config.clone(),
-            unavailable_cmt2avai_blocks: Arc::clone(unavailable_cmt2avai_blocks),
-            unavailable_avai_block2cmts: Arc::clone(unavailable_avai_block2cmts),
+            server: ctx.server.clone(),
+            multichain: Arc::clone(&ctx.multichain),
+            blk_buff: Arc::new(Mutex::new(HashMap::new())),
+            mempool: Arc::clone(&ctx.mempool),
+            symbolpool: Arc::clone(&ctx.symbolpool),
+            sync_manager: Arc::clone(&ctx.sync_manager),
+            in_flight: Arc::new(Mutex::new(InFlightRequests::new())),
+            compact_pool: Arc::new(Mutex::new(CompactPool::new())),
+            config: ctx.config.clone(),
+            miner: ctx.miner.clone(),
+            unavailable_cmt2avai_blocks: Arc::new(Mutex::new(HashMap::new())),
+            unavailable_avai_block2cmts: Arc::new(Mutex::new(HashMap::new())),
+            metrics: Arc::clone(&ctx.metrics),
+            events: Arc::clone(&ctx.events),
         }
     }
 
+    //time the requests on the given clock, e.g. the virtual one of a simulation
+    pub fn set_clock(&mut self, clock: &Clock) {
+        self.in_flight = Arc::new(Mutex::new(InFlightRequests::with_clock(clock.clone())));
+    }
+
     pub fn start(self) {
         let num_worker = self.num_worker;
         info!("num of network workers: {num_worker}");
@@ -103,127 +174,225 @@ impl Worker {
                 error!("network worker terminated {}", e);
                 break;
             }
-            let msg = result.unwrap();
-            let (msg, mut peer) = msg;
-            let msg: Message = bincode::deserialize(&msg).unwrap();
-            match msg {
-                Message::Ping(nonce) => {
-                    debug!("Ping: {}", nonce);
-                    peer.write(Message::Pong(nonce.to_string()));
+            let (msg, peer) = result.unwrap();
+            self.handle_message(&msg, peer);
+        }
+    }
+
+    //handle one frame received from a peer
+    pub fn handle_message(&mut self, msg: &[u8], mut peer: peer::Handle) {
+        let msg_len = msg.len();
+        let msg: Message = match Message::decode(msg) {
+            Ok(msg) => msg,
+            Err(e) => {
+                info!("Error decoding message from {}: {}", peer.addr(), e);
+                self.server.report_misbehavior(*peer.addr(), Misbehavior::MalformedMessage);
+                return;
+            }
+        };
+        self.metrics.message_received(&msg, msg_len);
+        //the sender has these items, there is no need to announce them back
+        let items = InvItem::from_message(&msg);
+        if !items.is_empty() {
+            self.server.mark_known(*peer.addr(), items);
+        }
+        match msg {
+            Message::Ping(nonce) => {
+                debug!("Ping: {}", nonce);
+                peer.write(Message::Pong(nonce.to_string()));
+            }
+            Message::Pong(nonce) => {
+                debug!("Pong: {}", nonce);
+                self.server.record_pong(*peer.addr(), nonce);
+            }
+            Message::GetPeers => {
+                peer.write(Message::Peers(self.server.get_peer_addrs()));
+            }
+            Message::Peers(peer_addrs) => {
+                self.server.add_peer_addrs(peer_addrs);
+            }
+            Message::NewTxBlockHash(tx_blk_hashs) => {
+                //debug!("New tx block hashs");
+                if let Some(response) = self
+                    .handle_new_tx_blk_hash(tx_blk_hashs) {
+                    peer.write(response);
+                } 
+            }
+            Message::GetTxBlocks(tx_blk_hashs) => {
+                //debug!("Get tx blocks");
+                if let Some(response) = self
+                    .handle_get_tx_blks(tx_blk_hashs) {
+                    peer.write(response);
+                } 
+            }
+            Message::TxBlocks(tx_blks) => {
+                //debug!("Comming tx blocks");
+                let shards: HashMap<H256, usize> = tx_blks
+                    .iter()
+                    .map(|blk| (blk.hash(), blk.get_shard_id()))
+                    .collect();
+                if let Some(Message::NewTxBlockHash(new_tx_blk_hashs)) = self.handle_tx_blocks(tx_blks, peer.addr()) {
+                    self.relay_tx_blk_hashes(new_tx_blk_hashs, &shards);
                 }
-                Message::Pong(nonce) => {
-                    debug!("Pong: {}", nonce);
+            }
+
+            
+            Message::NewBlockHash(hash_vec) => {
+                //debug!("New versa block hash");
+                //announcements are handled after the initial block download
+                {
+                    let mut sync_manager = self.sync_manager.lock().unwrap();
+                    if sync_manager.is_syncing() {
+                        sync_manager.defer_announcement(hash_vec, peer.clone());
+                        return;
+                    }
                 }
-                Message::NewTxBlockHash(tx_blk_hashs) => {
-                    //debug!("New tx block hashs");
-                    if let Some(response) = self
-                        .handle_new_tx_blk_hash(tx_blk_hashs) {
-                        peer.write(response);
-                    } 
+                if let Some(response) = self
+                    .handle_new_block_hash(hash_vec) {
+                    peer.write(response);
                 }
-                Message::GetTxBlocks(tx_blk_hashs) => {
-                    //debug!("Get tx blocks");
-                    if let Some(response) = self
-                        .handle_get_tx_blks(tx_blk_hashs) {
-                        peer.write(response);
-                    } 
+            }
+            Message::GetBlocks(hash_vec) => {
+                //debug!("Get versa blocks");
+                if let Some(response) = self
+                    .handle_get_blocks(hash_vec) {
+                    peer.write(response);
                 }
-                Message::TxBlocks(tx_blks) => {
-                    //debug!("Comming tx blocks");
-                    if let Some(response) = self.handle_tx_blocks(tx_blks) {
-                        if let Message::NewTxBlockHash(new_tx_blk_hashs) = response {
-                            self.server.broadcast(
-                                Message::NewTxBlockHash(new_tx_blk_hashs)
-                            )
-                        } 
+            }
+            Message::Blocks(blocks) => {
+                //debug!("Coming versa blocks");
+                self.process_blocks(blocks, &mut peer);
+            }
+            Message::GetCompactBlocks(hash_vec) => {
+                if let Some(Message::Blocks(blocks)) = self.handle_get_blocks(hash_vec) {
+                    //ordering blocks have no compact form
+                    let (compacts, full): (Vec<_>, Vec<_>) = blocks
+                        .into_iter()
+                        .map(|blk| (CompactBlock::from_block(&blk), blk))
+                        .partition(|(compact, _)| compact.is_some());
+                    let compacts: Vec<CompactBlock> = compacts
+                        .into_iter()
+                        .filter_map(|(compact, _)| compact)
+                        .collect();
+                    let full: Vec<VersaBlock> = full
+                        .into_iter()
+                        .map(|(_, blk)| blk)
+                        .collect();
+                    if !compacts.is_empty() {
+                        peer.write(Message::CompactBlocks(compacts));
                     }
-                }
-
-                
-                Message::NewBlockHash(hash_vec) => {
-                    //debug!("New versa block hash");
-                    if let Some(response) = self
-                        .handle_new_block_hash(hash_vec) {
-                        peer.write(response);
+                    if !full.is_empty() {
+                        peer.write(Message::Blocks(full));
                     }
                 }
-                Message::GetBlocks(hash_vec) => {
-                    //debug!("Get versa blocks");
-                    if let Some(response) = self
-                        .handle_get_blocks(hash_vec) {
-                        peer.write(response);
-                    }
+            }
+            Message::CompactBlocks(compacts) => {
+                let (blocks, requests) = self.handle_compact_blocks(compacts, peer.addr());
+                for request in requests {
+                    peer.write(request);
                 }
-                Message::Blocks(blocks) => {
-                    //debug!("Coming versa blocks");
-                    let (response_1, response_2, response_3) = self
-                        .handle_blocks(blocks); 
-                    if let Some(new_blks) = response_1 {
-                        self.server.broadcast(new_blks);
-                    }
-
-                    //handle missing blocks
-                    if let Some(missing_blks) = response_2 {
-                        peer.write(missing_blks);
-                    }
-
-                    //handle missing symbols
-                    if let Some(missing_symbol_indexs) = response_3 {
-                        self.server.broadcast(missing_symbol_indexs);
-                    }
+                if !blocks.is_empty() {
+                    self.process_blocks(blocks, &mut peer);
                 }
-                
-                Message::NewSymbols(symbol_indexs) => {
-                    //debug!("New Samples");
-                    if let Some(response) = self
-                        .handle_new_symbols(symbol_indexs) {
-                        peer.write(response);
-                    }
+            }
+            Message::GetBlockTxs((versa_hash, indexs)) => {
+                if let Some(response) = self.handle_get_block_txs(versa_hash, indexs) {
+                    peer.write(response);
                 }
-                Message::GetSymbols(symbol_indexs) => {
-                    //debug!("Get Samples");
-                    if let Some(response) = self
-                        .handle_get_symbols(symbol_indexs) {
-                        peer.write(response);
+            }
+            Message::BlockTxs((versa_hash, tx_blks)) => {
+                let (asked, result) = {
+                    let mut compact_pool = self.compact_pool.lock().unwrap();
+                    let asked = compact_pool.take_request(&versa_hash, peer.addr());
+                    (asked, compact_pool.fill(&versa_hash, tx_blks))
+                };
+                match result {
+                    Some(Ok(block)) => self.process_blocks(vec![block], &mut peer),
+                    Some(Err(e)) => {
+                        //fall back to the full block
+                        info!("Cannot rebuild compact block: {}", e);
+                        self.compact_pool.lock().unwrap().remove(&versa_hash);
+                        peer.write(Message::GetBlocks(vec![versa_hash]));
                     }
+                    //a late answer, the block was rebuilt or dropped since we asked
+                    None if asked => {}
+                    None => self.server.report_misbehavior(*peer.addr(), Misbehavior::UnsolicitedData),
+                }
+            }
+            Message::GetHeaders((chain, locator)) => {
+                if let Some(response) = self
+                    .handle_get_headers(chain, locator) {
+                    peer.write(response);
+                }
+            }
+            Message::Headers((chain, peer_height, headers)) => {
+                for request in self.handle_headers(chain, peer_height, headers, &peer) {
+                    peer.write(request);
+                }
+                self.handle_deferred_announcements();
+            }
+            
+            Message::NewSymbols(symbol_indexs) => {
+                //debug!("New Samples");
+                if let Some(response) = self
+                    .handle_new_symbols(symbol_indexs) {
+                    peer.write(response);
+                }
+            }
+            Message::GetSymbols(symbol_indexs) => {
+                //debug!("Get Samples");
+                if let Some(response) = self
+                    .handle_get_symbols(symbol_indexs) {
+                    peer.write(response);
+                }
+            }
+            Message::Symbols(samples) => {
+                //debug!("Coming Samples");
+                let (response_1, response_2, response_3) = self
+                    .handle_symbols(samples, &peer);
+                if let Some(Message::NewSymbols(new_symbol_indexs)) = response_1 {
+                    self.relay_symbol_indexs(new_symbol_indexs, Message::NewSymbols);
                 }
-                Message::Symbols(samples) => {
-                    //debug!("Coming Samples");
-                    let (response_1, response_2, response_3) = self
-                        .handle_symbols(samples);
-                    if let Some(new_symbol_hashes) = response_1 {
-                        self.server.broadcast(new_symbol_hashes);
-                    }
 
-                    // new block hashes
-                    if let Some(new_blks) = response_2 {
-                        self.server.broadcast(new_blks);
-                    }
+                // new block hashes
+                if let Some(Message::NewBlockHash(new_hashes)) = response_2 {
+                    self.relay_block_hashes(new_hashes);
+                }
 
-                    //handle missing blocks
-                    if let Some(missing_blks) = response_3 {
-                        self.server.broadcast(missing_blks);
-                    }
+                //handle missing blocks
+                if let Some(missing_blks) = response_3 {
+                    self.server.broadcast(missing_blks);
                 }
-                // Message::NewMissBlockHash((miss_blk_vec, shard_id)) => {
-                //     for blk in miss_blk_vec {
-                //         match self.multichain
-                //             .lock()
-                //             .unwrap()
-                //             .get_block_by_shard(
-                //             &blk,
-                //             shard_id as usize
-                //         ) {
-                //             Some(versa_block) => {
-                //                 peer.write(Message::Blocks(vec![versa_block]));
-                //             }
-                //             None => {}
-                //         }
-                //     }
-                // }
-                // _ => unimplemented!()
             }
+            // Message::NewMissBlockHash((miss_blk_vec, shard_id)) => {
+            //     for blk in miss_blk_vec {
+            //         match self.multichain
+            //             .lock()
+            //             .unwrap()
+            //             .get_block_by_shard(
+            //             &blk,
+            //             shard_id as usize
+            //         ) {
+            //             Some(versa_block) => {
+            //                 peer.write(Message::Blocks(vec![versa_block]));
+            //             }
+            //             None => {}
+            //         }
+            //     }
+            // }
+            // _ => unimplemented!()
         }
+        self.update_unavailable_metrics();
+    }
+
+    fn update_unavailable_metrics(&self) {
+        let cmts = self.unavailable_cmt2avai_blocks.lock().unwrap().len();
+        let avai_blocks = self.unavailable_avai_block2cmts.lock().unwrap().len();
+        self.metrics.set_unavailable(cmts, avai_blocks);
+    }
+
+    fn lock_multichain(&self) -> MutexGuard<'_, Multichain> {
+        self.metrics.lock_multichain(&self.multichain)
     }
    
     //handle transaction message
@@ -236,14 +405,17 @@ impl Worker {
             if self.mempool.lock().unwrap().check(tx_blk_hash) {
                 continue;
             }
-            if let Some(_) = self.multichain
-                .lock()
-                .unwrap()
+            if let Some(_) = self.lock_multichain()
                 .get_tx_blk_in_longest_proposer_chain(tx_blk_hash) {
                 continue;
             }
             unreceived_tx_blks.push(tx_blk_hash.clone());
         }
+        //skip the ones already requested from another peer
+        let unreceived_tx_blks = self.in_flight
+            .lock()
+            .unwrap()
+            .filter_new(unreceived_tx_blks, |h| InvItem::TxBlock(*h));
         if !unreceived_tx_blks.is_empty() {
             Some(Message::GetTxBlocks(unreceived_tx_blks))
         } else {
@@ -262,9 +434,7 @@ impl Worker {
                 continue;
             }
             //find tx in blockchain
-            if let Some(blk) = self.multichain
-                .lock()
-                .unwrap()
+            if let Some(blk) = self.lock_multichain()
                 .get_tx_blk_in_longest_proposer_chain(tx_blk_hash) {
                 res_tx_blks.push(blk);
             }
@@ -277,33 +447,27 @@ impl Worker {
     }
     fn handle_tx_blocks(
         &self, 
-        tx_blks: Vec<TransactionBlock>) -> Option<Message> 
+        tx_blks: Vec<TransactionBlock>,
+        peer: &std::net::SocketAddr) -> Option<Message> 
     {
         let mut new_tx_blk_hashes: Vec<H256> = Vec::new();
         for blk in tx_blks.iter() {
-            //find tx in mempool
             let hash = blk.hash();
-            if let Some(_) = self.mempool.lock().unwrap().get_tx_blk(&hash) {
-                continue;
-            }
-            //2.find tx in the longest proposer chain
-            if let Some(_) = self.multichain
-                .lock()
-                .unwrap()
-                .get_tx_blk_in_longest_proposer_chain(&hash){
-                continue;
+            self.metrics.block_received(metrics::TX_BLOCK);
+            self.in_flight.lock().unwrap().received(&InvItem::TxBlock(hash));
+            match accept_tx_blk(&self.multichain, &self.mempool, blk, &self.config) {
+                Ok(true) => new_tx_blk_hashes.push(hash),
+                Ok(false) => {}
+                Err(e) => {
+                    info!("Reject tx block: {}", e);
+                    self.metrics.block_rejected(metrics::TX_BLOCK, RejectReason::InvalidPow);
+                    self.server.report_misbehavior(*peer, Misbehavior::InvalidPow);
+                }
             }
-            // match self.validator.validate_tx(tx, None, None, ValidationSource::FromTransaction) {
-            //     Ok(_) => {}
-            //     Err(_) => {
-            //         continue;
-            //     }
-            // }
-            new_tx_blk_hashes.push(hash);
-            self.mempool.lock().unwrap().insert_tx_blk(blk.clone());
-            // info!("Incoming tx block {:?}", hash);
         }
         if !new_tx_blk_hashes.is_empty() {
+            //the block in mining should propose the new tx blocks
+            self.miner.update();
             Some(Message::NewTxBlockHash(new_tx_blk_hashes))
         } else {
             None
@@ -322,9 +486,7 @@ impl Worker {
         for versa_hash in block_hash_vec {
             match versa_hash.clone() {
                 VersaHash::OrderHash(order_hash) => {
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_order_block(
                         &order_hash) {
                         Some(_) => {}
@@ -334,9 +496,7 @@ impl Worker {
                     }
                 }
                 VersaHash::PropHash(prop_hash) => {
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_prop_block(
                         &prop_hash) {
                         Some(_) => {}
@@ -349,9 +509,7 @@ impl Worker {
                     let mut is_found = false;
                     //not sure the shard id of the exclusive block based on its hash
                     for id in 0..self.config.shard_num {
-                        match self.multichain
-                            .lock()
-                            .unwrap()
+                        match self.lock_multichain()
                             .get_avai_block_by_shard(
                             &ex_hash,
                             id
@@ -373,9 +531,7 @@ impl Worker {
                     let mut is_found = true;
                     //not sure the shard id of the exclusive block based on its hash
                     for id in 0..self.config.shard_num {
-                        match self.multichain
-                            .lock()
-                            .unwrap()
+                        match self.lock_multichain()
                             .get_avai_block_by_shard(
                             &in_hash,
                             id
@@ -398,8 +554,15 @@ impl Worker {
             
         
 
+        let unreceived_blks = self.in_flight
+            .lock()
+            .unwrap()
+            .filter_new(unreceived_blks, |h| InvItem::Block(h.clone()));
         if !unreceived_blks.is_empty() {
-            Some(Message::GetBlocks(unreceived_blks))
+            match self.config.compact_blocks {
+                true => Some(Message::GetCompactBlocks(unreceived_blks)),
+                false => Some(Message::GetBlocks(unreceived_blks)),
+            }
         } else {
             None
         }
@@ -418,9 +581,7 @@ impl Worker {
         for versa_hash in hash_vec {
             match versa_hash {
                 VersaHash::OrderHash(order_hash) => {
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_order_block(
                             &order_hash
                     ){
@@ -429,9 +590,7 @@ impl Worker {
                     }
                 }
                 VersaHash::PropHash(prop_hash) => {
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_prop_block(
                             &prop_hash
                     ){
@@ -441,9 +600,7 @@ impl Worker {
                 }
                 VersaHash::ExHash(ex_hash) => {
                     for id in 0..self.config.shard_num {
-                        match self.multichain
-                            .lock()
-                            .unwrap()
+                        match self.lock_multichain()
                             .get_avai_block_by_shard(
                             &ex_hash, 
                             id
@@ -458,9 +615,7 @@ impl Worker {
                 }
                 VersaHash::InHash(in_hash) => {
                     for id in 0..self.config.shard_num {
-                        match self.multichain
-                            .lock()
-                            .unwrap()
+                        match self.lock_multichain()
                             .get_avai_block_by_shard(
                             &in_hash, 
                             id
@@ -485,7 +640,7 @@ impl Worker {
         }
     }
 
-    fn handle_blocks(&mut self, blocks: Vec<VersaBlock>) 
+    fn handle_blocks(&mut self, blocks: Vec<VersaBlock>, peer: &peer::Handle)
         -> (Option<Message>, Option<Message>, Option<Message>) 
     //new_block_hash, missing block, missing symbols
     {
@@ -499,25 +654,29 @@ impl Worker {
         let mut missing_symbol_indexs: Vec<SymbolIndex> = vec![];
         // return tx
         for block in blocks {
+            let block_type = metrics::block_type(&block);
+            self.metrics.block_received(block_type);
             //verification
             //verify if hash is valid
     
             if !block.verify_hash() {
                 // return Err(String::from("Incorrect hash"));
                 info!("Reject block {:?} for incorrect hash", block.hash());
+                self.metrics.block_rejected(block_type, RejectReason::InvalidPow);
+                self.server.report_misbehavior(*peer.addr(), Misbehavior::InvalidPow);
                 continue;
             }
+            self.in_flight.lock().unwrap().received(&InvItem::Block(block.get_versa_hash()));
             let block_hash = block.hash();
             match block.clone() {
                 VersaBlock::PropBlock(_) => {
                     info!("Incoming proposer block {:?}", block_hash);
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_prop_block(
                         &block_hash) {
                         Some(_) => {
                             info!("Proposer block {:?} already exists", block_hash);
+                            self.metrics.block_rejected(block_type, RejectReason::Duplicate);
                             continue;
                         }
                         None => {}
@@ -526,15 +685,14 @@ impl Worker {
                 VersaBlock::ExAvaiBlock(_) => {
                     info!("Incoming exclusive availability block {:?}", block_hash);
                     let shard_id = block.get_shard_id().unwrap();
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_avai_block_by_shard(
                         &block_hash,
                         shard_id
                     ) {
                         Some(_) => {
                             info!("Exclusive availability block {:?} already exists", block_hash);
+                            self.metrics.block_rejected(block_type, RejectReason::Duplicate);
                             continue;
                         }
                         None => {}
@@ -545,13 +703,12 @@ impl Worker {
                 }
                 VersaBlock::OrderBlock(_) => {
                     info!("Incoming ordering block {:?}", block_hash);  
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_order_block(
                         &block_hash) {
                         Some(_) => {
                             info!("Ordering block {:?} already exists", block_hash);
+                            self.metrics.block_rejected(block_type, RejectReason::Duplicate);
                             continue;
                         }
                         None => {}
@@ -595,6 +752,7 @@ impl Worker {
                                 info!("Process block {:?}: in unavailable hash table", block_hash);    
                             } else {
                                 info!("Reject block {:?}: in unavailable hash table", block_hash);
+                                self.metrics.block_rejected(block_type, RejectReason::Unavailable);
                                 continue;
                             }
                         }
@@ -612,7 +770,7 @@ impl Worker {
                                 let requested_symbol_indexs = self.symbolpool
                                     .lock()
                                     .unwrap()
-                                    .request_symbols_for_new_cmt(&cmt_root, ex_or_in)
+                                    .request_symbols_for_new_cmt(&cmt_root, tx_blk.get_shard_id(), ex_or_in)
                                     .unwrap();
                                 unavailable_cmts.push(cmt_root);
                                 missing_symbol_indexs.extend(requested_symbol_indexs);
@@ -643,6 +801,12 @@ impl Worker {
                             }
                         }
                         info!("Reject block {:?}: unavailable, unavailable cmts: {:?}", block_hash, unavailable_cmts);
+                        self.events.publish(ChainEvent::BlockUnavailable {
+                            block_type: block_type.to_string(),
+                            hash: block_hash.to_string(),
+                            shard_id: block.get_shard_id().unwrap(),
+                            cmts: unavailable_cmts.iter().map(|x| x.to_string()).collect(),
+                        });
                         for un_cmt in unavailable_cmts.iter() {
                             let unreceived_symbols = self.symbolpool
                                     .lock()
@@ -652,14 +816,13 @@ impl Worker {
                             assert!(!unreceived_symbols.is_empty());
                         }
                         self.unavailable_avai_block2cmts.lock().unwrap().insert(block_hash, unavailable_cmts);
+                        self.metrics.block_rejected(block_type, RejectReason::Unavailable);
                         continue;
                     }
                 }
                 VersaBlock::OrderBlock(order_block) => {
                     let order_parent = order_block.get_order_parent();
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_confirmed_avai_set_by_order_hash(&order_parent) {
                             Ok(old_confirmed_avai_set) => {
                                 let new_confirmed_avai_set = order_block.get_confirmed_avai_set();
@@ -672,11 +835,13 @@ impl Worker {
                                 }
                                 if if_overlapping {
                                     info!("Reject block {:?}: overlapping confirmed availability set", block_hash);
+                                    self.metrics.block_rejected(block_type, RejectReason::OverlappingAvaiSet);
                                     continue;
                                 }
                             }                           
                             Err(e) => {
                                 info!("Error: {}", e);
+                                self.metrics.block_rejected(block_type, RejectReason::UnknownParent);
                                 continue;
                             }
                         }
@@ -696,7 +861,7 @@ impl Worker {
                         match self.symbolpool
                             .lock()
                             .unwrap()
-                            .request_symbols_for_new_cmt(&cmt, shard_id == self.config.shard_id) {
+                            .request_symbols_for_new_cmt(&cmt, shard_id, shard_id == self.config.shard_id) {
                             Ok(request_symbol_indexs) => {
                                 missing_symbol_indexs.extend(request_symbol_indexs);
                             }
@@ -715,6 +880,14 @@ impl Worker {
             false => Some(Message::NewBlockHash(new_hashs)),
         };
 
+        let (missing_parents, missing_symbol_indexs) = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            (
+                in_flight.filter_new(missing_parents, |h| InvItem::Block(h.clone())),
+                in_flight.filter_new(missing_symbol_indexs, |i| InvItem::Symbol(i.hash())),
+            )
+        };
+
         let res_missing_blks = match missing_parents.is_empty() {
             true => None,
             false => Some(Message::GetBlocks(missing_parents)),
@@ -754,6 +927,10 @@ impl Worker {
                 }
             }   
         }
+        let unreceived_symbols = self.in_flight
+            .lock()
+            .unwrap()
+            .filter_new(unreceived_symbols, |i| InvItem::Symbol(i.hash()));
         if !unreceived_symbols.is_empty() {
             Some(Message::GetSymbols(unreceived_symbols))
         } else {
@@ -788,7 +965,7 @@ impl Worker {
         }
     }
 
-    fn handle_symbols(&mut self, symbols: Vec<Symbol>) 
+    fn handle_symbols(&mut self, symbols: Vec<Symbol>, peer: &peer::Handle)
         -> (Option<Message>, Option<Message>, Option<Message>) //new_sample_hash, new_block_hash, missing parents
     {
         let mut new_symbols: Vec<SymbolIndex> = vec![];
@@ -797,11 +974,17 @@ impl Worker {
 
         for symbol in symbols {
             let symbol_index = symbol.get_index();
+            self.in_flight.lock().unwrap().received(&InvItem::Symbol(symbol_index.hash()));
             // info!("Incoming symbol: {:?}", symbol_index);
             let if_requested = self.symbolpool.lock()
                               .unwrap()
                               .check_if_requested(&symbol_index) ;
-            if if_requested {
+            if !if_requested {
+                self.server.report_misbehavior(*peer.addr(), Misbehavior::UnsolicitedData);
+            } else if !symbol.verify() {
+                info!("Reject symbol {:?}: incorrect proof", symbol_index);
+                self.server.report_misbehavior(*peer.addr(), Misbehavior::InvalidSymbol);
+            } else {
                 match self.symbolpool.lock()
                                .unwrap()
                                .insert_symbol(symbol) {
@@ -828,6 +1011,11 @@ impl Worker {
                                   .is_empty()
                 {
                     info!("cmt {:?} is now available", cmt_root);
+                    let shard_id = self.symbolpool.lock().unwrap().get_cmt_shard(&cmt_root);
+                    self.events.publish(ChainEvent::CmtAvailable {
+                        cmt: cmt_root.to_string(),
+                        shard_id,
+                    });
                     //all symbols for cmt in symbol_index is received
                     {
                         // 1) First, copy out the blocks corresponding to cmt_root.
@@ -910,6 +1098,10 @@ impl Worker {
             false => Some(Message::NewBlockHash(new_hashes)),
         };
 
+        let missing_parents = self.in_flight
+            .lock()
+            .unwrap()
+            .filter_new(missing_parents, |h| InvItem::Block(h.clone()));
         let res_missing_blks = match missing_parents.is_empty() {
             true => None,
             false => Some(Message::GetBlocks(missing_parents)),
@@ -918,6 +1110,213 @@ impl Worker {
         (res_new_symbols, res_new_hashes, res_missing_blks)
     }
 
+    //handle header-first sync messages
+    fn handle_get_headers(&self, chain: ChainType, locator: Vec<H256>) -> Option<Message> {
+        let multichain = self.lock_multichain();
+        let peer_height = multichain.get_chain_height(&chain)?;
+        let headers: Vec<SyncHeader> = multichain
+            .get_blocks_after(&chain, &locator, HEADER_BATCH_SIZE)
+            .iter()
+            .map(SyncHeader::from_block)
+            .collect();
+        Some(Message::Headers((chain, peer_height, headers)))
+    }
+    fn handle_headers(
+        &self,
+        chain: ChainType,
+        peer_height: usize,
+        headers: Vec<SyncHeader>,
+        peer: &peer::Handle) -> Vec<Message>
+    {
+        let mut missing: Vec<VersaHash> = vec![];
+        {
+            let sync_manager = self.sync_manager.lock().unwrap();
+            if !sync_manager.is_expecting_headers(&chain) {
+                self.server.report_misbehavior(*peer.addr(), Misbehavior::UnsolicitedData);
+                return vec![];
+            }
+            //a late answer of a sync peer we moved away from
+            if sync_manager.get_sync_peer() != Some(*peer.addr()) {
+                return vec![];
+            }
+            let multichain = self.lock_multichain();
+            let known_parent = sync_manager.connects(&chain, &headers, &multichain);
+            if let Err(e) = SyncManager::check_headers(&chain, &headers, known_parent, &self.config) {
+                info!("Reject headers of {:?}: {}", chain, e);
+                self.server.report_misbehavior(*peer.addr(), Misbehavior::InvalidHeaders);
+                return vec![];
+            }
+            for header in headers.iter() {
+                if !multichain.contains_block(&chain, &header.get_versa_hash().get_hash()) {
+                    missing.push(header.get_versa_hash());
+                }
+            }
+        }
+        self.sync_manager
+            .lock()
+            .unwrap()
+            .on_headers(&chain, peer_height, &headers, missing)
+    }
+    //insert received blocks, relay the new ones and ask for what is missing
+    fn process_blocks(&mut self, blocks: Vec<VersaBlock>, peer: &mut peer::Handle) {
+        let (response_1, response_2, response_3) = self
+            .handle_blocks(blocks, peer);
+        if let Some(Message::NewBlockHash(new_hashes)) = response_1 {
+            self.relay_block_hashes(new_hashes);
+        }
+
+        //handle missing blocks
+        if let Some(missing_blks) = response_2 {
+            peer.write(missing_blks);
+        }
+
+        //handle missing symbols
+        if let Some(Message::GetSymbols(missing_symbol_indexs)) = response_3 {
+            self.relay_symbol_indexs(missing_symbol_indexs, Message::GetSymbols);
+        }
+        self.handle_deferred_announcements();
+    }
+
+    //rebuild compact blocks from the local tx blocks, returning the complete blocks
+    //and the requests of the missing tx blocks
+    fn handle_compact_blocks(
+        &self,
+        compacts: Vec<CompactBlock>,
+        peer: &std::net::SocketAddr,
+    ) -> (Vec<VersaBlock>, Vec<Message>) {
+        let mut blocks: Vec<VersaBlock> = vec![];
+        let mut requests: Vec<Message> = vec![];
+        for compact in compacts {
+            let versa_hash = compact.get_versa_hash();
+            if self.has_block(&versa_hash) || self.compact_pool.lock().unwrap().contains(&versa_hash) {
+                continue;
+            }
+            //nothing is pooled or asked for a header without the work of its kind
+            if !meets_target(&versa_hash, &self.config) {
+                info!("Reject compact block {:?} for insufficient work", versa_hash);
+                self.server.report_misbehavior(*peer, Misbehavior::InvalidPow);
+                continue;
+            }
+            let tx_blks: Vec<Option<TransactionBlock>> = compact
+                .get_tx_blk_hashes()
+                .iter()
+                .map(|hash| {
+                    self.mempool
+                        .lock()
+                        .unwrap()
+                        .get_tx_blk(hash)
+                        .or_else(|| self.lock_multichain().get_tx_blk(hash))
+                })
+                .collect();
+            if tx_blks.iter().all(|b| b.is_some()) {
+                match compact.reconstruct(tx_blks.into_iter().flatten().collect()) {
+                    Ok(block) => blocks.push(block),
+                    Err(e) => {
+                        info!("Cannot rebuild compact block: {}", e);
+                        requests.push(Message::GetBlocks(vec![versa_hash]));
+                    }
+                }
+            } else {
+                let missing = {
+                    let mut compact_pool = self.compact_pool.lock().unwrap();
+                    compact_pool.mark_requested(&versa_hash, *peer);
+                    compact_pool.insert(compact, tx_blks)
+                };
+                requests.push(Message::GetBlockTxs((versa_hash, missing)));
+            }
+        }
+        (blocks, requests)
+    }
+
+    fn handle_get_block_txs(&self, versa_hash: VersaHash, indexs: Vec<usize>) -> Option<Message> {
+        let block = match self.handle_get_blocks(vec![versa_hash.clone()]) {
+            Some(Message::Blocks(mut blocks)) if !blocks.is_empty() => blocks.remove(0),
+            _ => return None,
+        };
+        if let VersaBlock::OrderBlock(_) = block {
+            return None;
+        }
+        let tx_set = block.get_tx_blocks();
+        let tx_blks: Vec<TransactionBlock> = indexs
+            .into_iter()
+            .filter_map(|i| tx_set.get(i).cloned())
+            .collect();
+        Some(Message::BlockTxs((versa_hash, tx_blks)))
+    }
+
+    //whether the block is stored in one of the chains
+    fn has_block(&self, versa_hash: &VersaHash) -> bool {
+        let multichain = self.lock_multichain();
+        match versa_hash {
+            VersaHash::PropHash(h) => multichain.get_prop_block(h).is_some(),
+            VersaHash::OrderHash(h) => multichain.get_order_block(h).is_some(),
+            VersaHash::ExHash(h) | VersaHash::InHash(h) => multichain.get_avai_block(h).is_some(),
+        }
+    }
+
+    //announce new tx blocks to the peers chosen by the relay policy
+    fn relay_tx_blk_hashes(&self, hashes: Vec<H256>, shards: &HashMap<H256, usize>) {
+        //in shard order, so that a simulation relays them the same way every run
+        let mut groups: BTreeMap<usize, Vec<H256>> = BTreeMap::new();
+        for hash in hashes {
+            let shard_id = shards.get(&hash).cloned().unwrap_or(self.config.shard_id);
+            groups.entry(shard_id).or_default().push(hash);
+        }
+        for (shard_id, hashes) in groups {
+            self.server.relay(Message::NewTxBlockHash(hashes), RelayClass::TxBlock(shard_id));
+        }
+    }
+
+    //announce new blocks to the peers chosen by the relay policy for their class
+    fn relay_block_hashes(&self, hashes: Vec<VersaHash>) {
+        let mut groups: Vec<(RelayClass, Vec<VersaHash>)> = vec![];
+        for hash in hashes {
+            let class = match &hash {
+                VersaHash::PropHash(_) => RelayClass::Proposer,
+                VersaHash::OrderHash(_) => RelayClass::Ordering,
+                VersaHash::ExHash(h) => RelayClass::ExAvailability(self.get_avai_shard(h)),
+                VersaHash::InHash(h) => RelayClass::InAvailability(self.get_avai_shard(h)),
+            };
+            match groups.iter_mut().find(|(c, _)| *c == class) {
+                Some((_, group)) => group.push(hash),
+                None => groups.push((class, vec![hash])),
+            }
+        }
+        for (class, hashes) in groups {
+            self.server.relay(Message::NewBlockHash(hashes), class);
+        }
+    }
+
+    //send symbol announcements or requests to the shards of their tx blocks
+    fn relay_symbol_indexs(&self, indexs: Vec<SymbolIndex>, to_msg: fn(Vec<SymbolIndex>) -> Message) {
+        let groups = self.symbolpool
+            .lock()
+            .unwrap()
+            .split_by_shard(indexs);
+        for (shard_id, indexs) in groups {
+            self.server.relay(to_msg(indexs), RelayClass::Symbol(shard_id));
+        }
+    }
+
+    fn get_avai_shard(&self, hash: &H256) -> Option<usize> {
+        self.lock_multichain()
+            .get_avai_block(hash)
+            .map(|blk| blk.get_shard_id())
+    }
+
+    //switch to relay mode once every chain is synced
+    fn handle_deferred_announcements(&self) {
+        let deferred = self.sync_manager
+            .lock()
+            .unwrap()
+            .take_deferred_announcements();
+        for (hash_vec, mut peer) in deferred {
+            if let Some(response) = self.handle_new_block_hash(hash_vec) {
+                peer.write(response);
+            }
+        }
+    }
+
     fn insert_block(&mut self, block: VersaBlock) -> (Vec<VersaHash>, Vec<VersaHash>) {
         let mut new_hashs: Vec<VersaHash> = vec![];
         // let mut missing_parents: HashMap<usize, Vec<H256>> = HashMap::new();
@@ -956,9 +1355,7 @@ impl Worker {
             let mut parent_not_exisit = false;
             match parent_hash.clone() {
                 VersaHash::PropHash(prop_hash) => {
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_prop_block(&prop_hash) {
                         Some(_) => {}
                         None => {
@@ -967,9 +1364,7 @@ impl Worker {
                     }
                 }
                 VersaHash::OrderHash(order_hash) => {
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_order_block(&order_hash) {
                         Some(_) => {}
                         None => {
@@ -978,9 +1373,7 @@ impl Worker {
                     }
                 }
                 VersaHash::ExHash(ex_hash) => {
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_avai_block_by_shard(&ex_hash, inserted_shard_id) {
                         Some(_) => {}
                         None => {
@@ -989,9 +1382,7 @@ impl Worker {
                     }
                 }
                 VersaHash::InHash(in_hash) => {
-                    match self.multichain
-                        .lock()
-                        .unwrap()
+                    match self.lock_multichain()
                         .get_avai_block_by_shard(&in_hash, inserted_shard_id) {
                         Some(_) => {}
                         None => {
@@ -1036,15 +1427,43 @@ impl Worker {
             let mut removed_buff: Vec<VersaHash> = vec![];
             while !inserted_blks.is_empty() {
                 let (inserted_blk, inserted_parent_hash) = inserted_blks.pop_front().unwrap();
-                match self.multichain
-                    .lock()
-                    .unwrap()
-                    .insert_block_with_parent(
-                    inserted_blk.clone(),
-                    &inserted_parent_hash,
-                    inserted_shard_id
-                ) {
-                    Ok(_) => {
+                //release the lock before handling the result, which locks the chains again
+                let result = {
+                    let mut multichain = self.lock_multichain();
+                    let chain = ChainType::of_parent(&inserted_parent_hash, inserted_shard_id);
+                    let old_tip = multichain.get_tip(&chain);
+                    let inserted = multichain.insert_block_with_parent(
+                        inserted_blk.clone(),
+                        &inserted_parent_hash,
+                        inserted_shard_id
+                    );
+                    inserted.map(|_| match (old_tip, multichain.get_tip(&chain)) {
+                        (Some(old_tip), Some(new_tip)) => {
+                            let chain_events = events::insertion_events(
+                                &self.events,
+                                &multichain,
+                                chain,
+                                &inserted_blk,
+                                &inserted_parent_hash.get_hash(),
+                                &old_tip,
+                            );
+                            //the tx blocks of the proposer blocks the tip moved onto, including
+                            //side blocks adopted by a reorg, are referenced, the left ones come back
+                            let fork_tx_blks = match chain {
+                                ChainType::Proposer if new_tip != old_tip => multichain.get_prop_fork_tx_blks(&old_tip, &new_tip),
+                                _ => (vec![], vec![]),
+                            };
+                            (chain_events, fork_tx_blks)
+                        }
+                        _ => (vec![], (vec![], vec![])),
+                    })
+                };
+                match result {
+                    Ok((chain_events, (adopted, abandoned))) => {
+                        self.events.publish_all(chain_events);
+                        if !adopted.is_empty() || !abandoned.is_empty() {
+                            self.mempool.lock().unwrap().follow_prop_fork(&adopted, abandoned);
+                        }
                         let new_hash = match inserted_blk.clone() {
                             VersaBlock::PropBlock(_) 
                                 => VersaHash::PropHash(inserted_blk.hash()),
@@ -1057,6 +1476,10 @@ impl Worker {
                         };
                         new_hashs.push(new_hash.clone());
                         info!("successfully inserting block: {:?}", new_hash);
+                        self.sync_manager
+                            .lock()
+                            .unwrap()
+                            .on_block_inserted(&new_hash);
                         
 
                         //if there are some blocks in the buff whose parent is the new block,
@@ -1083,6 +1506,10 @@ impl Worker {
                 self.blk_buff.lock().unwrap().remove(&item2);
             }
         }
+        //the tips moved, mine on top of them
+        if !new_hashs.is_empty() {
+            self.miner.update();
+        }
         (new_hashs, missing_parents)
     }
 }
@@ -0,0 +1,475 @@
+pub mod network;
+
+use crate::{
+    types::hash::{H256, Hashable},
+    optchain::{
+        new_multichain,
+        block::Block,
+        clock::Clock,
+        configuration::Configuration,
+        mempool::Mempool,
+        events::EventBus,
+        metrics::Metrics,
+        multichain::{Multichain, ChainType},
+        symbolpool::SymbolPool,
+        miner::{
+            self,
+            BlockKind,
+            worker::Worker as MinerWorker,
+        },
+        network::{
+            peer,
+            message::Message,
+            server::{self, ControlSignal},
+            worker::{Worker as NetworkWorker, WorkerContext},
+            sync::SyncManager,
+            inventory::KnownInventory,
+            misbehavior::Misbehavior,
+        },
+    },
+};
+use futures::channel::mpsc;
+use log::{debug, trace};
+use rand::Rng;
+use std::{
+    net,
+    sync::{Arc, Mutex},
+};
+use network::{SimNetwork, EventKind};
+
+//the most nonces tried for a block of a given kind before giving up
+const MAX_NONCE_TRIES: u32 = 1 << 20;
+
+//the parameters of a simulation, the node configurations come from a base one
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    pub num_nodes: usize,
+    pub seed: u64,
+    pub latency: u64, //in ms
+    pub jitter: u64, //in ms
+    pub loss: f64, //probability a frame is dropped
+    pub mining_interval: u64, //mean time in ms between two mining attempts of a node, 0 disables mining
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            num_nodes: 4,
+            seed: 0,
+            latency: 50,
+            jitter: 0,
+            loss: 0.0,
+            mining_interval: 0,
+        }
+    }
+}
+
+//the connection of a node to one of its peers
+struct SimPeer {
+    node: usize,
+    handle: peer::Handle,
+    write_queue: mpsc::UnboundedReceiver<Vec<u8>>,
+    known: KnownInventory,
+}
+
+//a node running the real network and miner workers, without threads nor sockets
+pub struct SimNode {
+    config: Configuration,
+    addr: net::SocketAddr,
+    multichain: Arc<Mutex<Multichain>>,
+    mempool: Arc<Mutex<Mempool>>,
+    symbolpool: Arc<Mutex<SymbolPool>>,
+    metrics: Arc<Metrics>,
+    events: Arc<EventBus>,
+    network_worker: NetworkWorker,
+    miner_worker: MinerWorker,
+    control_chan: smol::channel::Receiver<ControlSignal>,
+    peers: Vec<SimPeer>,
+    misbehaviors: Vec<(net::SocketAddr, Misbehavior)>,
+}
+
+impl SimNode {
+    fn new(config: Configuration, addr: net::SocketAddr, seed: u64, clock: &Clock) -> Self {
+        let multichain = Arc::new(Mutex::new(new_multichain(&config)));
+        let mempool = Arc::new(Mutex::new(Mempool::new(&config)));
+        let mut symbolpool = SymbolPool::new(&config);
+        symbolpool.seed_rng(seed);
+        let symbolpool = Arc::new(Mutex::new(symbolpool));
+        let metrics = Arc::new(Metrics::new());
+        let events = Arc::new(EventBus::new());
+        let (control_chan, server) = server::new_detached();
+        //the frames are handed to the worker by the simulator
+        let (_, msg_chan) = smol::channel::bounded(1);
+        //the blocks are mined by the simulator, the miner never runs and ignores the updates
+        let (_, miner, _) = miner::new(&multichain, &mempool, &config);
+        let ctx = WorkerContext {
+            server,
+            multichain: Arc::clone(&multichain),
+            mempool: Arc::clone(&mempool),
+            symbolpool: Arc::clone(&symbolpool),
+            sync_manager: Arc::new(Mutex::new(SyncManager::new())),
+            config: config.clone(),
+            miner,
+            metrics: Arc::clone(&metrics),
+            events: Arc::clone(&events),
+        };
+        let mut network_worker = NetworkWorker::new(1, msg_chan, &ctx);
+        network_worker.set_clock(clock);
+        //the mined blocks are handed to the worker by the simulator
+        let (_, finished_block_chan) = crossbeam::channel::unbounded();
+        let miner_worker = MinerWorker::new(finished_block_chan, &ctx);
+        SimNode {
+            config,
+            addr,
+            multichain,
+            mempool,
+            symbolpool,
+            metrics,
+            events,
+            network_worker,
+            miner_worker,
+            control_chan,
+            peers: vec![],
+            misbehaviors: vec![],
+        }
+    }
+
+    pub fn get_config(&self) -> &Configuration {
+        &self.config
+    }
+
+    pub fn get_addr(&self) -> &net::SocketAddr {
+        &self.addr
+    }
+
+    pub fn get_multichain(&self) -> &Arc<Mutex<Multichain>> {
+        &self.multichain
+    }
+
+    pub fn get_mempool(&self) -> &Arc<Mutex<Mempool>> {
+        &self.mempool
+    }
+
+    pub fn get_symbolpool(&self) -> &Arc<Mutex<SymbolPool>> {
+        &self.symbolpool
+    }
+
+    pub fn get_metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    pub fn get_events(&self) -> &Arc<EventBus> {
+        &self.events
+    }
+
+    //the misbehaviors this node reported about its peers
+    pub fn get_misbehaviors(&self) -> &[(net::SocketAddr, Misbehavior)] {
+        &self.misbehaviors
+    }
+
+    pub fn get_height(&self, chain: &ChainType) -> usize {
+        self.multichain
+            .lock()
+            .unwrap()
+            .get_chain_height(chain)
+            .unwrap_or(0)
+    }
+
+    fn peer_index_of(&self, addr: &net::SocketAddr) -> Option<usize> {
+        self.peers.iter().position(|p| p.handle.addr() == addr)
+    }
+
+    fn peer_index(&self, node: usize) -> Option<usize> {
+        self.peers.iter().position(|p| p.node == node)
+    }
+
+    //the message goes to the given peers, minus the announcements they know
+    fn send_to_peers(&mut self, peers: Vec<usize>, msg: &Message) {
+        for i in peers {
+            let peer = &mut self.peers[i];
+            if let Some(peer_msg) = peer.known.filter_announcement(msg) {
+                peer.handle.write(peer_msg);
+            }
+        }
+    }
+}
+
+//N nodes in one process, exchanging their messages through a simulated network.
+//The nodes only progress when the simulation runs, so a test fully controls the
+//order of the events.
+pub struct Simulation {
+    network: SimNetwork,
+    nodes: Vec<SimNode>,
+    mining_interval: u64,
+    //follows the time of the network, shared by the nodes
+    clock: Clock,
+}
+
+impl Simulation {
+    //node i gets the node id i and the shard i modulo the number of shards
+    pub fn new(sim_config: &SimConfig, base_config: &Configuration) -> Self {
+        let shard_num = base_config.shard_num.max(1);
+        let clock = Clock::new_virtual();
+        let nodes: Vec<SimNode> = (0..sim_config.num_nodes)
+            .map(|i| {
+                let mut config = base_config.clone();
+                config.shard_num = shard_num;
+                config.node_id = i;
+                config.shard_id = i % shard_num;
+                config.mempool_persist = false;
+                let addr = net::SocketAddr::from(([10, 0, (i / 256) as u8, (i % 256) as u8], 6000));
+                SimNode::new(config, addr, sim_config.seed ^ i as u64, &clock)
+            })
+            .collect();
+        let mut network = SimNetwork::new(sim_config.num_nodes, sim_config.seed);
+        network.set_latency(sim_config.latency, sim_config.jitter);
+        network.set_loss(sim_config.loss);
+        let mut sim = Simulation {
+            network,
+            nodes,
+            mining_interval: sim_config.mining_interval,
+            clock,
+        };
+        if sim.mining_interval > 0 {
+            for i in 0..sim.nodes.len() {
+                sim.schedule_mining(i);
+            }
+        }
+        sim
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn node(&self, i: usize) -> &SimNode {
+        &self.nodes[i]
+    }
+
+    //the virtual time in ms
+    pub fn now(&self) -> u64 {
+        self.network.now()
+    }
+
+    pub fn set_latency(&mut self, latency: u64, jitter: u64) {
+        self.network.set_latency(latency, jitter);
+    }
+
+    pub fn set_loss(&mut self, loss: f64) {
+        self.network.set_loss(loss);
+    }
+
+    pub fn partition(&mut self, groups: &[Vec<usize>]) {
+        self.network.partition(groups);
+    }
+
+    pub fn heal(&mut self) {
+        self.network.heal();
+    }
+
+    //connect two nodes both ways, as after a handshake
+    pub fn connect(&mut self, a: usize, b: usize) {
+        if a == b || self.nodes[a].peer_index(b).is_some() {
+            return;
+        }
+        for (local, remote) in [(a, b), (b, a)] {
+            let (write_queue, handle) = peer::new_simulated(self.nodes[remote].addr, &self.nodes[local].metrics);
+            self.nodes[local].peers.push(SimPeer {
+                node: remote,
+                handle,
+                write_queue,
+                known: KnownInventory::default(),
+            });
+        }
+    }
+
+    pub fn connect_all(&mut self) {
+        for a in 0..self.nodes.len() {
+            for b in (a + 1)..self.nodes.len() {
+                self.connect(a, b);
+            }
+        }
+    }
+
+    //mine a block of the given kind on top of the tips of a node, and relay it;
+    //the nonce is drawn until its pow hash falls in the range of the kind
+    pub fn mine(&mut self, node: usize, kind: BlockKind) -> Result<(), String> {
+        let hybrid_block = self.assemble(node);
+        let config = &self.nodes[node].config;
+        let nonce = (0..MAX_NONCE_TRIES)
+            .map(|_| self.network.rng().gen::<u32>())
+            .find(|nonce| {
+                miner::sortition(&H256::pow_hash(&hybrid_block.hash(), *nonce), config) == Some(kind)
+            })
+            .ok_or(format!("no nonce mines a {:?} block in {} tries", kind, MAX_NONCE_TRIES))?;
+        self.seal(node, &hybrid_block, nonce, kind);
+        Ok(())
+    }
+
+    //the block a node would mine now, from the seeded rng and the virtual clock
+    fn assemble(&mut self, node: usize) -> Block {
+        let node_ref = &self.nodes[node];
+        miner::assemble_block_with(
+            &node_ref.multichain,
+            &node_ref.mempool,
+            &node_ref.config,
+            self.network.rng(),
+            &self.clock,
+        )
+    }
+
+    fn seal(&mut self, node: usize, hybrid_block: &Block, nonce: u32, kind: BlockKind) {
+        debug!("node {} mines a {:?} block at {}ms", node, kind, self.network.now());
+        self.nodes[node].miner_worker.handle_mined(miner::seal(hybrid_block, nonce, kind));
+        self.flush();
+    }
+
+    //process the events of the next `duration` ms
+    pub fn run_for(&mut self, duration: u64) {
+        self.flush();
+        let end = self.network.now() + duration;
+        while let Some(at) = self.network.next_event_at() {
+            if at > end {
+                break;
+            }
+            self.step();
+        }
+        self.network.advance_to(end);
+        self.clock.set_elapsed(end);
+    }
+
+    //process the next delivery or mining attempt
+    fn step(&mut self) {
+        let event = match self.network.pop() {
+            Some(event) => event,
+            None => return,
+        };
+        self.clock.set_elapsed(self.network.now());
+        match event.kind {
+            EventKind::Deliver { from, to, frame } => {
+                self.deliver(from, to, frame);
+            }
+            EventKind::Mine(node) => {
+                self.try_mine(node);
+                self.schedule_mining(node);
+            }
+        }
+        self.flush();
+    }
+
+    fn deliver(&mut self, from: usize, to: usize, frame: Vec<u8>) {
+        if !self.network.is_reachable(from, to) {
+            trace!("frame from node {} to node {} lost in a partition", from, to);
+            return;
+        }
+        let node = &mut self.nodes[to];
+        let i = match node.peer_index(from) {
+            Some(i) => i,
+            None => return,
+        };
+        let handle = node.peers[i].handle.clone();
+        //answered here, the worker would wait for the server to list its peers
+        if let Ok(Message::GetPeers) = Message::decode(&frame) {
+            node.peers[i].handle.write(Message::Peers(vec![]));
+            return;
+        }
+        node.network_worker.handle_message(&frame, handle);
+    }
+
+    //a mining attempt hashes one nonce, and succeeds with the probabilities given
+    //by the difficulties
+    fn try_mine(&mut self, node: usize) {
+        let hybrid_block = self.assemble(node);
+        let nonce: u32 = self.network.rng().gen();
+        let hash = H256::pow_hash(&hybrid_block.hash(), nonce);
+        if let Some(kind) = miner::sortition(&hash, &self.nodes[node].config) {
+            self.seal(node, &hybrid_block, nonce, kind);
+        }
+    }
+
+    //the next mining attempt, after an exponential delay
+    fn schedule_mining(&mut self, node: usize) {
+        if self.mining_interval == 0 {
+            return;
+        }
+        let u: f64 = self.network.rng().gen();
+        let delay = (-(self.mining_interval as f64) * (1.0 - u).ln()) as u64;
+        let at = self.network.now() + delay.max(1);
+        self.network.schedule(at, EventKind::Mine(node));
+    }
+
+    //carry out the control signals of the nodes and put their frames on the links
+    fn flush(&mut self) {
+        for i in 0..self.nodes.len() {
+            while let Ok(signal) = self.nodes[i].control_chan.try_recv() {
+                self.handle_signal(i, signal);
+            }
+            let node = &mut self.nodes[i];
+            let mut frames: Vec<(usize, Vec<u8>)> = vec![];
+            for peer in node.peers.iter_mut() {
+                while let Ok(Some(frame)) = peer.write_queue.try_next() {
+                    frames.push((peer.node, frame));
+                }
+            }
+            for (to, frame) in frames {
+                self.network.send(i, to, frame);
+            }
+        }
+    }
+
+    fn handle_signal(&mut self, i: usize, signal: ControlSignal) {
+        let shard_of: Vec<usize> = self.nodes.iter().map(|n| n.config.shard_id).collect();
+        let node = &mut self.nodes[i];
+        match signal {
+            ControlSignal::BroadcastMessage(msg) => {
+                let peers = (0..node.peers.len()).collect();
+                node.send_to_peers(peers, &msg);
+            }
+            ControlSignal::BroadcastMessageWithShard((msg, shard_id)) => {
+                let peers = (0..node.peers.len())
+                    .filter(|p| shard_of[node.peers[*p].node] == shard_id)
+                    .collect();
+                node.send_to_peers(peers, &msg);
+            }
+            ControlSignal::Relay((msg, class)) => {
+                let shard_peers: Vec<usize> = match node.config.relay_policy.target_shard(&class) {
+                    Some(shard_id) => (0..node.peers.len())
+                        .filter(|p| shard_of[node.peers[*p].node] == shard_id)
+                        .collect(),
+                    None => vec![],
+                };
+                //flood when no peer of the shard is connected, as the server does
+                let peers = match shard_peers.is_empty() {
+                    true => (0..node.peers.len()).collect(),
+                    false => shard_peers,
+                };
+                node.send_to_peers(peers, &msg);
+            }
+            ControlSignal::SendToPeer((addr, msg)) => {
+                if let Some(p) = node.peer_index_of(&addr) {
+                    node.peers[p].handle.write(msg);
+                }
+            }
+            ControlSignal::MarkKnown((addr, items)) => {
+                if let Some(p) = node.peer_index_of(&addr) {
+                    for item in items {
+                        node.peers[p].known.insert(item);
+                    }
+                }
+            }
+            ControlSignal::Misbehaving((addr, misbehavior)) => {
+                debug!("node {} reports {} for {:?}", i, addr, misbehavior);
+                node.misbehaviors.push((addr, misbehavior));
+            }
+            ControlSignal::ConnectNewPeer(addr, result_chan) => {
+                let _ = result_chan.send(Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    format!("cannot dial {} in a simulation, use Simulation::connect", addr),
+                )));
+            }
+            //the peer lists and the connections are managed by the simulation
+            _ => {}
+        }
+    }
+
+}
@@ -0,0 +1,150 @@
+use rand::{
+    Rng,
+    SeedableRng,
+    rngs::StdRng,
+};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+};
+
+//what happens at some virtual time
+pub enum EventKind {
+    Deliver {
+        from: usize,
+        to: usize,
+        frame: Vec<u8>,
+    },
+    Mine(usize), //the node tries to mine a block
+}
+
+pub struct Event {
+    pub at: u64, //virtual time in ms
+    seq: u64, //breaks the ties, in scheduling order
+    pub kind: EventKind,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Event {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+//The links between simulated nodes: a virtual clock, a queue of events and the
+//faults of the links. All the randomness comes from one seeded generator, so a
+//run only depends on its seed and on the calls made by the test.
+pub struct SimNetwork {
+    now: u64,
+    next_seq: u64,
+    events: BinaryHeap<Reverse<Event>>,
+    rng: StdRng,
+    latency: u64, //in ms
+    jitter: u64, //in ms, added uniformly to the latency
+    loss: f64, //probability a frame is dropped
+    groups: Vec<usize>, //the partition each node belongs to
+    //the arrival time of the last frame of each link, frames of a link are not reordered
+    last_arrival: HashMap<(usize, usize), u64>,
+}
+
+impl SimNetwork {
+    pub fn new(num_nodes: usize, seed: u64) -> Self {
+        SimNetwork {
+            now: 0,
+            next_seq: 0,
+            events: BinaryHeap::new(),
+            rng: StdRng::seed_from_u64(seed),
+            latency: 0,
+            jitter: 0,
+            loss: 0.0,
+            groups: vec![0; num_nodes],
+            last_arrival: HashMap::new(),
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    pub fn set_latency(&mut self, latency: u64, jitter: u64) {
+        self.latency = latency;
+        self.jitter = jitter;
+    }
+
+    pub fn set_loss(&mut self, loss: f64) {
+        self.loss = loss.clamp(0.0, 1.0);
+    }
+
+    //only the nodes of a same group can reach each other, the nodes not listed
+    //are isolated
+    pub fn partition(&mut self, groups: &[Vec<usize>]) {
+        let num_nodes = self.groups.len();
+        self.groups = (0..num_nodes).map(|i| groups.len() + i).collect();
+        for (group_id, group) in groups.iter().enumerate() {
+            for node in group {
+                self.groups[*node] = group_id;
+            }
+        }
+    }
+
+    pub fn heal(&mut self) {
+        self.groups.iter_mut().for_each(|g| *g = 0);
+    }
+
+    pub fn is_reachable(&self, from: usize, to: usize) -> bool {
+        self.groups[from] == self.groups[to]
+    }
+
+    pub fn schedule(&mut self, at: u64, kind: EventKind) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.events.push(Reverse(Event { at, seq, kind }));
+    }
+
+    //put a frame on the link, unless it is lost
+    pub fn send(&mut self, from: usize, to: usize, frame: Vec<u8>) {
+        if self.loss > 0.0 && self.rng.gen_bool(self.loss) {
+            return;
+        }
+        let jitter = match self.jitter {
+            0 => 0,
+            jitter => self.rng.gen_range(0..=jitter),
+        };
+        let last = self.last_arrival.entry((from, to)).or_insert(0);
+        let at = (self.now + self.latency + jitter).max(*last);
+        *last = at;
+        self.schedule(at, EventKind::Deliver { from, to, frame });
+    }
+
+    pub fn next_event_at(&self) -> Option<u64> {
+        self.events.peek().map(|Reverse(e)| e.at)
+    }
+
+    //pop the next event and move the clock to it
+    pub fn pop(&mut self) -> Option<Event> {
+        let Reverse(event) = self.events.pop()?;
+        self.now = self.now.max(event.at);
+        Some(event)
+    }
+
+    pub fn advance_to(&mut self, at: u64) {
+        self.now = self.now.max(at);
+    }
+}
@@ -4,11 +4,12 @@ use std::{
     time::{SystemTime},
     // thread,
     // sync::{Arc,Mutex},
-    collections::HashMap,
+    collections::{HashMap, BTreeMap},
 };
 use serde::{Serialize, Deserialize};
 use rand::{
-    thread_rng,
+    SeedableRng,
+    rngs::StdRng,
     seq::IteratorRandom,
     // Rng,
 };
@@ -113,7 +114,11 @@ pub struct SymbolPool {
     //cmt_root -> requested index
     //requested symbols
     root2index: HashMap<H256, Vec<usize>>, 
+    //cmt_root -> shard of the tx block, used to route the symbol requests
+    root2shard: HashMap<H256, usize>,
     config: Configuration,
+    //draws the symbols to request, seeded by a simulation to repeat its runs
+    rng: StdRng,
 }
 
 impl SymbolPool {
@@ -125,9 +130,15 @@ impl SymbolPool {
         Self {
             hash2symbol,
             root2index: HashMap::new(),
+            root2shard: HashMap::new(),
             config: config.clone(),
+            rng: StdRng::from_entropy(),
         }
     }
+
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
     pub fn check_if_requested(&self, symbol_index: &SymbolIndex) -> bool {
         if let Some(value) = self.root2index.get(&symbol_index.get_root()) {
             if value.contains(&symbol_index.get_index()) {
@@ -139,6 +150,34 @@ impl SymbolPool {
             return false;
         }
     }
+    //the symbol indexs requested for a cmt, None if it was never requested
+    pub fn get_requested_indexs(&self, root: &H256) -> Option<Vec<usize>> {
+        self.root2index.get(root).cloned()
+    }
+
+    pub fn get_num_requested_cmts(&self) -> usize {
+        self.root2index.len()
+    }
+
+    pub fn get_num_symbols(&self) -> usize {
+        self.hash2symbol.len()
+    }
+
+    //the shard of the tx block a requested cmt belongs to
+    pub fn get_cmt_shard(&self, root: &H256) -> Option<usize> {
+        self.root2shard.get(root).cloned()
+    }
+
+    //group symbol indexs by the shard of their cmt, None for unknown cmts
+    pub fn split_by_shard(&self, indexs: Vec<SymbolIndex>) -> Vec<(Option<usize>, Vec<SymbolIndex>)> {
+        let mut groups: BTreeMap<Option<usize>, Vec<SymbolIndex>> = BTreeMap::new();
+        for index in indexs {
+            let shard_id = self.get_cmt_shard(&index.get_root());
+            groups.entry(shard_id).or_default().push(index);
+        }
+        groups.into_iter().collect()
+    }
+
     pub fn get_symbol(&self, symbol_index: &SymbolIndex) -> Result<Symbol, String> {
         match self.hash2symbol.get(&symbol_index.hash()) {
             Some(value) => Ok(value),
@@ -147,19 +186,19 @@ impl SymbolPool {
     }
 
     //``ex_or_in`` is used to distinguish exclusive and inclusive transaction block 
-    pub fn request_symbols_for_new_cmt(&mut self, hash: &H256, ex_or_in: bool) -> Result<Vec<SymbolIndex>, String> {
+    pub fn request_symbols_for_new_cmt(&mut self, hash: &H256, shard_id: usize, ex_or_in: bool) -> Result<Vec<SymbolIndex>, String> {
         match self.root2index.get(hash) {
             Some(_) => {
                 Err(format!("cmt {:?} has already been requested", hash))
             }
             None => {
-                let mut rng = thread_rng();
                 let req_num = match ex_or_in {
                     true => self.config.ex_req_num,
                     false => self.config.in_req_num,
                 };
-                let request_indexs = (0..self.config.num_symbol_per_block).choose_multiple(&mut rng, req_num);
+                let request_indexs = (0..self.config.num_symbol_per_block).choose_multiple(&mut self.rng, req_num);
                 self.root2index.insert(hash.clone(), request_indexs.clone());
+                self.root2shard.insert(*hash, shard_id);
                 // info!("cmt {:?} requested (indexs: {:?})", hash, request_indexs);
                 
                 let request_symbol_index: Vec<SymbolIndex> = request_indexs
@@ -195,13 +234,14 @@ impl SymbolPool {
         }
     }
 
-    pub fn request_symbols(&mut self, root: &H256, indexs: Vec<usize>) -> Result<bool, String> {
+    pub fn request_symbols(&mut self, root: &H256, shard_id: usize, indexs: Vec<usize>) -> Result<bool, String> {
         match self.root2index.get(root) {
             Some(_) => {
                 Err(String::from("The cmt_root is already requested"))
             }
             None => {
                 self.root2index.insert(root.clone(), indexs);
+                self.root2shard.insert(*root, shard_id);
                 Ok(true)
             }
         }
@@ -18,6 +18,7 @@ use crate::{
         network::{
             server::Handle as ServerHandle,
             message::Message,
+            relay::RelayClass,
             // worker::{SampleIndex},
         },
         symbolpool::{
@@ -97,7 +98,7 @@ impl Context {
                             let new_symbol_indexs = self.symbol_pool
                                 .lock()
                                 .unwrap()
-                                .request_symbols_for_new_cmt(&cmt_root, shard_id == self.config.shard_id)
+                                .request_symbols_for_new_cmt(&cmt_root, shard_id, shard_id == self.config.shard_id)
                                 .unwrap();
                             req_symbol_indexs.extend(new_symbol_indexs);
                         }
@@ -105,7 +106,14 @@ impl Context {
                     
                 }
                 if !req_symbol_indexs.is_empty() {
-                    self.server.broadcast(Message::GetSymbols(req_symbol_indexs));
+                    //ask the shards holding the tx blocks
+                    let groups = self.symbol_pool
+                        .lock()
+                        .unwrap()
+                        .split_by_shard(req_symbol_indexs);
+                    for (shard_id, indexs) in groups {
+                        self.server.relay(Message::GetSymbols(indexs), RelayClass::Symbol(shard_id));
+                    }
                 }
             } else {
                 //info!("no unverified blocks");
@@ -57,23 +57,7 @@ pub struct UtxoOutput {
 
 impl Random for Transaction {
     fn random() -> Self {
-        let mut rng = rand::thread_rng();
-        let rand_addr: [u8; 32] = rng.gen();
-        let rand_addr_hash = (&rand_addr).into();
-        let input = UtxoInput::default();
-        let output = UtxoOutput {
-            receiver_addr: rand_addr_hash,
-            value: rng.gen_range(1..1000) as u32,
-            public_key_ref: key_pair::random().public_key().as_ref().to_vec(),
-        };
-        let inputs: Vec<UtxoInput> = vec![input; 20];
-        let outputs: Vec<UtxoOutput> = vec![output; 20];
-
-        Transaction {
-            inputs,
-            outputs,
-            flag: TxFlag::Initial,
-        }
+        Transaction::random_from(&mut rand::thread_rng())
     }
 }
 
@@ -162,6 +146,26 @@ impl Default for Transaction {
 
 
 impl Transaction {
+    //a random tx drawn from `rng`, so that a seeded generator always gives the same txs
+    pub fn random_from<R: Rng>(rng: &mut R) -> Self {
+        let rand_addr: [u8; 32] = rng.gen();
+        let rand_addr_hash = (&rand_addr).into();
+        let input = UtxoInput::default();
+        let output = UtxoOutput {
+            receiver_addr: rand_addr_hash,
+            value: rng.gen_range(1..1000) as u32,
+            public_key_ref: key_pair::from_seed(&rng.gen()).public_key().as_ref().to_vec(),
+        };
+        let inputs: Vec<UtxoInput> = vec![input; 20];
+        let outputs: Vec<UtxoOutput> = vec![output; 20];
+
+        Transaction {
+            inputs,
+            outputs,
+            flag: TxFlag::Initial,
+        }
+    }
+
     //generate a random transaction
     // pub fn gen_rand_tx() -> Self {
     //     let mut rng = rand::thread_rng();
@@ -201,20 +205,30 @@ impl Transaction {
         }
         input_mem_size + output_mem_size + std::mem::size_of::<TxFlag>()
     }
+    /// The digest signed by the inputs of a transaction: the transaction without
+    /// the signatures, under the chain id, so a signature is bound to its outputs
+    /// and flag and cannot be replayed into another transaction or network
+    pub fn sighash(&self, chain_id: &str) -> H256 {
+        let mut unsigned = self.clone();
+        for input in unsigned.inputs.iter_mut() {
+            input.sig_ref = vec![];
+        }
+        let serialized_tx: Vec<u8> = bincode::serialize(&(chain_id, &unsigned)).unwrap();
+        ring::digest::digest(&ring::digest::SHA256, serialized_tx.as_slice()).into()
+    }
+
     /// Create digital signature of a transaction
-    pub fn sign(t: &Transaction, key: &Ed25519KeyPair) -> Signature {
-        let serialized_tx: Vec<u8> = bincode::serialize(t).unwrap();
-        key.sign(serialized_tx.as_slice())
+    pub fn sign(t: &Transaction, key: &Ed25519KeyPair, chain_id: &str) -> Signature {
+        key.sign(t.sighash(chain_id).as_ref())
     }
 
     /// Verify digital signature of a transaction, using public key instead of secret key
-    pub fn verify(t: &Transaction, public_key_ref: &[u8], sig_ref: &[u8]) -> bool {
+    pub fn verify(t: &Transaction, public_key_ref: &[u8], sig_ref: &[u8], chain_id: &str) -> bool {
         let peer_public_key = signature::UnparsedPublicKey::new(
             &signature::ED25519, 
             public_key_ref
         );
-        let serialized_tx: Vec<u8> = bincode::serialize(t).unwrap();
-        let res = peer_public_key.verify(serialized_tx.as_slice(), sig_ref);
+        let res = peer_public_key.verify(t.sighash(chain_id).as_ref(), sig_ref);
         match res {
             Ok(()) => {
                 true
@@ -225,9 +239,11 @@ impl Transaction {
         }
     }
 
+    //whether each input of tx is signed by the owner of the output it spends
     pub fn verify_owner(
         tx: &Transaction,  
         input_txs: Vec<&Transaction>, 
+        chain_id: &str,
     ) -> bool {
         if tx.inputs.len() != input_txs.len() {
             return false;
@@ -235,10 +251,18 @@ impl Transaction {
         for i in 0..tx.inputs.len() {
             let input = &tx.inputs[i];
             let input_tx = input_txs[i];
+            if input.tx_hash != input_tx.hash() {
+                return false;
+            }
+            let output = match input_tx.outputs.get(input.index as usize) {
+                Some(output) => output,
+                None => return false,
+            };
             if !Self::verify(
-                input_tx,
-                &input_tx.outputs[input.index as usize].public_key_ref,
+                tx,
+                &output.public_key_ref,
                 &input.sig_ref,
+                chain_id,
             ) {
                 return false;
             }
@@ -271,6 +295,22 @@ impl Transaction {
         senders: Vec<(&H256, &Ed25519KeyPair)>, //user_addr, user_key, sent_coin
         receivers: Vec<(&H256, &Ed25519KeyPair, usize)>, //user_addr, user_key, received_coin
         flag: TxFlag,
+        chain_id: &str,
+    ) -> Option<Transaction> {
+        let receivers: Vec<(&H256, &[u8], usize)> = receivers
+            .into_iter()
+            .map(|(addr, key, coins)| (addr, key.public_key().as_ref(), coins))
+            .collect();
+        Self::consume_to(utxos, senders, receivers, flag, chain_id)
+    }
+
+    //consume() for receivers known by their public key only, like the payees of a wallet
+    pub fn consume_to(
+        utxos: Vec<(&Transaction, usize)>, //tx, index 
+        senders: Vec<(&H256, &Ed25519KeyPair)>, //user_addr, user_key
+        receivers: Vec<(&H256, &[u8], usize)>, //user_addr, user_public_key, received_coin
+        flag: TxFlag,
+        chain_id: &str,
     ) -> Option<Transaction> {
 
         let mut inputs: Vec<UtxoInput> = vec![];
@@ -287,7 +327,7 @@ impl Transaction {
                         tx_hash: tx.hash(),
                         value: tx.outputs[index].value,
                         index: index as u32,
-                        sig_ref: Transaction::sign(tx, sender.1).as_ref().to_vec(),
+                        sig_ref: vec![],
                     }
                 }
                 &TxFlag::Domestic => {
@@ -296,7 +336,7 @@ impl Transaction {
                         tx_hash: tx.hash(),
                         value: tx.outputs[index].value,
                         index: index as u32,
-                        sig_ref: Transaction::sign(tx, sender.1).as_ref().to_vec(),
+                        sig_ref: vec![],
                     }
                 }
                 &TxFlag::Output => {
@@ -305,7 +345,7 @@ impl Transaction {
                         tx_hash: tx.hash(),
                         value: tx.outputs[index].value,
                         index: index as u32,
-                        sig_ref: Transaction::sign(tx, sender.1).as_ref().to_vec(),
+                        sig_ref: vec![],
                     }
                 }
                 &TxFlag::Reject => {
@@ -331,7 +371,7 @@ impl Transaction {
             let output  = UtxoOutput {
                 receiver_addr: x.0.clone(),
                 value: x.2 as u32,
-                public_key_ref: x.1.public_key().as_ref().to_vec(),
+                public_key_ref: x.1.to_vec(),
             };
             outputs.push(output);
             received_coins += x.2;
@@ -342,11 +382,18 @@ impl Transaction {
         }
         
 
-        Some(Transaction {
+        //the inputs sign the whole new tx, so it is only signed once complete
+        let mut tx = Transaction {
             inputs,
             outputs,
             flag,
-        })
+        };
+        for i in 0..utxos.len() {
+            if utxos[i].0.flag != TxFlag::Reject {
+                tx.inputs[i].sig_ref = Transaction::sign(&tx, senders[i].1, chain_id).as_ref().to_vec();
+            }
+        }
+        Some(tx)
                 
     }
 }
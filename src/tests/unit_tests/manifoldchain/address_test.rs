@@ -0,0 +1,119 @@
+#[cfg(test)]
+use crate::{
+    manifoldchain::{
+        api::UtxoView,
+        configuration::DEFAULT_CHAIN_ID,
+        transaction::{Transaction, TxFlag},
+        validator::Validator,
+    },
+    types::{
+        address::Address,
+        hash::H256,
+        key_pair,
+    },
+};
+#[cfg(test)]
+use ring::signature::{Ed25519KeyPair, KeyPair};
+
+#[cfg(test)]
+fn user() -> (H256, Ed25519KeyPair) {
+    let key = key_pair::random();
+    let address = Address::from_public_key(key.public_key().as_ref()).unwrap();
+    (address.into(), key)
+}
+
+#[test]
+fn address_bech32_vector() {
+    //the valid string of BIP 173 whose data are the 32 characters, i.e. 20 bytes
+    let vector = "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw";
+    let address = Address::decode(vector, "abcdef").unwrap();
+    let bytes: Address = hex!("00443214c74254b635cf84653a56d7c675be77df").into();
+    assert_eq!(address, bytes);
+    assert_eq!(address.encode("abcdef"), vector);
+    assert_eq!(Address::decode(&vector.to_uppercase(), "abcdef").unwrap(), address);
+}
+
+#[test]
+fn address_bech32_errors() {
+    let address = Address::from_public_key(&[7u8; 32]).unwrap();
+    let encoded = address.encode("testnet");
+    assert!(encoded.starts_with("testnet1"));
+    assert_eq!(Address::decode(&encoded, "testnet").unwrap(), address);
+
+    let err = Address::decode(&encoded, "mainnet").unwrap_err();
+    assert!(err.contains("not of chain mainnet"));
+    //one character changed
+    let last = if encoded.ends_with('q') { 'p' } else { 'q' };
+    let typo = format!("{}{}", &encoded[..encoded.len() - 1], last);
+    assert!(Address::decode(&typo, "testnet").unwrap_err().contains("wrong checksum"));
+    let mixed = format!("T{}", &encoded[1..]);
+    assert!(Address::decode(&mixed, "testnet").unwrap_err().contains("mixed case"));
+    assert!(Address::decode("testnet1b", "testnet").is_err());
+    assert!(Address::decode(&address.to_string(), "testnet").is_err());
+    //longer than the 90 characters of BIP 173, whatever the checksum
+    let long = format!("testnet1{}", "q".repeat(83));
+    assert!(Address::decode(&long, "testnet").unwrap_err().contains("longer than 90"));
+
+    assert!(Address::check_prefix("testnet").is_ok());
+    assert!(Address::check_prefix(DEFAULT_CHAIN_ID).is_ok());
+    assert!(Address::check_prefix("").is_err());
+    assert!(Address::check_prefix("Testnet").is_err());
+    assert!(Address::check_prefix(&"a".repeat(51)).is_err());
+    assert!(Address::from_public_key(&[7u8; 31]).is_err());
+}
+
+#[test]
+fn address_on_chain() {
+    let address = Address::from_public_key(&[7u8; 32]).unwrap();
+    let hash: H256 = address.into();
+    assert_eq!(Address::from_h256(&hash).unwrap(), address);
+    //the shard of an address is the one of its last bytes
+    let bytes: [u8; 32] = hash.into();
+    assert_eq!(bytes[..12], [0u8; 12]);
+    assert_eq!(Validator::get_shard_id(&hash, 256), bytes[31] as usize);
+    assert!(Address::from_h256(&H256::from([1u8; 32])).is_err());
+}
+
+#[test]
+fn address_matches_key() {
+    let (user1, key1) = user();
+    let (user2, key2) = user();
+    let ini_tx = Transaction::create_initial_tx((&user1, &key1), 10);
+    assert!(ini_tx.check_addresses());
+
+    let tx = Transaction::consume(
+        vec![(&ini_tx, 0)],
+        vec![(&user1, &key1)],
+        vec![(&user2, &key2, 6), (&user1, &key1, 4)],
+        TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
+    ).unwrap();
+    assert!(tx.check_addresses());
+
+    //coins sent to an address which the key of the output does not own
+    let stolen = Transaction::consume(
+        vec![(&ini_tx, 0)],
+        vec![(&user1, &key1)],
+        vec![(&user2, &key1, 10)],
+        TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
+    ).unwrap();
+    assert!(!stolen.check_addresses());
+    let mut unkeyed = tx.clone();
+    unkeyed.outputs[0].public_key_ref = vec![];
+    assert!(!unkeyed.check_addresses());
+}
+
+#[test]
+fn address_in_utxo_view() {
+    let (user1, key1) = user();
+    let tx = Transaction::create_initial_tx((&user1, &key1), 10);
+    let address = Address::from_h256(&user1).unwrap();
+    let view = UtxoView::new(&tx, 0, &address, "testnet");
+    assert_eq!(view.address, address.encode("testnet"));
+    assert_eq!(view.value, 10);
+    assert_eq!(view.index, 0);
+    assert_eq!(view.get_tx().unwrap(), tx);
+    let json = serde_json::to_string(&view).unwrap();
+    assert_eq!(serde_json::from_str::<UtxoView>(&json).unwrap(), view);
+}
@@ -7,6 +7,7 @@ use crate::{
             // inclusive_block::*,
         },
         transaction::*,
+        configuration::DEFAULT_CHAIN_ID,
         testimony::*,
     },
     types::{
@@ -39,6 +40,7 @@ fn block_test_one() {
         vec![(&user2, &key2)],
         vec![(&user4, &key4, 5), (&user2, &key2, 5)],
         TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
     ).unwrap();
     let tmy_units = TestimonyUnit::create(
         H256::default(),
@@ -0,0 +1,141 @@
+#[cfg(test)]
+use ring::signature::{Ed25519KeyPair, KeyPair};
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+#[cfg(test)]
+use crate::{
+    manifoldchain::{
+        block::versa_block::{ExclusiveFullBlock, VersaBlock},
+        blockchain::Blockchain,
+        configuration::{Configuration, DEFAULT_CHAIN_ID},
+        fraudproof::FraudProof,
+        mempool::Mempool,
+        multichain::Multichain,
+        transaction::{Transaction, TxFlag},
+        validator::Validator,
+    },
+    types::{
+        address::Address,
+        hash::{H256, Hashable},
+        key_pair,
+        sig_verifier::{SigVerifier, DEFAULT_CACHE_SIZE},
+    },
+};
+
+#[cfg(test)]
+fn address_of(key: &Ed25519KeyPair) -> H256 {
+    let public_key: [u8; 32] = key.public_key().as_ref().try_into().unwrap();
+    Address::from_public_key_bytes(&public_key).into()
+}
+
+#[cfg(test)]
+fn full_block(config: &Configuration, parent: H256, txs: Vec<Transaction>) -> ExclusiveFullBlock {
+    ExclusiveFullBlock::generate(
+        parent,
+        config.shard_id,
+        0,
+        config.difficulty,
+        txs,
+        vec![],
+        vec![parent],
+        vec![(vec![parent], config.shard_id)],
+    )
+}
+
+#[test]
+fn wrong_signature_proof_end_to_end() {
+    let mut config = Configuration::new();
+    config.shard_id = 0;
+    config.shard_num = 1;
+    //the merkle proofs are checked against blocks of block_size txs
+    config.block_size = 2;
+    let chain = Arc::new(Mutex::new(Blockchain::new(&config, 0)));
+    let mut multichain = Multichain::create(vec![&chain], &config);
+    let mempool = Arc::new(Mutex::new(Mempool::new()));
+    let sig_verifier = SigVerifier::new(1, DEFAULT_CACHE_SIZE);
+    let validator = Validator::new(&multichain, &mempool, &config, &sig_verifier);
+
+    let key1 = key_pair::random();
+    let key2 = key_pair::random();
+    let (user1, user2) = (address_of(&key1), address_of(&key2));
+    let genesis = multichain.get_longest_verified_fork();
+    let ini_tx = Transaction::create_initial_tx((&user1, &key1), 10);
+    let block1 = full_block(&config, genesis, vec![Transaction::create_initial_tx((&user2, &key2), 10), ini_tx.clone()]);
+    multichain.insert_block_with_parent(VersaBlock::ExFullBlock(block1.clone()), &genesis, 0).unwrap();
+
+    //the signature of a payment to user 2, replayed into one paying user 1 back
+    let tx = Transaction::consume(
+        vec![(&ini_tx, 0)],
+        vec![(&user1, &key1)],
+        vec![(&user2, &key2, 10)],
+        TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
+    ).unwrap();
+    let mut replayed = tx.clone();
+    replayed.outputs[0].receiver_addr = user1;
+    replayed.outputs[0].public_key_ref = key1.public_key().as_ref().to_vec();
+    let block2 = full_block(&config, block1.hash(), vec![tx.clone(), replayed.clone()]);
+    let fp = match validator.validate_block(&VersaBlock::ExFullBlock(block2.clone()), &block1.hash()) {
+        Err(FraudProof::WrongSignature(fp)) => fp,
+        res => panic!("expected a wrong signature proof, got {:?}", res),
+    };
+    //the conflict tx is the one holding the spent utxo, found in block 1
+    assert_eq!(fp.invalid_block_hash, block2.hash());
+    assert_eq!(fp.invalid_tx, replayed);
+    assert_eq!(fp.invalid_index, 1);
+    assert_eq!(fp.conflict_tx, ini_tx);
+    assert_eq!(fp.conflict_block_hash, block1.hash());
+    assert_eq!(fp.conflict_index, 1);
+
+    //a node holding the invalid block checks the proof
+    multichain.insert_block_with_parent(VersaBlock::ExFullBlock(block2.clone()), &block1.hash(), 0).unwrap();
+    assert!(validator.verify_wrongsig_fp(&fp));
+
+    //a proof against the valid tx, or pointing at another block, does not hold
+    let mut honest = fp.clone();
+    honest.invalid_tx = tx;
+    assert!(!validator.verify_wrongsig_fp(&honest));
+    let mut misplaced = fp.clone();
+    misplaced.conflict_block_hash = block2.hash();
+    assert!(!validator.verify_wrongsig_fp(&misplaced));
+}
+
+#[test]
+fn spent_state_lookup() {
+    let mut config = Configuration::new();
+    config.shard_id = 0;
+    config.shard_num = 1;
+    let chain = Arc::new(Mutex::new(Blockchain::new(&config, 0)));
+    let mut multichain = Multichain::create(vec![&chain], &config);
+
+    let key1 = key_pair::random();
+    let key2 = key_pair::random();
+    let (user1, user2) = (address_of(&key1), address_of(&key2));
+    let genesis = multichain.get_longest_verified_fork();
+    let ini_tx1 = Transaction::create_initial_tx((&user1, &key1), 10);
+    let ini_tx2 = Transaction::create_initial_tx((&user2, &key2), 10);
+    let block1 = full_block(&config, genesis, vec![ini_tx1.clone(), ini_tx2.clone()]);
+    multichain.insert_block_with_parent(VersaBlock::ExFullBlock(block1.clone()), &genesis, 0).unwrap();
+
+    let tx = Transaction::consume(
+        vec![(&ini_tx1, 0)],
+        vec![(&user1, &key1)],
+        vec![(&user2, &key2, 10)],
+        TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
+    ).unwrap();
+    //only the utxo the tx spends is read, as the whole state holds it
+    let spent = multichain.get_spent_state(&block1.hash(), std::slice::from_ref(&tx)).unwrap();
+    let state = multichain.get_state(&block1.hash()).unwrap();
+    assert_eq!(state.len(), 2);
+    assert_eq!(spent.len(), 1);
+    assert_eq!(spent.get(&(ini_tx1.hash(), 0)), state.get(&(ini_tx1.hash(), 0)));
+    //a utxo not in the state, or a block not in the chain
+    assert!(multichain.get_spent_state(&genesis, std::slice::from_ref(&tx)).unwrap().is_empty());
+    assert!(multichain.get_spent_state(&tx.hash(), std::slice::from_ref(&tx)).is_none());
+
+    //the state after the block spending it no longer holds it
+    let block2 = full_block(&config, block1.hash(), vec![tx.clone()]);
+    multichain.insert_block_with_parent(VersaBlock::ExFullBlock(block2.clone()), &block1.hash(), 0).unwrap();
+    assert!(multichain.get_spent_state(&block2.hash(), std::slice::from_ref(&tx)).unwrap().is_empty());
+}
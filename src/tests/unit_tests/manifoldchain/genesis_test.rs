@@ -0,0 +1,94 @@
+#[cfg(test)]
+use crate::{
+    manifoldchain::{
+        blockchain::Blockchain,
+        configuration::Configuration,
+        transaction::{Transaction, TxFlag},
+        validator::Validator,
+    },
+    types::{
+        address::Address,
+        genesis::GenesisSpec,
+        hash::Hashable,
+    },
+};
+
+//a public key whose address is in the shard, of 2 shards
+#[cfg(test)]
+fn key_in_shard(shard_id: usize) -> [u8; 32] {
+    (0..=255u8)
+        .map(|i| [i; 32])
+        .find(|key| {
+            let address = Address::from_public_key(key).unwrap();
+            Validator::get_shard_id(&address.into(), 2) == shard_id
+        })
+        .unwrap()
+}
+
+#[cfg(test)]
+fn genesis_config(chain_id: &str) -> Configuration {
+    //the last byte of an address picks its shard
+    let (key0, key1) = (key_in_shard(0), key_in_shard(1));
+    let address0 = Address::from_public_key(&key0).unwrap().encode(chain_id);
+    let address1 = Address::from_public_key(&key1).unwrap().encode(chain_id);
+    let json = format!(
+        r#"{{
+            "chain_id": "{}",
+            "shard_num": 2,
+            "allocations": [
+                {{"address": "{}", "public_key": "{}", "value": 10}},
+                {{"address": "{}", "public_key": "{}", "value": 20}},
+                {{"address": "{}", "public_key": "{}", "value": 30}}
+            ]
+        }}"#,
+        chain_id,
+        address0, hex::encode(key0),
+        address1, hex::encode(key1),
+        address0, hex::encode(key0),
+    );
+    let mut config = Configuration::new();
+    config.shard_num = 2;
+    config.genesis = Some(GenesisSpec::from_json(&json).unwrap());
+    config
+}
+
+#[test]
+fn genesis_state_allocations() {
+    let config = genesis_config("testnet");
+    let state = Blockchain::genesis_state(&config, 0);
+    let mut values: Vec<u32> = state.values().map(|(tx, _)| tx.outputs[0].value).collect();
+    values.sort();
+    //the equal allocations are kept apart
+    assert_eq!(values, vec![10, 30]);
+    for ((_, index), (tx, tmy)) in state.iter() {
+        assert_eq!(*index, 0);
+        assert_eq!(tx.flag, TxFlag::Initial);
+        assert!(tmy.is_none());
+    }
+    let state = Blockchain::genesis_state(&config, 1);
+    assert_eq!(state.len(), 1);
+    assert!(Blockchain::genesis_state(&Configuration::new(), 0).is_empty());
+}
+
+#[test]
+fn genesis_block_commitment() {
+    let config = genesis_config("testnet");
+    let hash = Blockchain::genesis_block(&config, 0).hash();
+    assert_eq!(Blockchain::genesis_block(&config, 0).hash(), hash);
+    assert_ne!(Blockchain::genesis_block(&config, 1).hash(), hash);
+    assert_ne!(Blockchain::genesis_block(&genesis_config("othernet"), 0).hash(), hash);
+    assert_ne!(Blockchain::genesis_block(&Configuration::new(), 0).hash(), hash);
+}
+
+#[test]
+fn genesis_rejects_initial_txs() {
+    assert!(Validator::check_initial_tx(&Configuration::new()).is_ok());
+    assert!(Validator::check_initial_tx(&genesis_config("testnet")).is_err());
+    let config = genesis_config("testnet");
+    let allocation = &config.genesis.as_ref().unwrap().allocations[0];
+    assert_eq!(Transaction::create_allocation_tx(0, allocation).flag, TxFlag::Initial);
+    assert_ne!(
+        Transaction::create_allocation_tx(0, allocation).hash(),
+        Transaction::create_allocation_tx(2, allocation).hash()
+    );
+}
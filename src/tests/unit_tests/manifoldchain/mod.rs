@@ -4,4 +4,9 @@ pub mod hash_test;
 pub mod mempool_test;
 pub mod block_test;
 pub mod database_test;
+pub mod genesis_test;
 
+pub mod wallet_test;
+pub mod address_test;
+pub mod sig_verifier_test;
+pub mod fraudproof_test;
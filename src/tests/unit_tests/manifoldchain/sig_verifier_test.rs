@@ -0,0 +1,105 @@
+#[cfg(test)]
+use ring::signature::{Ed25519KeyPair, KeyPair};
+#[cfg(test)]
+use crate::{
+    manifoldchain::{
+        transaction::{Transaction, TxFlag},
+        configuration::DEFAULT_CHAIN_ID,
+        blockchain::State,
+        validator::Validator,
+    },
+    types::{
+        key_pair,
+        hash::{H256, Hashable},
+        sig_verifier::{SigJob, SigVerifier},
+    },
+};
+
+#[cfg(test)]
+fn job(key: &Ed25519KeyPair, tx_hash: &H256, input_index: u32, message: &[u8]) -> SigJob {
+    SigJob {
+        tx_hash: *tx_hash,
+        input_index,
+        public_key: key.public_key().as_ref().to_vec(),
+        message: message.to_vec(),
+        signature: key.sign(message).as_ref().to_vec(),
+    }
+}
+
+#[test]
+fn sig_verifier_batch() {
+    let verifier = SigVerifier::new(4, 100);
+    let key = key_pair::random();
+    let tx_hash: H256 = (&[1u8; 32]).into();
+    let mut jobs: Vec<SigJob> = (0..20u32)
+        .map(|i| job(&key, &tx_hash, i, &i.to_be_bytes()))
+        .collect();
+    //signed by another key
+    jobs[7].signature = key_pair::random().sign(&7u32.to_be_bytes()).as_ref().to_vec();
+    //of another message
+    jobs[12].message = vec![0u8; 4];
+
+    let results = verifier.verify_batch(jobs);
+    for (i, valid) in results.iter().enumerate() {
+        assert_eq!(*valid, i != 7 && i != 12);
+    }
+    assert_eq!(verifier.cache_len(), 18);
+    assert!(verifier.is_verified(&tx_hash, 0));
+    assert!(!verifier.is_verified(&tx_hash, 7));
+    assert!(verifier.verify_batch(vec![]).is_empty());
+}
+
+#[test]
+fn sig_verifier_cache() {
+    let verifier = SigVerifier::new(2, 3);
+    let key = key_pair::random();
+    let tx_hash: H256 = (&[2u8; 32]).into();
+    for i in 0..4u32 {
+        assert!(verifier.verify(job(&key, &tx_hash, i, b"tx")));
+    }
+    //the oldest pair is evicted
+    assert_eq!(verifier.cache_len(), 3);
+    assert!(!verifier.is_verified(&tx_hash, 0));
+    assert!(verifier.is_verified(&tx_hash, 3));
+
+    //a cached pair is not verified again
+    let mut cached = job(&key, &tx_hash, 3, b"tx");
+    cached.signature = vec![];
+    assert!(verifier.verify(cached));
+    let mut evicted = job(&key, &tx_hash, 0, b"tx");
+    evicted.signature = vec![];
+    assert!(!verifier.verify(evicted));
+    assert_eq!(verifier.cache_len(), 3);
+}
+
+#[test]
+fn sig_verifier_check_input() {
+    let verifier = SigVerifier::new(1, 16);
+    let user1: H256 = (&[1u8; 32]).into();
+    let user2: H256 = (&[2u8; 32]).into();
+    let key1 = key_pair::random();
+    let key2 = key_pair::random();
+    let ini_tx = Transaction::create_initial_tx((&user1, &key1), 10);
+    let mut state = State::new();
+    state.insert((ini_tx.hash(), 0), (ini_tx.clone(), None));
+    let tx = Transaction::consume(
+        vec![(&ini_tx, 0)],
+        vec![(&user1, &key1)],
+        vec![(&user2, &key2, 10)],
+        TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
+    ).unwrap();
+
+    assert!(!verifier.is_verified(&tx.hash(), 0));
+    assert!(Validator::check_input_from_state(&tx, 0, &state, DEFAULT_CHAIN_ID, &verifier).is_ok());
+    assert!(verifier.is_verified(&tx.hash(), 0));
+    assert!(Validator::check_input_from_state(&tx, 0, &state, DEFAULT_CHAIN_ID, &verifier).is_ok());
+    assert_eq!(verifier.cache_len(), 1);
+
+    //an invalid signature is never cached
+    assert!(Validator::check_input_from_state(&tx, 0, &state, "another-chain", &SigVerifier::new(1, 16)).is_err());
+    let mut forged = tx.clone();
+    forged.inputs[0].sig_ref = vec![0u8; 64];
+    assert!(Validator::check_input_from_state(&forged, 0, &state, DEFAULT_CHAIN_ID, &verifier).is_err());
+    assert!(!verifier.is_verified(&forged.hash(), 0));
+}
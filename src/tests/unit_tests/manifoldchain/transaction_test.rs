@@ -4,10 +4,15 @@
 use crate::{
     manifoldchain::{
         transaction::*,
+        configuration::DEFAULT_CHAIN_ID,
+        blockchain::State,
+        fraudproof::FraudProof,
+        validator::Validator,
     },
     types::{
         key_pair,
-        hash::{H256},
+        hash::{H256, Hashable},
+        sig_verifier::SigVerifier,
     }
 };
 #[cfg(test)]
@@ -52,16 +57,18 @@ fn transaction_test_one() {
         vec![(&user1, &key1)],
         vec![(&user2, &key2, 5), (&user1, &key1, 5)],
         TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
     ).unwrap();
     let tx2 = Transaction::consume(
         vec![(&ini_tx_3, 0)],
         vec![(&user3, &key3)],
         vec![(&user4, &key4, 5), (&user3, &key3, 5)],
         TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
     ).unwrap();
 
-    assert!(Transaction::verify_owner(&tx1, vec![&ini_tx_1]));
-    assert!(Transaction::verify_owner(&tx2, vec![&ini_tx_3]));
+    assert!(Transaction::verify_owner(&tx1, vec![&ini_tx_1], DEFAULT_CHAIN_ID));
+    assert!(Transaction::verify_owner(&tx2, vec![&ini_tx_3], DEFAULT_CHAIN_ID));
 
     assert_eq!(tx1.outputs.len(), 2);
     assert_eq!(tx2.outputs.len(), 2);
@@ -79,17 +86,105 @@ fn transaction_test_one() {
         vec![(&user2, &key2), (&user4, &key4)],
         vec![(&user1, &key1, 6), (&user2, &key2, 2), (&user4, &key4, 2)],
         TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
     ).unwrap();
    let tx4 = Transaction::consume(
         vec![(&tx3, 1), (&tx3, 2)],
         vec![(&user2, &key2), (&user4, &key4)],
         vec![(&user3, &key3, 4)],
         TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
     ).unwrap();
 
-    assert!(Transaction::verify_owner(&tx3, vec![&tx1, &tx2]));
-    assert!(Transaction::verify_owner(&tx4, vec![&tx3, &tx3]));
+    assert!(Transaction::verify_owner(&tx3, vec![&tx1, &tx2], DEFAULT_CHAIN_ID));
+    assert!(Transaction::verify_owner(&tx4, vec![&tx3, &tx3], DEFAULT_CHAIN_ID));
 
     assert_eq!(tx3.outputs.len(), 3);
     assert_eq!(tx4.outputs.len(), 1);
 }
+
+#[test]
+fn transaction_test_replay() {
+    let user1: H256 = (&[1u8; 32]).into();
+    let user2: H256 = (&[2u8; 32]).into();
+    let user3: H256 = (&[3u8; 32]).into();
+    let key1: Ed25519KeyPair = key_pair::random();
+    let key2: Ed25519KeyPair = key_pair::random();
+    let key3: Ed25519KeyPair = key_pair::random();
+    let ini_tx = Transaction::create_initial_tx((&user1, &key1), 10);
+
+    let tx = Transaction::consume(
+        vec![(&ini_tx, 0)],
+        vec![(&user1, &key1)],
+        vec![(&user2, &key2, 10)],
+        TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
+    ).unwrap();
+    assert!(Transaction::verify_owner(&tx, vec![&ini_tx], DEFAULT_CHAIN_ID));
+
+    //the signature of tx moved into another tx spending the same output
+    let mut replayed = Transaction::consume(
+        vec![(&ini_tx, 0)],
+        vec![(&user1, &key3)],
+        vec![(&user3, &key3, 10)],
+        TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
+    ).unwrap();
+    replayed.inputs[0].sig_ref = tx.inputs[0].sig_ref.clone();
+    assert!(!Transaction::verify_owner(&replayed, vec![&ini_tx], DEFAULT_CHAIN_ID));
+
+    //the outputs redirected to another receiver
+    let mut redirected = tx.clone();
+    redirected.outputs[0].receiver_addr = user3;
+    redirected.outputs[0].public_key_ref = tx.outputs[0].public_key_ref.iter().map(|x| !x).collect();
+    assert!(!Transaction::verify_owner(&redirected, vec![&ini_tx], DEFAULT_CHAIN_ID));
+
+    //the value of an output changed
+    let mut inflated = tx.clone();
+    inflated.outputs[0].value = 5;
+    assert!(!Transaction::verify_owner(&inflated, vec![&ini_tx], DEFAULT_CHAIN_ID));
+
+    //a domestic tx turned into a cross-shard one
+    let mut reflagged = tx.clone();
+    reflagged.flag = TxFlag::Input;
+    assert!(!Transaction::verify_owner(&reflagged, vec![&ini_tx], DEFAULT_CHAIN_ID));
+
+    //the same tx on another network
+    assert!(!Transaction::verify_owner(&tx, vec![&ini_tx], "another-chain"));
+
+    //the signatures do not sign themselves
+    let mut unsigned = tx.clone();
+    unsigned.inputs[0].sig_ref = vec![];
+    assert_eq!(unsigned.sighash(DEFAULT_CHAIN_ID), tx.sighash(DEFAULT_CHAIN_ID));
+}
+
+#[test]
+fn transaction_test_replay_from_state() {
+    let user1: H256 = (&[1u8; 32]).into();
+    let user2: H256 = (&[2u8; 32]).into();
+    let key1: Ed25519KeyPair = key_pair::random();
+    let key2: Ed25519KeyPair = key_pair::random();
+    let ini_tx = Transaction::create_initial_tx((&user1, &key1), 10);
+    let mut state = State::new();
+    state.insert((ini_tx.hash(), 0), (ini_tx.clone(), None));
+
+    let tx = Transaction::consume(
+        vec![(&ini_tx, 0)],
+        vec![(&user1, &key1)],
+        vec![(&user2, &key2, 10)],
+        TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
+    ).unwrap();
+    let verifier = SigVerifier::new(1, 16);
+    assert!(Validator::check_input_from_state(&tx, 0, &state, DEFAULT_CHAIN_ID, &verifier).is_ok());
+
+    let mut replayed = tx.clone();
+    replayed.outputs[0].receiver_addr = user1;
+    match Validator::check_input_from_state(&replayed, 0, &state, DEFAULT_CHAIN_ID, &verifier) {
+        Err(FraudProof::WrongSignature(proof)) => {
+            assert_eq!(proof.invalid_tx, replayed);
+            assert_eq!(proof.conflict_tx, ini_tx);
+        }
+        _ => panic!("a replayed signature is accepted"),
+    }
+}
@@ -19,6 +19,7 @@ use crate::{
     types::{
         hash::*,
         key_pair,
+        sig_verifier::*,
     },
 };
 use std::{net, process, thread, time, sync::{Arc, Mutex}};
@@ -51,6 +52,7 @@ fn validator_test_one() {
     let mut multichain0 = Multichain::create(
         vec![&chain0_for_shard0, &chain1_for_shard0],
         &config0,
+        &sig_verifier,
     );
     let mut multichain1 = Multichain::create(
         vec![&chain0_for_shard1, &chain1_for_shard1],
@@ -58,6 +60,7 @@ fn validator_test_one() {
     );
 
     //generate a mempool
+    let sig_verifier = SigVerifier::new(1, DEFAULT_CACHE_SIZE);
     let mempool0 = Arc::new(Mutex::new(Mempool::new()));
     let validator0 = Validator::new(
         &multichain0,
@@ -69,6 +72,7 @@ fn validator_test_one() {
         &multichain1,
         &mempool1,
         &config1,
+        &sig_verifier,
     );
 
 
@@ -174,12 +178,14 @@ fn validator_test_one() {
         vec![(&user2, &key2)],
         vec![(&user4, &key4, 5), (&user2, &key2, 5)],
         TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
     ).unwrap();
     let tx4 = Transaction::consume(
         vec![(&tx2, 0)],
         vec![(&user4, &key4)],
         vec![(&user2, &key2, 5), (&user4, &key4, 5)],
         TxFlag::Domestic,
+        DEFAULT_CHAIN_ID,
     ).unwrap();
     let block2 = ExclusiveFullBlock::generate(
         block1.hash(),
@@ -437,6 +443,7 @@ fn validator_test_one() {
         vec![(&user3, &key3)],
         vec![(&user2, &key2, 3), (&user4, &key4, 3), (&user3, &key3, 4)],
         TxFlag::Input,
+        DEFAULT_CHAIN_ID,
     ).unwrap();
     let block8 = ExclusiveFullBlock::generate(
         block7.hash(),
@@ -0,0 +1,181 @@
+#[cfg(test)]
+use ring::signature::{Ed25519KeyPair, KeyPair};
+#[cfg(test)]
+use serde_json::json;
+#[cfg(test)]
+use std::{fs, path::PathBuf};
+#[cfg(test)]
+use crate::{
+    manifoldchain::{
+        transaction::{Transaction, TxFlag},
+        validator::Validator,
+    },
+    types::{
+        address::Address,
+        hash::H256,
+    },
+    wallet::{
+        build_transfer,
+        NodeInfo,
+        client::RpcClient,
+        keystore::{address_of, Keystore},
+        parse_payee,
+        select_utxos,
+    },
+};
+
+#[cfg(test)]
+fn temp_wallet(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("powchain-wallet-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[cfg(test)]
+fn funded(key: &Ed25519KeyPair, values: &[usize]) -> Vec<(Transaction, u32)> {
+    let addr = address_of(key.public_key().as_ref());
+    values
+        .iter()
+        .map(|value| (Transaction::create_initial_tx((&addr, key), *value), 0))
+        .collect()
+}
+
+#[cfg(test)]
+fn node(shard_num: usize) -> NodeInfo {
+    NodeInfo {
+        protocol: String::from("manifoldchain"),
+        chain_id: String::from("testnet"),
+        shard_id: 0,
+        shard_num,
+    }
+}
+
+#[test]
+fn keystore_round_trip() {
+    let dir = temp_wallet("round-trip");
+    let keystore = Keystore::open(dir.to_str().unwrap()).unwrap();
+    let key = keystore.create("alice").unwrap();
+    assert!(keystore.create("alice").is_err());
+    assert!(keystore.create("../alice").is_err());
+
+    let exported = dir.join("exported.der");
+    keystore.export("alice", exported.to_str().unwrap()).unwrap();
+    let imported = keystore.import("bob", exported.to_str().unwrap()).unwrap();
+    assert_eq!(imported.public_key().as_ref(), key.public_key().as_ref());
+    assert_eq!(keystore.list().unwrap(), vec!["alice", "bob"]);
+    assert_eq!(H256::from(keystore.address("bob").unwrap()), address_of(key.public_key().as_ref()));
+
+    fs::write(dir.join("garbage.der"), b"not a key").unwrap();
+    assert!(keystore.import("carol", dir.join("garbage.der").to_str().unwrap()).is_err());
+    assert!(keystore.load("carol").is_err());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(dir.join("alice.pk8")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn address_derivation() {
+    let public_key = [7u8; 32];
+    assert_eq!(address_of(&public_key), H256::from(Address::from_public_key_bytes(&public_key)));
+    assert_ne!(address_of(&public_key), address_of(&[8u8; 32]));
+}
+
+#[test]
+fn payee_parsing() {
+    let (key, value) = parse_payee(&format!("{}:5", "ab".repeat(32))).unwrap();
+    assert_eq!(key, vec![0xab; 32]);
+    assert_eq!(value, 5);
+    assert!(parse_payee(&"ab".repeat(32)).is_err());
+    assert!(parse_payee(&format!("{}:5", "ab".repeat(31))).is_err());
+    assert!(parse_payee(&format!("{}:0", "ab".repeat(32))).is_err());
+    assert!(parse_payee("zz:5").is_err());
+    //a value is the u32 of an output, never truncated
+    assert_eq!(parse_payee(&format!("{}:{}", "ab".repeat(32), u32::MAX)).unwrap().1, u32::MAX);
+    assert!(parse_payee(&format!("{}:{}", "ab".repeat(32), u32::MAX as u64 + 1)).is_err());
+}
+
+#[test]
+fn utxo_selection() {
+    let dir = temp_wallet("selection");
+    let keystore = Keystore::open(dir.to_str().unwrap()).unwrap();
+    let key = keystore.create("alice").unwrap();
+    let utxos = funded(&key, &[3, 4, 5]);
+    let (selected, total) = select_utxos(&utxos, 6).unwrap();
+    assert_eq!(selected.len(), 2);
+    assert_eq!(total, 7);
+    assert_eq!(select_utxos(&utxos, 12).unwrap().1, 12);
+    assert!(select_utxos(&utxos, 13).is_none());
+    assert!(select_utxos(&[], 0).is_none());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn transfer_with_change() {
+    let dir = temp_wallet("transfer");
+    let keystore = Keystore::open(dir.to_str().unwrap()).unwrap();
+    let key = keystore.create("alice").unwrap();
+    let payee = keystore.create("bob").unwrap();
+    let payee_key = payee.public_key().as_ref().to_vec();
+    let utxos = funded(&key, &[3, 4]);
+
+    let tx = build_transfer(&key, &utxos, &[(payee_key.clone(), 5)], &node(1)).unwrap();
+    assert_eq!(tx.flag, TxFlag::Domestic);
+    assert_eq!(tx.inputs.len(), 2);
+    assert_eq!(tx.outputs.len(), 2);
+    assert_eq!(tx.outputs[0].receiver_addr, address_of(&payee_key));
+    assert_eq!(tx.outputs[0].value, 5);
+    assert_eq!(tx.outputs[1].receiver_addr, address_of(key.public_key().as_ref()));
+    assert_eq!(tx.outputs[1].value, 2);
+    let input_txs: Vec<&Transaction> = utxos.iter().map(|x| &x.0).collect();
+    assert!(Transaction::verify_owner(&tx, input_txs.clone(), "testnet"));
+    assert!(!Transaction::verify_owner(&tx, input_txs, "mainnet"));
+
+    //no change output when the utxos are spent exactly
+    let tx = build_transfer(&key, &utxos, &[(payee_key.clone(), 7)], &node(1)).unwrap();
+    assert_eq!(tx.outputs.len(), 1);
+
+    let err = build_transfer(&key, &utxos, &[(payee_key, 8)], &node(1)).unwrap_err();
+    assert!(err.contains("insufficient balance"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn transfer_across_shards() {
+    let dir = temp_wallet("cross");
+    let keystore = Keystore::open(dir.to_str().unwrap()).unwrap();
+    let key = keystore.create("alice").unwrap();
+    let sender_shard = Validator::get_shard_id(&address_of(key.public_key().as_ref()), 2);
+    //a payee of each shard
+    let mut domestic = None;
+    let mut foreign = None;
+    for i in 0..=255u8 {
+        let public_key = vec![i; 32];
+        if Validator::get_shard_id(&address_of(&public_key), 2) == sender_shard {
+            domestic.get_or_insert(public_key);
+        } else {
+            foreign.get_or_insert(public_key);
+        }
+    }
+    let utxos = funded(&key, &[10]);
+    let tx = build_transfer(&key, &utxos, &[(domestic.clone().unwrap(), 1)], &node(2)).unwrap();
+    assert_eq!(tx.flag, TxFlag::Domestic);
+    let payees = vec![(domestic.unwrap(), 1), (foreign.unwrap(), 1)];
+    let tx = build_transfer(&key, &utxos, &payees, &node(2)).unwrap();
+    assert_eq!(tx.flag, TxFlag::Input);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rpc_results() {
+    let shard: usize = RpcClient::result_of("node_getInfo", json!({"jsonrpc": "2.0", "id": 1, "result": 3})).unwrap();
+    assert_eq!(shard, 3);
+    let err = RpcClient::result_of::<String>(
+        "tx_submit",
+        json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32000, "message": "invalid transaction"}}),
+    ).unwrap_err();
+    assert_eq!(err, "tx_submit failed: invalid transaction");
+}
@@ -0,0 +1,82 @@
+#[cfg(test)]
+use crate::optchain::network::address_book::{AddressBook, PeerAddr};
+#[cfg(test)]
+use std::collections::{HashMap, HashSet};
+
+#[cfg(test)]
+fn peer_addr(port: u16, shard_id: usize) -> PeerAddr {
+    PeerAddr {
+        addr: format!("127.0.0.1:{}", port).parse().unwrap(),
+        shard_id,
+        node_id: port as usize,
+    }
+}
+
+#[test]
+fn address_book_select_per_shard() {
+    let mut book = AddressBook::new(None);
+    for port in 6000..6004 {
+        assert!(book.add(peer_addr(port, 0)));
+    }
+    assert!(book.add(peer_addr(6100, 1)));
+    assert!(!book.add(peer_addr(6100, 1)));
+    assert_eq!(book.len(), 5);
+
+    //shard 1 is served before shard 0 gets a third connection
+    let candidates = book.select_candidates(&HashMap::new(), &HashSet::new(), 3, 1);
+    assert_eq!(candidates.len(), 3);
+    assert!(candidates.contains(&peer_addr(6100, 1)));
+
+    //connected addresses and full targets are skipped
+    let mut outbound: HashMap<usize, usize> = HashMap::new();
+    outbound.insert(0, 2);
+    let connected: HashSet<_> = vec![peer_addr(6100, 1).addr].into_iter().collect();
+    let candidates = book.select_candidates(&outbound, &connected, 3, 2);
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].shard_id, 0);
+    assert!(book.select_candidates(&outbound, &connected, 2, 2).is_empty());
+}
+
+#[test]
+fn address_book_failures_and_persistence() {
+    let path = std::env::temp_dir()
+        .join(format!("optchain-address-book-test-{}", std::process::id()))
+        .join("peers.json")
+        .to_string_lossy()
+        .into_owned();
+    let _ = std::fs::remove_file(&path);
+    let mut book = AddressBook::new(Some(path.clone()));
+    assert!(book.is_empty());
+    book.mark_seen(peer_addr(6000, 0));
+    book.add(peer_addr(6001, 1));
+    for _ in 0..5 {
+        book.mark_failed(&peer_addr(6001, 1).addr);
+    }
+    assert!(!book.contains(&peer_addr(6001, 1).addr));
+    book.add(peer_addr(6002, 1));
+    //the seen address is gossiped first
+    assert_eq!(book.get_peer_addrs(1), vec![peer_addr(6000, 0)]);
+    book.save();
+
+    let restored = AddressBook::new(Some(path.clone()));
+    assert_eq!(restored.len(), 2);
+    assert!(restored.contains(&peer_addr(6002, 1).addr));
+    assert_eq!(restored.get_peer_addrs(1), vec![peer_addr(6000, 0)]);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn address_book_confirmed_shard() {
+    let mut book = AddressBook::new(None);
+    //a gossiped claim can be corrected by another one
+    book.add(peer_addr(6000, 0));
+    book.add(peer_addr(6000, 1));
+    assert!(!book.is_confirmed(&peer_addr(6000, 1).addr));
+    assert_eq!(book.get_peer_addrs(1), vec![peer_addr(6000, 1)]);
+
+    //but not once a handshake told us the shard
+    book.mark_seen(peer_addr(6000, 0));
+    assert!(book.is_confirmed(&peer_addr(6000, 0).addr));
+    book.add(peer_addr(6000, 1));
+    assert_eq!(book.get_peer_addrs(1), vec![peer_addr(6000, 0)]);
+}
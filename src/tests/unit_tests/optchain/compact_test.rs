@@ -0,0 +1,117 @@
+#[cfg(test)]
+use crate::{
+    optchain::{
+        block::{
+            BlockHeader,
+            transaction_block::TransactionBlock,
+            proposer_block::ProposerBlock,
+            ordering_block::OrderingBlock,
+            versa_block::VersaBlock,
+        },
+        network::compact::{CompactBlock, CompactPool},
+        configuration::Configuration,
+        miner::meets_target,
+    },
+    types::{
+        merkle::MerkleTree,
+        hash::H256,
+    },
+};
+#[cfg(test)]
+use std::time::SystemTime;
+
+#[cfg(test)]
+fn header(shard_id: usize, prop_root: H256) -> BlockHeader {
+    BlockHeader::create(
+        shard_id, //shard_id
+        H256::default(), //prop_parent,
+        H256::default(), //inter_parent,
+        vec![], //global_parent,
+        H256::default(), //order_parent,
+        prop_root, //prop_root,
+        H256::default(), //avai_root
+        H256::default(), //order_root
+        H256::default(), //cmt_root
+        SystemTime::now(), //timestamp
+    )
+}
+
+#[cfg(test)]
+fn prop_block_with_tx_blks(num: usize) -> (VersaBlock, Vec<TransactionBlock>) {
+    let tx_blks: Vec<TransactionBlock> = (0..num)
+        .map(|i| TransactionBlock::new(header(i, H256::default()), i as u32))
+        .collect();
+    let prop_tx_set = MerkleTree::<TransactionBlock>::new(tx_blks.as_slice());
+    let block = ProposerBlock::new(header(0, prop_tx_set.root()), 0, prop_tx_set);
+    (VersaBlock::PropBlock(block), tx_blks)
+}
+
+#[test]
+fn compact_block_reconstruct() {
+    let (block, tx_blks) = prop_block_with_tx_blks(3);
+    let compact = CompactBlock::from_block(&block).unwrap();
+    assert_eq!(compact.get_versa_hash(), block.get_versa_hash());
+    assert_eq!(compact.get_tx_blk_hashes().len(), 3);
+    assert!(bincode::serialize(&compact).unwrap().len() < bincode::serialize(&block).unwrap().len());
+
+    let rebuilt = compact.reconstruct(tx_blks.clone()).unwrap();
+    assert!(rebuilt == block);
+    //the tx blocks must match the hashes and their order
+    let mut swapped = tx_blks.clone();
+    swapped.swap(0, 1);
+    assert!(compact.reconstruct(swapped).is_err());
+    assert!(compact.reconstruct(tx_blks[..2].to_vec()).is_err());
+
+    //ordering blocks are relayed in full
+    assert!(CompactBlock::from_block(&VersaBlock::OrderBlock(OrderingBlock::default())).is_none());
+}
+
+#[test]
+fn compact_pool_fill() {
+    let (block, tx_blks) = prop_block_with_tx_blks(3);
+    let compact = CompactBlock::from_block(&block).unwrap();
+    let versa_hash = block.get_versa_hash();
+    let mut pool = CompactPool::new();
+
+    let known = vec![Some(tx_blks[0].clone()), None, None];
+    assert_eq!(pool.insert(compact, known), vec![1, 2]);
+    assert!(pool.contains(&versa_hash));
+
+    //a partial answer keeps the block pending
+    assert!(matches!(pool.fill(&versa_hash, vec![tx_blks[1].clone()]), Some(Err(_))));
+    match pool.fill(&versa_hash, vec![tx_blks[2].clone()]) {
+        Some(Ok(rebuilt)) => assert!(rebuilt == block),
+        _ => panic!("the block should be rebuilt"),
+    }
+    assert!(pool.is_empty());
+    //unsolicited tx blocks
+    assert!(pool.fill(&versa_hash, tx_blks).is_none());
+}
+
+#[test]
+fn compact_pool_requests() {
+    let (block, _) = prop_block_with_tx_blks(2);
+    let versa_hash = block.get_versa_hash();
+    let peer: std::net::SocketAddr = "127.0.0.1:6000".parse().unwrap();
+    let other: std::net::SocketAddr = "127.0.0.1:6001".parse().unwrap();
+    let mut pool = CompactPool::new();
+    pool.mark_requested(&versa_hash, peer);
+    //only the peer asked is expected to answer, once
+    assert!(!pool.take_request(&versa_hash, &other));
+    assert!(pool.take_request(&versa_hash, &peer));
+    assert!(!pool.take_request(&versa_hash, &peer));
+}
+
+#[test]
+fn compact_block_target() {
+    let (block, _) = prop_block_with_tx_blks(2);
+    let compact = CompactBlock::from_block(&block).unwrap();
+    //a proposer header is only pooled if its hash falls in the proposer range
+    let mut config = Configuration::new();
+    config.order_diff = [0u8; 32].into();
+    config.avai_diff = [0u8; 32].into();
+    config.in_avai_diff = [0u8; 32].into();
+    assert!(meets_target(&compact.get_versa_hash(), &config));
+    config.prop_diff = [0u8; 32].into();
+    assert!(!meets_target(&compact.get_versa_hash(), &config));
+}
@@ -0,0 +1,133 @@
+#[cfg(test)]
+use crate::{
+    optchain::configuration::Configuration,
+    types::{hash::H256, options::Options},
+};
+#[cfg(test)]
+use clap::{App, Arg, ArgMatches};
+
+#[cfg(test)]
+fn matches(args: &[&str]) -> ArgMatches<'static> {
+    App::new("node")
+        .arg(Arg::with_name("shard_id").long("shardId").takes_value(true))
+        .arg(Arg::with_name("shard_num").long("shardNum").takes_value(true).default_value("4"))
+        .arg(Arg::with_name("known_peer").short("c").takes_value(true).multiple(true))
+        .arg(Arg::with_name("mempool_persist").long("mempoolPersist"))
+        .get_matches_from(args)
+}
+
+#[cfg(test)]
+fn valid_config() -> Configuration {
+    let mut config = Configuration::new();
+    config.shard_id = 1;
+    config.shard_num = 2;
+    config.block_size = 8;
+    config.symbol_size = 2;
+    config.num_symbol_per_block = 4;
+    config.ex_req_num = 2;
+    config.in_req_num = 1;
+    config.in_avai_diff = [0x10u8; 32].into();
+    config.avai_diff = [0x20u8; 32].into();
+    config.order_diff = [0x30u8; 32].into();
+    config.prop_diff = [0x40u8; 32].into();
+    config.tx_diff = H256::default();
+    config
+}
+
+#[test]
+fn options_precedence() {
+    let file = r#"{"shard_id": 1, "shard_num": "8", "known_peer": ["127.0.0.1:6000"], "mempool_persist": true}"#;
+    //the file over the defaults
+    let args = matches(&["node"]);
+    let options = Options::with_file(&args, file).unwrap();
+    assert_eq!(options.parse::<usize>("shard_id"), Ok(1));
+    assert_eq!(options.parse::<usize>("shard_num"), Ok(8));
+    assert_eq!(options.values_of("known_peer"), Ok(vec![String::from("127.0.0.1:6000")]));
+    assert_eq!(options.is_present("mempool_persist"), Ok(true));
+    //the command line over the file
+    let args = matches(&["node", "--shardId", "2", "-c", "127.0.0.1:6001"]);
+    let options = Options::with_file(&args, file).unwrap();
+    assert_eq!(options.parse::<usize>("shard_id"), Ok(2));
+    assert_eq!(options.values_of("known_peer"), Ok(vec![String::from("127.0.0.1:6001")]));
+    //the defaults without a file
+    let args = matches(&["node"]);
+    let options = Options::with_file(&args, "{}").unwrap();
+    assert_eq!(options.parse::<usize>("shard_num"), Ok(4));
+    assert!(options.parse::<usize>("shard_id").unwrap_err().contains("missing shard_id"));
+    assert_eq!(options.values_of("known_peer"), Ok(vec![]));
+    assert_eq!(options.is_present("mempool_persist"), Ok(false));
+}
+
+#[test]
+fn options_errors() {
+    let args = matches(&["node"]);
+    assert!(Options::with_file(&args, "[1]").is_err());
+    assert!(Options::with_file(&args, "{").is_err());
+    let options = Options::with_file(&args, r#"{"shard_id": "x", "known_peer": "127.0.0.1:6000", "shard_nun": 2}"#).unwrap();
+    assert!(options.parse::<usize>("shard_id").unwrap_err().contains("invalid shard_id x"));
+    assert!(options.values_of("known_peer").is_err());
+    options.parse::<usize>("shard_num").unwrap();
+    //the misspelled key is the one never read
+    let unknown = options.check_unknown().unwrap_err();
+    assert!(unknown.contains("shard_nun"));
+    assert!(!unknown.contains("shard_id"));
+}
+
+#[test]
+fn options_dump_reloads() {
+    let args = matches(&["node", "--shardId", "3", "-c", "127.0.0.1:6000", "--mempoolPersist"]);
+    let options = Options::with_file(&args, "{}").unwrap();
+    options.parse::<usize>("shard_id").unwrap();
+    options.parse::<usize>("shard_num").unwrap();
+    options.values_of("known_peer").unwrap();
+    options.is_present("mempool_persist").unwrap();
+    let dump = options.dump();
+    let args = matches(&["node"]);
+    let reloaded = Options::with_file(&args, &dump).unwrap();
+    assert_eq!(reloaded.parse::<usize>("shard_id"), Ok(3));
+    assert_eq!(reloaded.parse::<usize>("shard_num"), Ok(4));
+    assert_eq!(reloaded.values_of("known_peer"), Ok(vec![String::from("127.0.0.1:6000")]));
+    assert_eq!(reloaded.is_present("mempool_persist"), Ok(true));
+    assert_eq!(reloaded.check_unknown(), Ok(()));
+    assert_eq!(reloaded.dump(), dump);
+}
+
+#[test]
+fn config_validate() {
+    assert_eq!(valid_config().validate(), Ok(()));
+
+    let mut config = valid_config();
+    config.shard_id = 2;
+    assert!(config.validate().unwrap_err()[0].contains("shard_id 2 is out of range"));
+
+    let mut config = valid_config();
+    config.block_size = 9;
+    assert!(config.validate().unwrap_err()[0].contains("not a multiple of symbol_size"));
+
+    let mut config = valid_config();
+    config.ex_req_num = 5;
+    config.in_req_num = 5;
+    assert_eq!(config.validate().unwrap_err().len(), 2);
+
+    let mut config = valid_config();
+    config.avai_diff = config.prop_diff;
+    config.order_diff = config.in_avai_diff;
+    let errors = config.validate().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("avai_diff"));
+
+    //compact blocks cannot be rebuilt without their tx blocks
+    let mut config = valid_config();
+    config.compact_blocks = true;
+    config.relay_policy = "shard,tx=origin".parse().unwrap();
+    assert!(config.validate().unwrap_err()[0].contains("relay tx=all"));
+    config.compact_blocks = false;
+    assert_eq!(config.validate(), Ok(()));
+
+    //every broken invariant is reported at once
+    let mut config = valid_config();
+    config.shard_num = 0;
+    config.symbol_size = 0;
+    config.miner_threads = 0;
+    assert_eq!(config.validate().unwrap_err().len(), 3);
+}
@@ -0,0 +1,182 @@
+#[cfg(test)]
+use crate::optchain::{
+    api::stream::{self, StreamFormat},
+    configuration::Configuration,
+    events::{ChainEvent, EventBus, EventFilter},
+    miner::{BlockKind, simulated::SimulatedPow},
+    multichain::ChainType,
+    simulator::{Simulation, SimConfig},
+};
+#[cfg(test)]
+use crossbeam::channel::Receiver;
+#[cfg(test)]
+use std::time::Duration;
+
+#[cfg(test)]
+fn base_config() -> Configuration {
+    let mut config = Configuration::new();
+    config.shard_num = 2;
+    config.shard_size = 2;
+    config.block_size = 4;
+    config.symbol_size = 2;
+    config.num_symbol_per_block = 2;
+    config.prop_size = 4;
+    config.avai_size = 4;
+    //every kind of block is in reach of a few nonces
+    "tx=1,prop=1,order=1,avai=1,inAvai=1".parse::<SimulatedPow>().unwrap().apply_targets(&mut config);
+    config
+}
+
+#[cfg(test)]
+fn drain(receiver: &Receiver<ChainEvent>) -> Vec<ChainEvent> {
+    receiver.try_iter().collect()
+}
+
+#[test]
+fn events_filter() {
+    let filter = stream::parse_filter(Some("availability,ordering"), Some("1")).unwrap();
+    assert_eq!(filter.chains, vec!["availability", "ordering"]);
+    assert_eq!(filter.shard_id, Some(1));
+    assert!(stream::parse_filter(Some("main"), None).is_err());
+    assert!(stream::parse_filter(None, Some("x")).is_err());
+    assert_eq!(stream::parse_format(None), Ok(StreamFormat::Sse));
+    assert_eq!(stream::parse_format(Some("jsonl")), Ok(StreamFormat::JsonLines));
+    assert!(stream::parse_format(Some("xml")).is_err());
+
+    let tip = |chain| ChainEvent::TipChanged { chain, hash: String::new(), height: 1 };
+    assert!(filter.matches(&tip(ChainType::Availability(1))));
+    assert!(!filter.matches(&tip(ChainType::Availability(0))));
+    assert!(!filter.matches(&tip(ChainType::Proposer)));
+    assert!(filter.matches(&tip(ChainType::Ordering)));
+    let confirmed = |shard_id| ChainEvent::AvaiConfirmed {
+        hash: String::new(),
+        shard_id,
+        order_hash: String::new(),
+    };
+    assert!(filter.matches(&confirmed(1)));
+    assert!(!filter.matches(&confirmed(0)));
+    let mined_tx = ChainEvent::BlockMined { block_type: String::new(), hash: String::new(), chain: None };
+    assert!(!filter.matches(&mined_tx));
+    assert!(EventFilter::default().matches(&mined_tx));
+
+    //a dropped receiver ends the subscription
+    let bus = EventBus::new();
+    let receiver = bus.subscribe(EventFilter::default());
+    bus.publish(tip(ChainType::Proposer));
+    assert_eq!(drain(&receiver).len(), 1);
+    drop(receiver);
+    bus.publish(tip(ChainType::Proposer));
+    assert_eq!(bus.num_subscribers(), 0);
+}
+
+#[test]
+fn events_stream_format() {
+    let bus = EventBus::new();
+    let receiver = bus.subscribe(EventFilter::default());
+    bus.publish(ChainEvent::TipChanged { chain: ChainType::Availability(1), hash: String::from("ab"), height: 2 });
+    drop(bus);
+    let mut out: Vec<u8> = vec![];
+    stream::serve(&mut out, &receiver, StreamFormat::Sse, Duration::from_millis(10)).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n"));
+    assert!(out.ends_with(
+        "\r\n\r\ndata: {\"event\":\"tip_changed\",\"chain\":{\"Availability\":1},\"hash\":\"ab\",\"height\":2}\n\n"
+    ));
+}
+
+#[test]
+fn events_of_mined_and_relayed_blocks() {
+    let mut sim = Simulation::new(&SimConfig { num_nodes: 2, ..Default::default() }, &base_config());
+    sim.connect_all();
+    let mined = sim.node(0).get_events().subscribe(EventFilter::default());
+    let filter = EventFilter { chains: vec![String::from("proposer")], shard_id: None };
+    let received = sim.node(1).get_events().subscribe(filter);
+    sim.mine(0, BlockKind::Prop).unwrap();
+    sim.run_for(200);
+    sim.mine(0, BlockKind::Order).unwrap();
+    sim.run_for(200);
+
+    let mined = drain(&mined);
+    let names: Vec<&str> = mined
+        .iter()
+        .map(|e| match e {
+            ChainEvent::BlockMined { .. } => "mined",
+            ChainEvent::BlockAccepted { .. } => "accepted",
+            ChainEvent::TipChanged { .. } => "tip",
+            _ => "other",
+        })
+        .collect();
+    assert_eq!(names, vec!["mined", "accepted", "tip", "mined", "accepted", "tip"]);
+    assert!(matches!(&mined[5], ChainEvent::TipChanged { chain: ChainType::Ordering, height: 1, .. }));
+
+    //the ordering block is filtered out
+    let received = drain(&received);
+    assert_eq!(received.len(), 2);
+    let hash = match &mined[0] {
+        ChainEvent::BlockMined { hash, .. } => hash.clone(),
+        _ => unreachable!(),
+    };
+    assert_eq!(received[0], ChainEvent::BlockAccepted {
+        block_type: String::from("proposer"),
+        hash: hash.clone(),
+        chain: ChainType::Proposer,
+        height: 1,
+    });
+    assert_eq!(received[1], ChainEvent::TipChanged { chain: ChainType::Proposer, hash, height: 1 });
+}
+
+#[test]
+fn events_reorg() {
+    let mut sim = Simulation::new(&SimConfig { num_nodes: 2, ..Default::default() }, &base_config());
+    sim.connect_all();
+    sim.partition(&[vec![0], vec![1]]);
+    sim.mine(0, BlockKind::Prop).unwrap();
+    sim.mine(1, BlockKind::Prop).unwrap();
+    sim.mine(1, BlockKind::Prop).unwrap();
+    sim.run_for(500);
+    let events = sim.node(0).get_events().subscribe(EventFilter::default());
+    //the next block brings its missing ancestors, whose chain is longer
+    sim.heal();
+    sim.mine(1, BlockKind::Prop).unwrap();
+    sim.run_for(2000);
+    let reorgs: Vec<ChainEvent> = drain(&events)
+        .into_iter()
+        .filter(|e| matches!(e, ChainEvent::Reorg { .. }))
+        .collect();
+    assert_eq!(reorgs.len(), 1);
+    assert!(matches!(&reorgs[0], ChainEvent::Reorg { chain: ChainType::Proposer, depth: 1, .. }));
+}
+
+#[test]
+fn events_confirmed_by_longest_chain() {
+    let mut config = base_config();
+    //an availability block is confirmed as soon as it is in the longest chain
+    config.k = 0;
+    let mut sim = Simulation::new(&SimConfig { num_nodes: 2, ..Default::default() }, &config);
+    sim.connect_all();
+    sim.mine(0, BlockKind::ExAvai).unwrap();
+    sim.run_for(500);
+    //node 0 goes ahead before it hears of the single ordering block of node 1
+    sim.mine(0, BlockKind::Order).unwrap();
+    sim.mine(0, BlockKind::Order).unwrap();
+    let side = sim.node(0).get_events().subscribe(EventFilter::default());
+    let longest = sim.node(1).get_events().subscribe(EventFilter::default());
+    sim.mine(1, BlockKind::Order).unwrap();
+    sim.run_for(2000);
+    let confirmations = |events: &[ChainEvent]| -> usize {
+        events
+            .iter()
+            .filter(|e| matches!(e, ChainEvent::AvaiConfirmed { .. }))
+            .count()
+    };
+    //the block of node 1 only reaches node 0 as a side block
+    let events = drain(&side);
+    let accepted = events
+        .iter()
+        .filter(|e| matches!(e, ChainEvent::BlockAccepted { chain: ChainType::Ordering, .. }))
+        .count();
+    assert_eq!(accepted, 1);
+    assert_eq!(confirmations(&events), 0);
+    //node 1 confirms on its own block, then again on the longer chain of node 0
+    assert_eq!(confirmations(&drain(&longest)), 2);
+}
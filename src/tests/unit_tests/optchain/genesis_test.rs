@@ -0,0 +1,116 @@
+#[cfg(test)]
+use crate::{
+    optchain::{
+        new_multichain,
+        configuration::Configuration,
+        block::proposer_block::ProposerBlock,
+        multichain::ChainType,
+    },
+    types::{
+        address::Address,
+        genesis::GenesisSpec,
+        hash::{H256, Hashable},
+    },
+};
+
+#[cfg(test)]
+fn address_of(public_key: &[u8], chain_id: &str) -> String {
+    Address::from_public_key(public_key).unwrap().encode(chain_id)
+}
+
+#[cfg(test)]
+fn spec_json(chain_id: &str) -> String {
+    format!(
+        r#"{{
+            "chain_id": "{}",
+            "shard_num": 2,
+            "timestamp": 1700000000,
+            "targets": {{"tx_diff": "{}"}},
+            "allocations": [
+                {{"address": "{}", "public_key": "{}", "value": 1000}}
+            ]
+        }}"#,
+        chain_id,
+        "f".repeat(64),
+        address_of(&[2u8; 32], chain_id),
+        "02".repeat(32),
+    )
+}
+
+#[cfg(test)]
+fn base_config(genesis: Option<GenesisSpec>) -> Configuration {
+    let mut config = Configuration::new();
+    config.shard_num = 2;
+    config.genesis = genesis;
+    config
+}
+
+#[test]
+fn genesis_spec_parse() {
+    let spec = GenesisSpec::from_json(&spec_json("testnet")).unwrap();
+    assert_eq!(spec.chain_id, "testnet");
+    assert_eq!(spec.shard_num, 2);
+    assert_eq!(spec.get_target("tx_diff"), Some(H256::default()));
+    assert_eq!(spec.get_target("prop_diff"), None);
+    assert_eq!(spec.allocations[0].get_address(), H256::from(Address::from_public_key(&[2u8; 32]).unwrap()));
+    assert_eq!(spec.allocations[0].get_public_key(), vec![2u8; 32]);
+
+    //only the chain id and the shards are required
+    let spec = GenesisSpec::from_json(r#"{"chain_id": "x", "shard_num": 1}"#).unwrap();
+    assert!(spec.allocations.is_empty());
+
+    assert!(GenesisSpec::from_json(r#"{"chain_id": "", "shard_num": 1}"#).is_err());
+    assert!(GenesisSpec::from_json(r#"{"chain_id": "x", "shard_num": 0}"#).is_err());
+    assert!(GenesisSpec::from_json(r#"{"chain_id": "x", "shard_num": 1, "shards": 2}"#).is_err());
+    assert!(GenesisSpec::from_json(r#"{"chain_id": "Test Net", "shard_num": 1}"#).is_err());
+    let address = address_of(&[2u8; 32], "x");
+    let bad_address = spec_json("x").replace(&address, &address[..address.len() - 1]);
+    assert!(GenesisSpec::from_json(&bad_address).unwrap_err().contains("allocation 0"));
+    //the address of another chain, or of another key
+    let other_chain = spec_json("x").replace(&address, &address_of(&[2u8; 32], "y"));
+    assert!(GenesisSpec::from_json(&other_chain).unwrap_err().contains("not of chain x"));
+    let other_key = spec_json("x").replace(&address, &address_of(&[3u8; 32], "x"));
+    assert!(GenesisSpec::from_json(&other_key).unwrap_err().contains("not the address of its public key"));
+    let no_value = spec_json("x").replace("1000", "0");
+    assert!(GenesisSpec::from_json(&no_value).is_err());
+    let bad_target = spec_json("x").replace(&"f".repeat(64), "ff");
+    assert!(GenesisSpec::from_json(&bad_target).is_err());
+}
+
+#[test]
+fn genesis_blocks_deterministic() {
+    let genesis = GenesisSpec::from_json(&spec_json("testnet")).unwrap();
+    let hashes = new_multichain(&base_config(Some(genesis.clone()))).get_genesis_hashes();
+    assert_eq!(hashes.len(), 4);
+    assert_eq!(new_multichain(&base_config(Some(genesis.clone()))).get_genesis_hashes(), hashes);
+
+    //another chain id gives other genesis blocks on every chain
+    let other = GenesisSpec::from_json(&spec_json("othernet")).unwrap();
+    let other_hashes = new_multichain(&base_config(Some(other))).get_genesis_hashes();
+    for (x, y) in hashes.iter().zip(other_hashes.iter()) {
+        assert_eq!(x.0, y.0);
+        assert_ne!(x.1, y.1);
+    }
+
+    //without a genesis file, the default genesis blocks
+    let default_hashes = new_multichain(&base_config(None)).get_genesis_hashes();
+    assert_eq!(default_hashes[0], (ChainType::Proposer, ProposerBlock::default().hash()));
+    assert_ne!(default_hashes, hashes);
+}
+
+#[test]
+fn genesis_config_validate() {
+    let genesis = GenesisSpec::from_json(&spec_json("testnet")).unwrap();
+    let mut config = base_config(Some(genesis.clone()));
+    config.symbol_size = 1;
+    assert_eq!(config.validate(), Ok(()));
+
+    config.shard_num = 3;
+    assert!(config.validate().unwrap_err()[0].contains("genesis file"));
+
+    let mut genesis = genesis;
+    genesis.targets.insert(String::from("exclusive_diff"), "0".repeat(64));
+    let mut config = base_config(Some(genesis));
+    config.symbol_size = 1;
+    assert!(config.validate().unwrap_err()[0].contains("unknown target exclusive_diff"));
+}
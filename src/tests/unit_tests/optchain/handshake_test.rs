@@ -0,0 +1,67 @@
+#[cfg(test)]
+use crate::{
+    optchain::{
+        configuration::Configuration,
+        multichain::ChainType,
+        network::handshake::{Handshake, PROTOCOL_VERSION},
+    },
+    types::hash::H256,
+};
+#[cfg(test)]
+use smol::Async;
+#[cfg(test)]
+use std::net;
+
+#[cfg(test)]
+fn test_handshake(shard_id: usize) -> Handshake {
+    let mut config = Configuration::new();
+    config.shard_num = 2;
+    config.symbol_size = 128;
+    config.shard_id = shard_id;
+    let genesis_hashes = vec![
+        (ChainType::Proposer, H256::from([1u8; 32])),
+        (ChainType::Availability(0), H256::from([2u8; 32])),
+        (ChainType::Availability(1), H256::from([3u8; 32])),
+        (ChainType::Ordering, H256::from([4u8; 32])),
+    ];
+    Handshake::new(&config, genesis_hashes, "127.0.0.1:6000".parse().unwrap())
+}
+
+#[test]
+fn handshake_check() {
+    let local = test_handshake(0);
+    assert_eq!(local.version, PROTOCOL_VERSION);
+    assert!(local.check(&test_handshake(1)).is_ok());
+
+    let mut remote = test_handshake(1);
+    remote.version += 1;
+    assert!(local.check(&remote).is_err());
+
+    let mut remote = test_handshake(1);
+    remote.params.symbol_size = 256;
+    assert!(local.check(&remote).is_err());
+
+    let mut remote = test_handshake(1);
+    remote.genesis_hashes[3].1 = H256::default();
+    assert!(local.check(&remote).is_err());
+
+    assert!(local.check(&test_handshake(2)).is_err());
+}
+
+#[test]
+fn handshake_framing() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let sent = test_handshake(1);
+    smol::block_on(async {
+        let mut outgoing = Async::<net::TcpStream>::connect(addr).await.unwrap();
+        let (incoming, _) = listener.accept().unwrap();
+        let mut incoming = Async::new(incoming).unwrap();
+        sent.write_to(&mut outgoing).await.unwrap();
+        let received = Handshake::read_from(&mut incoming).await.unwrap();
+        assert_eq!(received.node_id, sent.node_id);
+        assert_eq!(received.listen_addr, sent.listen_addr);
+        assert_eq!(received.genesis_hashes, sent.genesis_hashes);
+        assert!(sent.check(&received).is_ok());
+    });
+}
@@ -0,0 +1,92 @@
+#[cfg(test)]
+use crate::optchain::{
+    new_multichain,
+    configuration::Configuration,
+    mempool::Mempool,
+    miner::{
+        self,
+        rate::{RateLimiter, HashCounter},
+    },
+};
+#[cfg(test)]
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+#[test]
+fn hash_rate_limiter_paces_reservations() {
+    let limiter = RateLimiter::new(1000);
+    assert_eq!(limiter.batch_size(4096), 10);
+    //the first batch starts now, the next ones wait for the budget
+    assert!(limiter.reserve(100) < Duration::from_millis(5));
+    let wait = limiter.reserve(100);
+    assert!(wait > Duration::from_millis(90) && wait <= Duration::from_millis(100));
+    let wait = limiter.reserve(1);
+    assert!(wait > Duration::from_millis(190) && wait <= Duration::from_millis(200));
+
+    //a new rate drops the reservations
+    limiter.set_rate(0);
+    assert_eq!(limiter.reserve(1_000_000), Duration::ZERO);
+    assert_eq!(limiter.batch_size(4096), 4096);
+    limiter.set_rate(50);
+    assert_eq!(limiter.batch_size(4096), 1);
+    assert!(limiter.reserve(1) < Duration::from_millis(5));
+}
+
+#[test]
+fn hash_rate_counter_stats() {
+    let counter = HashCounter::new();
+    assert_eq!(counter.get_stats(0).hash_rate, 0.0);
+    counter.start();
+    counter.add(500);
+    thread::sleep(Duration::from_millis(100));
+    counter.pause();
+    let stats = counter.get_stats(7);
+    assert_eq!(stats.target_hash_rate, 7);
+    assert_eq!(stats.hashes, 500);
+    assert!(stats.elapsed >= 0.1);
+    //the paused time does not count
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(counter.get_stats(7), stats);
+    counter.start();
+    assert_eq!(counter.get_stats(7).hashes, 0);
+}
+
+#[test]
+fn hash_rate_miner_keeps_budget() {
+    let mut config = Configuration::new();
+    config.shard_id = 1;
+    config.shard_num = 2;
+    config.shard_size = 2;
+    config.block_size = 4;
+    config.symbol_size = 2;
+    config.num_symbol_per_block = 2;
+    config.prop_size = 4;
+    config.avai_size = 4;
+    config.miner_threads = 3;
+    config.hash_rate = 2000;
+    //no hash is a block
+    config.tx_diff = [0u8; 32].into();
+    config.prop_diff = [0u8; 32].into();
+    config.order_diff = [0u8; 32].into();
+    config.avai_diff = [0u8; 32].into();
+    config.in_avai_diff = [0u8; 32].into();
+    let multichain = Arc::new(Mutex::new(new_multichain(&config)));
+    let mempool = Arc::new(Mutex::new(Mempool::new(&config)));
+    let (miner_ctx, miner, _finished_block_chan) = miner::new(&multichain, &mempool, &config);
+    miner_ctx.start();
+    miner.start(0);
+    thread::sleep(Duration::from_millis(1000));
+    let stats = miner.get_hash_rate_stats();
+    assert_eq!(stats.target_hash_rate, 2000);
+    assert!(stats.hash_rate > 1000.0 && stats.hash_rate < 2400.0, "{:?}", stats);
+
+    miner.set_hash_rate(200);
+    miner.start(0);
+    thread::sleep(Duration::from_millis(1000));
+    let stats = miner.get_hash_rate_stats();
+    assert!(stats.hash_rate > 100.0 && stats.hash_rate < 260.0, "{:?}", stats);
+    miner.exit();
+}
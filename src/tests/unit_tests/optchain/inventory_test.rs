@@ -0,0 +1,68 @@
+#[cfg(test)]
+use crate::{
+    types::hash::{H256, Hashable},
+    optchain::{
+        block::versa_block::VersaHash,
+        network::{
+            inventory::{InFlightRequests, InvItem, KnownInventory},
+            message::Message,
+        },
+        symbolpool::SymbolIndex,
+    },
+};
+
+#[cfg(test)]
+fn hash(i: u8) -> H256 {
+    H256::from([i; 32])
+}
+
+#[test]
+fn inventory_filter_announcement() {
+    let mut known = KnownInventory::new(10);
+    known.insert(InvItem::Block(VersaHash::PropHash(hash(1))));
+
+    //only the unknown hash is announced, and it becomes known
+    let msg = Message::NewBlockHash(vec![VersaHash::PropHash(hash(1)), VersaHash::PropHash(hash(2))]);
+    match known.filter_announcement(&msg) {
+        Some(Message::NewBlockHash(hashes)) => assert_eq!(hashes, vec![VersaHash::PropHash(hash(2))]),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(known.contains(&InvItem::Block(VersaHash::PropHash(hash(2)))));
+    assert!(known.filter_announcement(&msg).is_none());
+
+    let index = SymbolIndex::new(hash(3), 0);
+    let msg = Message::NewSymbols(vec![index.clone()]);
+    assert!(known.filter_announcement(&msg).is_some());
+    assert!(known.contains(&InvItem::Symbol(index.hash())));
+    assert!(known.filter_announcement(&msg).is_none());
+
+    //other messages are never filtered
+    let ping = Message::Ping(String::from("nonce"));
+    assert!(known.filter_announcement(&ping).is_some());
+    assert!(known.filter_announcement(&ping).is_some());
+}
+
+#[test]
+fn inventory_capacity() {
+    let mut known = KnownInventory::new(2);
+    for i in 0..3 {
+        known.insert(InvItem::TxBlock(hash(i)));
+    }
+    assert!(!known.contains(&InvItem::TxBlock(hash(0))));
+    assert!(known.contains(&InvItem::TxBlock(hash(1))));
+    assert!(known.contains(&InvItem::TxBlock(hash(2))));
+}
+
+#[test]
+fn inventory_in_flight_requests() {
+    let mut in_flight = InFlightRequests::new();
+    let first = in_flight.filter_new(vec![hash(1), hash(2)], |h| InvItem::TxBlock(*h));
+    assert_eq!(first, vec![hash(1), hash(2)]);
+    //a second peer announcing the same items is not asked for them
+    let second = in_flight.filter_new(vec![hash(2), hash(3)], |h| InvItem::TxBlock(*h));
+    assert_eq!(second, vec![hash(3)]);
+
+    in_flight.received(&InvItem::TxBlock(hash(1)));
+    let third = in_flight.filter_new(vec![hash(1)], |h| InvItem::TxBlock(*h));
+    assert_eq!(third, vec![hash(1)]);
+}
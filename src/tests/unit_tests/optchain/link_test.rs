@@ -0,0 +1,51 @@
+#[cfg(test)]
+use crate::optchain::network::link::{LinkEmulation, LinkSpec, TokenBucket};
+#[cfg(test)]
+use std::{
+    net,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn link_emulation_from_config() {
+    //the experiment config has more fields, they are ignored
+    let config = r#"{
+        "shard_num": 2,
+        "propagation_delay": 100,
+        "bandwidths": [[0, 20], [20, 0]],
+        "peer_links": {"127.0.0.1:6005": {"delay": 5, "bandwidth": 1.5}}
+    }"#;
+    let emulation: LinkEmulation = serde_json::from_str(config).unwrap();
+    let addr: net::SocketAddr = "127.0.0.1:6001".parse().unwrap();
+
+    let same_shard = emulation.get_link(0, 0, &addr);
+    assert_eq!(same_shard, LinkSpec { delay: 100, bandwidth: 0.0 });
+    assert_eq!(same_shard.get_rate(), None);
+    assert!(!same_shard.is_ideal());
+
+    let cross_shard = emulation.get_link(0, 1, &addr);
+    assert_eq!(cross_shard.get_rate(), Some(2_500_000.0));
+    //shards missing from the matrix are not limited
+    assert_eq!(emulation.get_link(3, 1, &addr).bandwidth, 0.0);
+
+    let peer = emulation.get_link(0, 1, &"127.0.0.1:6005".parse().unwrap());
+    assert_eq!(peer, LinkSpec { delay: 5, bandwidth: 1.5 });
+    assert!(LinkSpec { delay: 0, bandwidth: 0.0 }.is_ideal());
+}
+
+#[test]
+fn link_token_bucket() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(1000.0, 500.0, start);
+    //the burst goes through
+    assert_eq!(bucket.reserve(500, start), Duration::ZERO);
+    //then the frames wait for the rate
+    assert_eq!(bucket.reserve(250, start), Duration::from_millis(250));
+    //the debt is paid after 250ms, then 100 bytes take 100ms more
+    let later = start + Duration::from_millis(250);
+    assert_eq!(bucket.reserve(100, later), Duration::from_millis(100));
+    //a long idle period only refills up to the capacity
+    let idle = later + Duration::from_secs(10);
+    assert_eq!(bucket.reserve(500, idle), Duration::ZERO);
+    assert!(bucket.reserve(1, idle) > Duration::ZERO);
+}
@@ -0,0 +1,187 @@
+#[cfg(test)]
+use crate::{
+    optchain::{
+        configuration::Configuration,
+        block::transaction_block::TransactionBlock,
+        mempool::*,
+    },
+    types::{
+        hash::{
+            H256,
+            Hashable,
+        },
+        random::Random,
+    },
+};
+
+#[cfg(test)]
+fn test_config(node_id: usize, capacity: usize, policy: EvictionPolicy) -> Configuration {
+    let mut config = Configuration::new();
+    config.shard_id = 99;
+    config.node_id = node_id;
+    config.mempool_capacity = capacity;
+    config.mempool_policy = policy;
+    config
+}
+
+#[test]
+fn mempool_order_and_dedupe() {
+    let config = test_config(0, 0, EvictionPolicy::Oldest);
+    let mut mempool = Mempool::new(&config);
+    let tx_blks: Vec<TransactionBlock> = (0..3).map(|_| TransactionBlock::random()).collect();
+    for tx_blk in tx_blks.iter() {
+        assert!(mempool.insert_tx_blk(tx_blk.clone()));
+    }
+    assert!(!mempool.insert_tx_blk(tx_blks[1].clone()));
+    assert_eq!(mempool.get_size(), 3);
+    let expected: Vec<H256> = tx_blks.iter().map(|x| x.hash()).collect();
+    assert_eq!(mempool.get_all_tx_blk_hash(), expected);
+
+    assert!(mempool.delete_txs(vec![expected[1]]));
+    assert!(!mempool.check(&expected[1]));
+    assert_eq!(mempool.pop_one_tx_blk().unwrap().hash(), expected[0]);
+    assert_eq!(mempool.pop_one_tx_blk().unwrap().hash(), expected[2]);
+    assert!(mempool.pop_one_tx_blk().is_none());
+    assert_eq!(mempool.get_size(), 0);
+
+    let stats = mempool.get_stats();
+    assert_eq!(stats.inserted, 3);
+    assert_eq!(stats.duplicated, 1);
+    assert_eq!(stats.popped, 2);
+}
+
+#[test]
+fn mempool_evict_oldest() {
+    let config = test_config(1, 2, EvictionPolicy::Oldest);
+    let mut mempool = Mempool::new(&config);
+    let tx_blks: Vec<TransactionBlock> = (0..3).map(|_| TransactionBlock::random()).collect();
+    for tx_blk in tx_blks.iter() {
+        assert!(mempool.insert_tx_blk(tx_blk.clone()));
+    }
+    assert_eq!(mempool.get_size(), 2);
+    assert!(!mempool.check(&tx_blks[0].hash()));
+    assert!(mempool.check(&tx_blks[1].hash()));
+    assert!(mempool.check(&tx_blks[2].hash()));
+    assert_eq!(mempool.get_stats().evicted, 1);
+}
+
+#[test]
+fn mempool_evict_lowest_priority() {
+    let config = test_config(2, 2, EvictionPolicy::LowestPriority);
+    let mut mempool = Mempool::new(&config);
+    let local = TransactionBlock::random();
+    let relayed = TransactionBlock::random();
+    assert!(mempool.insert_tx_blk_with_priority(local.clone(), LOCAL_PRIORITY));
+    assert!(mempool.insert_tx_blk(relayed.clone()));
+
+    //a new local block replaces the relayed one although the local one is older
+    let new_local = TransactionBlock::random();
+    assert!(mempool.insert_tx_blk_with_priority(new_local.clone(), LOCAL_PRIORITY));
+    assert!(mempool.check(&local.hash()));
+    assert!(!mempool.check(&relayed.hash()));
+
+    //a relayed block cannot evict local ones
+    assert!(!mempool.insert_tx_blk(TransactionBlock::random()));
+    assert_eq!(mempool.get_size(), 2);
+    let stats = mempool.get_stats();
+    assert_eq!(stats.evicted, 1);
+    assert_eq!(stats.rejected_full, 1);
+}
+
+#[test]
+fn mempool_reject_referenced() {
+    let config = test_config(3, 0, EvictionPolicy::Oldest);
+    let mut mempool = Mempool::new(&config);
+    let tx_blk = TransactionBlock::random();
+    let hash = tx_blk.hash();
+    assert!(mempool.insert_tx_blk(tx_blk.clone()));
+    assert_eq!(mempool.remove_referenced(&[hash]), 1);
+    assert!(!mempool.check(&hash));
+    assert!(mempool.is_referenced(&hash));
+    assert!(!mempool.insert_tx_blk(tx_blk));
+    assert_eq!(mempool.get_stats().rejected_referenced, 1);
+}
+
+#[test]
+fn mempool_restore_referenced() {
+    let config = test_config(5, 0, EvictionPolicy::Oldest);
+    let mut mempool = Mempool::new(&config);
+    let tx_blks: Vec<TransactionBlock> = (0..2).map(|_| TransactionBlock::random()).collect();
+    let hashes: Vec<H256> = tx_blks.iter().map(|x| x.hash()).collect();
+    for tx_blk in tx_blks.iter() {
+        assert!(mempool.insert_tx_blk(tx_blk.clone()));
+    }
+    assert_eq!(mempool.remove_referenced(&hashes), 2);
+    assert_eq!(mempool.get_size(), 0);
+
+    //a reorg leaves the first block out of the longest chain
+    assert_eq!(mempool.restore_referenced(vec![tx_blks[0].clone()]), 1);
+    assert!(mempool.check(&hashes[0]));
+    assert!(!mempool.is_referenced(&hashes[0]));
+    assert!(mempool.is_referenced(&hashes[1]));
+    assert_eq!(mempool.get_referenced_size(), 1);
+    assert_eq!(mempool.get_stats().restored_referenced, 1);
+}
+
+#[test]
+fn mempool_referenced_capped() {
+    let config = test_config(6, 0, EvictionPolicy::Oldest);
+    let mut mempool = Mempool::new(&config);
+    let first = H256::random();
+    mempool.remove_referenced(&[first]);
+    let hashes: Vec<H256> = (0..MAX_REFERENCED).map(|_| H256::random()).collect();
+    mempool.remove_referenced(&hashes);
+    assert_eq!(mempool.get_referenced_size(), MAX_REFERENCED);
+    assert!(!mempool.is_referenced(&first));
+    assert!(mempool.is_referenced(hashes.last().unwrap()));
+}
+
+//a persistent mempool of its own for every run, as it reopens the same database
+#[cfg(test)]
+fn persistent_config() -> Configuration {
+    let mut config = test_config(rand::random::<u32>() as usize, 0, EvictionPolicy::Oldest);
+    config.mempool_persist = true;
+    config
+}
+
+#[test]
+fn mempool_persist_order() {
+    let config = persistent_config();
+
+    let tx_blks: Vec<TransactionBlock> = (0..4).map(|_| TransactionBlock::random()).collect();
+    let expected: Vec<H256> = tx_blks.iter().skip(1).map(|x| x.hash()).collect();
+    {
+        let mut mempool = Mempool::new(&config);
+        for tx_blk in tx_blks.iter() {
+            assert!(mempool.insert_tx_blk(tx_blk.clone()));
+        }
+        mempool.pop_one_tx_blk().unwrap();
+    }
+    let mut mempool = Mempool::new(&config);
+    assert_eq!(mempool.get_size(), 3);
+    assert_eq!(mempool.get_all_tx_blk_hash(), expected);
+    //new blocks are queued after the restored ones
+    let new_tx_blk = TransactionBlock::random();
+    assert!(mempool.insert_tx_blk(new_tx_blk.clone()));
+    assert_eq!(mempool.get_all_tx_blk_hash().last().unwrap(), &new_tx_blk.hash());
+}
+
+#[test]
+fn mempool_persist_referenced() {
+    let config = persistent_config();
+    let tx_blks: Vec<TransactionBlock> = (0..3).map(|_| TransactionBlock::random()).collect();
+    let hashes: Vec<H256> = tx_blks.iter().map(|x| x.hash()).collect();
+    {
+        let mut mempool = Mempool::new(&config);
+        mempool.remove_referenced(&hashes);
+        mempool.restore_referenced(vec![tx_blks[0].clone()]);
+    }
+    //the tx blocks referenced before the restart are still rejected
+    let mut mempool = Mempool::new(&config);
+    assert_eq!(mempool.get_referenced_size(), 2);
+    assert!(!mempool.insert_tx_blk(tx_blks[1].clone()));
+    assert!(!mempool.insert_tx_blk(tx_blks[2].clone()));
+    //the restored one is queued, and no longer referenced
+    assert!(!mempool.is_referenced(&hashes[0]));
+    assert_eq!(mempool.get_all_tx_blk_hash(), vec![hashes[0]]);
+}
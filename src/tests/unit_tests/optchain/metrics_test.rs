@@ -0,0 +1,108 @@
+#[cfg(test)]
+use crate::optchain::{
+    new_multichain,
+    block::versa_block::VersaBlock,
+    configuration::Configuration,
+    mempool::Mempool,
+    metrics::{self, Gauges, Metrics, RejectReason, TX_BLOCK},
+    miner::{BlockKind, simulated::SimulatedPow},
+    network::message::Message,
+    simulator::{Simulation, SimConfig},
+    symbolpool::SymbolPool,
+};
+#[cfg(test)]
+use std::sync::Mutex;
+
+#[cfg(test)]
+fn base_config() -> Configuration {
+    let mut config = Configuration::new();
+    config.shard_id = 1;
+    config.shard_num = 2;
+    config.shard_size = 2;
+    config.block_size = 4;
+    config.symbol_size = 2;
+    config.num_symbol_per_block = 2;
+    config.prop_size = 4;
+    config.avai_size = 4;
+    //every kind of block is in reach of a few nonces
+    "tx=1,prop=1,order=1,avai=1,inAvai=1".parse::<SimulatedPow>().unwrap().apply_targets(&mut config);
+    config
+}
+
+#[test]
+fn metrics_render() {
+    let config = base_config();
+    let multichain = Mutex::new(new_multichain(&config));
+    let mempool = Mempool::new(&config);
+    let symbolpool = SymbolPool::new(&config);
+    let metrics = Metrics::new();
+    let prop_type = metrics::block_type(&VersaBlock::PropBlock(Default::default()));
+    metrics.block_mined(TX_BLOCK);
+    metrics.block_received(prop_type);
+    metrics.block_received(prop_type);
+    metrics.block_rejected(prop_type, RejectReason::Duplicate);
+    metrics.message_received(&Message::Ping(String::from("a")), 10);
+    metrics.message_received(&Message::Ping(String::from("b")), 12);
+    metrics.message_sent(&Message::GetPeers, 4);
+    metrics.set_unavailable(3, 2);
+    let gauges = {
+        let multichain = metrics.lock_multichain(&multichain);
+        Gauges::collect(&multichain, mempool.get_stats(), &symbolpool)
+    };
+    assert_eq!(metrics.get_blocks_received("proposer"), 2);
+    assert_eq!(metrics.get_blocks_rejected("proposer", RejectReason::Duplicate), 1);
+    assert_eq!(metrics.get_messages_received("Ping"), (2, 22));
+
+    let text = metrics.render(&gauges);
+    let lines: Vec<&str> = text.lines().collect();
+    for line in [
+        "# TYPE optchain_blocks_mined_total counter",
+        "optchain_blocks_mined_total{type=\"transaction\"} 1",
+        "optchain_blocks_received_total{type=\"proposer\"} 2",
+        "optchain_blocks_rejected_total{type=\"proposer\",reason=\"duplicate\"} 1",
+        "optchain_chain_height{chain=\"proposer\"} 0",
+        "optchain_chain_height{chain=\"availability\",shard=\"1\"} 0",
+        "optchain_chain_forking_rate{chain=\"ordering\"} 1",
+        "optchain_mempool_size 0",
+        "optchain_symbolpool_symbols 0",
+        "optchain_unavailable_cmts 3",
+        "optchain_unavailable_avai_blocks 2",
+        "optchain_messages_received_total{type=\"Ping\"} 2",
+        "optchain_message_bytes_received_total{type=\"Ping\"} 22",
+        "optchain_messages_sent_total{type=\"GetPeers\"} 1",
+        "# TYPE optchain_multichain_lock_wait_seconds histogram",
+        "optchain_multichain_lock_wait_seconds_bucket{le=\"+Inf\"} 1",
+        "optchain_multichain_lock_wait_seconds_count 1",
+    ] {
+        assert!(lines.contains(&line), "missing {}", line);
+    }
+    //every sample line is a name, optional labels and a value
+    for line in lines.iter().filter(|l| !l.starts_with('#')) {
+        let value = line.rsplit(' ').next().unwrap();
+        assert!(value.parse::<f64>().is_ok(), "bad sample {}", line);
+    }
+}
+
+#[test]
+fn metrics_count_relayed_blocks() {
+    let mut sim = Simulation::new(&SimConfig { num_nodes: 2, ..Default::default() }, &base_config());
+    sim.connect_all();
+    for _ in 0..2 {
+        sim.mine(0, BlockKind::Prop).unwrap();
+        sim.run_for(200);
+    }
+    let miner = sim.node(0).get_metrics();
+    let peer = sim.node(1).get_metrics();
+    assert_eq!(miner.get_blocks_mined("proposer"), 2);
+    assert_eq!(peer.get_blocks_received("proposer"), 2);
+    assert_eq!(peer.get_blocks_rejected("proposer", RejectReason::Duplicate), 0);
+
+    //the frames of one node are those received by the other
+    let mut pushed = 0;
+    for name in ["Blocks", "CompactBlocks", "NewBlockHash"] {
+        let sent = miner.get_messages_sent(name);
+        assert_eq!(peer.get_messages_received(name), sent);
+        pushed += sent.0;
+    }
+    assert!(pushed >= 2);
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+use crate::{
+    types::{
+        hash::{H256, Hashable},
+        random::Random,
+    },
+    optchain::{
+        new_multichain,
+        block::{
+            Content,
+            transaction_block::TransactionBlock,
+            versa_block::VersaBlock,
+        },
+        configuration::Configuration,
+        mempool::Mempool,
+        miner::{self, assemble_block, MinerMessage},
+    },
+};
+#[cfg(test)]
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[cfg(test)]
+fn test_config(threads: usize) -> Configuration {
+    let mut config = Configuration::new();
+    config.shard_id = 1;
+    config.shard_num = 2;
+    config.shard_size = 2;
+    config.block_size = 4;
+    config.symbol_size = 2;
+    config.num_symbol_per_block = 2;
+    config.prop_size = 4;
+    config.avai_size = 4;
+    config.miner_threads = threads;
+    config
+}
+
+#[test]
+fn miner_extra_nonce_changes_hash() {
+    let config = test_config(1);
+    let multichain = Arc::new(Mutex::new(new_multichain(&config)));
+    let mempool = Arc::new(Mutex::new(Mempool::new(&config)));
+    let block = assemble_block(&multichain, &mempool, &config);
+    let other = block.with_extra_nonce(1);
+    assert_eq!(other.get_header().get_extra_nonce(), 1);
+    assert_ne!(other.hash(), block.hash());
+    assert_eq!(other.get_content(), block.get_content());
+    assert_eq!(block.with_extra_nonce(0).hash(), block.hash());
+}
+
+#[test]
+fn miner_template_keeps_mempool() {
+    let config = test_config(1);
+    let multichain = Arc::new(Mutex::new(new_multichain(&config)));
+    let mempool = Arc::new(Mutex::new(Mempool::new(&config)));
+    let tx_blks: Vec<TransactionBlock> = (0..6).map(|_| TransactionBlock::random()).collect();
+    for tx_blk in tx_blks.iter() {
+        mempool.lock().unwrap().insert_tx_blk(tx_blk.clone());
+    }
+    //a template packs the oldest tx blocks and leaves them in the mempool
+    let block = assemble_block(&multichain, &mempool, &config);
+    let packed: Vec<H256> = block.get_prop_tx_set().iter().map(|x| x.hash()).collect();
+    let expected: Vec<H256> = tx_blks[..4].iter().map(|x| x.hash()).collect();
+    assert_eq!(packed, expected);
+    assert_eq!(mempool.lock().unwrap().get_size(), 6);
+
+    let excluded: HashSet<H256> = expected[..2].iter().cloned().collect();
+    let peeked: Vec<H256> = mempool
+        .lock()
+        .unwrap()
+        .peek_tx_blks(3, &excluded)
+        .iter()
+        .map(|x| x.hash())
+        .collect();
+    assert_eq!(peeked, vec![expected[2], expected[3], tx_blks[4].hash()]);
+}
+
+#[test]
+fn miner_threads_mine_and_update() {
+    let mut config = test_config(3);
+    //every hash is a proposer block
+    config.tx_diff = [0xffu8; 32].into();
+    config.prop_diff = [0xffu8; 32].into();
+    config.order_diff = [0u8; 32].into();
+    config.avai_diff = [0u8; 32].into();
+    config.in_avai_diff = [0u8; 32].into();
+    let multichain = Arc::new(Mutex::new(new_multichain(&config)));
+    let mempool = Arc::new(Mutex::new(Mempool::new(&config)));
+    let (miner_ctx, miner, finished_block_chan) = miner::new(&multichain, &mempool, &config);
+    miner_ctx.start();
+    miner.start(0);
+    let proposed = |message: MinerMessage| match message {
+        MinerMessage::VersaBlk(VersaBlock::PropBlock(prop_block)) => prop_block
+            .get_prop_tx_set()
+            .iter()
+            .map(|x| x.hash())
+            .collect::<Vec<H256>>(),
+        _ => panic!("expect a proposer block"),
+    };
+    let first = finished_block_chan.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(proposed(first).is_empty());
+
+    //the block found retires its template, an update packs the new tx block
+    let tx_blk = TransactionBlock::random();
+    mempool.lock().unwrap().insert_tx_blk(tx_blk.clone());
+    miner.update();
+    let second = finished_block_chan.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(proposed(second), vec![tx_blk.hash()]);
+    miner.exit();
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+use crate::optchain::network::{
+    message::Message,
+    misbehavior::{ban_key, BanList, Misbehavior, Score, BAN_THRESHOLD, SCORE_DECAY_INTERVAL},
+};
+#[cfg(test)]
+use std::{net, thread, time::{Duration, Instant}};
+
+#[test]
+fn message_decode_rejects_garbage() {
+    let msg = Message::Ping(String::from("nonce"));
+    let bytes = bincode::serialize(&msg).unwrap();
+    assert!(matches!(Message::decode(&bytes), Ok(Message::Ping(n)) if n == "nonce"));
+
+    assert!(Message::decode(&[]).is_err());
+    assert!(Message::decode(&[255, 255, 255, 255]).is_err());
+    //a vector claiming u64::MAX elements must not be allocated
+    let mut bytes = bincode::serialize(&Message::NewTxBlockHash(vec![])).unwrap();
+    let len = bytes.len();
+    bytes[len - 8..].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(Message::decode(&bytes).is_err());
+}
+
+#[test]
+fn misbehavior_ban_list() {
+    assert_eq!(Misbehavior::OversizedMessage.score(), BAN_THRESHOLD);
+    assert!(Misbehavior::UnsolicitedData.score() < BAN_THRESHOLD);
+
+    let loopback: net::SocketAddr = "127.0.0.1:6000".parse().unwrap();
+    let remote: net::IpAddr = "10.0.0.1".parse().unwrap();
+    assert_eq!(ban_key(&loopback, loopback.ip()), loopback);
+    assert_eq!(ban_key(&loopback, remote), net::SocketAddr::new(remote, 0));
+
+    let mut ban_list = BanList::new();
+    ban_list.ban(loopback, Duration::from_millis(50));
+    assert!(ban_list.is_banned(&loopback));
+    assert!(!ban_list.is_banned(&"127.0.0.1:6001".parse().unwrap()));
+    thread::sleep(Duration::from_millis(60));
+    assert!(!ban_list.is_banned(&loopback));
+}
+
+#[test]
+fn misbehavior_score_decay() {
+    let start = Instant::now();
+    let mut score = Score::new(start);
+    assert_eq!(score.add(50, start), 50);
+    assert_eq!(score.get(start + SCORE_DECAY_INTERVAL * 10), 40);
+    //a part of an interval is not lost when points are added
+    let later = start + SCORE_DECAY_INTERVAL * 10 + SCORE_DECAY_INTERVAL / 2;
+    assert_eq!(score.add(2, later), 42);
+    assert_eq!(score.get(start + SCORE_DECAY_INTERVAL * 11), 41);
+    //a quiet peer is forgiven
+    let quiet = start + SCORE_DECAY_INTERVAL * 1000;
+    assert_eq!(score.get(quiet), 0);
+    assert_eq!(score.add(Misbehavior::UnsolicitedData.score(), quiet), 2);
+    assert_eq!(score.get(quiet + SCORE_DECAY_INTERVAL), 1);
+}
+
+#[test]
+fn misbehavior_invalid_pow_score() {
+    let start = Instant::now();
+    let mut score = Score::new(start);
+    //one stale block does not ban its relayer, a second one soon after does
+    assert!(score.add(Misbehavior::InvalidPow.score(), start) < BAN_THRESHOLD);
+    assert!(score.add(Misbehavior::InvalidPow.score(), start) >= BAN_THRESHOLD);
+    let mut score = Score::new(start);
+    score.add(Misbehavior::InvalidPow.score(), start);
+    let later = start + SCORE_DECAY_INTERVAL * 100;
+    assert!(score.add(Misbehavior::InvalidPow.score(), later) < BAN_THRESHOLD);
+}
@@ -1,5 +1,27 @@
 // #[cfg(test)]
 pub mod block_test;
+pub mod mempool_test;
+pub mod sync_test;
+pub mod handshake_test;
+pub mod address_book_test;
+pub mod network_test;
+pub mod misbehavior_test;
+pub mod inventory_test;
+pub mod relay_test;
+pub mod compact_test;
+pub mod link_test;
+pub mod sim_test;
+pub mod simulated_pow_test;
+pub mod miner_threads_test;
+pub mod template_test;
+pub mod hash_rate_test;
+pub mod query_test;
+pub mod rpc_test;
+pub mod metrics_test;
+pub mod events_test;
+pub mod config_test;
+pub mod genesis_test;
+pub mod wallet_test;
 //pub mod merkle_test;
 //pub mod blockchain_test;
 //pub mod multichain_test;
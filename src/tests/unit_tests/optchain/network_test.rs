@@ -0,0 +1,150 @@
+#[cfg(test)]
+use crate::optchain::{
+    configuration::Configuration,
+    metrics::Metrics,
+    network::{
+        handshake::Handshake,
+        link::LinkEmulation,
+        message::{Message, MAX_MESSAGE_SIZE},
+        peer,
+        server,
+    },
+};
+#[cfg(test)]
+use futures::io::AsyncWriteExt;
+#[cfg(test)]
+use smol::Async;
+#[cfg(test)]
+use std::{net, sync::Arc, thread, time};
+
+//the address books of the test nodes, away from the source tree
+#[cfg(test)]
+fn test_data_dir() -> String {
+    std::env::temp_dir()
+        .join(format!("optchain-network-test-{}", std::process::id()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+fn start_server(addr: &str, node_id: usize) -> (server::Handle, smol::channel::Receiver<(Vec<u8>, peer::Handle)>) {
+    let addr: net::SocketAddr = addr.parse().unwrap();
+    let mut config = Configuration::new();
+    config.shard_num = 1;
+    config.node_id = node_id;
+    config.shard_id = 0;
+    config.data_dir = test_data_dir();
+    //keep the test away from the address book of real nodes
+    config.max_outbound = 0;
+    let handshake = Handshake::new(&config, vec![], addr);
+    let (msg_tx, msg_rx) = smol::channel::bounded(100);
+    let (ctx, handle) = server::new(addr, msg_tx, handshake, &config, &Arc::new(Metrics::new())).unwrap();
+    ctx.start().unwrap();
+    (handle, msg_rx)
+}
+
+#[test]
+fn network_peers_status() {
+    let (server_a, _rx_a) = start_server("127.0.0.1:46731", 900);
+    let (server_b, rx_b) = start_server("127.0.0.1:46732", 901);
+
+    let mut peer_a = server_b.connect("127.0.0.1:46731".parse().unwrap()).unwrap();
+    //connecting twice reuses the connection
+    server_b.connect("127.0.0.1:46731".parse().unwrap()).unwrap();
+    thread::sleep(time::Duration::from_millis(200));
+
+    let peers_a = server_a.get_peers();
+    let peers_b = server_b.get_peers();
+    assert_eq!(peers_a.len(), 1);
+    assert_eq!(peers_b.len(), 1);
+    assert_eq!(peers_a[0].direction, peer::Direction::Incoming);
+    assert_eq!(peers_a[0].node_id, 901);
+    assert_eq!(peers_b[0].direction, peer::Direction::Outgoing);
+    assert_eq!(peers_b[0].listen_addr, "127.0.0.1:46731".parse().unwrap());
+
+    peer_a.write(Message::Ping(String::from("test")));
+    server_a.broadcast(Message::Pong(String::from("test")));
+    //B receives the broadcast Pong
+    let (msg, _) = smol::block_on(rx_b.recv()).unwrap();
+    assert!(matches!(bincode::deserialize(&msg).unwrap(), Message::Pong(_)));
+    thread::sleep(time::Duration::from_millis(200));
+
+    let peers_a = server_a.get_peers();
+    let peers_b = server_b.get_peers();
+    assert!(peers_a[0].bytes_in > 0);
+    assert_eq!(peers_a[0].bytes_out, peers_b[0].bytes_in);
+    assert!(peers_b[0].last_seen > 0);
+}
+
+#[test]
+fn network_ban_oversized_frame() {
+    let (server_a, _rx_a) = start_server("127.0.0.1:46733", 902);
+    let mut config = Configuration::new();
+    config.shard_num = 1;
+    let listen_addr: net::SocketAddr = "127.0.0.1:46734".parse().unwrap();
+    let handshake = Handshake::new(&config, vec![], listen_addr);
+    smol::block_on(async {
+        let mut stream = Async::<net::TcpStream>::connect(server_a_addr()).await.unwrap();
+        handshake.write_to(&mut stream).await.unwrap();
+        Handshake::read_from(&mut stream).await.unwrap();
+        stream.write_all(&(MAX_MESSAGE_SIZE as u32 + 1).to_be_bytes()).await.unwrap();
+        stream.flush().await.unwrap();
+    });
+    thread::sleep(time::Duration::from_millis(200));
+    assert!(server_a.get_peers().is_empty());
+
+    //the same node is refused during the ban
+    smol::block_on(async {
+        let mut stream = Async::<net::TcpStream>::connect(server_a_addr()).await.unwrap();
+        handshake.write_to(&mut stream).await.unwrap();
+        assert!(Handshake::read_from(&mut stream).await.is_err());
+    });
+}
+
+#[cfg(test)]
+fn server_a_addr() -> net::SocketAddr {
+    "127.0.0.1:46733".parse().unwrap()
+}
+
+#[test]
+fn network_link_emulation_delay() {
+    let addr_a: net::SocketAddr = "127.0.0.1:46735".parse().unwrap();
+    let mut config = Configuration::new();
+    config.shard_num = 1;
+    config.node_id = 903;
+    config.data_dir = test_data_dir();
+    config.max_outbound = 0;
+    config.link_emulation = Some(LinkEmulation {
+        propagation_delay: 300,
+        ..Default::default()
+    });
+    let (msg_tx, _rx_a) = smol::channel::bounded(100);
+    let (ctx, server_a) = server::new(addr_a, msg_tx, Handshake::new(&config, vec![], addr_a), &config, &Arc::new(Metrics::new())).unwrap();
+    ctx.start().unwrap();
+    let (server_b, rx_b) = start_server("127.0.0.1:46736", 904);
+    server_b.connect(addr_a).unwrap();
+    thread::sleep(time::Duration::from_millis(200));
+
+    //the messages sent by A reach B after the emulated delay
+    let sent_at = time::Instant::now();
+    server_a.broadcast(Message::Pong(String::from("delayed")));
+    let (msg, _) = smol::block_on(rx_b.recv()).unwrap();
+    assert!(matches!(bincode::deserialize(&msg).unwrap(), Message::Pong(_)));
+    assert!(sent_at.elapsed() >= time::Duration::from_millis(300));
+}
+
+#[test]
+fn network_silent_handshake() {
+    let (server_a, _rx_a) = start_server("127.0.0.1:46737", 905);
+    let (server_b, _rx_b) = start_server("127.0.0.1:46738", 906);
+    //a connection that never sends its handshake
+    let _silent = net::TcpStream::connect("127.0.0.1:46737").unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+
+    //does not hold up the other peers
+    let started_at = time::Instant::now();
+    server_b.connect("127.0.0.1:46737".parse().unwrap()).unwrap();
+    assert!(started_at.elapsed() < time::Duration::from_secs(2));
+    thread::sleep(time::Duration::from_millis(200));
+    assert_eq!(server_a.get_peers().len(), 1);
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+use crate::{
+    types::{
+        hash::{H256, Hashable},
+        random::Random,
+    },
+    optchain::{
+        api::query::{self, BlockType},
+        block::transaction_block::TransactionBlock,
+        configuration::Configuration,
+        miner::{BlockKind, simulated::SimulatedPow},
+        multichain::ChainType,
+        simulator::{Simulation, SimConfig},
+    },
+};
+
+#[cfg(test)]
+fn mined_simulation() -> Simulation {
+    let mut config = Configuration::new();
+    config.shard_num = 2;
+    config.shard_size = 2;
+    config.block_size = 4;
+    config.symbol_size = 2;
+    config.num_symbol_per_block = 2;
+    config.prop_size = 4;
+    config.avai_size = 4;
+    //every kind of block is in reach of a few nonces
+    "tx=1,prop=1,order=1,avai=1,inAvai=1".parse::<SimulatedPow>().unwrap().apply_targets(&mut config);
+    let mut sim = Simulation::new(&SimConfig { num_nodes: 2, ..Default::default() }, &config);
+    sim.connect_all();
+    for _ in 0..3 {
+        sim.mine(0, BlockKind::Prop).unwrap();
+        sim.run_for(200);
+    }
+    sim.mine(1, BlockKind::Order).unwrap();
+    sim.run_for(200);
+    sim
+}
+
+#[test]
+fn query_parse_params() {
+    let hash = H256::random();
+    assert_eq!(query::parse_hash(&hash.to_string()), Ok(hash));
+    assert!(query::parse_hash("zz").is_err());
+    assert!(query::parse_hash("abcd").is_err());
+
+    assert_eq!(query::parse_chain("proposer", None), Ok(ChainType::Proposer));
+    assert_eq!(query::parse_chain("availability", Some("1")), Ok(ChainType::Availability(1)));
+    assert!(query::parse_chain("availability", None).is_err());
+    assert!(query::parse_chain("availability", Some("x")).is_err());
+    assert!(query::parse_chain("main", None).is_err());
+}
+
+#[test]
+fn query_blocks_and_chains() {
+    let sim = mined_simulation();
+    let multichain = sim.node(1).get_multichain().lock().unwrap();
+
+    let tips = query::get_tips(&multichain);
+    assert_eq!(tips.len(), 4);
+    assert_eq!(tips[0].chain, ChainType::Proposer);
+    assert_eq!(tips[0].height, 3);
+    assert_eq!(tips[3].chain, ChainType::Ordering);
+    assert_eq!(tips[3].height, 1);
+
+    let page = query::get_chain_page(&multichain, &ChainType::Proposer, 1, 2).unwrap();
+    assert_eq!(page.height, 3);
+    let heights: Vec<usize> = page.blocks.iter().map(|x| x.height).collect();
+    assert_eq!(heights, vec![1, 2]);
+    assert!(query::get_chain_page(&multichain, &ChainType::Availability(5), 0, 10).is_err());
+
+    let tip = query::parse_hash(&tips[0].hash).unwrap();
+    let view = query::get_block(&multichain, &tip).unwrap();
+    assert_eq!(view.block_type, BlockType::Proposer);
+    assert_eq!(view.positions.len(), 1);
+    assert_eq!(view.positions[0].height, 3);
+    assert!(view.positions[0].in_longest_chain);
+    assert_eq!(view.header.prop_parent, page.blocks[1].hash);
+
+    let order_tip = query::parse_hash(&tips[3].hash).unwrap();
+    let view = query::get_block(&multichain, &order_tip).unwrap();
+    assert_eq!(view.block_type, BlockType::Ordering);
+    assert!(view.tx_blocks.is_empty());
+    assert!(query::get_block(&multichain, &H256::random()).is_none());
+
+    //the schemas the dashboards rely on
+    let json = serde_json::to_value(&view).unwrap();
+    assert_eq!(json["block_type"], "ordering");
+    assert_eq!(json["positions"][0]["chain"], "Ordering");
+    assert_eq!(json["header"]["order_parent"].as_str().unwrap().len(), 64);
+    let json = serde_json::to_value(&query::get_tips(&multichain)[1]).unwrap();
+    assert_eq!(json["chain"]["Availability"], 0);
+}
+
+#[test]
+fn query_tx_blocks_and_symbols() {
+    let sim = mined_simulation();
+    let node = sim.node(0);
+    let tx_blk = TransactionBlock::random();
+    node.get_mempool().lock().unwrap().insert_tx_blk(tx_blk.clone());
+    let view = query::get_tx_block(node.get_multichain(), node.get_mempool(), &tx_blk.hash()).unwrap();
+    assert!(view.in_mempool);
+    assert!(!view.in_longest_proposer_chain);
+    assert_eq!(view.nonce, tx_blk.get_nonce());
+    assert!(query::get_tx_block(node.get_multichain(), node.get_mempool(), &H256::random()).is_none());
+
+    let cmt_root = H256::random();
+    let mut symbolpool = node.get_symbolpool().lock().unwrap();
+    assert!(query::get_symbol_status(&symbolpool, &cmt_root).is_none());
+    symbolpool.request_symbols(&cmt_root, 1, vec![0, 1]).unwrap();
+    let status = query::get_symbol_status(&symbolpool, &cmt_root).unwrap();
+    assert_eq!(status.shard_id, Some(1));
+    assert_eq!(status.requested, vec![0, 1]);
+    assert_eq!(status.missing, vec![0, 1]);
+    assert!(!status.available);
+}
@@ -0,0 +1,39 @@
+#[cfg(test)]
+use crate::optchain::network::relay::{RelayClass, RelayPolicy, RelayScope};
+
+#[test]
+fn relay_policy_targets() {
+    let policy = RelayPolicy::shard_aware();
+    //tx blocks reach every peer a compact block carrying them is sent to
+    assert_eq!(policy.target_shard(&RelayClass::TxBlock(2)), None);
+    assert_eq!(policy.target_shard(&RelayClass::Proposer), None);
+    assert_eq!(policy.target_shard(&RelayClass::Ordering), None);
+    assert_eq!(policy.target_shard(&RelayClass::ExAvailability(Some(1))), Some(1));
+    assert_eq!(policy.target_shard(&RelayClass::InAvailability(Some(1))), None);
+    assert_eq!(policy.target_shard(&RelayClass::Symbol(Some(3))), Some(3));
+    //an item of unknown shard is flooded
+    assert_eq!(policy.target_shard(&RelayClass::Symbol(None)), None);
+
+    let flood = RelayPolicy::flood();
+    assert_eq!(flood.target_shard(&RelayClass::TxBlock(2)), None);
+    assert_eq!(flood.target_shard(&RelayClass::ExAvailability(Some(1))), None);
+}
+
+#[test]
+fn relay_policy_parse() {
+    assert_eq!("flood".parse::<RelayPolicy>().unwrap(), RelayPolicy::flood());
+    assert_eq!("shard".parse::<RelayPolicy>().unwrap(), RelayPolicy::shard_aware());
+
+    let policy: RelayPolicy = "shard, symbol=all, inAvai=origin".parse().unwrap();
+    assert_eq!(policy.symbol, RelayScope::All);
+    assert_eq!(policy.in_availability, RelayScope::Origin);
+    assert_eq!(policy.tx_block, RelayScope::All);
+    let policy: RelayPolicy = "shard,tx=origin".parse().unwrap();
+    assert_eq!(policy.target_shard(&RelayClass::TxBlock(2)), Some(2));
+    assert_eq!(RelayPolicy::default(), RelayPolicy::flood());
+
+    assert!("gossip".parse::<RelayPolicy>().is_err());
+    assert!("shard,symbol".parse::<RelayPolicy>().is_err());
+    assert!("shard,blocks=all".parse::<RelayPolicy>().is_err());
+    assert!("shard,symbol=some".parse::<RelayPolicy>().is_err());
+}
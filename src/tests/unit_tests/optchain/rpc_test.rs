@@ -0,0 +1,223 @@
+#[cfg(test)]
+use crate::{
+    types::{
+        hash::{H256, Hashable},
+        random::Random,
+        key_pair,
+        jsonrpc::{self, Params, RpcError, METHOD_NOT_FOUND, INVALID_PARAMS, INVALID_REQUEST, PARSE_ERROR, NOT_FOUND, REJECTED},
+    },
+    optchain::{
+        new_multichain,
+        api::rpc::{self, RpcContext},
+        block::{Content, transaction_block::TransactionBlock},
+        configuration::Configuration,
+        mempool::Mempool,
+        miner::{self, template::TemplatePool},
+        network::{
+            server::{self, ControlSignal},
+            sync::SyncManager,
+        },
+        symbolpool::SymbolPool,
+        transaction::{Transaction, TxFlag},
+    },
+    wallet::keystore::address_of,
+};
+#[cfg(test)]
+use ring::signature::KeyPair;
+#[cfg(test)]
+use serde_json::{json, Value};
+#[cfg(test)]
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+#[cfg(test)]
+fn echo(method: &str, params: &Params) -> Result<Value, RpcError> {
+    match method {
+        "echo" => Ok(params.require::<Value>("value", 0)?),
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, "not found")),
+    }
+}
+
+#[cfg(test)]
+fn call(body: &str) -> Value {
+    serde_json::from_str(&jsonrpc::handle(body, echo).unwrap()).unwrap()
+}
+
+#[test]
+fn rpc_single_and_batch() {
+    let resp = call(r#"{"jsonrpc":"2.0","method":"echo","params":[7],"id":1}"#);
+    assert_eq!(resp, json!({"jsonrpc": "2.0", "result": 7, "id": 1}));
+    let resp = call(r#"{"jsonrpc":"2.0","method":"echo","params":{"value":"x"},"id":"a"}"#);
+    assert_eq!(resp["result"], "x");
+    assert_eq!(resp["id"], "a");
+
+    let resp = call(r#"[
+        {"jsonrpc":"2.0","method":"echo","params":[1],"id":1},
+        {"jsonrpc":"2.0","method":"echo","params":[2]},
+        {"jsonrpc":"2.0","method":"nope","id":3}
+    ]"#);
+    let resp = resp.as_array().unwrap();
+    //the notification gets no response
+    assert_eq!(resp.len(), 2);
+    assert_eq!(resp[0]["result"], 1);
+    assert_eq!(resp[1]["error"]["code"], METHOD_NOT_FOUND);
+    assert_eq!(resp[1]["id"], 3);
+    assert!(jsonrpc::handle(r#"[{"jsonrpc":"2.0","method":"echo","params":[1]}]"#, echo).is_none());
+}
+
+#[test]
+fn rpc_error_codes() {
+    assert_eq!(call("{").get("error").unwrap()["code"], PARSE_ERROR);
+    assert_eq!(call("[]")["error"]["code"], INVALID_REQUEST);
+    assert_eq!(call(r#"{"method":"echo","id":1}"#)["error"]["code"], INVALID_REQUEST);
+    assert_eq!(call(r#"{"jsonrpc":"2.0","method":"echo","params":3,"id":1}"#)["error"]["code"], INVALID_REQUEST);
+    assert_eq!(call(r#"{"jsonrpc":"2.0","method":"echo","id":[1]}"#)["id"], Value::Null);
+    let resp = call(r#"[1]"#);
+    assert_eq!(resp[0]["error"]["code"], INVALID_REQUEST);
+    let resp = call(r#"{"jsonrpc":"2.0","method":"echo","params":[],"id":2}"#);
+    assert_eq!(resp["error"]["code"], INVALID_PARAMS);
+    assert_eq!(resp["id"], 2);
+    assert!(resp.get("result").is_none());
+}
+
+#[cfg(test)]
+fn test_context() -> (RpcContext, smol::channel::Receiver<ControlSignal>) {
+    let mut config = Configuration::new();
+    config.shard_id = 1;
+    config.shard_num = 2;
+    config.shard_size = 2;
+    config.block_size = 4;
+    config.symbol_size = 2;
+    config.num_symbol_per_block = 2;
+    config.prop_size = 4;
+    config.avai_size = 4;
+    //no hash is a block
+    config.tx_diff = [0u8; 32].into();
+    config.prop_diff = [0u8; 32].into();
+    config.order_diff = [0u8; 32].into();
+    config.avai_diff = [0u8; 32].into();
+    config.in_avai_diff = [0u8; 32].into();
+    let multichain = Arc::new(Mutex::new(new_multichain(&config)));
+    let mempool = Arc::new(Mutex::new(Mempool::new(&config)));
+    let (_, miner, _) = miner::new(&multichain, &mempool, &config);
+    let (control_chan, network) = server::new_detached();
+    let ctx = RpcContext {
+        miner,
+        network,
+        multichain,
+        mempool,
+        symbolpool: Arc::new(Mutex::new(SymbolPool::new(&config))),
+        sync_manager: Arc::new(Mutex::new(SyncManager::new())),
+        templates: Arc::new(Mutex::new(TemplatePool::new())),
+        config,
+    };
+    (ctx, control_chan)
+}
+
+#[cfg(test)]
+fn rpc(ctx: &RpcContext, method: &str, params: Value) -> Value {
+    let body = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1}).to_string();
+    let resp = jsonrpc::handle(&body, |method, params| rpc::dispatch(ctx, method, params)).unwrap();
+    serde_json::from_str(&resp).unwrap()
+}
+
+#[test]
+fn rpc_optchain_methods() {
+    let (ctx, control_chan) = test_context();
+    let tips = rpc(&ctx, "chain_getTips", json!([]));
+    assert_eq!(tips["result"].as_array().unwrap().len(), 4);
+    let genesis = tips["result"][0]["hash"].clone();
+    let block = rpc(&ctx, "chain_getBlock", json!({"hash": genesis}));
+    assert_eq!(block["result"]["block_type"], "proposer");
+    let resp = rpc(&ctx, "chain_getBlock", json!([H256::random().to_string()]));
+    assert_eq!(resp["error"]["code"], NOT_FOUND);
+    let resp = rpc(&ctx, "chain_getBlock", json!(["xyz"]));
+    assert_eq!(resp["error"]["code"], INVALID_PARAMS);
+    let page = rpc(&ctx, "chain_getChain", json!({"chain": "availability", "shard_id": 1}));
+    assert_eq!(page["result"]["chain"]["Availability"], 1);
+    assert_eq!(rpc(&ctx, "net_ping", json!([]))["result"], true);
+    assert!(matches!(control_chan.try_recv(), Ok(ControlSignal::BroadcastMessage(_))));
+    assert_eq!(rpc(&ctx, "no_such_method", json!([]))["error"]["code"], METHOD_NOT_FOUND);
+
+    //a tx block without the work of a tx block is refused, as from a peer
+    let tx_blk = TransactionBlock::random();
+    let raw = hex::encode(bincode::serialize(&tx_blk).unwrap());
+    assert_eq!(rpc(&ctx, "tx_submit", json!([raw]))["error"]["code"], REJECTED);
+
+    //a tx block submitted in bincode is in the mempool and relayed
+    let mut tx_ctx = ctx.clone();
+    //every hash is a tx block
+    tx_ctx.config.tx_diff = H256::default();
+    let resp = rpc(&tx_ctx, "tx_submit", json!({"raw": raw}));
+    assert_eq!(resp["result"], tx_blk.hash().to_string());
+    assert!(matches!(control_chan.try_recv(), Ok(ControlSignal::Relay(_))));
+    assert_eq!(rpc(&ctx, "mempool_status", json!([]))["result"]["inserted"], 1);
+    let resp = rpc(&tx_ctx, "tx_submit", json!([raw]));
+    assert_eq!(resp["error"]["code"], REJECTED);
+    let tx = rpc(&ctx, "chain_getTxBlock", json!([tx_blk.hash().to_string()]));
+    assert_eq!(tx["result"]["in_mempool"], true);
+
+    let info = rpc(&ctx, "node_getInfo", json!([]));
+    assert_eq!(info["result"]["protocol"], "optchain");
+    assert_eq!(info["result"]["shard_id"], 1);
+
+    //a signed tx is pending until the miner packs it
+    let key = key_pair::random();
+    let addr = address_of(key.public_key().as_ref());
+    let funding = Transaction::create_initial_tx((&addr, &key), 5);
+    let tx = Transaction::consume(vec![(&funding, 0)], vec![(&addr, &key)], vec![(&addr, &key, 5)], TxFlag::Domestic, "optchain").unwrap();
+    let raw = hex::encode(bincode::serialize(&tx).unwrap());
+    let resp = rpc(&ctx, "tx_submitTransaction", json!([raw]));
+    assert_eq!(resp["result"], tx.hash().to_string());
+    assert_eq!(rpc(&ctx, "tx_submitTransaction", json!([raw]))["error"]["code"], REJECTED);
+    let mut unbalanced = tx.clone();
+    unbalanced.outputs[0].value = 6;
+    let raw = hex::encode(bincode::serialize(&unbalanced).unwrap());
+    assert_eq!(rpc(&ctx, "tx_submitTransaction", json!([raw]))["error"]["code"], REJECTED);
+    assert_eq!(rpc(&ctx, "tx_submitTransaction", json!(["zz"]))["error"]["code"], INVALID_PARAMS);
+    assert_eq!(rpc(&ctx, "mempool_status", json!([]))["result"]["pending_txs"], 1);
+    let block = miner::assemble_block(&ctx.multichain, &ctx.mempool, &ctx.config);
+    assert_eq!(block.get_txs()[0], tx);
+    assert_eq!(block.get_txs().len(), ctx.config.num_symbol_per_block * ctx.config.symbol_size);
+
+    let template = rpc(&ctx, "miner_getTemplate", json!([]));
+    let id = template["result"]["id"].clone();
+    let resp = rpc(&ctx, "miner_submit", json!({"template": id, "nonce": 1}));
+    assert_eq!(resp["error"]["code"], REJECTED);
+}
+
+#[cfg(test)]
+fn http_request(request: &str) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let addr = server.server_addr();
+    let handle = thread::spawn(move || {
+        let req = server.recv().unwrap();
+        jsonrpc::serve(req, echo);
+    });
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).unwrap();
+    handle.join().unwrap();
+    resp
+}
+
+#[test]
+fn rpc_post_only() {
+    let resp = http_request("GET /rpc HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+    assert!(resp.starts_with("HTTP/1.1 405"));
+    assert!(resp.contains("Allow: POST"));
+
+    let body = r#"{"jsonrpc":"2.0","method":"echo","params":[7],"id":1}"#;
+    let resp = http_request(&format!(
+        "POST /rpc HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body,
+    ));
+    assert!(resp.starts_with("HTTP/1.1 200"));
+    assert!(resp.ends_with(r#"{"id":1,"jsonrpc":"2.0","result":7}"#));
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+use crate::{
+    types::hash::{H256, Hashable},
+    optchain::{
+        configuration::Configuration,
+        miner::{BlockKind, simulated::SimulatedPow},
+        multichain::ChainType,
+        simulator::{Simulation, SimConfig},
+    },
+};
+#[cfg(test)]
+use std::collections::HashSet;
+
+#[cfg(test)]
+fn base_config() -> Configuration {
+    let mut config = Configuration::new();
+    config.shard_num = 2;
+    config.shard_size = 2;
+    config.block_size = 4;
+    config.symbol_size = 2;
+    config.num_symbol_per_block = 2;
+    config.prop_size = 4;
+    config.avai_size = 4;
+    config.ex_req_num = 1;
+    config.in_req_num = 1;
+    //every kind of block is in reach of a few nonces
+    "tx=1,prop=1,order=1,avai=1,inAvai=1".parse::<SimulatedPow>().unwrap().apply_targets(&mut config);
+    config
+}
+
+#[cfg(test)]
+fn heights(sim: &Simulation, chain: &ChainType) -> Vec<usize> {
+    (0..sim.num_nodes())
+        .map(|i| sim.node(i).get_height(chain))
+        .collect()
+}
+
+#[test]
+fn sim_blocks_propagate() {
+    let mut sim = Simulation::new(&SimConfig::default(), &base_config());
+    sim.connect_all();
+    for _ in 0..3 {
+        sim.mine(0, BlockKind::Prop).unwrap();
+        sim.run_for(500);
+    }
+    sim.mine(1, BlockKind::Order).unwrap();
+    sim.run_for(500);
+    assert_eq!(heights(&sim, &ChainType::Proposer), vec![3; 4]);
+    assert_eq!(heights(&sim, &ChainType::Ordering), vec![1; 4]);
+    assert!(sim.now() >= 2000);
+    assert!(sim.node(2).get_misbehaviors().is_empty());
+
+    //a node only reached through another one gets the blocks too
+    let mut sim = Simulation::new(&SimConfig { num_nodes: 3, ..Default::default() }, &base_config());
+    sim.connect(0, 1);
+    sim.connect(1, 2);
+    sim.mine(0, BlockKind::Prop).unwrap();
+    sim.run_for(50);
+    assert_eq!(heights(&sim, &ChainType::Proposer), vec![1, 1, 0]);
+    sim.run_for(500);
+    assert_eq!(heights(&sim, &ChainType::Proposer), vec![1, 1, 1]);
+}
+
+#[test]
+fn sim_partition_heal() {
+    let mut sim = Simulation::new(&SimConfig::default(), &base_config());
+    sim.connect_all();
+    sim.partition(&[vec![0, 1], vec![2, 3]]);
+    sim.mine(0, BlockKind::Prop).unwrap();
+    sim.run_for(500);
+    sim.mine(1, BlockKind::Prop).unwrap();
+    sim.run_for(500);
+    sim.mine(2, BlockKind::Prop).unwrap();
+    sim.run_for(500);
+    assert_eq!(heights(&sim, &ChainType::Proposer), vec![2, 2, 1, 1]);
+
+    //the next block brings its missing ancestors to the other side
+    sim.heal();
+    sim.mine(0, BlockKind::Prop).unwrap();
+    sim.run_for(2000);
+    assert_eq!(heights(&sim, &ChainType::Proposer), vec![3; 4]);
+    let tips: Vec<H256> = (0..4)
+        .map(|i| sim.node(i).get_multichain().lock().unwrap().get_highest_prop_block())
+        .collect();
+    assert!(tips.iter().all(|tip| *tip == tips[0]));
+}
+
+#[test]
+fn sim_adopted_side_block_references() {
+    let mut sim = Simulation::new(&SimConfig { num_nodes: 3, ..Default::default() }, &base_config());
+    sim.connect_all();
+    sim.partition(&[vec![0, 1], vec![2]]);
+    sim.mine(1, BlockKind::Tx).unwrap();
+    sim.run_for(500);
+    let tx_blk = sim.node(1).get_mempool().lock().unwrap().peek_tx_blks(1, &HashSet::new());
+    let hash = tx_blk[0].hash();
+    assert!(sim.node(0).get_mempool().lock().unwrap().check(&hash));
+
+    //node 0 follows a block of node 2 which does not know the tx block
+    sim.partition(&[vec![0, 2], vec![1]]);
+    sim.mine(2, BlockKind::Prop).unwrap();
+    sim.mine(1, BlockKind::Prop).unwrap();
+    sim.mine(1, BlockKind::Prop).unwrap();
+    sim.run_for(500);
+    assert!(sim.node(0).get_mempool().lock().unwrap().check(&hash));
+
+    //the block of node 1 referencing it is a side block when node 0 inserts it,
+    //the reorg onto its children adopts it
+    sim.heal();
+    sim.mine(1, BlockKind::Prop).unwrap();
+    sim.run_for(2000);
+    assert_eq!(heights(&sim, &ChainType::Proposer), vec![3; 3]);
+    let mempool = sim.node(0).get_mempool().lock().unwrap();
+    assert!(!mempool.check(&hash));
+    assert!(mempool.is_referenced(&hash));
+}
+
+#[test]
+fn sim_same_seed_same_run() {
+    let mut config = base_config();
+    "tx=20,prop=6,order=2,avai=1,inAvai=1".parse::<SimulatedPow>().unwrap().apply_targets(&mut config);
+    let run = |seed: u64| {
+        let sim_config = SimConfig {
+            seed,
+            jitter: 40,
+            loss: 0.05,
+            mining_interval: 200,
+            ..Default::default()
+        };
+        let mut sim = Simulation::new(&sim_config, &config);
+        sim.connect_all();
+        sim.run_for(5000);
+        (0..sim.num_nodes())
+            .map(|i| {
+                let multichain = sim.node(i).get_multichain().lock().unwrap();
+                (
+                    multichain.get_highest_prop_block(),
+                    multichain.get_highest_order_block(),
+                    multichain.get_chain_height(&ChainType::Proposer).unwrap_or(0),
+                )
+            })
+            .collect::<Vec<(H256, H256, usize)>>()
+    };
+    let tips = run(7);
+    assert!(tips.iter().any(|(_, _, height)| *height > 0));
+    assert_eq!(run(7), tips);
+    assert_ne!(run(8), tips);
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+use crate::{
+    types::hash::H256,
+    optchain::{
+        configuration::Configuration,
+        miner::{
+            sortition,
+            BlockKind,
+            simulated::SimulatedPow,
+        },
+    },
+};
+#[cfg(test)]
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+#[test]
+fn simulated_pow_from_str() {
+    let sim_pow: SimulatedPow = "power=0.25,tx=20,prop=1,order=1,avai=2,inAvai=0.5".parse().unwrap();
+    assert_eq!(sim_pow.hash_power, 0.25);
+    assert_eq!(sim_pow.tx_rate, 20.0);
+    assert_eq!(sim_pow.in_avai_rate, 0.5);
+    assert!((sim_pow.get_rate() - 6.125).abs() < 1e-9);
+    //the share defaults to the whole network
+    assert_eq!("prop=2".parse::<SimulatedPow>().unwrap().hash_power, 1.0);
+
+    assert!("power=0,prop=1".parse::<SimulatedPow>().is_err());
+    assert!("power=1.5,prop=1".parse::<SimulatedPow>().is_err());
+    assert!("power=0.5".parse::<SimulatedPow>().is_err());
+    assert!("prop=-1".parse::<SimulatedPow>().is_err());
+    assert!("prop".parse::<SimulatedPow>().is_err());
+    assert!("blocks=1".parse::<SimulatedPow>().is_err());
+}
+
+#[test]
+fn simulated_pow_sortition_follows_rates() {
+    let sim_pow: SimulatedPow = "tx=50,prop=20,order=10,avai=15,inAvai=5".parse().unwrap();
+    let mut config = Configuration::new();
+    sim_pow.apply_targets(&mut config);
+    let mut rng = StdRng::seed_from_u64(1);
+    let kinds = [BlockKind::Tx, BlockKind::Prop, BlockKind::Order, BlockKind::ExAvai, BlockKind::InAvai];
+    let mut counts = [0usize; 5];
+    let n = 20000;
+    for _ in 0..n {
+        let mut bytes = [0u8; 32];
+        rng.fill(&mut bytes);
+        let hash: H256 = bytes.into();
+        //every hash is a block
+        let kind = sortition(&hash, &config).unwrap();
+        counts[kinds.iter().position(|k| *k == kind).unwrap()] += 1;
+    }
+    for (count, expected) in counts.iter().zip([0.5, 0.2, 0.1, 0.15, 0.05]) {
+        assert!((*count as f64 / n as f64 - expected).abs() < 0.02);
+    }
+}
+
+#[test]
+fn simulated_pow_arrivals() {
+    let sim_pow: SimulatedPow = "power=0.5,prop=4".parse().unwrap();
+    let mut rng = StdRng::seed_from_u64(2);
+    let n = 20000;
+    let total: f64 = (0..n)
+        .map(|_| sim_pow.next_arrival(&mut rng).as_secs_f64())
+        .sum();
+    //a node with half of the hash power mines 2 blocks per second
+    assert!((total / n as f64 - 0.5).abs() < 0.02);
+}
@@ -0,0 +1,220 @@
+#[cfg(test)]
+use crate::{
+    optchain::{
+        blockchain::Blockchain,
+        configuration::Configuration,
+        block::{
+            BlockHeader,
+            transaction_block::TransactionBlock,
+            proposer_block::ProposerBlock,
+            availability_block::AvailabilityBlock,
+            ordering_block::OrderingBlock,
+            versa_block::{VersaBlock, VersaHash},
+        },
+        multichain::{Multichain, ChainType},
+        network::{
+            message::Message,
+            sync::{SyncManager, SyncHeader, SyncStatus, HEADER_BATCH_SIZE, SYNC_TIMEOUT},
+        },
+    },
+    types::{
+        merkle::MerkleTree,
+        hash::{H256, Hashable},
+    },
+};
+#[cfg(test)]
+use std::{
+    net::SocketAddr,
+    time::{Instant, SystemTime},
+};
+
+#[cfg(test)]
+fn prop_block_with_parent(parent: H256) -> VersaBlock {
+    let prop_tx_set = MerkleTree::<TransactionBlock>::new(&[]);
+    let block_header = BlockHeader::create(
+        0, //shard_id
+        parent, //prop_parent,
+        H256::default(), //inter_parent,
+        vec![], //global_parent,
+        H256::default(), //order_parent,
+        prop_tx_set.root(), //prop_root,
+        H256::default(), //avai_root
+        H256::default(), //order_root
+        H256::default(), //cmt_root
+        SystemTime::now(), //timestamp
+    );
+    VersaBlock::PropBlock(ProposerBlock::new(block_header, 0, prop_tx_set))
+}
+
+#[cfg(test)]
+fn build_chain(len: usize) -> (Blockchain, Vec<VersaBlock>) {
+    let mut config = Configuration::new();
+    config.shard_id = 98;
+    let mut blockchain = Blockchain::new(VersaBlock::PropBlock(ProposerBlock::default()), &config);
+    let mut blocks: Vec<VersaBlock> = vec![];
+    for _ in 0..len {
+        let parent = blockchain.tip();
+        let block = prop_block_with_parent(parent);
+        blockchain.insert_block_with_parent(block.clone(), &parent).expect("insert failure");
+        blocks.push(block);
+    }
+    (blockchain, blocks)
+}
+
+#[test]
+fn sync_block_locator() {
+    let (blockchain, blocks) = build_chain(40);
+    let path = blockchain.all_blocks_in_longest_chain();
+    let locator = blockchain.get_block_locator();
+    assert_eq!(locator[0], blockchain.tip());
+    assert_eq!(locator.last().unwrap(), &path[0]);
+    //the first ten entries are consecutive, then the step doubles
+    assert_eq!(locator[9], path[31]);
+    assert_eq!(locator[10], path[29]);
+    assert_eq!(locator[11], path[25]);
+    assert!(locator.len() < path.len());
+
+    //a peer knowing the first 30 blocks gets the 10 following ones
+    let peer_locator = vec![H256::default(), blocks[29].hash()];
+    let after: Vec<H256> = blockchain
+        .get_blocks_after(&peer_locator, 500)
+        .iter()
+        .map(|b| b.hash())
+        .collect();
+    let expected: Vec<H256> = blocks[30..].iter().map(|b| b.hash()).collect();
+    assert_eq!(after, expected);
+    //an unknown locator starts after the genesis block
+    assert_eq!(blockchain.get_blocks_after(&[], 5).len(), 5);
+}
+
+//every pow hash is a proposer block
+#[cfg(test)]
+fn prop_config() -> Configuration {
+    let mut config = Configuration::new();
+    config.order_diff = [0u8; 32].into();
+    config.avai_diff = [0u8; 32].into();
+    config.in_avai_diff = [0u8; 32].into();
+    config
+}
+
+#[cfg(test)]
+fn sync_multichain(node_id: usize) -> Multichain {
+    let mut config = Configuration::new();
+    config.shard_id = 0;
+    config.node_id = node_id;
+    config.shard_num = 1;
+    Multichain::new(
+        Blockchain::new(VersaBlock::PropBlock(ProposerBlock::default()), &config),
+        vec![Blockchain::new(VersaBlock::ExAvaiBlock(AvailabilityBlock::default()), &config)],
+        Blockchain::new(VersaBlock::OrderBlock(OrderingBlock::default()), &config),
+        &config,
+    )
+}
+
+#[test]
+fn sync_check_headers() {
+    let (_, blocks) = build_chain(5);
+    let headers: Vec<SyncHeader> = blocks.iter().map(SyncHeader::from_block).collect();
+    let chain = ChainType::Proposer;
+    let config = prop_config();
+    assert!(SyncManager::check_headers(&chain, &headers, true, &config).is_ok());
+    assert!(SyncManager::check_headers(&chain, &headers, false, &config).is_err());
+    assert!(SyncManager::check_headers(&ChainType::Ordering, &headers, true, &config).is_err());
+
+    let unlinked: Vec<SyncHeader> = vec![headers[0].clone(), headers[2].clone()];
+    assert!(SyncManager::check_headers(&chain, &unlinked, true, &config).is_err());
+
+    //a valid pow hash above the difficulty of proposer blocks
+    let mut hard = prop_config();
+    hard.prop_diff = [0u8; 32].into();
+    assert!(SyncManager::check_headers(&chain, &headers, true, &hard).is_err());
+}
+
+#[test]
+fn sync_peer_failover() {
+    let multichain = sync_multichain(96);
+    let peers: Vec<SocketAddr> = vec!["127.0.0.1:6001".parse().unwrap(), "127.0.0.1:6002".parse().unwrap()];
+    let mut sync_manager = SyncManager::new();
+    //nobody to sync from yet
+    assert!(sync_manager.drive(&multichain, &[], Instant::now()).is_none());
+    assert!(!sync_manager.is_syncing());
+
+    //any connected peer is used
+    let (peer, requests) = sync_manager.drive(&multichain, &peers[1..], Instant::now()).unwrap();
+    assert_eq!(peer, peers[1]);
+    assert_eq!(requests.len(), 3);
+    assert_eq!(sync_manager.get_sync_peer(), Some(peers[1]));
+    assert!(sync_manager.drive(&multichain, &peers, Instant::now()).is_none());
+
+    //the stalled peer is replaced
+    let later = Instant::now() + SYNC_TIMEOUT * 2;
+    assert!(sync_manager.is_stalled(later));
+    let (peer, requests) = sync_manager.drive(&multichain, &peers, later).unwrap();
+    assert_eq!(peer, peers[0]);
+    assert_eq!(requests.len(), 3);
+    assert!(sync_manager.is_syncing());
+
+    //no peer left, the node gives up the download
+    let later = Instant::now() + SYNC_TIMEOUT * 2;
+    assert!(sync_manager.drive(&multichain, &peers, later).is_none());
+    assert!(!sync_manager.is_syncing());
+    assert!(sync_manager.is_done());
+}
+
+#[test]
+fn sync_pending_bodies() {
+    let multichain = sync_multichain(97);
+    let mut sync_manager = SyncManager::new();
+    assert!(!sync_manager.is_syncing());
+    let requests = sync_manager.start(&multichain, "127.0.0.1:6001".parse().unwrap());
+    assert_eq!(requests.len(), 3);
+    assert!(sync_manager.is_syncing());
+
+    let (_, blocks) = build_chain(3);
+    let headers: Vec<SyncHeader> = blocks.iter().map(SyncHeader::from_block).collect();
+    let missing: Vec<VersaHash> = headers.iter().map(|h| h.get_versa_hash()).collect();
+    let requests = sync_manager.on_headers(&ChainType::Proposer, 3, &headers, missing.clone());
+    assert_eq!(requests.len(), 1);
+    //unsolicited headers are ignored once the chain left the header stage
+    assert!(sync_manager.on_headers(&ChainType::Proposer, 3, &headers, missing.clone()).is_empty());
+    for chain in [ChainType::Availability(0), ChainType::Ordering] {
+        assert!(sync_manager.on_headers(&chain, 0, &[], vec![]).is_empty());
+    }
+    assert_eq!(sync_manager.get_progress(&multichain).status, SyncStatus::Bodies);
+
+    for versa_hash in missing.iter() {
+        sync_manager.on_block_inserted(versa_hash);
+    }
+    assert!(!sync_manager.is_syncing());
+    let progress = sync_manager.get_progress(&multichain);
+    assert_eq!(progress.status, SyncStatus::Synced);
+    assert_eq!(progress.chains[0].headers_received, 3);
+    assert_eq!(progress.chains[0].bodies_pending, 0);
+}
+
+#[test]
+fn sync_batches_before_bodies() {
+    let multichain = sync_multichain(95);
+    let config = prop_config();
+    let chain = ChainType::Proposer;
+    let mut sync_manager = SyncManager::new();
+    sync_manager.start(&multichain, "127.0.0.1:6001".parse().unwrap());
+
+    let (_, blocks) = build_chain(HEADER_BATCH_SIZE + 3);
+    let headers: Vec<SyncHeader> = blocks.iter().map(SyncHeader::from_block).collect();
+    let (first, second) = headers.split_at(HEADER_BATCH_SIZE);
+    assert!(sync_manager.connects(&chain, first, &multichain));
+    let missing: Vec<VersaHash> = first.iter().map(|h| h.get_versa_hash()).collect();
+    let requests = sync_manager.on_headers(&chain, headers.len(), first, missing);
+    let last = first.last().unwrap().get_versa_hash().get_hash();
+    assert!(matches!(requests.last(), Some(Message::GetHeaders((_, locator))) if locator == &vec![last]));
+
+    //none of the bodies is inserted, yet the next batch connects to the last header
+    assert!(sync_manager.connects(&chain, second, &multichain));
+    assert!(SyncManager::check_headers(&chain, second, true, &config).is_ok());
+    let missing: Vec<VersaHash> = second.iter().map(|h| h.get_versa_hash()).collect();
+    sync_manager.on_headers(&chain, headers.len(), second, missing);
+    assert_eq!(sync_manager.get_progress(&multichain).chains[0].headers_received, headers.len());
+    //a batch starting anywhere else does not
+    assert!(!sync_manager.connects(&chain, &second[1..], &multichain));
+}
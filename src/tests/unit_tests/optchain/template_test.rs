@@ -0,0 +1,98 @@
+#[cfg(test)]
+use crate::{
+    types::hash::{H256, Hashable},
+    optchain::{
+        new_multichain,
+        block::{
+            Info,
+            versa_block::VersaBlock,
+        },
+        configuration::Configuration,
+        mempool::Mempool,
+        miner::{
+            assemble_block,
+            MinerMessage,
+            template::{TemplatePool, MAX_TEMPLATES},
+        },
+    },
+};
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+fn test_config() -> Configuration {
+    let mut config = Configuration::new();
+    config.shard_id = 1;
+    config.shard_num = 2;
+    config.shard_size = 2;
+    config.block_size = 4;
+    config.symbol_size = 2;
+    config.num_symbol_per_block = 2;
+    config.prop_size = 4;
+    config.avai_size = 4;
+    config
+}
+
+#[test]
+fn template_describes_block() {
+    let config = test_config();
+    let multichain = Arc::new(Mutex::new(new_multichain(&config)));
+    let mempool = Arc::new(Mutex::new(Mempool::new(&config)));
+    let block = assemble_block(&multichain, &mempool, &config);
+    let mut pool = TemplatePool::new();
+    let template = pool.insert(block.clone(), &config);
+    assert_eq!(template.pow_base, block.hash().to_string());
+    assert_eq!(template.prop_parent, multichain.lock().unwrap().get_highest_prop_block().to_string());
+    assert_eq!(template.shard_id, 1);
+    assert_eq!(template.global_parents.len(), block.get_global_parents().len());
+    assert_eq!(template.targets.tx, config.tx_diff.to_string());
+    assert_eq!(pool.insert(block, &config).id, template.id + 1);
+}
+
+#[test]
+fn template_submit_seals_block() {
+    let mut config = test_config();
+    //every hash is a proposer block
+    config.order_diff = [0u8; 32].into();
+    config.avai_diff = [0u8; 32].into();
+    config.in_avai_diff = [0u8; 32].into();
+    let multichain = Arc::new(Mutex::new(new_multichain(&config)));
+    let mempool = Arc::new(Mutex::new(Mempool::new(&config)));
+    let block = assemble_block(&multichain, &mempool, &config);
+    let mut pool = TemplatePool::new();
+    let id = pool.insert(block.clone(), &config).id;
+
+    assert!(pool.submit(id + 1, 7, &config).is_err());
+    match pool.submit(id, 7, &config) {
+        Ok(MinerMessage::VersaBlk(VersaBlock::PropBlock(prop_block))) => {
+            assert_eq!(prop_block.get_prop_parent(), block.get_prop_parent());
+            assert_eq!(prop_block.hash(), H256::pow_hash(&block.hash(), 7));
+        }
+        _ => panic!("expect a proposer block"),
+    }
+    //a template yields a single block
+    assert!(pool.submit(id, 8, &config).is_err());
+
+    //a nonce above every target is rejected and the template kept
+    config.tx_diff = [0u8; 32].into();
+    config.prop_diff = [0u8; 32].into();
+    let id = pool.insert(block, &config).id;
+    assert!(pool.submit(id, 7, &config).is_err());
+    assert_eq!(pool.len(), 1);
+}
+
+#[test]
+fn template_pool_drops_oldest() {
+    let config = test_config();
+    let multichain = Arc::new(Mutex::new(new_multichain(&config)));
+    let mempool = Arc::new(Mutex::new(Mempool::new(&config)));
+    let block = assemble_block(&multichain, &mempool, &config);
+    let mut pool = TemplatePool::new();
+    let first = pool.insert(block.clone(), &config).id;
+    for _ in 0..MAX_TEMPLATES {
+        pool.insert(block.clone(), &config);
+    }
+    assert_eq!(pool.len(), MAX_TEMPLATES);
+    let err = pool.submit(first, 0, &config).err().unwrap();
+    assert!(err.contains("unknown or expired template"));
+}
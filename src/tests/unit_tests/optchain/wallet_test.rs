@@ -0,0 +1,105 @@
+#[cfg(test)]
+use ring::signature::{Ed25519KeyPair, KeyPair};
+#[cfg(test)]
+use crate::{
+    optchain::transaction::{Transaction, TxFlag, UtxoInput, UtxoOutput},
+    types::{
+        hash::Hashable,
+        key_pair,
+    },
+    wallet::{
+        keystore::address_of,
+        optchain::{build_transfer, parse_utxo},
+    },
+};
+
+#[cfg(test)]
+fn funded(key: &Ed25519KeyPair, values: &[usize]) -> Vec<(Transaction, u32)> {
+    let addr = address_of(key.public_key().as_ref());
+    values
+        .iter()
+        .map(|value| (Transaction::create_initial_tx((&addr, key), *value), 0))
+        .collect()
+}
+
+#[cfg(test)]
+fn raw(tx: &Transaction) -> String {
+    hex::encode(bincode::serialize(tx).unwrap())
+}
+
+#[test]
+fn utxo_parsing() {
+    let key = key_pair::random();
+    let (tx, _) = funded(&key, &[5]).remove(0);
+    let (parsed, index) = parse_utxo(&format!("{}:0", raw(&tx))).unwrap();
+    assert_eq!(parsed.hash(), tx.hash());
+    assert_eq!(index, 0);
+    //the initial tx has a single output
+    assert!(parse_utxo(&format!("{}:1", raw(&tx))).is_err());
+    assert!(parse_utxo(&raw(&tx)).is_err());
+    assert!(parse_utxo("zz:0").is_err());
+    assert!(parse_utxo("abcd:0").is_err());
+}
+
+#[test]
+fn optchain_transfer() {
+    let key = key_pair::random();
+    let payee = key_pair::random();
+    let payee_key = payee.public_key().as_ref().to_vec();
+    let utxos = funded(&key, &[3, 4]);
+
+    let tx = build_transfer(&key, &utxos, &[(payee_key.clone(), 5)], "testnet").unwrap();
+    assert_eq!(tx.inputs.len(), 2);
+    assert_eq!(tx.outputs.len(), 2);
+    assert_eq!(tx.outputs[0].receiver_addr, address_of(&payee_key));
+    assert_eq!(tx.outputs[0].value, 5);
+    assert_eq!(tx.outputs[1].receiver_addr, address_of(key.public_key().as_ref()));
+    assert_eq!(tx.outputs[1].value, 2);
+    let input_txs: Vec<&Transaction> = utxos.iter().map(|x| &x.0).collect();
+    assert!(Transaction::verify_owner(&tx, input_txs.clone(), "testnet"));
+    assert!(!Transaction::verify_owner(&tx, input_txs, "optchain"));
+
+    //the change output can be spent by the next transfer
+    let change = vec![(tx.clone(), 1)];
+    assert!(build_transfer(&key, &change, &[(payee_key.clone(), 2)], "testnet").is_ok());
+    let err = build_transfer(&key, &utxos, &[(payee_key.clone(), 8)], "testnet").unwrap_err();
+    assert!(err.contains("insufficient utxos"));
+    //the output paid to the payee is not a utxo of the key
+    let err = build_transfer(&key, &[(tx, 0)], &[(payee_key, 1)], "testnet").unwrap_err();
+    assert!(err.contains("is not paid to the key"));
+}
+
+//a rejected tx giving back 4 coins to the key, with more outputs than inputs
+#[cfg(test)]
+fn rejected(key: &Ed25519KeyPair) -> Transaction {
+    let input = UtxoInput {
+        sender_addr: address_of(key.public_key().as_ref()),
+        value: 4,
+        ..Default::default()
+    };
+    Transaction {
+        inputs: vec![input],
+        outputs: vec![UtxoOutput::default(), UtxoOutput::default()],
+        flag: TxFlag::Reject,
+    }
+}
+
+#[test]
+fn optchain_rejected_utxo() {
+    let key = key_pair::random();
+    let payee = key_pair::random().public_key().as_ref().to_vec();
+    let tx = rejected(&key);
+    //the coins of a rejected tx are its inputs, so only index 0 exists
+    assert!(parse_utxo(&format!("{}:0", raw(&tx))).is_ok());
+    assert!(parse_utxo(&format!("{}:1", raw(&tx))).unwrap_err().contains("no utxo 1"));
+    let err = build_transfer(&key, &[(tx.clone(), 1)], &[(payee.clone(), 1)], "testnet").unwrap_err();
+    assert!(err.contains("does not exist"));
+
+    //owned by the sender of the input, whatever its outputs pay
+    let spent = build_transfer(&key, &[(tx.clone(), 0)], &[(payee.clone(), 3)], "testnet").unwrap();
+    assert_eq!(spent.inputs[0].value, 4);
+    assert_eq!(spent.outputs[1].value, 1);
+    let other = key_pair::random();
+    let err = build_transfer(&other, &[(tx, 0)], &[(payee, 3)], "testnet").unwrap_err();
+    assert!(err.contains("is not paid to the key"));
+}
@@ -48,6 +48,85 @@ impl std::fmt::Debug for Address {
     }
 }
 
+//an address on chain, in the 32 bytes of a tx: 12 zero bytes then the address,
+//so the shard of an address is the one of its last bytes
+impl std::convert::From<&Address> for H256 {
+    fn from(input: &Address) -> H256 {
+        let mut buffer: [u8; 32] = [0; 32];
+        buffer[12..].copy_from_slice(&input.0);
+        buffer.into()
+    }
+}
+
+impl std::convert::From<Address> for H256 {
+    fn from(input: Address) -> H256 {
+        (&input).into()
+    }
+}
+
+//the charset and the generator of the checksum of bech32 (BIP 173)
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const CHECKSUM_LEN: usize = 6;
+const MAX_PREFIX_LEN: usize = 50;
+//the longest bech32 string, prefix included (BIP 173)
+const MAX_ADDRESS_LEN: usize = 90;
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ (*value as u32);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn expand_prefix(prefix: &str) -> Vec<u8> {
+    let mut values: Vec<u8> = prefix.bytes().map(|x| x >> 5).collect();
+    values.push(0);
+    values.extend(prefix.bytes().map(|x| x & 31));
+    values
+}
+
+fn checksum(prefix: &str, data: &[u8]) -> Vec<u8> {
+    let mut values = expand_prefix(prefix);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; CHECKSUM_LEN]);
+    let chk = polymod(&values) ^ 1;
+    (0..CHECKSUM_LEN)
+        .map(|i| ((chk >> (5 * (CHECKSUM_LEN - 1 - i))) & 31) as u8)
+        .collect()
+}
+
+//regroups the bits of data from groups of `from` bits to groups of `to` bits
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let mut result = vec![];
+    let max: u32 = (1 << to) - 1;
+    for value in data {
+        acc = (acc << from) | (*value as u32);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            result.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+        return None;
+    }
+    Some(result)
+}
+
 impl Address {
     pub fn from_public_key_bytes(bytes: &[u8; 32]) -> Address {
         let clipped_bytes: &[u8; 32] = bytes.try_into().unwrap();
@@ -58,6 +137,92 @@ impl Address {
         let address: Address = std::convert::From::from(address_slice);
         address
     }
+
+    pub fn from_public_key(public_key: &[u8]) -> Result<Address, String> {
+        let bytes: &[u8; 32] = public_key
+            .try_into()
+            .map_err(|_| format!("invalid public key of {} bytes: expected 32", public_key.len()))?;
+        Ok(Self::from_public_key_bytes(bytes))
+    }
+
+    //the address held by the 32 bytes of a tx
+    pub fn from_h256(hash: &H256) -> Result<Address, String> {
+        let bytes: [u8; 32] = hash.into();
+        if bytes[..12].iter().any(|x| *x != 0) {
+            return Err(format!("{} is not an address", hash));
+        }
+        let address: [u8; 20] = bytes[12..].try_into().unwrap();
+        Ok(address.into())
+    }
+
+    //a prefix is the chain id, e.g. testnet in testnet1qqs...
+    pub fn check_prefix(prefix: &str) -> Result<(), String> {
+        if prefix.is_empty() || prefix.len() > MAX_PREFIX_LEN {
+            return Err(format!(
+                "invalid address prefix {}: it must have 1 to {} characters",
+                prefix, MAX_PREFIX_LEN
+            ));
+        }
+        if !prefix.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+            return Err(format!(
+                "invalid address prefix {}: use lowercase letters, digits and -",
+                prefix
+            ));
+        }
+        Ok(())
+    }
+
+    //the bech32 form of the address, prefixed by the chain id
+    pub fn encode(&self, prefix: &str) -> String {
+        let mut data = convert_bits(&self.0, 8, 5, true).unwrap();
+        data.extend(checksum(prefix, &data));
+        let chars: String = data.iter().map(|x| CHARSET[*x as usize] as char).collect();
+        format!("{}1{}", prefix, chars)
+    }
+
+    //an address of the bech32 form, which must be of the chain of the prefix
+    pub fn decode(s: &str, prefix: &str) -> Result<Address, String> {
+        if s.len() > MAX_ADDRESS_LEN {
+            return Err(format!(
+                "invalid address {}: longer than {} characters",
+                s, MAX_ADDRESS_LEN
+            ));
+        }
+        if s.chars().any(|c| c.is_ascii_uppercase()) && s.chars().any(|c| c.is_ascii_lowercase()) {
+            return Err(format!("invalid address {}: mixed case", s));
+        }
+        let lower = s.to_ascii_lowercase();
+        let (hrp, chars) = lower
+            .rsplit_once('1')
+            .ok_or_else(|| format!("invalid address {}: no prefix", s))?;
+        if hrp != prefix {
+            return Err(format!(
+                "address {} is not of chain {}: expected the prefix {}1",
+                s, prefix, prefix
+            ));
+        }
+        let mut data = vec![];
+        for c in chars.bytes() {
+            match CHARSET.iter().position(|x| *x == c) {
+                Some(value) => data.push(value as u8),
+                None => return Err(format!("invalid address {}: bad character {}", s, c as char)),
+            }
+        }
+        if data.len() < CHECKSUM_LEN {
+            return Err(format!("invalid address {}: too short", s));
+        }
+        let mut values = expand_prefix(hrp);
+        values.extend_from_slice(&data);
+        if polymod(&values) != 1 {
+            return Err(format!("invalid address {}: wrong checksum", s));
+        }
+        let bytes = convert_bits(&data[..data.len() - CHECKSUM_LEN], 5, 8, false)
+            .ok_or_else(|| format!("invalid address {}: bad padding", s))?;
+        let bytes: [u8; 20] = bytes
+            .try_into()
+            .map_err(|_| format!("invalid address {}: expected 20 bytes", s))?;
+        Ok(bytes.into())
+    }
 }
 // DO NOT CHANGE THIS COMMENT, IT IS FOR AUTOGRADER. BEFORE TEST
 
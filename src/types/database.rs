@@ -30,22 +30,27 @@ impl<T> Database<T>
         options.create_if_missing(true);
         let absolute_path = format!("./DB/{}", path);
         let db = DB::open(&options, absolute_path.clone()).unwrap();
+        //an existing database may be reopened, so count what is already stored
+        let counter = db.iterator(IteratorMode::Start).count();
 
         Self {
             path: absolute_path,
             db,
             sample_data: T::default(),
-            counter: 0,
+            counter,
         }
     }
 
     pub fn insert(&mut self, hash: H256, data: T) -> Result<bool, String> {
         let serialized_key = bincode::serialize(&hash).unwrap();
         let serialized_value = bincode::serialize(&data).unwrap();
+        let existed = self.contains_key(&hash);
 
         match self.db.put(&serialized_key, &serialized_value) {
             Ok(_) => {
-                self.counter += 1;
+                if !existed {
+                    self.counter += 1;
+                }
                 Ok(true)
             }
             Err(_) => Err(String::from("Insertion fails")),
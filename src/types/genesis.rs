@@ -0,0 +1,121 @@
+use serde::{Serialize, Deserialize};
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+    fs,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use crate::types::{
+    address::Address,
+    hash::H256,
+};
+
+//the genesis of a network, from which every node builds the same genesis blocks;
+//nodes of different genesis files do not share a genesis hash, so they refuse
+//each other in the handshake
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisSpec {
+    pub chain_id: String,
+    pub shard_num: usize,
+    #[serde(default)]
+    pub timestamp: u64, //seconds since the unix epoch, of every genesis block
+    #[serde(default)]
+    pub targets: BTreeMap<String, String>, //the initial targets by option name, e.g. tx_diff
+    #[serde(default)]
+    pub allocations: Vec<Allocation>,
+}
+
+//coins owned by an address from the genesis on
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Allocation {
+    pub address: String, //the address of the public key, in the bech32 form of the chain
+    pub public_key: String, //hex of the ed25519 key spending the coins
+    pub value: u32,
+}
+
+impl Allocation {
+    //the address on chain, already checked by GenesisSpec::validate()
+    pub fn get_address(&self) -> H256 {
+        let public_key = self.get_public_key();
+        Address::from_public_key(&public_key).unwrap().into()
+    }
+
+    pub fn get_public_key(&self) -> Vec<u8> {
+        hex::decode(&self.public_key).unwrap()
+    }
+}
+
+fn parse_hash(s: &str) -> Result<H256, String> {
+    let bytes = hex::decode(s).map_err(|e| format!("invalid hash {}: {}", s, e))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| format!("invalid hash {}: expected 32 bytes", s))?;
+    Ok(bytes.into())
+}
+
+impl GenesisSpec {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("error reading the genesis file {}: {}", path, e))?;
+        Self::from_json(&content)
+            .map_err(|e| format!("error in the genesis file {}: {}", path, e))
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let spec: GenesisSpec = serde_json::from_str(content)
+            .map_err(|e| format!("invalid genesis: {}", e))?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        //the chain id prefixes the addresses of the chain
+        Address::check_prefix(&self.chain_id)
+            .map_err(|e| format!("invalid chain_id {}: {}", self.chain_id, e))?;
+        if self.shard_num == 0 {
+            return Err(String::from("shard_num is 0: there must be at least one shard"));
+        }
+        for (name, target) in self.targets.iter() {
+            if target.len() != 64 || !target.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("invalid target {} {}: expected 64 hex digits", name, target));
+            }
+        }
+        for (i, allocation) in self.allocations.iter().enumerate() {
+            let address = Address::decode(&allocation.address, &self.chain_id)
+                .map_err(|e| format!("allocation {}: {}", i, e))?;
+            let key = match hex::decode(&allocation.public_key) {
+                Ok(key) if key.len() == 32 => key,
+                Ok(_) => return Err(format!("allocation {}: the public key must be 32 bytes", i)),
+                Err(e) => return Err(format!("allocation {}: invalid public key: {}", i, e)),
+            };
+            let key_address = Address::from_public_key(&key).unwrap();
+            if address != key_address {
+                return Err(format!(
+                    "allocation {}: {} is not the address of its public key, which is {}",
+                    i, allocation.address, key_address.encode(&self.chain_id)
+                ));
+            }
+            if allocation.value == 0 {
+                return Err(format!("allocation {} of {} has no value", i, allocation.address));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_timestamp(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.timestamp)
+    }
+
+    //a target of the file, already checked by validate()
+    pub fn get_target(&self, name: &str) -> Option<H256> {
+        self.targets.get(name).map(|x| parse_hash(x).unwrap())
+    }
+
+    //the digest of the whole spec, which the genesis blocks commit to
+    pub fn commitment(&self) -> H256 {
+        let json = serde_json::to_string(self).unwrap();
+        ring::digest::digest(&ring::digest::SHA256, json.as_bytes()).into()
+    }
+}
@@ -0,0 +1,187 @@
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response};
+
+//JSON-RPC 2.0 over the API servers: the requests, the batches and the error
+//codes are handled here, the methods by the dispatcher of each protocol.
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+//the server errors shared by the protocols, in the range reserved by the spec
+pub const NOT_FOUND: i64 = -32001;
+pub const REJECTED: i64 = -32002;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(INTERNAL_ERROR, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(NOT_FOUND, message)
+    }
+
+    pub fn rejected(message: impl Into<String>) -> Self {
+        Self::new(REJECTED, message)
+    }
+}
+
+//the params of a request, given by name in an object or by position in an array
+pub struct Params<'a> {
+    value: Option<&'a Value>,
+}
+
+impl<'a> Params<'a> {
+    pub fn new(value: Option<&'a Value>) -> Self {
+        Params { value }
+    }
+
+    //None when the param is absent or null
+    pub fn get<T: DeserializeOwned>(&self, name: &str, position: usize) -> Result<Option<T>, RpcError> {
+        let value = match self.value {
+            Some(Value::Object(map)) => map.get(name),
+            Some(Value::Array(values)) => values.get(position),
+            _ => None,
+        };
+        match value {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => serde_json::from_value(value.clone())
+                .map(Some)
+                .map_err(|e| RpcError::invalid_params(format!("invalid {}: {}", name, e))),
+        }
+    }
+
+    pub fn require<T: DeserializeOwned>(&self, name: &str, position: usize) -> Result<T, RpcError> {
+        self.get(name, position)?
+            .ok_or(RpcError::invalid_params(format!("missing {}", name)))
+    }
+}
+
+pub fn to_result<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::internal(e.to_string()))
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => json!({ "jsonrpc": "2.0", "error": error, "id": id }),
+    }
+}
+
+//None for a notification, which gets no response
+fn handle_one<F>(request: &Value, dispatch: &F) -> Option<Value>
+where
+    F: Fn(&str, &Params) -> Result<Value, RpcError>,
+{
+    let object = match request.as_object() {
+        Some(object) => object,
+        None => return Some(response(Value::Null, Err(RpcError::new(INVALID_REQUEST, "request is not an object")))),
+    };
+    let id = object.get("id").cloned();
+    let valid_id = matches!(id, None | Some(Value::Null) | Some(Value::Number(_)) | Some(Value::String(_)));
+    let method = object.get("method").and_then(|m| m.as_str());
+    let params = object.get("params");
+    let error = if object.get("jsonrpc") != Some(&json!("2.0")) {
+        Some("jsonrpc must be \"2.0\"")
+    } else if method.is_none() {
+        Some("method must be a string")
+    } else if !valid_id {
+        Some("id must be a string, a number or null")
+    } else if !matches!(params, None | Some(Value::Array(_)) | Some(Value::Object(_))) {
+        Some("params must be an array or an object")
+    } else {
+        None
+    };
+    if let Some(message) = error {
+        let id = if valid_id { id.unwrap_or(Value::Null) } else { Value::Null };
+        return Some(response(id, Err(RpcError::new(INVALID_REQUEST, message))));
+    }
+    let result = dispatch(method.unwrap(), &Params::new(params));
+    id.map(|id| response(id, result))
+}
+
+//Handle the body of an HTTP request, a request or a batch of them. None when
+//nothing is to be answered, i.e. only notifications were sent.
+pub fn handle<F>(body: &str, dispatch: F) -> Option<String>
+where
+    F: Fn(&str, &Params) -> Result<Value, RpcError>,
+{
+    let request: Value = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(e) => {
+            let error = RpcError::new(PARSE_ERROR, format!("parse error: {}", e));
+            return Some(response(Value::Null, Err(error)).to_string());
+        }
+    };
+    match request {
+        Value::Array(requests) if requests.is_empty() => {
+            let error = RpcError::new(INVALID_REQUEST, "empty batch");
+            Some(response(Value::Null, Err(error)).to_string())
+        }
+        Value::Array(requests) => {
+            let responses: Vec<Value> = requests
+                .iter()
+                .filter_map(|request| handle_one(request, &dispatch))
+                .collect();
+            match responses.is_empty() {
+                true => None,
+                false => Some(Value::Array(responses).to_string()),
+            }
+        }
+        request => handle_one(&request, &dispatch).map(|response| response.to_string()),
+    }
+}
+
+//Answer an HTTP request to /rpc: the body of a POST is handled, any other method
+//gets 405.
+pub fn serve<F>(mut req: Request, dispatch: F)
+where
+    F: Fn(&str, &Params) -> Result<Value, RpcError>,
+{
+    if *req.method() != Method::Post {
+        let allow = "Allow: POST".parse::<Header>().unwrap();
+        let resp = Response::from_string("JSON-RPC requests must be sent with POST")
+            .with_status_code(405)
+            .with_header(allow);
+        let _ = req.respond(resp);
+        return;
+    }
+    let mut body = String::new();
+    if let Err(e) = req.as_reader().read_to_string(&mut body) {
+        let error = RpcError::new(PARSE_ERROR, format!("error reading body: {}", e));
+        let resp = Response::from_string(response(Value::Null, Err(error)).to_string()).with_status_code(400);
+        let _ = req.respond(resp);
+        return;
+    }
+    match handle(&body, dispatch) {
+        Some(response) => {
+            let content_type = "Content-Type: application/json".parse::<Header>().unwrap();
+            let _ = req.respond(Response::from_string(response).with_header(content_type));
+        }
+        None => {
+            let _ = req.respond(Response::empty(204));
+        }
+    }
+}
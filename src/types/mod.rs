@@ -3,4 +3,8 @@ pub mod hash;
 pub mod merkle;
 pub mod key_pair;
 pub mod random;
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod jsonrpc;
+pub mod options;
+pub mod genesis;
+pub mod sig_verifier;
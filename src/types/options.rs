@@ -0,0 +1,195 @@
+use serde_json::{Map, Value};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    convert::TryInto,
+    fmt::Display,
+    fs,
+    str::FromStr,
+};
+use crate::types::{
+    hash::H256,
+    genesis::GenesisSpec,
+};
+
+//the options of a subcommand, from its command line and a JSON config file of
+//--config whose keys are the names of the arguments, e.g.
+//{"shard_id": 1, "block_size": 2048, "known_peer": ["127.0.0.1:6000"]};
+//the command line wins over the file, which wins over the defaults
+pub struct Options<'a> {
+    matches: &'a clap::ArgMatches<'a>,
+    file: Map<String, Value>,
+    resolved: RefCell<BTreeMap<String, Value>>, //the effective options read so far
+}
+
+//not node options, so never dumped
+const META_OPTIONS: [&str; 2] = ["config", "dump_config"];
+
+impl<'a> Options<'a> {
+    pub fn load(matches: &'a clap::ArgMatches<'a>) -> Result<Self, String> {
+        match matches.value_of("config") {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .map_err(|e| format!("error reading the config file {}: {}", path, e))?;
+                Self::with_file(matches, &content)
+                    .map_err(|e| format!("error in the config file {}: {}", path, e))
+            }
+            None => Ok(Self::new(matches, Map::new())),
+        }
+    }
+
+    pub fn with_file(matches: &'a clap::ArgMatches<'a>, content: &str) -> Result<Self, String> {
+        match serde_json::from_str::<Value>(content) {
+            Ok(Value::Object(file)) => Ok(Self::new(matches, file)),
+            Ok(_) => Err(String::from("expected a JSON object of option names to values")),
+            Err(e) => Err(format!("invalid JSON: {}", e)),
+        }
+    }
+
+    pub fn new(matches: &'a clap::ArgMatches<'a>, file: Map<String, Value>) -> Self {
+        Options {
+            matches,
+            file,
+            resolved: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    fn on_command_line(&self, name: &str) -> bool {
+        self.matches.occurrences_of(name) > 0
+    }
+
+    fn resolve(&self, name: &str, value: Value) {
+        self.resolved.borrow_mut().insert(name.to_string(), value);
+    }
+
+    pub fn value_of(&self, name: &str) -> Result<Option<String>, String> {
+        let value = match self.file.get(name) {
+            Some(_) if self.on_command_line(name) => self.matches.value_of(name).map(|x| x.to_string()),
+            Some(Value::String(x)) => Some(x.clone()),
+            Some(Value::Number(x)) => Some(x.to_string()),
+            Some(x) => {
+                return Err(format!("\"{}\" in the config file should be a string or a number, not {}", name, x));
+            }
+            None => self.matches.value_of(name).map(|x| x.to_string()),
+        };
+        if let Some(x) = value.as_ref() {
+            self.resolve(name, Value::String(x.clone()));
+        }
+        Ok(value)
+    }
+
+    //empty when not given
+    pub fn values_of(&self, name: &str) -> Result<Vec<String>, String> {
+        let values: Vec<String> = match self.file.get(name) {
+            Some(_) if self.on_command_line(name) => self.matches
+                .values_of(name)
+                .map(|x| x.map(|y| y.to_string()).collect())
+                .unwrap_or_default(),
+            Some(Value::Array(xs)) => {
+                let mut values = vec![];
+                for x in xs {
+                    match x {
+                        Value::String(x) => values.push(x.clone()),
+                        x => return Err(format!("\"{}\" in the config file should hold strings, not {}", name, x)),
+                    }
+                }
+                values
+            }
+            Some(x) => {
+                return Err(format!("\"{}\" in the config file should be an array of strings, not {}", name, x));
+            }
+            None => self.matches
+                .values_of(name)
+                .map(|x| x.map(|y| y.to_string()).collect())
+                .unwrap_or_default(),
+        };
+        self.resolve(name, Value::Array(values.iter().cloned().map(Value::String).collect()));
+        Ok(values)
+    }
+
+    //a flag, true or false in the file
+    pub fn is_present(&self, name: &str) -> Result<bool, String> {
+        let present = match self.file.get(name) {
+            _ if self.on_command_line(name) => true,
+            Some(Value::Bool(x)) => *x,
+            Some(x) => {
+                return Err(format!("\"{}\" in the config file should be true or false, not {}", name, x));
+            }
+            None => false,
+        };
+        self.resolve(name, Value::Bool(present));
+        Ok(present)
+    }
+
+    pub fn parse<T>(&self, name: &str) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.parse_opt(name)? {
+            Some(x) => Ok(x),
+            None => Err(format!(
+                "missing {}: set it on the command line or as \"{}\" in the config file",
+                name, name
+            )),
+        }
+    }
+
+    //None when not given
+    pub fn parse_opt<T>(&self, name: &str) -> Result<Option<T>, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.value_of(name)? {
+            Some(x) => x
+                .parse::<T>()
+                .map(Some)
+                .map_err(|e| format!("invalid {} {}: {}", name, x, e)),
+            None => Ok(None),
+        }
+    }
+
+    //a 256-bit target as 64 hex digits, the one of the genesis file when not given
+    pub fn parse_difficulty(&self, name: &str, genesis: Option<&GenesisSpec>) -> Result<H256, String> {
+        if self.value_of(name)?.is_none() {
+            if let Some(target) = genesis.and_then(|x| x.get_target(name)) {
+                return Ok(target);
+            }
+        }
+        let hex: String = self.parse(name)?;
+        if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid {} {}: expected 64 hex digits", name, hex));
+        }
+        let bytes: [u8; 32] = hex::decode(&hex)
+            .map_err(|e| format!("invalid {} {}: {}", name, hex, e))?
+            .try_into()
+            .unwrap();
+        Ok(bytes.into())
+    }
+
+    //the keys of the file that were never read, most likely misspelled
+    pub fn check_unknown(&self) -> Result<(), String> {
+        let resolved = self.resolved.borrow();
+        let unknown: Vec<&String> = self.file
+            .keys()
+            .filter(|key| !resolved.contains_key(key.as_str()) && !META_OPTIONS.contains(&key.as_str()))
+            .collect();
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unknown options in the config file: {:?}", unknown))
+        }
+    }
+
+    //the effective options read so far, as a config file for --config
+    pub fn dump(&self) -> String {
+        let dump: Map<String, Value> = self.resolved
+            .borrow()
+            .iter()
+            .filter(|(key, _)| !META_OPTIONS.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        serde_json::to_string_pretty(&Value::Object(dump)).unwrap()
+    }
+}
@@ -0,0 +1,128 @@
+use crossbeam::channel::{unbounded, Sender};
+use ring::signature::{self, UnparsedPublicKey};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{Arc, Mutex},
+    thread,
+};
+use crate::types::hash::H256;
+
+pub const DEFAULT_CACHE_SIZE: usize = 100000;
+
+//an Ed25519 signature of the input of a tx over its sighash
+#[derive(Debug, Clone)]
+pub struct SigJob {
+    pub tx_hash: H256,
+    pub input_index: u32,
+    pub public_key: Vec<u8>,
+    pub message: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+pub fn verify_signature(public_key: &[u8], message: &[u8], sig: &[u8]) -> bool {
+    UnparsedPublicKey::new(&signature::ED25519, public_key)
+        .verify(message, sig)
+        .is_ok()
+}
+
+//the (tx hash, input index) pairs with a valid signature, oldest evicted first
+struct SigCache {
+    set: HashSet<(H256, u32)>,
+    order: VecDeque<(H256, u32)>,
+    capacity: usize,
+}
+
+impl SigCache {
+    fn insert(&mut self, key: (H256, u32)) {
+        if self.capacity == 0 || !self.set.insert(key) {
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            let oldest = self.order.pop_front().unwrap();
+            self.set.remove(&oldest);
+        }
+    }
+}
+
+type Task = (usize, SigJob, Sender<(usize, bool)>);
+
+//verifies the signatures of batches on a pool of worker threads, so the
+//caller holds no chain lock meanwhile, and remembers the valid ones so a
+//relayed tx is not verified again when a block includes it
+#[derive(Clone)]
+pub struct SigVerifier {
+    task_chan: Sender<Task>,
+    cache: Arc<Mutex<SigCache>>,
+}
+
+impl SigVerifier {
+    //the workers exit once every clone of the verifier is dropped
+    pub fn new(num_worker: usize, cache_size: usize) -> Self {
+        let (task_tx, task_rx) = unbounded::<Task>();
+        for i in 0..num_worker.max(1) {
+            let task_rx = task_rx.clone();
+            thread::Builder::new()
+                .name(format!("sig-verifier-{}", i))
+                .spawn(move || {
+                    for (pos, job, result_chan) in task_rx.iter() {
+                        let valid = verify_signature(&job.public_key, &job.message, &job.signature);
+                        //the caller may be gone
+                        let _ = result_chan.send((pos, valid));
+                    }
+                })
+                .unwrap();
+        }
+        SigVerifier {
+            task_chan: task_tx,
+            cache: Arc::new(Mutex::new(SigCache {
+                set: HashSet::new(),
+                order: VecDeque::new(),
+                capacity: cache_size,
+            })),
+        }
+    }
+
+    pub fn is_verified(&self, tx_hash: &H256, input_index: u32) -> bool {
+        self.cache.lock().unwrap().set.contains(&(*tx_hash, input_index))
+    }
+
+    pub fn cache_len(&self) -> usize {
+        self.cache.lock().unwrap().order.len()
+    }
+
+    //whether each signature is valid, in the order of the jobs; the cached
+    //pairs are valid without verifying them again
+    pub fn verify_batch(&self, jobs: Vec<SigJob>) -> Vec<bool> {
+        let mut results = vec![false; jobs.len()];
+        let mut keys = Vec::with_capacity(jobs.len());
+        let (result_tx, result_rx) = unbounded();
+        let mut pending = 0;
+        for (pos, job) in jobs.into_iter().enumerate() {
+            keys.push((job.tx_hash, job.input_index));
+            if self.is_verified(&job.tx_hash, job.input_index) {
+                results[pos] = true;
+                continue;
+            }
+            self.task_chan.send((pos, job, result_tx.clone())).unwrap();
+            pending += 1;
+        }
+        drop(result_tx);
+        let mut valid = vec![];
+        for (pos, is_valid) in result_rx.iter().take(pending) {
+            results[pos] = is_valid;
+            if is_valid {
+                valid.push(keys[pos]);
+            }
+        }
+        let mut cache = self.cache.lock().unwrap();
+        for key in valid {
+            cache.insert(key);
+        }
+        results
+    }
+
+    pub fn verify(&self, job: SigJob) -> bool {
+        self.verify_batch(vec![job])[0]
+    }
+}
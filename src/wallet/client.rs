@@ -0,0 +1,46 @@
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+//calls the JSON-RPC endpoint of a node, e.g. http://127.0.0.1:7000/rpc
+pub struct RpcClient {
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> Self {
+        RpcClient {
+            url: url.to_string(),
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    pub fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, String> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response: Value = self.client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .and_then(|x| x.json())
+            .map_err(|e| format!("error calling {} on {}: {}", method, self.url, e))?;
+        Self::result_of(method, response)
+    }
+
+    //the result of a response, or its error as a message
+    pub fn result_of<T: DeserializeOwned>(method: &str, mut response: Value) -> Result<T, String> {
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(|x| x.as_str()).unwrap_or("unknown error");
+            return Err(format!("{} failed: {}", method, message));
+        }
+        match response.get_mut("result") {
+            Some(result) => serde_json::from_value(result.take())
+                .map_err(|e| format!("unexpected result of {}: {}", method, e)),
+            None => Err(format!("no result for {}", method)),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use crate::types::{
+    address::Address,
+    hash::H256,
+};
+
+//the keys of a wallet, one PKCS#8 file per key named <name>.pk8 in a directory
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+const KEY_EXTENSION: &str = "pk8";
+
+//the address of the coins of a key, as the txs hold it
+pub fn address_of(public_key: &[u8]) -> H256 {
+    Address::from_public_key(public_key).unwrap().into()
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid key name {}: use letters, digits, - and _", name))
+    }
+}
+
+fn parse_key(pkcs8: &[u8]) -> Result<Ed25519KeyPair, String> {
+    Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|e| format!("not an Ed25519 PKCS#8 key: {}", e))
+}
+
+//readable by the owner only, as it holds the private key
+fn write_private(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("error creating {}: {}", path.display(), e))?;
+    file.write_all(bytes)
+        .map_err(|e| format!("error writing {}: {}", path.display(), e))
+}
+
+impl Keystore {
+    pub fn open(dir: &str) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("error opening the wallet {}: {}", dir, e))?;
+        Ok(Keystore {
+            dir: PathBuf::from(dir),
+        })
+    }
+
+    fn path_of(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, KEY_EXTENSION))
+    }
+
+    //the PKCS#8 file of a new random key
+    pub fn create(&self, name: &str) -> Result<Ed25519KeyPair, String> {
+        check_name(name)?;
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| String::from("error generating a key"))?;
+        write_private(&self.path_of(name), pkcs8.as_ref())?;
+        parse_key(pkcs8.as_ref())
+    }
+
+    pub fn import(&self, name: &str, path: &str) -> Result<Ed25519KeyPair, String> {
+        check_name(name)?;
+        let pkcs8 = fs::read(path).map_err(|e| format!("error reading {}: {}", path, e))?;
+        let key = parse_key(&pkcs8).map_err(|e| format!("error importing {}: {}", path, e))?;
+        write_private(&self.path_of(name), &pkcs8)?;
+        Ok(key)
+    }
+
+    pub fn export(&self, name: &str, path: &str) -> Result<(), String> {
+        let pkcs8 = self.read(name)?;
+        write_private(Path::new(path), &pkcs8)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+        check_name(name)?;
+        let path = self.path_of(name);
+        fs::read(&path).map_err(|e| format!("error reading the key {} at {}: {}", name, path.display(), e))
+    }
+
+    pub fn load(&self, name: &str) -> Result<Ed25519KeyPair, String> {
+        let pkcs8 = self.read(name)?;
+        parse_key(&pkcs8).map_err(|e| format!("error loading the key {}: {}", name, e))
+    }
+
+    //the names of the keys, sorted
+    pub fn list(&self) -> Result<Vec<String>, String> {
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| format!("error listing {}: {}", self.dir.display(), e))?;
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|x| x == KEY_EXTENSION))
+            .filter_map(|path| path.file_stem().and_then(|x| x.to_str()).map(|x| x.to_string()))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn address(&self, name: &str) -> Result<Address, String> {
+        Address::from_public_key(self.load(name)?.public_key().as_ref())
+    }
+}
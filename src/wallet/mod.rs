@@ -0,0 +1,254 @@
+pub mod client;
+pub mod keystore;
+pub mod optchain;
+
+use log::error;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::Deserialize;
+use serde_json::json;
+use std::{collections::HashSet, process};
+use crate::{
+    manifoldchain::{
+        api::UtxoView,
+        transaction::{Transaction, TxFlag},
+        validator::Validator,
+    },
+    types::{
+        address::Address,
+        hash::H256,
+    },
+};
+use client::RpcClient;
+use keystore::{address_of, Keystore};
+
+//the protocol, the chain and the shard of a node, from node_getInfo
+#[derive(Deserialize, Debug, Clone)]
+pub struct NodeInfo {
+    pub protocol: String,
+    pub chain_id: String,
+    pub shard_id: usize,
+    pub shard_num: usize,
+}
+
+//the coins of a utxo; a rejected tx gives back the coins of its inputs
+pub fn utxo_value(tx: &Transaction, index: usize) -> usize {
+    match tx.flag {
+        TxFlag::Reject => tx.inputs[index].value as usize,
+        _ => tx.outputs[index].value as usize,
+    }
+}
+
+//the utxos spent to pay the amount, oldest first, and their coins;
+//None if they do not cover it
+pub fn select_utxos(utxos: &[(Transaction, u32)], amount: usize) -> Option<(Vec<(&Transaction, usize)>, usize)> {
+    select_by(utxos, amount, utxo_value)
+}
+
+//select_utxos() for the txs of either protocol, whose coins `value` tells
+pub fn select_by<T>(
+    utxos: &[(T, u32)],
+    amount: usize,
+    value: fn(&T, usize) -> usize,
+) -> Option<(Vec<(&T, usize)>, usize)> {
+    let mut selected = vec![];
+    let mut total = 0;
+    for (tx, index) in utxos.iter() {
+        if total >= amount && !selected.is_empty() {
+            break;
+        }
+        selected.push((tx, *index as usize));
+        total += value(tx, *index as usize);
+    }
+    if total >= amount && !selected.is_empty() {
+        Some((selected, total))
+    } else {
+        None
+    }
+}
+
+//a payee of send, PUBKEY_HEX:VALUE; the value is the u32 of an output
+pub fn parse_payee(s: &str) -> Result<(Vec<u8>, u32), String> {
+    let (key, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid payee {}: expected PUBKEY_HEX:VALUE", s))?;
+    let key = hex::decode(key).map_err(|e| format!("invalid public key {}: {}", key, e))?;
+    if key.len() != 32 {
+        return Err(format!("invalid payee {}: the public key must be 32 bytes", s));
+    }
+    let value = value
+        .parse::<u32>()
+        .map_err(|e| format!("invalid value of payee {}: {}", s, e))?;
+    if value == 0 {
+        return Err(format!("invalid payee {}: the value is 0", s));
+    }
+    Ok((key, value))
+}
+
+//a tx paying the payees from the utxos of the key, with the change back to the
+//key; an input tx when a payee is out of the shard of the key
+pub fn build_transfer(
+    key: &Ed25519KeyPair,
+    utxos: &[(Transaction, u32)],
+    payees: &[(Vec<u8>, u32)],
+    info: &NodeInfo,
+) -> Result<Transaction, String> {
+    let sender = address_of(key.public_key().as_ref());
+    let amount: usize = payees.iter().map(|x| x.1 as usize).sum();
+    let (spent, total) = select_utxos(utxos, amount).ok_or_else(|| {
+        let balance: usize = utxos.iter().map(|(tx, i)| utxo_value(tx, *i as usize)).sum();
+        format!("insufficient balance: {} to send, {} available", amount, balance)
+    })?;
+
+    let addresses: Vec<H256> = payees.iter().map(|x| address_of(&x.0)).collect();
+    let mut receivers: Vec<(&H256, &[u8], usize)> = payees
+        .iter()
+        .zip(addresses.iter())
+        .map(|(payee, addr)| (addr, payee.0.as_slice(), payee.1 as usize))
+        .collect();
+    if total > amount {
+        receivers.push((&sender, key.public_key().as_ref(), total - amount));
+    }
+    let sender_shard = Validator::get_shard_id(&sender, info.shard_num);
+    let shards: HashSet<usize> = addresses
+        .iter()
+        .map(|x| Validator::get_shard_id(x, info.shard_num))
+        .collect();
+    let flag = if shards.iter().any(|x| *x != sender_shard) {
+        TxFlag::Input
+    } else {
+        TxFlag::Domestic
+    };
+    let senders: Vec<(&H256, &Ed25519KeyPair)> = vec![(&sender, key); spent.len()];
+    Transaction::consume_to(spent, senders, receivers, flag, &info.chain_id)
+        .ok_or_else(|| String::from("a utxo cannot be spent"))
+}
+
+fn exit_on_error<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| {
+        error!("{}", e);
+        process::exit(1);
+    })
+}
+
+//the node behind --rpc, which must be of the chain of the wallet
+fn get_info(rpc: &RpcClient, chain_id: &str) -> Result<NodeInfo, String> {
+    let info: NodeInfo = rpc.call("node_getInfo", json!([]))?;
+    if info.chain_id != chain_id {
+        return Err(format!(
+            "the node is of chain {}, not {}: use --chain {} or the --rpc of a node of chain {}",
+            info.chain_id, chain_id, info.chain_id, chain_id
+        ));
+    }
+    Ok(info)
+}
+
+//the utxos of an address on the node of its shard; optchain nodes keep no utxo
+//set, so only manifoldchain nodes are asked
+fn get_utxos(rpc: &RpcClient, info: &NodeInfo, address: &Address, chain_id: &str) -> Result<Vec<UtxoView>, String> {
+    if info.protocol == "optchain" {
+        return Err(String::from(
+            "optchain nodes keep no utxo set: give the utxos to spend to send with --utxo RAW_TX_HEX:INDEX"
+        ));
+    }
+    let shard_id = Validator::get_shard_id(&address.into(), info.shard_num);
+    if shard_id != info.shard_id {
+        return Err(format!(
+            "{} belongs to shard {} but the node serves shard {}: use --rpc of a node of shard {}",
+            address.encode(chain_id), shard_id, info.shard_id, shard_id
+        ));
+    }
+    rpc.call("chain_getAvailableUtxos", json!([address.encode(chain_id)]))
+}
+
+pub fn start(sub_com: &clap::ArgMatches) {
+    let keystore = exit_on_error(Keystore::open(sub_com.value_of("dir").unwrap()));
+    let rpc = RpcClient::new(sub_com.value_of("rpc").unwrap());
+    let chain_id = sub_com.value_of("chain").unwrap();
+    exit_on_error(Address::check_prefix(chain_id));
+
+    match sub_com.subcommand() {
+        ("create", Some(m)) => {
+            let name = m.value_of("name").unwrap();
+            exit_on_error(keystore.create(name));
+            println!("{} {}", name, exit_on_error(keystore.address(name)).encode(chain_id));
+        }
+        ("import", Some(m)) => {
+            let name = m.value_of("name").unwrap();
+            exit_on_error(keystore.import(name, m.value_of("file").unwrap()));
+            println!("{} {}", name, exit_on_error(keystore.address(name)).encode(chain_id));
+        }
+        ("export", Some(m)) => {
+            exit_on_error(keystore.export(m.value_of("name").unwrap(), m.value_of("file").unwrap()));
+        }
+        ("list", Some(_)) => {
+            for name in exit_on_error(keystore.list()) {
+                println!("{} {}", name, exit_on_error(keystore.address(&name)).encode(chain_id));
+            }
+        }
+        ("address", Some(m)) => {
+            let key = exit_on_error(keystore.load(m.value_of("name").unwrap()));
+            let address = exit_on_error(Address::from_public_key(key.public_key().as_ref()));
+            println!("address: {}", address.encode(chain_id));
+            println!("public key: {}", hex::encode(key.public_key().as_ref()));
+        }
+        ("utxos", Some(m)) => {
+            let address = exit_on_error(keystore.address(m.value_of("name").unwrap()));
+            let info = exit_on_error(get_info(&rpc, chain_id));
+            let utxos = exit_on_error(get_utxos(&rpc, &info, &address, chain_id));
+            for utxo in utxos.iter() {
+                println!("{}:{} {}", utxo.tx_hash, utxo.index, utxo.value);
+            }
+        }
+        ("balance", Some(m)) => {
+            let address = exit_on_error(keystore.address(m.value_of("name").unwrap()));
+            let info = exit_on_error(get_info(&rpc, chain_id));
+            let utxos = exit_on_error(get_utxos(&rpc, &info, &address, chain_id));
+            let balance: usize = utxos.iter().map(|x| x.value as usize).sum();
+            println!("{}", balance);
+        }
+        ("send", Some(m)) => {
+            let name = m.value_of("name").unwrap();
+            let key = exit_on_error(keystore.load(name));
+            let payees: Vec<(Vec<u8>, u32)> = exit_on_error(
+                m.values_of("to").unwrap().map(parse_payee).collect()
+            );
+            let given: Vec<&str> = m.values_of("utxo").map(|x| x.collect()).unwrap_or_default();
+            let info = exit_on_error(get_info(&rpc, chain_id));
+            if info.protocol == "optchain" {
+                if given.is_empty() {
+                    error!("optchain nodes keep no utxo set: give the utxos to spend with --utxo RAW_TX_HEX:INDEX");
+                    process::exit(1);
+                }
+                let utxos: Vec<(_, u32)> = exit_on_error(
+                    given.into_iter().map(optchain::parse_utxo).collect()
+                );
+                let tx = exit_on_error(optchain::build_transfer(&key, &utxos, &payees, &info.chain_id));
+                let raw = hex::encode(bincode::serialize(&tx).unwrap());
+                let hash: String = exit_on_error(rpc.call("tx_submitTransaction", json!([raw])));
+                println!("{}", hash);
+                //the change is the last output, spent by the next send with --utxo
+                if tx.outputs.len() > payees.len() {
+                    println!("change: {}:{}", raw, tx.outputs.len() - 1);
+                }
+                return;
+            }
+            if !given.is_empty() {
+                error!("manifoldchain nodes select the utxos to spend, --utxo is for optchain nodes");
+                process::exit(1);
+            }
+            let address = exit_on_error(keystore.address(name));
+            let utxos = exit_on_error(get_utxos(&rpc, &info, &address, chain_id));
+            let utxos: Vec<(Transaction, u32)> = exit_on_error(
+                utxos.iter().map(|x| x.get_tx().map(|tx| (tx, x.index))).collect()
+            );
+            let tx = exit_on_error(build_transfer(&key, &utxos, &payees, &info));
+            let raw = hex::encode(bincode::serialize(&tx).unwrap());
+            let hash: String = exit_on_error(rpc.call("tx_submit", json!([raw])));
+            println!("{}", hash);
+        }
+        _ => {
+            error!("missing the wallet command, see wallet --help");
+            process::exit(1);
+        }
+    }
+}
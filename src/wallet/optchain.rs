@@ -0,0 +1,78 @@
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use crate::{
+    optchain::transaction::{Transaction, TxFlag},
+    types::hash::{H256, Hashable},
+};
+use super::{select_by, keystore::address_of};
+
+//optchain nodes keep no utxo set, so the utxos a transfer spends are given to
+//the wallet, as the raw tx holding them and the index of the output
+
+//the owner and the coins of a utxo, none if the tx has no such utxo; a rejected
+//tx gives back the coins of its inputs to their senders
+pub fn utxo_of(tx: &Transaction, index: usize) -> Option<(H256, usize)> {
+    match tx.flag {
+        TxFlag::Reject => tx.inputs.get(index).map(|x| (x.sender_addr, x.value as usize)),
+        _ => tx.outputs.get(index).map(|x| (x.receiver_addr, x.value as usize)),
+    }
+}
+
+pub fn utxo_value(tx: &Transaction, index: usize) -> usize {
+    utxo_of(tx, index).map_or(0, |x| x.1)
+}
+
+//a utxo of send, RAW_TX_HEX:INDEX with the tx encoded with bincode as hex
+pub fn parse_utxo(s: &str) -> Result<(Transaction, u32), String> {
+    let (raw, index) = s
+        .rsplit_once(':')
+        .ok_or_else(|| format!("invalid utxo {}: expected RAW_TX_HEX:INDEX", s))?;
+    let bytes = hex::decode(raw).map_err(|e| format!("invalid raw tx of utxo {}: {}", s, e))?;
+    let tx: Transaction = bincode::deserialize(&bytes)
+        .map_err(|e| format!("invalid raw tx of utxo {}: {}", s, e))?;
+    let index = index
+        .parse::<u32>()
+        .map_err(|e| format!("invalid index of utxo {}: {}", s, e))?;
+    if utxo_of(&tx, index as usize).is_none() {
+        return Err(format!("invalid utxo {}: the {} tx has no utxo {}", s, tx.flag.to_string(), index));
+    }
+    Ok((tx, index))
+}
+
+//a tx paying the payees from the given utxos of the key, with the change back
+//to the key
+pub fn build_transfer(
+    key: &Ed25519KeyPair,
+    utxos: &[(Transaction, u32)],
+    payees: &[(Vec<u8>, u32)],
+    chain_id: &str,
+) -> Result<Transaction, String> {
+    let sender = address_of(key.public_key().as_ref());
+    for (tx, index) in utxos.iter() {
+        match utxo_of(tx, *index as usize) {
+            Some((owner, _)) if owner == sender => {}
+            Some(_) => return Err(format!("utxo {}:{} is not paid to the key", tx.hash(), index)),
+            None => return Err(format!("utxo {}:{} does not exist", tx.hash(), index)),
+        }
+    }
+    let amount: usize = payees.iter().map(|x| x.1 as usize).sum();
+    let (spent, total) = select_by(utxos, amount, utxo_value).ok_or_else(|| {
+        let balance: usize = utxos.iter().map(|(tx, i)| utxo_value(tx, *i as usize)).sum();
+        format!("insufficient utxos: {} to send, {} given", amount, balance)
+    })?;
+
+    let addresses: Vec<H256> = payees.iter().map(|x| address_of(&x.0)).collect();
+    let mut receivers: Vec<(&H256, &[u8], usize)> = payees
+        .iter()
+        .zip(addresses.iter())
+        .map(|(payee, addr)| (addr, payee.0.as_slice(), payee.1 as usize))
+        .collect();
+    if total - amount > u32::MAX as usize {
+        return Err(format!("change of {} coins does not fit in an output", total - amount));
+    }
+    if total > amount {
+        receivers.push((&sender, key.public_key().as_ref(), total - amount));
+    }
+    let senders: Vec<(&H256, &Ed25519KeyPair)> = vec![(&sender, key); spent.len()];
+    Transaction::consume_to(spent, senders, receivers, TxFlag::Domestic, chain_id)
+        .ok_or_else(|| String::from("a utxo cannot be spent"))
+}